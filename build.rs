@@ -42,6 +42,28 @@ fn main() {
     if !version.probe(61, 2022, 3, 7) {
         println!("cargo:rustc-cfg=atomic_maybe_uninit_no_const_fn_trait_bound");
     }
+    // cfg_target_has_atomic stabilized in Rust 1.60 (nightly-2022-02-11): https://github.com/rust-lang/rust/pull/93824
+    if !version.probe(60, 2022, 2, 10) {
+        println!("cargo:rustc-cfg=atomic_maybe_uninit_no_cfg_target_has_atomic");
+    }
+    // MaybeUninit<T> register operands of asm! (https://github.com/rust-lang/rust/pull/114790)
+    // merged in Rust 1.74 (nightly-2023-08-24).
+    let no_asm_maybe_uninit = !version.probe(74, 2023, 8, 23);
+
+    // cfg(sanitize = "...") requires unstable feature(cfg_sanitize), so
+    // check it here and emit our own cfgs.
+    let sanitize = env::var("CARGO_CFG_SANITIZE").unwrap_or_default();
+    if sanitize.contains("thread") {
+        println!("cargo:rustc-cfg=atomic_maybe_uninit_sanitize_thread");
+    }
+    if sanitize.contains("memory") {
+        println!("cargo:rustc-cfg=atomic_maybe_uninit_sanitize_memory");
+    }
+    // MemorySanitizer reports uninitialized bytes in register operands of asm!,
+    // so pass values as initialized integers as on older compilers (see arch/mod.rs).
+    if no_asm_maybe_uninit || sanitize.contains("memory") {
+        println!("cargo:rustc-cfg=atomic_maybe_uninit_no_asm_maybe_uninit");
+    }
 
     let is_apple =
         target_os == "macos" || target_os == "ios" || target_os == "tvos" || target_os == "watchos";
@@ -185,6 +207,9 @@ fn main() {
                 }
             }
             target_feature_if("mclass", is_mclass, &version, None, true);
+            // Some instructions (e.g., SWP, MRS/MSR) are not available in Thumb mode on pre-v6 ARM
+            // (thumbv4t-none-eabi and thumbv5te-none-eabi).
+            target_feature_if("thumb-mode", target.starts_with("thumb"), &version, None, true);
             let mut v5te = known && subarch.starts_with("v5te");
             let mut v6 = known && subarch.starts_with("v6");
             let mut v7 = known && subarch.starts_with("v7");
@@ -229,6 +254,17 @@ fn main() {
                 None,
                 true,
             );
+            // Note: target_feature "zacas"/"zabha" is not available on rustc side on stable,
+            // so parse RUSTFLAGS. Zacas/Zabha is not enabled by default on any target.
+            target_feature_if("zacas", false, &version, None, false);
+            target_feature_if("zabha", false, &version, None, false);
+        }
+        "loongarch64" => {
+            // LA664 supports LoongArch v1.1 features including LAMCAS and LAM_BH.
+            let is_la664 = target_cpu().as_deref() == Some("la664");
+            // Note: target_feature "lamcas"/"lam-bh" is not available on rustc side on stable.
+            target_feature_if("lamcas", is_la664, &version, None, false);
+            target_feature_if("lam-bh", is_la664, &version, None, false);
         }
         "powerpc64" => {
             let target_endian =
@@ -278,6 +314,50 @@ fn main() {
             // bcr 14,0
             target_feature_if("fast-serialization", has_arch9_features, &version, None, false);
         }
+        "sparc" => {
+            // sparc-unknown-linux-gnu is V8+ (target-cpu=v9 with 32-bit ABI) by default.
+            // https://github.com/rust-lang/rust/blob/1.70.0/compiler/rustc_target/src/spec/sparc_unknown_linux_gnu.rs
+            let is_v8plus_target = target == "sparc-unknown-linux-gnu";
+            let mut v9 = false;
+            let mut leoncasa = false;
+            match target_cpu().as_deref() {
+                None => v9 = is_v8plus_target,
+                // https://github.com/llvm/llvm-project/blob/llvmorg-17.0.0-rc2/llvm/lib/Target/Sparc/Sparc.td
+                Some(
+                    "v9" | "ultrasparc" | "ultrasparc3" | "niagara" | "niagara2" | "niagara3"
+                    | "niagara4",
+                ) => v9 = true,
+                // CASA is optional on LEON3, so LLVM doesn't enable leoncasa on leon3 by default.
+                Some(
+                    "leon4" | "gr712rc" | "gr740" | "myriad2" | "myriad2.1" | "myriad2.2"
+                    | "myriad2.3" | "ma2100" | "ma2150" | "ma2155" | "ma2450" | "ma2455" | "ma2x5x"
+                    | "ma2080" | "ma2085" | "ma2480" | "ma2485" | "ma2x8x",
+                ) => leoncasa = true,
+                _ => {}
+            }
+            // Note: As of rustc 1.70, target_feature "v8plus"/"v9"/"leoncasa" is not available on rustc side:
+            // https://github.com/rust-lang/rust/blob/1.70.0/compiler/rustc_codegen_ssa/src/target_features.rs
+            // V8+ mode allows the use of V9 instructions in 32-bit code.
+            target_feature_if("v8plus", is_v8plus_target, &version, None, false);
+            // membar, cas, casx, etc.
+            target_feature_if("v9", v9, &version, None, false);
+            // casa on LEON3/LEON4
+            target_feature_if("leoncasa", leoncasa, &version, None, false);
+        }
+        "m68k" => {
+            // m68k-unknown-linux-gnu is 68020 by default:
+            // https://github.com/rust-lang/rust/blob/1.70.0/compiler/rustc_target/src/spec/m68k_unknown_linux_gnu.rs
+            let mut isa_68020 = target_os == "linux";
+            // target-cpu is preferred over the target's default.
+            if let Some(cpu) = target_cpu() {
+                // https://github.com/llvm/llvm-project/blob/llvmorg-17.0.0-rc2/llvm/lib/Target/M68k/M68k.td
+                isa_68020 = matches!(&*cpu, "M68020" | "M68030" | "M68040" | "M68060");
+            }
+            // Note: As of rustc 1.70, target_feature "isa-68020" is not available on rustc side:
+            // https://github.com/rust-lang/rust/blob/1.70.0/compiler/rustc_codegen_ssa/src/target_features.rs
+            // cas and cas2
+            target_feature_if("isa-68020", isa_68020, &version, None, false);
+        }
         _ => {}
     }
 }
@@ -22,13 +22,37 @@
 // - aarch64 (+lse2,+lse128) https://godbolt.org/z/joMq5vv1h
 // - aarch64 (+lse2,+lse128,+rcpc3) https://godbolt.org/z/WdbsccKcz
 
+#[path = "pair.rs"]
+mod pair;
+
 use core::{
     arch::asm,
     mem::{self, MaybeUninit},
     sync::atomic::Ordering,
 };
 
-use crate::raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap};
+use self::pair::{from_pair, load_pair, Pair};
+use super::{from_reg, load_reg, Reg};
+use crate::{
+    info::Strategy,
+    raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap},
+};
+
+// Register pair operands of LDP/STP, LDXP/STXP, CASP, and SWPP are in memory
+// order: the first register is the lower half on little-endian and the upper
+// half on big-endian.
+#[cfg(target_endian = "little")]
+macro_rules! select_le_or_be {
+    ($le:expr, $be:expr) => {
+        $le
+    };
+}
+#[cfg(target_endian = "big")]
+macro_rules! select_le_or_be {
+    ($le:expr, $be:expr) => {
+        $be
+    };
+}
 
 macro_rules! atomic_rmw {
     ($op:ident, $order:ident) => {
@@ -52,7 +76,8 @@ macro_rules! atomic_rmw {
 }
 
 macro_rules! atomic {
-    ($int_type:ident, $asm_suffix:tt, $val_modifier:tt) => {
+    ($int_type:ident, $asm_suffix:tt, $val_modifier:tt, $cmp_extend:tt) => {
+        impl_load_reg!($int_type, reg, concat!("ldr", $asm_suffix, " {v", $val_modifier, "}, [{src}]"));
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
@@ -62,19 +87,18 @@ macro_rules! atomic {
             ) {
                 debug_assert!(src as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(src, order);
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let v: Reg<Self>;
                     macro_rules! atomic_load {
                         ($acquire:tt) => {
                             asm!(
-                                // (atomic) load from src to tmp
-                                concat!("ld", $acquire, "r", $asm_suffix, " {tmp", $val_modifier, "}, [{src}]"),
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp", $val_modifier, "}, [{out}]"),
+                                // (atomic) load from src to v
+                                concat!("ld", $acquire, "r", $asm_suffix, " {v", $val_modifier, "}, [{src}]"),
                                 src = in(reg) ptr_reg!(src),
-                                out = inout(reg) ptr_reg!(out) => _,
-                                tmp = lateout(reg) _,
+                                v = lateout(reg) v,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -85,13 +109,10 @@ macro_rules! atomic {
                         Ordering::Acquire => {
                             // SAFETY: cfg guarantee that the CPU supports FEAT_LRCPC.
                             asm!(
-                                // (atomic) load from src to tmp
-                                concat!("ldapr", $asm_suffix, " {tmp", $val_modifier, "}, [{src}]"),
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp", $val_modifier, "}, [{out}]"),
+                                // (atomic) load from src to v
+                                concat!("ldapr", $asm_suffix, " {v", $val_modifier, "}, [{src}]"),
                                 src = in(reg) ptr_reg!(src),
-                                out = inout(reg) ptr_reg!(out) => _,
-                                tmp = lateout(reg) _,
+                                v = lateout(reg) v,
                                 options(nostack, preserves_flags),
                             );
                         }
@@ -100,6 +121,7 @@ macro_rules! atomic {
                         Ordering::SeqCst => atomic_load!("a"),
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_reg(v));
                 }
             }
         }
@@ -112,20 +134,19 @@ macro_rules! atomic {
             ) {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(dst, order);
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
                     macro_rules! atomic_store {
                         ($release:tt, $fence:tt) => {
                             asm!(
-                                // load from val to tmp
-                                concat!("ldr", $asm_suffix, " {tmp", $val_modifier, "}, [{val}]"),
-                                // (atomic) store tmp to dst
-                                concat!("st", $release, "r", $asm_suffix, " {tmp", $val_modifier, "}, [{dst}]"),
+                                // (atomic) store val to dst
+                                concat!("st", $release, "r", $asm_suffix, " {val", $val_modifier, "}, [{dst}]"),
                                 $fence,
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                val = in(reg) ptr_reg!(val),
-                                tmp = lateout(reg) _,
+                                dst = in(reg) ptr_reg!(dst),
+                                val = in(reg) val,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -156,25 +177,23 @@ macro_rules! atomic {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(dst, order);
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     #[cfg(any(target_feature = "lse", atomic_maybe_uninit_target_feature = "lse"))]
                     macro_rules! swap {
                         ($acquire:tt, $release:tt, $fence:tt) => {
                             asm!(
-                                // load from val to tmp
-                                concat!("ldr", $asm_suffix, " {tmp", $val_modifier, "}, [{val}]"),
                                 // (atomic) swap
                                 // Refs: https://developer.arm.com/documentation/dui0801/g/A64-Data-Transfer-Instructions/SWPA--SWPAL--SWP--SWPL--SWPAL--SWP--SWPL
-                                concat!("swp", $acquire, $release, $asm_suffix, " {tmp", $val_modifier, "}, {tmp", $val_modifier, "}, [{dst}]"),
+                                concat!("swp", $acquire, $release, $asm_suffix, " {val", $val_modifier, "}, {prev", $val_modifier, "}, [{dst}]"),
                                 $fence,
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp", $val_modifier, "}, [{out}]"),
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                val = in(reg) ptr_reg!(val),
-                                out = inout(reg) ptr_reg!(out) => _,
-                                tmp = lateout(reg) _,
+                                dst = in(reg) ptr_reg!(dst),
+                                val = in(reg) val,
+                                prev = lateout(reg) prev,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -183,30 +202,25 @@ macro_rules! atomic {
                     macro_rules! swap {
                         ($acquire:tt, $release:tt, $fence:tt) => {
                             asm!(
-                                // load from val to val_tmp
-                                concat!("ldr", $asm_suffix, " {val_tmp", $val_modifier, "}, [{val}]"),
                                 // (atomic) swap (LL/SC loop)
                                 "2:",
-                                    // load from dst to out_tmp
-                                    concat!("ld", $acquire, "xr", $asm_suffix, " {out_tmp", $val_modifier, "}, [{dst}]"),
+                                    // load from dst to prev
+                                    concat!("ld", $acquire, "xr", $asm_suffix, " {prev", $val_modifier, "}, [{dst}]"),
                                     // try to store val to dst
-                                    concat!("st", $release, "xr", $asm_suffix, " {r:w}, {val_tmp", $val_modifier, "}, [{dst}]"),
+                                    concat!("st", $release, "xr", $asm_suffix, " {r:w}, {val", $val_modifier, "}, [{dst}]"),
                                     // 0 if the store was successful, 1 if no store was performed
                                     "cbnz {r:w}, 2b",
                                 $fence,
-                                // store out_tmp to out
-                                concat!("str", $asm_suffix, " {out_tmp", $val_modifier, "}, [{out}]"),
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                val = in(reg) ptr_reg!(val),
-                                val_tmp = out(reg) _,
-                                out = inout(reg) ptr_reg!(out) => _,
-                                out_tmp = out(reg) _,
-                                r = lateout(reg) _,
+                                dst = in(reg) ptr_reg!(dst),
+                                val = in(reg) val,
+                                prev = out(reg) prev,
+                                r = out(reg) _,
                                 options(nostack, preserves_flags),
                             )
                         };
                     }
                     atomic_rmw!(swap, order);
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -225,35 +239,32 @@ macro_rules! atomic {
                 debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
                 let order = crate::utils::upgrade_success_ordering(success, failure);
+                let _tsan = super::tsan::Hooks::compare_exchange(dst, success, failure);
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let mut r: i32;
                     #[cfg(any(target_feature = "lse", atomic_maybe_uninit_target_feature = "lse"))]
                     macro_rules! cmpxchg {
                         ($acquire:tt, $release:tt, $fence:tt) => {{
                             asm!(
-                                // load from old/new to old_tmp/new_tmp
-                                concat!("ldr", $asm_suffix, " {old_tmp", $val_modifier, "}, [{old}]"),
-                                concat!("ldr", $asm_suffix, " {new_tmp", $val_modifier, "}, [{new}]"),
                                 // cas writes the current value to the first register,
                                 // so copy the `old`'s value for later comparison.
-                                concat!("mov {out_tmp", $val_modifier, "}, {old_tmp", $val_modifier, "}"),
+                                concat!("mov {prev", $val_modifier, "}, {old", $val_modifier, "}"),
                                 // (atomic) CAS
                                 // Refs: https://developer.arm.com/documentation/dui0801/g/A64-Data-Transfer-Instructions/CASA--CASAL--CAS--CASL--CASAL--CAS--CASL
-                                concat!("cas", $acquire, $release, $asm_suffix, " {out_tmp", $val_modifier, "}, {new_tmp", $val_modifier, "}, [{dst}]"),
+                                concat!("cas", $acquire, $release, $asm_suffix, " {prev", $val_modifier, "}, {new", $val_modifier, "}, [{dst}]"),
                                 $fence,
-                                concat!("cmp {out_tmp", $val_modifier, "}, {old_tmp", $val_modifier, "}"),
-                                // store out_tmp to out
-                                concat!("str", $asm_suffix, " {out_tmp", $val_modifier, "}, [{out}]"),
+                                // The upper bits of old are undefined, so compare only the lower bits
+                                // (prev is zero-extended by cas).
+                                concat!("cmp {prev", $val_modifier, "}, {old", $val_modifier, "}", $cmp_extend),
                                 "cset {r:w}, eq",
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                old = in(reg) ptr_reg!(old),
-                                old_tmp = out(reg) _,
-                                new = in(reg) ptr_reg!(new),
-                                new_tmp = out(reg) _,
-                                out = inout(reg) ptr_reg!(out) => _,
-                                out_tmp = out(reg) _,
+                                dst = in(reg) ptr_reg!(dst),
+                                old = in(reg) old,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
                                 r = lateout(reg) r,
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
@@ -266,15 +277,14 @@ macro_rules! atomic {
                     macro_rules! cmpxchg {
                         ($acquire:tt, $release:tt, $fence:tt) => {{
                             asm!(
-                                // load from old/new to old_tmp/new_tmp
-                                concat!("ldr", $asm_suffix, " {new_tmp", $val_modifier, "}, [{new}]"),
-                                concat!("ldr", $asm_suffix, " {old_tmp", $val_modifier, "}, [{old}]"),
                                 // (atomic) CAS (LL/SC loop)
                                 "2:",
-                                    concat!("ld", $acquire, "xr", $asm_suffix, " {out_tmp", $val_modifier, "}, [{dst}]"),
-                                    concat!("cmp {out_tmp", $val_modifier, "}, {old_tmp", $val_modifier, "}"),
+                                    concat!("ld", $acquire, "xr", $asm_suffix, " {prev", $val_modifier, "}, [{dst}]"),
+                                    // The upper bits of old are undefined, so compare only the lower bits
+                                    // (prev is zero-extended by ldxr).
+                                    concat!("cmp {prev", $val_modifier, "}, {old", $val_modifier, "}", $cmp_extend),
                                     "b.ne 3f", // jump if compare failed
-                                    concat!("st", $release, "xr", $asm_suffix, " {r:w}, {new_tmp", $val_modifier, "}, [{dst}]"),
+                                    concat!("st", $release, "xr", $asm_suffix, " {r:w}, {new", $val_modifier, "}, [{dst}]"),
                                     // 0 if the store was successful, 1 if no store was performed
                                     "cbnz {r:w}, 2b", // continue loop if store failed
                                     $fence,
@@ -283,16 +293,11 @@ macro_rules! atomic {
                                     "mov {r:w}, #1", // mark as failed
                                     "clrex",
                                 "4:",
-                                // store out_tmp to out
-                                concat!("str", $asm_suffix, " {out_tmp", $val_modifier, "}, [{out}]"),
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                old = in(reg) ptr_reg!(old),
-                                old_tmp = out(reg) _,
-                                new = in(reg) ptr_reg!(new),
-                                new_tmp = out(reg) _,
-                                out = inout(reg) ptr_reg!(out) => _,
-                                out_tmp = out(reg) _,
-                                r = lateout(reg) r,
+                                dst = in(reg) ptr_reg!(dst),
+                                old = in(reg) old,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
+                                r = out(reg) r,
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
                             );
@@ -301,7 +306,9 @@ macro_rules! atomic {
                             r == 0
                         }};
                     }
-                    atomic_rmw!(cmpxchg, order, write = success)
+                    let res = atomic_rmw!(cmpxchg, order, write = success);
+                    out.write(from_reg(prev));
+                    res
                 }
             }
             #[cfg(not(any(target_feature = "lse", atomic_maybe_uninit_target_feature = "lse")))]
@@ -319,21 +326,23 @@ macro_rules! atomic {
                 debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
                 let order = crate::utils::upgrade_success_ordering(success, failure);
+                let _tsan = super::tsan::Hooks::compare_exchange(dst, success, failure);
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let r: i32;
                     macro_rules! cmpxchg_weak {
                         ($acquire:tt, $release:tt, $fence:tt) => {
                             asm!(
-                                // load from old/new to old_tmp/new_tmp
-                                concat!("ldr", $asm_suffix, " {new_tmp", $val_modifier, "}, [{new}]"),
-                                concat!("ldr", $asm_suffix, " {old_tmp", $val_modifier, "}, [{old}]"),
                                 // (atomic) CAS
-                                concat!("ld", $acquire, "xr", $asm_suffix, " {out_tmp", $val_modifier, "}, [{dst}]"),
-                                concat!("cmp {out_tmp", $val_modifier, "}, {old_tmp", $val_modifier, "}"),
+                                concat!("ld", $acquire, "xr", $asm_suffix, " {prev", $val_modifier, "}, [{dst}]"),
+                                // The upper bits of old are undefined, so compare only the lower bits
+                                // (prev is zero-extended by ldxr).
+                                concat!("cmp {prev", $val_modifier, "}, {old", $val_modifier, "}", $cmp_extend),
                                 "b.ne 3f",
-                                concat!("st", $release, "xr", $asm_suffix, " {r:w}, {new_tmp", $val_modifier, "}, [{dst}]"),
+                                concat!("st", $release, "xr", $asm_suffix, " {r:w}, {new", $val_modifier, "}, [{dst}]"),
                                 // TODO: only emit when the above sc succeed
                                 // // 0 if the store was successful, 1 if no store was performed
                                 // "cbnz {r:w}, 4f",
@@ -343,22 +352,18 @@ macro_rules! atomic {
                                     "mov {r:w}, #1",
                                     "clrex",
                                 "4:",
-                                // store out_tmp to out
-                                concat!("str", $asm_suffix, " {out_tmp", $val_modifier, "}, [{out}]"),
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                old = in(reg) ptr_reg!(old),
-                                old_tmp = out(reg) _,
-                                new = in(reg) ptr_reg!(new),
-                                new_tmp = out(reg) _,
-                                out = inout(reg) ptr_reg!(out) => _,
-                                out_tmp = out(reg) _,
-                                r = lateout(reg) r,
+                                dst = in(reg) ptr_reg!(dst),
+                                old = in(reg) old,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
+                                r = out(reg) r,
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
                             )
                         };
                     }
                     atomic_rmw!(cmpxchg_weak, order, write = success);
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     // 0 if the store was successful, 1 if no store was performed
                     r == 0
@@ -368,22 +373,22 @@ macro_rules! atomic {
     };
 }
 
-atomic!(i8, "b", ":w");
-atomic!(u8, "b", ":w");
-atomic!(i16, "h", ":w");
-atomic!(u16, "h", ":w");
-atomic!(i32, "", ":w");
-atomic!(u32, "", ":w");
-atomic!(i64, "", "");
-atomic!(u64, "", "");
+atomic!(i8, "b", ":w", ", uxtb");
+atomic!(u8, "b", ":w", ", uxtb");
+atomic!(i16, "h", ":w", ", uxth");
+atomic!(u16, "h", ":w", ", uxth");
+atomic!(i32, "", ":w", "");
+atomic!(u32, "", ":w", "");
+atomic!(i64, "", "", "");
+atomic!(u64, "", "", "");
 #[cfg(target_pointer_width = "32")]
-atomic!(isize, "", ":w");
+atomic!(isize, "", ":w", "");
 #[cfg(target_pointer_width = "32")]
-atomic!(usize, "", ":w");
+atomic!(usize, "", ":w", "");
 #[cfg(target_pointer_width = "64")]
-atomic!(isize, "", "");
+atomic!(isize, "", "", "");
 #[cfg(target_pointer_width = "64")]
-atomic!(usize, "", "");
+atomic!(usize, "", "", "");
 
 // There are a few ways to implement 128-bit atomic operations in AArch64.
 //
@@ -424,7 +429,9 @@ macro_rules! atomic128 {
             ) {
                 debug_assert!(src as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(src, order);
 
+                let (prev_lo, prev_hi): (Reg<u64>, Reg<u64>);
                 #[cfg(any(target_feature = "lse2", atomic_maybe_uninit_target_feature = "lse2"))]
                 // SAFETY: the caller must guarantee that `dst` is valid for reads,
                 // 16-byte aligned, that there are no concurrent non-atomic operations.
@@ -433,15 +440,12 @@ macro_rules! atomic128 {
                     macro_rules! atomic_load_relaxed {
                         ($acquire:tt) => {
                             asm!(
-                                // (atomic) load from src to tmp pair
-                                "ldp {tmp_lo}, {tmp_hi}, [{src}]",
+                                // (atomic) load from src to prev pair
+                                select_le_or_be!("ldp {prev_lo}, {prev_hi}, [{src}]", "ldp {prev_hi}, {prev_lo}, [{src}]"),
                                 $acquire,
-                                // store tmp pair to out
-                                "stp {tmp_lo}, {tmp_hi}, [{out}]",
                                 src = in(reg) ptr_reg!(src),
-                                out = in(reg) ptr_reg!(out),
-                                tmp_hi = out(reg) _,
-                                tmp_lo = out(reg) _,
+                                prev_hi = lateout(reg) prev_hi,
+                                prev_lo = lateout(reg) prev_lo,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -453,14 +457,11 @@ macro_rules! atomic128 {
                             // SAFETY: cfg guarantee that the CPU supports FEAT_LRCPC3.
                             // Refs: https://developer.arm.com/documentation/ddi0602/2023-03/Base-Instructions/LDIAPP--Load-Acquire-RCpc-ordered-Pair-of-registers-
                             asm!(
-                                // (atomic) load from src to tmp pair
-                                "ldiapp {tmp_lo}, {tmp_hi}, [{src}]",
-                                // store tmp pair to out
-                                "stp {tmp_lo}, {tmp_hi}, [{out}]",
+                                // (atomic) load from src to prev pair
+                                select_le_or_be!("ldiapp {prev_lo}, {prev_hi}, [{src}]", "ldiapp {prev_hi}, {prev_lo}, [{src}]"),
                                 src = in(reg) ptr_reg!(src),
-                                out = in(reg) ptr_reg!(out),
-                                tmp_hi = out(reg) _,
-                                tmp_lo = out(reg) _,
+                                prev_hi = lateout(reg) prev_hi,
+                                prev_lo = lateout(reg) prev_lo,
                                 options(nostack, preserves_flags),
                             );
                         }
@@ -471,15 +472,12 @@ macro_rules! atomic128 {
                                 // ldar (or dmb ishld) is required to prevent reordering with preceding stlxp.
                                 // https://gcc.gnu.org/bugzilla/show_bug.cgi?id=108891
                                 "ldar {tmp}, [{src}]",
-                                // (atomic) load from src to tmp pair
-                                "ldp {tmp_lo}, {tmp_hi}, [{src}]",
+                                // (atomic) load from src to prev pair
+                                select_le_or_be!("ldp {prev_lo}, {prev_hi}, [{src}]", "ldp {prev_hi}, {prev_lo}, [{src}]"),
                                 "dmb ishld",
-                                // store tmp pair to out
-                                "stp {tmp_lo}, {tmp_hi}, [{out}]",
                                 src = in(reg) ptr_reg!(src),
-                                out = in(reg) ptr_reg!(out),
-                                tmp_hi = out(reg) _,
-                                tmp_lo = out(reg) _,
+                                prev_hi = lateout(reg) prev_hi,
+                                prev_lo = lateout(reg) prev_lo,
                                 tmp = out(reg) _,
                                 options(nostack, preserves_flags),
                             );
@@ -499,13 +497,10 @@ macro_rules! atomic128 {
                                 // - https://developer.arm.com/documentation/dui0801/g/A64-Data-Transfer-Instructions/CASPA--CASPAL--CASP--CASPL--CASPAL--CASP--CASPL
                                 // - https://github.com/taiki-e/portable-atomic/pull/20
                                 concat!("casp", $acquire, $release, " x2, x3, x2, x3, [{src}]"),
-                                // store out pair to out
-                                "stp x2, x3, [{out}]",
                                 src = in(reg) ptr_reg!(src),
-                                out = in(reg) ptr_reg!(out),
                                 // must be allocated to even/odd register pair
-                                inout("x2") 0_u64 => _, // out_lo
-                                inout("x3") 0_u64 => _, // out_lo
+                                inout("x2") 0_u64 => select_le_or_be!(prev_lo, prev_hi),
+                                inout("x3") 0_u64 => select_le_or_be!(prev_hi, prev_lo),
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -514,20 +509,17 @@ macro_rules! atomic128 {
                     macro_rules! atomic_load {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // (atomic) load from src to tmp pair
+                                // (atomic) load from src to prev pair
                                 "2:",
-                                    // load from src to tmp pair
-                                    concat!("ld", $acquire, "xp {tmp_lo}, {tmp_hi}, [{src}]"),
-                                    // store tmp pair to src
-                                    concat!("st", $release, "xp {r:w}, {tmp_lo}, {tmp_hi}, [{src}]"),
+                                    // load from src to prev pair
+                                    concat!("ld", $acquire, "xp ", select_le_or_be!("{prev_lo}, {prev_hi}", "{prev_hi}, {prev_lo}"), ", [{src}]"),
+                                    // store prev pair to src
+                                    concat!("st", $release, "xp {r:w}, ", select_le_or_be!("{prev_lo}, {prev_hi}", "{prev_hi}, {prev_lo}"), ", [{src}]"),
                                     // 0 if the store was successful, 1 if no store was performed
                                     "cbnz {r:w}, 2b",
-                                // store tmp pair to out
-                                "stp {tmp_lo}, {tmp_hi}, [{out}]",
                                 src = in(reg) ptr_reg!(src),
-                                out = in(reg) ptr_reg!(out),
-                                tmp_hi = out(reg) _,
-                                tmp_lo = out(reg) _,
+                                prev_hi = out(reg) prev_hi,
+                                prev_lo = out(reg) prev_lo,
                                 r = out(reg) _,
                                 options(nostack, preserves_flags),
                             )
@@ -540,6 +532,8 @@ macro_rules! atomic128 {
                         _ => unreachable!("{:?}", order),
                     }
                 }
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe { out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi })) }
             }
         }
         impl AtomicStore for $int_type {
@@ -551,7 +545,10 @@ macro_rules! atomic128 {
             ) {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(dst, order);
 
+                // SAFETY: the caller must uphold the safety contract.
+                let val = unsafe { load_pair::<Self, u64>(val) };
                 #[cfg(any(target_feature = "lse2", atomic_maybe_uninit_target_feature = "lse2"))]
                 // SAFETY: the caller must guarantee that `dst` is valid for writes,
                 // 16-byte aligned, that there are no concurrent non-atomic operations.
@@ -560,16 +557,13 @@ macro_rules! atomic128 {
                     macro_rules! atomic_store {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // load from val to val pair
-                                "ldp {val_lo}, {val_hi}, [{val}]",
                                 // (atomic) store val pair to dst
                                 $release,
-                                "stp {val_lo}, {val_hi}, [{dst}]",
+                                select_le_or_be!("stp {val_lo}, {val_hi}, [{dst}]", "stp {val_hi}, {val_lo}, [{dst}]"),
                                 $acquire,
                                 dst = in(reg) ptr_reg!(dst),
-                                val = in(reg) ptr_reg!(val),
-                                val_hi = out(reg) _,
-                                val_lo = out(reg) _,
+                                val_hi = in(reg) val.hi,
+                                val_lo = in(reg) val.lo,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -580,15 +574,12 @@ macro_rules! atomic128 {
                     macro_rules! atomic_store_swpp {
                         ($acquire:tt, $release:tt, $fence:tt) => {
                             asm!(
-                                // load from val to val pair
-                                "ldp {val_lo}, {val_hi}, [{val}]",
                                 // (atomic) swap
-                                concat!("swpp", $acquire, $release, " {val_lo}, {val_hi}, [{dst}]"),
+                                concat!("swpp", $acquire, $release, " ", select_le_or_be!("{val_lo}, {val_hi}", "{val_hi}, {val_lo}"), ", [{dst}]"),
                                 $fence,
                                 dst = in(reg) ptr_reg!(dst),
-                                val = in(reg) ptr_reg!(val),
-                                val_hi = out(reg) _,
-                                val_lo = out(reg) _,
+                                val_hi = inout(reg) val.hi => _,
+                                val_lo = inout(reg) val.lo => _,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -600,14 +591,11 @@ macro_rules! atomic128 {
                             // SAFETY: cfg guarantee that the CPU supports FEAT_LRCPC3.
                             // Refs: https://developer.arm.com/documentation/ddi0602/2023-03/Base-Instructions/STILP--Store-Release-ordered-Pair-of-registers-
                             asm!(
-                                // load from val to val pair
-                                "ldp {val_lo}, {val_hi}, [{val}]",
                                 // (atomic) store val pair to dst
-                                "stilp {val_lo}, {val_hi}, [{dst}]",
+                                select_le_or_be!("stilp {val_lo}, {val_hi}, [{dst}]", "stilp {val_hi}, {val_lo}, [{dst}]"),
                                 dst = in(reg) ptr_reg!(dst),
-                                val = in(reg) ptr_reg!(val),
-                                val_hi = out(reg) _,
-                                val_lo = out(reg) _,
+                                val_hi = in(reg) val.hi,
+                                val_lo = in(reg) val.lo,
                                 options(nostack, preserves_flags),
                             );
                         }
@@ -630,22 +618,19 @@ macro_rules! atomic128 {
                     macro_rules! store {
                         ($acquire:tt, $release:tt, $fence:tt) => {
                             asm!(
-                                // load from val to val pair
-                                "ldp {val_lo}, {val_hi}, [{val}]",
                                 // (atomic) store val pair to dst (LL/SC loop)
                                 "2:",
                                     // load from dst to xzr/tmp pair
                                     concat!("ld", $acquire, "xp xzr, {tmp}, [{dst}]"),
                                     // try to store val pair to dst
-                                    concat!("st", $release, "xp {tmp:w}, {val_lo}, {val_hi}, [{dst}]"),
+                                    concat!("st", $release, "xp {tmp:w}, ", select_le_or_be!("{val_lo}, {val_hi}", "{val_hi}, {val_lo}"), ", [{dst}]"),
                                     // 0 if the store was successful, 1 if no store was performed
                                     "cbnz {tmp:w}, 2b",
                                 $fence,
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                val = in(reg) ptr_reg!(val),
-                                val_hi = out(reg) _,
-                                val_lo = out(reg) _,
-                                tmp = lateout(reg) _,
+                                dst = in(reg) ptr_reg!(dst),
+                                val_hi = in(reg) val.hi,
+                                val_lo = in(reg) val.lo,
+                                tmp = out(reg) _,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -665,25 +650,22 @@ macro_rules! atomic128 {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(dst, order);
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_pair::<Self, u64>(val);
+                    let (prev_lo, prev_hi): (Reg<u64>, Reg<u64>);
                     #[cfg(any(target_feature = "lse128", atomic_maybe_uninit_target_feature = "lse128"))]
                     macro_rules! swap {
                         ($acquire:tt, $release:tt, $fence:tt) => {
                             asm!(
-                                // load from val to val pair
-                                "ldp {val_lo}, {val_hi}, [{val}]",
                                 // (atomic) swap
-                                concat!("swpp", $acquire, $release, " {val_lo}, {val_hi}, [{dst}]"),
+                                concat!("swpp", $acquire, $release, " ", select_le_or_be!("{val_lo}, {val_hi}", "{val_hi}, {val_lo}"), ", [{dst}]"),
                                 $fence,
-                                // store out pair to out
-                                "stp {val_lo}, {val_hi}, [{out}]",
                                 dst = in(reg) ptr_reg!(dst),
-                                val = in(reg) ptr_reg!(val),
-                                out = in(reg) ptr_reg!(out),
-                                val_hi = out(reg) _,
-                                val_lo = out(reg) _,
+                                val_hi = inout(reg) val.hi => prev_hi,
+                                val_lo = inout(reg) val.lo => prev_lo,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -692,32 +674,27 @@ macro_rules! atomic128 {
                     macro_rules! swap {
                         ($acquire:tt, $release:tt, $fence:tt) => {
                             asm!(
-                                // load from val to val pair
-                                "ldp {val_lo}, {val_hi}, [{val}]",
                                 // (atomic) swap (LL/SC loop)
                                 "2:",
-                                    // load from dst to out pair
-                                    concat!("ld", $acquire, "xp {out_lo}, {out_hi}, [{dst}]"),
+                                    // load from dst to prev pair
+                                    concat!("ld", $acquire, "xp ", select_le_or_be!("{prev_lo}, {prev_hi}", "{prev_hi}, {prev_lo}"), ", [{dst}]"),
                                     // try to store val pair to dst
-                                    concat!("st", $release, "xp {r:w}, {val_lo}, {val_hi}, [{dst}]"),
+                                    concat!("st", $release, "xp {r:w}, ", select_le_or_be!("{val_lo}, {val_hi}", "{val_hi}, {val_lo}"), ", [{dst}]"),
                                     // 0 if the store was successful, 1 if no store was performed
                                     "cbnz {r:w}, 2b",
                                 $fence,
-                                // store out pair to out
-                                "stp {out_lo}, {out_hi}, [{out}]",
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                val = in(reg) ptr_reg!(val),
-                                out = inout(reg) ptr_reg!(out) => _,
-                                val_hi = out(reg) _,
-                                val_lo = out(reg) _,
-                                out_hi = out(reg) _,
-                                out_lo = out(reg) _,
-                                r = lateout(reg) _,
+                                dst = in(reg) ptr_reg!(dst),
+                                val_hi = in(reg) val.hi,
+                                val_lo = in(reg) val.lo,
+                                prev_hi = out(reg) prev_hi,
+                                prev_lo = out(reg) prev_lo,
+                                r = out(reg) _,
                                 options(nostack, preserves_flags),
                             )
                         };
                     }
                     atomic_rmw!(swap, order);
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                 }
             }
         }
@@ -736,44 +713,38 @@ macro_rules! atomic128 {
                 debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
                 let order = crate::utils::upgrade_success_ordering(success, failure);
+                let _tsan = super::tsan::Hooks::compare_exchange(dst, success, failure);
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let old = load_pair::<Self, u64>(old);
+                    let new = load_pair::<Self, u64>(new);
+                    let (prev_lo, prev_hi): (Reg<u64>, Reg<u64>);
                     let mut r: i32;
                     #[cfg(any(target_feature = "lse", atomic_maybe_uninit_target_feature = "lse"))]
                     macro_rules! cmpxchg {
                         ($acquire:tt, $release:tt, $fence:tt) => {{
                             asm!(
-                                // load from old/new to old/new pairs
-                                "ldp {old_lo}, {old_hi}, [{old}]",
-                                "ldp x4, x5, [{new}]",
-                                // casp writes the current value to the first register pair,
-                                // so copy the `old`'s value for later comparison.
-                                "mov x8, {old_lo}",
-                                "mov x9, {old_hi}",
                                 // (atomic) CAS
                                 // Refs: https://developer.arm.com/documentation/dui0801/g/A64-Data-Transfer-Instructions/CASPA--CASPAL--CASP--CASPL--CASPAL--CASP--CASPL
                                 concat!("casp", $acquire, $release, " x8, x9, x4, x5, [{dst}]"),
                                 $fence,
-                                // compare old pair and out pair
-                                "cmp x8, {old_lo}",
-                                "ccmp x9, {old_hi}, #0, eq",
+                                // compare old pair and prev pair
+                                select_le_or_be!("cmp x8, {old_lo}", "cmp x8, {old_hi}"),
+                                select_le_or_be!("ccmp x9, {old_hi}, #0, eq", "ccmp x9, {old_lo}, #0, eq"),
                                 "cset {r:w}, eq",
-                                // store out pair to out
-                                "stp x8, x9, [{out}]",
                                 dst = in(reg) ptr_reg!(dst),
-                                old = in(reg) ptr_reg!(old),
-                                new = in(reg) ptr_reg!(new),
-                                out = inout(reg) ptr_reg!(out) => _,
-                                old_lo = out(reg) _,
-                                old_hi = out(reg) _,
+                                old_lo = in(reg) old.lo,
+                                old_hi = in(reg) old.hi,
                                 r = lateout(reg) r,
                                 // new pair - must be allocated to even/odd register pair
-                                out("x4") _, // new_lo
-                                out("x5") _, // new_hi
-                                // out pair - must be allocated to even/odd register pair
-                                out("x8") _, // out_lo
-                                out("x9") _, // out_hi
+                                in("x4") select_le_or_be!(new.lo, new.hi),
+                                in("x5") select_le_or_be!(new.hi, new.lo),
+                                // casp writes the current value to the first register pair,
+                                // so pass a copy of old for later comparison.
+                                // must be allocated to even/odd register pair
+                                inout("x8") select_le_or_be!(old.lo, old.hi) => select_le_or_be!(prev_lo, prev_hi),
+                                inout("x9") select_le_or_be!(old.hi, old.lo) => select_le_or_be!(prev_hi, prev_lo),
                                 // Do not use `preserves_flags` because CMP and CCMP modify the condition flags.
                                 options(nostack),
                             );
@@ -785,41 +756,33 @@ macro_rules! atomic128 {
                     macro_rules! cmpxchg {
                         ($acquire:tt, $release:tt, $fence:tt) => {{
                             asm!(
-                                // load from old/new to old/new pair
-                                "ldp {new_lo}, {new_hi}, [{new}]",
-                                "ldp {old_lo}, {old_hi}, [{old}]",
                                 // (atomic) CAS (LL/SC loop)
                                 "2:",
-                                    concat!("ld", $acquire, "xp {out_lo}, {out_hi}, [{dst}]"),
-                                    "cmp {out_lo}, {old_lo}",
+                                    concat!("ld", $acquire, "xp ", select_le_or_be!("{prev_lo}, {prev_hi}", "{prev_hi}, {prev_lo}"), ", [{dst}]"),
+                                    "cmp {prev_lo}, {old_lo}",
                                     "cset {r:w}, ne",
-                                    "cmp {out_hi}, {old_hi}",
+                                    "cmp {prev_hi}, {old_hi}",
                                     "cinc {r:w}, {r:w}, ne",
                                     "cbz {r:w}, 3f", // jump if compare succeed
-                                    concat!("st", $release, "xp {r:w}, {out_lo}, {out_hi}, [{dst}]"),
+                                    concat!("st", $release, "xp {r:w}, ", select_le_or_be!("{prev_lo}, {prev_hi}", "{prev_hi}, {prev_lo}"), ", [{dst}]"),
                                     // 0 if the store was successful, 1 if no store was performed
                                     "cbnz {r:w}, 2b", // continue loop if store failed
                                     "mov {r:w}, #1", // mark as failed
                                     "b 4f",
                                 "3:",
-                                    concat!("st", $release, "xp {r:w}, {new_lo}, {new_hi}, [{dst}]"),
+                                    concat!("st", $release, "xp {r:w}, ", select_le_or_be!("{new_lo}, {new_hi}", "{new_hi}, {new_lo}"), ", [{dst}]"),
                                     // 0 if the store was successful, 1 if no store was performed
                                     "cbnz {r:w}, 2b", // continue loop if store failed
                                 "4:",
                                 $fence,
-                                // store out_tmp to out
-                                "stp {out_lo}, {out_hi}, [{out}]",
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                old = in(reg) ptr_reg!(old),
-                                old_hi = out(reg) _,
-                                old_lo = out(reg) _,
-                                new = in(reg) ptr_reg!(new),
-                                new_hi = out(reg) _,
-                                new_lo = out(reg) _,
-                                out = inout(reg) ptr_reg!(out) => _,
-                                out_hi = out(reg) _,
-                                out_lo = out(reg) _,
-                                r = lateout(reg) r,
+                                dst = in(reg) ptr_reg!(dst),
+                                old_hi = in(reg) old.hi,
+                                old_lo = in(reg) old.lo,
+                                new_hi = in(reg) new.hi,
+                                new_lo = in(reg) new.lo,
+                                prev_hi = out(reg) prev_hi,
+                                prev_lo = out(reg) prev_lo,
+                                r = out(reg) r,
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
                             );
@@ -828,7 +791,9 @@ macro_rules! atomic128 {
                             r == 0
                         }};
                     }
-                    atomic_rmw!(cmpxchg, order, write = success)
+                    let res = atomic_rmw!(cmpxchg, order, write = success);
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
+                    res
                 }
             }
         }
@@ -838,6 +803,37 @@ macro_rules! atomic128 {
 atomic128!(i128);
 atomic128!(u128);
 
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{CompareExchange, CompareExchangeWeak, Load, Store, Swap};
+        let lse = cfg!(any(target_feature = "lse", atomic_maybe_uninit_target_feature = "lse"));
+        let lse2 = cfg!(any(target_feature = "lse2", atomic_maybe_uninit_target_feature = "lse2"));
+        let lse128 =
+            cfg!(any(target_feature = "lse128", atomic_maybe_uninit_target_feature = "lse128"));
+        let rcpc = cfg!(any(target_feature = "rcpc", atomic_maybe_uninit_target_feature = "rcpc"));
+        let rcpc3 =
+            cfg!(any(target_feature = "rcpc3", atomic_maybe_uninit_target_feature = "rcpc3"));
+        Strategy::new(match (size, op) {
+            (16, Load) if lse2 && rcpc3 => "LDP/LDIAPP (FEAT_LSE2, FEAT_LRCPC3)",
+            (16, Load) if lse2 => "LDP with DMB (FEAT_LSE2)",
+            (16, Load) if lse => "CASP (FEAT_LSE)",
+            (16, Store) if lse2 && lse128 => "STP/SWPP (FEAT_LSE2, FEAT_LSE128)",
+            (16, Store) if lse2 && rcpc3 => "STP/STILP with DMB (FEAT_LSE2, FEAT_LRCPC3)",
+            (16, Store) if lse2 => "STP with DMB (FEAT_LSE2)",
+            (16, Swap) if lse128 => "SWPP (FEAT_LSE128)",
+            (16, CompareExchange | CompareExchangeWeak) if lse => "CASP (FEAT_LSE)",
+            (16, _) => "LDXP/STXP loop",
+            (_, Load) if rcpc => "LDR/LDAPR (FEAT_LRCPC)",
+            (_, Load) => "LDR/LDAR",
+            (_, Store) => "STR/STLR",
+            (_, Swap) if lse => "SWP (FEAT_LSE)",
+            (_, CompareExchange | CompareExchangeWeak) if lse => "CAS (FEAT_LSE)",
+            (_, Swap | CompareExchange) => "LDXR/STXR loop",
+            (_, CompareExchangeWeak) => "LDXR/STXR",
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     test_atomic!(isize);
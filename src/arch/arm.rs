@@ -13,17 +13,47 @@
 // - armv6 https://godbolt.org/z/T5M337jYK
 // - armv6-m https://godbolt.org/z/q88qPah4W
 
+#[cfg(not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")))]
+#[path = "pair.rs"]
+mod pair;
+
 use core::{
     mem::{self, MaybeUninit},
     sync::atomic::Ordering,
 };
 
+#[cfg(not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")))]
+use self::pair::{from_pair, load_pair, Pair};
+use super::{from_reg, load_reg, Reg};
+#[cfg(not(all(
+    feature = "critical-section",
+    not(any(target_feature = "v7", atomic_maybe_uninit_target_feature = "v7")),
+    any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass"),
+)))]
+use crate::raw::AtomicStore;
 #[cfg(any(
     any(target_feature = "v7", atomic_maybe_uninit_target_feature = "v7"),
     not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")),
 ))]
 use crate::raw::{AtomicCompareExchange, AtomicSwap};
-use crate::raw::{AtomicLoad, AtomicStore};
+use crate::{info::Strategy, raw::AtomicLoad};
+
+// Register pair operands of LDREXD/STREXD are in memory order: the first
+// register is the lower half on little-endian and the upper half on big-endian.
+#[cfg(not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")))]
+#[cfg(target_endian = "little")]
+macro_rules! select_le_or_be {
+    ($le:expr, $be:expr) => {
+        $le
+    };
+}
+#[cfg(not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")))]
+#[cfg(target_endian = "big")]
+macro_rules! select_le_or_be {
+    ($le:expr, $be:expr) => {
+        $be
+    };
+}
 
 #[cfg(any(target_feature = "v7", atomic_maybe_uninit_target_feature = "v7"))]
 #[cfg(not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")))]
@@ -113,7 +143,8 @@ macro_rules! asm_use_dmb {
 }
 
 macro_rules! atomic {
-    ($int_type:ident, $asm_suffix:tt) => {
+    ($int_type:ident, $asm_suffix:tt, $cmp_extend:tt) => {
+        impl_load_reg!($int_type, reg, concat!("ldr", $asm_suffix, " {v}, [{src}]"));
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
@@ -126,18 +157,16 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let v: Reg<Self>;
                     macro_rules! atomic_load {
                         ($asm:ident, $acquire:expr) => {
                             $asm!(
                                 options(nostack, preserves_flags),
-                                // (atomic) load from src to tmp
-                                concat!("ldr", $asm_suffix, " {tmp}, [{src}]"),
+                                // (atomic) load from src to v
+                                concat!("ldr", $asm_suffix, " {v}, [{src}]"),
                                 $acquire, // acquire fence
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
                                 src = in(reg) src,
-                                out = inout(reg) out => _,
-                                tmp = lateout(reg) _,
+                                v = lateout(reg) v,
                             )
                         };
                     }
@@ -147,9 +176,16 @@ macro_rules! atomic {
                         Ordering::Acquire | Ordering::SeqCst => atomic_load!(asm_use_dmb, dmb!()),
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_reg(v));
                 }
             }
         }
+        // See atomic_single_core.
+        #[cfg(not(all(
+            feature = "critical-section",
+            not(any(target_feature = "v7", atomic_maybe_uninit_target_feature = "v7")),
+            any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass"),
+        )))]
         impl AtomicStore for $int_type {
             #[inline]
             unsafe fn atomic_store(
@@ -162,19 +198,17 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
                     macro_rules! atomic_store {
                         ($asm:ident, $acquire:expr, $release:expr) => {
                             $asm!(
                                 options(nostack, preserves_flags),
-                                // load from val to tmp
-                                concat!("ldr", $asm_suffix, " {tmp}, [{val}]"),
-                                // (atomic) store tmp to dst
+                                // (atomic) store val to dst
                                 $release, // release fence
-                                concat!("str", $asm_suffix, " {tmp}, [{dst}]"),
+                                concat!("str", $asm_suffix, " {val}, [{dst}]"),
                                 $acquire, // acquire fence
-                                dst = inout(reg) dst => _,
+                                dst = in(reg) dst,
                                 val = in(reg) val,
-                                tmp = lateout(reg) _,
                             )
                         };
                     }
@@ -205,31 +239,28 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     macro_rules! atomic_swap {
                         ($asm:ident, $acquire:expr, $release:expr) => {
                             $asm!(
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
-                                // load from val (ptr) to val (val)
-                                concat!("ldr", $asm_suffix, " {val}, [{val}]"),
                                 // (atomic) swap (LL/SC loop)
                                 $release, // release fence
                                 "2:",
-                                    // load from dst to tmp
-                                    concat!("ldrex", $asm_suffix, " {tmp}, [{dst}]"),
+                                    // load from dst to prev
+                                    concat!("ldrex", $asm_suffix, " {prev}, [{dst}]"),
                                     // try to store val to dst
                                     concat!("strex", $asm_suffix, " {r}, {val}, [{dst}]"),
                                     // 0 if the store was successful, 1 if no store was performed
                                     "cmp {r}, 0x0",
                                     "bne 2b",
                                 $acquire, // acquire fence
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
                                 dst = in(reg) dst,
-                                val = inout(reg) val => _,
-                                out = in(reg) out,
+                                val = in(reg) val,
                                 r = out(reg) _,
-                                tmp = out(reg) _,
+                                prev = out(reg) prev,
                             )
                         };
                     }
@@ -243,6 +274,7 @@ macro_rules! atomic {
                         }
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -269,19 +301,19 @@ macro_rules! atomic {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
                     use core::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release, SeqCst};
+                    let (old, new) = (load_reg(old), load_reg(new));
                     let mut r: i32;
+                    let prev: Reg<Self>;
                     macro_rules! cmpxchg_store_relaxed {
                         ($asm:ident, $acquire_success:expr, $acquire_failure:expr) => {
                             $asm!(
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
-                                // load from old/new (ptr) to old/new (val)
-                                concat!("ldr", $asm_suffix, " {old}, [{old}]"),
-                                concat!("ldr", $asm_suffix, " {new}, [{new}]"),
+                                $cmp_extend, // zero-extend old for comparison (ldrex{b,h} zero-extends)
                                 // (atomic) CAS (LL/SC loop)
                                 "2:",
-                                    concat!("ldrex", $asm_suffix, " {tmp}, [{dst}]"),
-                                    "cmp {tmp}, {old}",
+                                    concat!("ldrex", $asm_suffix, " {prev}, [{dst}]"),
+                                    "cmp {prev}, {old}",
                                     "bne 3f", // jump if compare failed
                                     concat!("strex", $asm_suffix, " {r}, {new}, [{dst}]"),
                                     // 0 if the store was successful, 1 if no store was performed
@@ -295,14 +327,11 @@ macro_rules! atomic {
                                     clrex!(),
                                     $acquire_failure,
                                 "4:",
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
                                 dst = in(reg) dst,
                                 r = out(reg) r,
                                 old = inout(reg) old => _,
-                                new = inout(reg) new => _,
-                                out = in(reg) out,
-                                tmp = out(reg) _,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
                             )
                         };
                     }
@@ -311,12 +340,10 @@ macro_rules! atomic {
                             asm_use_dmb!(
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
-                                // load from old/new (ptr) to old/new (val)
-                                concat!("ldr", $asm_suffix, " {old}, [{old}]"),
-                                concat!("ldr", $asm_suffix, " {new}, [{new}]"),
+                                $cmp_extend, // zero-extend old for comparison (ldrex{b,h} zero-extends)
                                 // (atomic) CAS (LL/SC loop)
-                                concat!("ldrex", $asm_suffix, " {tmp}, [{dst}]"),
-                                "cmp {tmp}, {old}",
+                                concat!("ldrex", $asm_suffix, " {prev}, [{dst}]"),
+                                "cmp {prev}, {old}",
                                 "bne 3f", // jump if compare failed
                                 dmb!(), // release
                                 "2:",
@@ -324,8 +351,8 @@ macro_rules! atomic {
                                     // 0 if the store was successful, 1 if no store was performed
                                     "cmp {r}, #0",
                                     "beq 4f", // jump if store succeed
-                                    concat!("ldrex", $asm_suffix, " {tmp}, [{dst}]"),
-                                    "cmp {tmp}, {old}",
+                                    concat!("ldrex", $asm_suffix, " {prev}, [{dst}]"),
+                                    "cmp {prev}, {old}",
                                     "beq 2b", // continue loop if compare succeed
                                 "3:",
                                     // compare failed, set r to 1
@@ -333,14 +360,11 @@ macro_rules! atomic {
                                     clrex!(),
                                     $acquire_failure,
                                 "4:",
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
                                 dst = in(reg) dst,
                                 r = out(reg) r,
                                 old = inout(reg) old => _,
-                                new = inout(reg) new => _,
-                                out = in(reg) out,
-                                tmp = out(reg) _,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
                             )
                         };
                     }
@@ -349,12 +373,10 @@ macro_rules! atomic {
                             asm_use_dmb!(
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
-                                // load from old/new (ptr) to old/new (val)
-                                concat!("ldr", $asm_suffix, " {old}, [{old}]"),
-                                concat!("ldr", $asm_suffix, " {new}, [{new}]"),
+                                $cmp_extend, // zero-extend old for comparison (ldrex{b,h} zero-extends)
                                 // (atomic) CAS (LL/SC loop)
-                                concat!("ldrex", $asm_suffix, " {tmp}, [{dst}]"),
-                                "cmp {tmp}, {old}",
+                                concat!("ldrex", $asm_suffix, " {prev}, [{dst}]"),
+                                "cmp {prev}, {old}",
                                 "bne 3f", // jump if compare failed
                                 dmb!(), // release
                                 "2:",
@@ -362,8 +384,8 @@ macro_rules! atomic {
                                     // 0 if the store was successful, 1 if no store was performed
                                     "cmp {r}, #0",
                                     "beq 4f", // jump if store succeed
-                                    concat!("ldrex", $asm_suffix, " {tmp}, [{dst}]"),
-                                    "cmp {tmp}, {old}",
+                                    concat!("ldrex", $asm_suffix, " {prev}, [{dst}]"),
+                                    "cmp {prev}, {old}",
                                     "beq 2b", // continue loop if compare succeed
                                 "3:",
                                     // compare failed, set r to 1
@@ -374,14 +396,11 @@ macro_rules! atomic {
                                 "4:", // store succeed
                                     dmb!(), // acquire_success
                                 "5:",
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
                                 dst = in(reg) dst,
                                 r = out(reg) r,
                                 old = inout(reg) old => _,
-                                new = inout(reg) new => _,
-                                out = in(reg) out,
-                                tmp = out(reg) _,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
                             )
                         };
                     }
@@ -401,6 +420,7 @@ macro_rules! atomic {
                         (AcqRel | SeqCst, _) => cmpxchg_acqrel!(dmb!()),
                         _ => unreachable!("{:?}, {:?}", success, failure),
                     }
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     // 0 if the store was successful, 1 if no store was performed
                     r == 0
@@ -423,17 +443,17 @@ macro_rules! atomic {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
                     use core::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release, SeqCst};
+                    let (old, new) = (load_reg(old), load_reg(new));
                     let mut r: i32;
+                    let prev: Reg<Self>;
                     macro_rules! cmpxchg_weak {
                         ($asm:ident, $acquire:expr, $release:expr) => {
                             $asm!(
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
-                                // load from old/new (ptr) to old/new (val)
-                                concat!("ldr", $asm_suffix, " {old}, [{old}]"),
-                                concat!("ldr", $asm_suffix, " {new}, [{new}]"),
-                                concat!("ldrex", $asm_suffix, " {tmp}, [{dst}]"),
-                                "cmp {tmp}, {old}",
+                                $cmp_extend, // zero-extend old for comparison (ldrex{b,h} zero-extends)
+                                concat!("ldrex", $asm_suffix, " {prev}, [{dst}]"),
+                                "cmp {prev}, {old}",
                                 "bne 3f", // jump if compare failed
                                 $release,
                                 concat!("strex", $asm_suffix, " {r}, {new}, [{dst}]"),
@@ -444,14 +464,11 @@ macro_rules! atomic {
                                     clrex!(),
                                 "4:",
                                 $acquire,
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
                                 dst = in(reg) dst,
                                 r = out(reg) r,
                                 old = inout(reg) old => _,
-                                new = inout(reg) new => _,
-                                out = in(reg) out,
-                                tmp = out(reg) _,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
                             )
                         };
                     }
@@ -460,11 +477,9 @@ macro_rules! atomic {
                             asm_use_dmb!(
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
-                                // load from old/new (ptr) to old/new (val)
-                                concat!("ldr", $asm_suffix, " {old}, [{old}]"),
-                                concat!("ldr", $asm_suffix, " {new}, [{new}]"),
-                                concat!("ldrex", $asm_suffix, " {tmp}, [{dst}]"),
-                                "cmp {tmp}, {old}",
+                                $cmp_extend, // zero-extend old for comparison (ldrex{b,h} zero-extends)
+                                concat!("ldrex", $asm_suffix, " {prev}, [{dst}]"),
+                                "cmp {prev}, {old}",
                                 "bne 3f", // jump if compare failed
                                 $release,
                                 concat!("strex", $asm_suffix, " {r}, {new}, [{dst}]"),
@@ -480,14 +495,11 @@ macro_rules! atomic {
                                 "4:", // store succeed
                                     dmb!(), // acquire_success
                                 "5:",
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
                                 dst = in(reg) dst,
                                 r = out(reg) r,
                                 old = inout(reg) old => _,
-                                new = inout(reg) new => _,
-                                out = in(reg) out,
-                                tmp = out(reg) _,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
                             )
                         };
                     }
@@ -496,11 +508,9 @@ macro_rules! atomic {
                             asm_use_dmb!(
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
-                                // load from old/new (ptr) to old/new (val)
-                                concat!("ldr", $asm_suffix, " {old}, [{old}]"),
-                                concat!("ldr", $asm_suffix, " {new}, [{new}]"),
-                                concat!("ldrex", $asm_suffix, " {tmp}, [{dst}]"),
-                                "cmp {tmp}, {old}",
+                                $cmp_extend, // zero-extend old for comparison (ldrex{b,h} zero-extends)
+                                concat!("ldrex", $asm_suffix, " {prev}, [{dst}]"),
+                                "cmp {prev}, {old}",
                                 "bne 3f", // jump if compare failed
                                 $release,
                                 concat!("strex", $asm_suffix, " {r}, {new}, [{dst}]"),
@@ -515,14 +525,11 @@ macro_rules! atomic {
                                 "4:", // compare or store failed
                                     dmb!(), // acquire_failure
                                 "5:",
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
                                 dst = in(reg) dst,
                                 r = out(reg) r,
                                 old = inout(reg) old => _,
-                                new = inout(reg) new => _,
-                                out = in(reg) out,
-                                tmp = out(reg) _,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
                             )
                         };
                     }
@@ -538,6 +545,7 @@ macro_rules! atomic {
                         (AcqRel | SeqCst, _) => cmpxchg_weak!(asm_use_dmb, dmb!(), dmb!()),
                         _ => unreachable!("{:?}, {:?}", success, failure),
                     }
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     // 0 if the store was successful, 1 if no store was performed
                     r == 0
@@ -547,14 +555,204 @@ macro_rules! atomic {
     };
 }
 
-atomic!(i8, "b");
-atomic!(u8, "b");
-atomic!(i16, "h");
-atomic!(u16, "h");
-atomic!(i32, "");
-atomic!(u32, "");
-atomic!(isize, "");
-atomic!(usize, "");
+atomic!(i8, "b", "uxtb {old}, {old}");
+atomic!(u8, "b", "uxtb {old}, {old}");
+atomic!(i16, "h", "uxth {old}, {old}");
+atomic!(u16, "h", "uxth {old}, {old}");
+atomic!(i32, "", "");
+atomic!(u32, "", "");
+atomic!(isize, "", "");
+atomic!(usize, "", "");
+
+// ARMv6-M doesn't have LDREX/STREX, so swap/CAS are implemented by disabling
+// interrupts when `--cfg atomic_maybe_uninit_unsafe_assume_single_core` is set.
+// This is only sound on single-core systems and requires privileged mode.
+//
+// When the `critical-section` feature is enabled, the critical section provided
+// by the critical-section crate is used instead (see cs.rs), and stores are
+// also routed through it.
+//
+// See also avr.rs and portable-atomic's interrupt module.
+#[cfg(feature = "critical-section")]
+#[cfg(not(any(target_feature = "v7", atomic_maybe_uninit_target_feature = "v7")))]
+#[cfg(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass"))]
+use super::cs::{disable, restore};
+#[cfg(atomic_maybe_uninit_unsafe_assume_single_core)]
+#[cfg(not(feature = "critical-section"))]
+#[cfg(not(any(target_feature = "v7", atomic_maybe_uninit_target_feature = "v7")))]
+#[cfg(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass"))]
+#[inline]
+fn disable() -> u32 {
+    let primask: u32;
+    // SAFETY: reading the priority mask register (PRIMASK) and disabling interrupts are safe.
+    unsafe {
+        // Do not use `nomem` and `readonly` because prevent subsequent memory accesses from being reordered before interrupts are disabled.
+        asm_no_dmb!(
+            options(nostack, preserves_flags),
+            "mrs {0}, PRIMASK",
+            "cpsid i",
+            out(reg) primask,
+        );
+    }
+    primask
+}
+#[cfg(atomic_maybe_uninit_unsafe_assume_single_core)]
+#[cfg(not(feature = "critical-section"))]
+#[cfg(not(any(target_feature = "v7", atomic_maybe_uninit_target_feature = "v7")))]
+#[cfg(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass"))]
+#[inline]
+unsafe fn restore(primask: u32) {
+    // SAFETY: the caller must guarantee that the state was retrieved by the previous `disable`,
+    unsafe {
+        // Do not use `nomem` and `readonly` because prevent preceding memory accesses from being reordered after interrupts are enabled.
+        asm_no_dmb!(options(nostack, preserves_flags), "msr PRIMASK, {0}", in(reg) primask,);
+    }
+}
+
+#[cfg(any(atomic_maybe_uninit_unsafe_assume_single_core, feature = "critical-section"))]
+#[cfg(not(any(target_feature = "v7", atomic_maybe_uninit_target_feature = "v7")))]
+#[cfg(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass"))]
+macro_rules! atomic_single_core {
+    ($int_type:ident, $asm_suffix:tt, $cmp_extend:tt) => {
+        #[cfg(feature = "critical-section")]
+        impl crate::arch::NotLockFree for $int_type {}
+        #[cfg(feature = "critical-section")]
+        impl AtomicStore for $int_type {
+            #[inline]
+            unsafe fn atomic_store(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the critical section guarantees exclusive access to dst.
+                unsafe {
+                    let val = load_reg(val);
+                    let s = disable();
+                    asm_no_dmb!(
+                        options(nostack, preserves_flags),
+                        // store val to dst
+                        concat!("str", $asm_suffix, " {val}, [{dst}]"),
+                        dst = in(reg) dst,
+                        val = in(reg) val,
+                    );
+                    restore(s);
+                }
+            }
+        }
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the critical section guarantees exclusive access to dst.
+                unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
+                    let s = disable();
+                    asm_no_dmb!(
+                        options(nostack, preserves_flags),
+                        // load from dst to prev
+                        concat!("ldr", $asm_suffix, " {prev}, [{dst}]"),
+                        // store val to dst
+                        concat!("str", $asm_suffix, " {val}, [{dst}]"),
+                        dst = in(reg) dst,
+                        val = in(reg) val,
+                        prev = out(reg) prev,
+                    );
+                    restore(s);
+                    out.write(from_reg(prev));
+                }
+            }
+        }
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the critical section guarantees exclusive access to dst.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let r: u32;
+                    let prev: Reg<Self>;
+                    let s = disable();
+                    asm_no_dmb!(
+                        // Do not use `preserves_flags` because MOVS and CMP modify the condition flags.
+                        options(nostack),
+                        $cmp_extend, // zero-extend old for comparison (ldr{b,h} zero-extends)
+                        // load from dst to prev
+                        concat!("ldr", $asm_suffix, " {prev}, [{dst}]"),
+                        "movs {r}, #0",
+                        "cmp {prev}, {old}",
+                        "bne 2f", // jump if compare failed
+                        // store new to dst
+                        concat!("str", $asm_suffix, " {new}, [{dst}]"),
+                        "movs {r}, #1",
+                        "2:",
+                        dst = in(reg) dst,
+                        old = inout(reg) old => _,
+                        new = in(reg) new,
+                        prev = out(reg) prev,
+                        r = out(reg) r,
+                    );
+                    restore(s);
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+        }
+    };
+}
+
+#[cfg(any(atomic_maybe_uninit_unsafe_assume_single_core, feature = "critical-section"))]
+#[cfg(not(any(target_feature = "v7", atomic_maybe_uninit_target_feature = "v7")))]
+#[cfg(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass"))]
+mod single_core {
+    use core::{
+        mem::{self, MaybeUninit},
+        sync::atomic::Ordering,
+    };
+
+    use super::{disable, restore};
+    #[cfg(feature = "critical-section")]
+    use crate::raw::AtomicStore;
+    use crate::{
+        arch::{from_reg, load_reg, Reg},
+        raw::{AtomicCompareExchange, AtomicSwap},
+    };
+
+    atomic_single_core!(i8, "b", "uxtb {old}, {old}");
+    atomic_single_core!(u8, "b", "uxtb {old}, {old}");
+    atomic_single_core!(i16, "h", "uxth {old}, {old}");
+    atomic_single_core!(u16, "h", "uxth {old}, {old}");
+    atomic_single_core!(i32, "", "");
+    atomic_single_core!(u32, "", "");
+    atomic_single_core!(isize, "", "");
+    atomic_single_core!(usize, "", "");
+}
 
 // Refs:
 // - https://developer.arm.com/documentation/ddi0406/cb/Application-Level-Architecture/Instruction-Details/Alphabetical-list-of-instructions/LDREXD
@@ -575,21 +773,19 @@ macro_rules! atomic64 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
                     macro_rules! atomic_load {
                         ($asm:ident, $acquire:expr) => {
                             $asm!(
                                 options(nostack, preserves_flags),
-                                // (atomic) load from src to tmp pair
+                                // (atomic) load from src to prev pair
                                 "ldrexd r2, r3, [{src}]",
                                 clrex!(),
                                 $acquire, // acquire fence
-                                // store tmp pair to out
-                                "strd r2, r3, [{out}]",
                                 src = in(reg) src,
-                                out = in(reg) out,
-                                // tmp pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
+                                // prev pair - must be even-numbered and not R14
+                                lateout("r2") select_le_or_be!(prev_lo, prev_hi),
+                                lateout("r3") select_le_or_be!(prev_hi, prev_lo),
                             )
                         };
                     }
@@ -599,6 +795,7 @@ macro_rules! atomic64 {
                         Ordering::Acquire | Ordering::SeqCst => atomic_load!(asm_use_dmb, dmb!()),
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                 }
             }
         }
@@ -615,13 +812,12 @@ macro_rules! atomic64 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_pair::<Self, u32>(val);
                     macro_rules! atomic_store {
                         ($asm:ident, $acquire:expr, $release:expr) => {
                             $asm!(
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
-                                // load from val to val pair
-                                "ldrd r2, r3, [{val}]",
                                 // (atomic) store val pair to dst (LL/SC loop)
                                 $release, // release fence
                                 "2:",
@@ -633,12 +829,11 @@ macro_rules! atomic64 {
                                     "cmp {r}, 0x0",
                                     "bne 2b",
                                 $acquire, // acquire fence
-                                dst = inout(reg) dst => _,
-                                val = in(reg) val,
-                                r = lateout(reg) _,
+                                dst = in(reg) dst,
+                                r = out(reg) _,
                                 // val pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
+                                in("r2") select_le_or_be!(val.lo, val.hi),
+                                in("r3") select_le_or_be!(val.hi, val.lo),
                                 // tmp pair - must be even-numbered and not R14
                                 out("r4") _,
                                 out("r5") _,
@@ -669,17 +864,17 @@ macro_rules! atomic64 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_pair::<Self, u32>(val);
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
                     macro_rules! atomic_swap {
                         ($asm:ident, $acquire:expr, $release:expr) => {
                             $asm!(
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
-                                // load from val to val pair
-                                "ldrd r2, r3, [{val}]",
                                 // (atomic) swap (LL/SC loop)
                                 $release, // release fence
                                 "2:",
-                                    // load from dst to out pair
+                                    // load from dst to prev pair
                                     "ldrexd r4, r5, [{dst}]",
                                     // try to store val pair to dst
                                     "strexd {r}, r2, r3, [{dst}]",
@@ -687,18 +882,14 @@ macro_rules! atomic64 {
                                     "cmp {r}, 0x0",
                                     "bne 2b",
                                 $acquire, // acquire fence
-                                // store out pair to out
-                                "strd r4, r5, [{out}]",
-                                dst = inout(reg) dst => _,
-                                val = in(reg) val,
-                                out = inout(reg) out => _,
-                                r = lateout(reg) _,
+                                dst = in(reg) dst,
+                                r = out(reg) _,
                                 // val pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
-                                // out pair - must be even-numbered and not R14
-                                out("r4") _,
-                                out("r5") _,
+                                in("r2") select_le_or_be!(val.lo, val.hi),
+                                in("r3") select_le_or_be!(val.hi, val.lo),
+                                // prev pair - must be even-numbered and not R14
+                                out("r4") select_le_or_be!(prev_lo, prev_hi),
+                                out("r5") select_le_or_be!(prev_hi, prev_lo),
                             )
                         };
                     }
@@ -710,6 +901,7 @@ macro_rules! atomic64 {
                         Ordering::AcqRel | Ordering::SeqCst => atomic_swap!(asm_use_dmb, dmb!(), dmb!()),
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                 }
             }
         }
@@ -732,14 +924,15 @@ macro_rules! atomic64 {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
                     use core::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release, SeqCst};
+                    let old = load_pair::<Self, u32>(old);
+                    let new = load_pair::<Self, u32>(new);
                     let mut r: i32;
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
                     macro_rules! cmpxchg_store_relaxed {
                         ($asm:ident, $acquire_success:expr, $acquire_failure:expr) => {
                             $asm!(
                                 // Do not use `preserves_flags` because CMP and ORRS modify the condition flags.
                                 options(nostack),
-                                "ldrd r2, r3, [{old}]",
-                                "ldrd r8, r9, [{new}]",
                                 // (atomic) CAS (LL/SC loop)
                                 "2:",
                                     "ldrexd r4, r5, [{dst}]",
@@ -759,23 +952,18 @@ macro_rules! atomic64 {
                                     clrex!(),
                                     $acquire_failure,
                                 "4:",
-                                // store out pair to out
-                                "strd r4, r5, [{out}]",
-                                dst = inout(reg) dst => _,
-                                r = lateout(reg) r,
-                                old = in(reg) old,
-                                new = in(reg) new,
-                                out = inout(reg) out => _,
+                                dst = in(reg) dst,
+                                r = out(reg) r,
                                 tmp = out(reg) _,
                                 // old pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
-                                // out pair - must be even-numbered and not R14
-                                out("r4") _,
-                                out("r5") _,
+                                in("r2") select_le_or_be!(old.lo, old.hi),
+                                in("r3") select_le_or_be!(old.hi, old.lo),
+                                // prev pair - must be even-numbered and not R14
+                                out("r4") select_le_or_be!(prev_lo, prev_hi),
+                                out("r5") select_le_or_be!(prev_hi, prev_lo),
                                 // new pair - must be even-numbered and not R14
-                                out("r8") _,
-                                out("r9") _,
+                                in("r8") select_le_or_be!(new.lo, new.hi),
+                                in("r9") select_le_or_be!(new.hi, new.lo),
                             )
                         };
                     }
@@ -784,8 +972,6 @@ macro_rules! atomic64 {
                             asm_use_dmb!(
                                 // Do not use `preserves_flags` because CMP and ORRS modify the condition flags.
                                 options(nostack),
-                                "ldrd r2, r3, [{old}]",
-                                "ldrd r8, r9, [{new}]",
                                 // (atomic) CAS (LL/SC loop)
                                 "ldrexd r4, r5, [{dst}]",
                                 "eor {tmp}, r5, r3",
@@ -809,23 +995,18 @@ macro_rules! atomic64 {
                                     clrex!(),
                                     $acquire_failure,
                                 "4:",
-                                // store out pair to out
-                                "strd r4, r5, [{out}]",
-                                dst = inout(reg) dst => _,
-                                r = lateout(reg) r,
-                                old = in(reg) old,
-                                new = in(reg) new,
-                                out = inout(reg) out => _,
+                                dst = in(reg) dst,
+                                r = out(reg) r,
                                 tmp = out(reg) _,
                                 // old pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
-                                // out pair - must be even-numbered and not R14
-                                out("r4") _,
-                                out("r5") _,
+                                in("r2") select_le_or_be!(old.lo, old.hi),
+                                in("r3") select_le_or_be!(old.hi, old.lo),
+                                // prev pair - must be even-numbered and not R14
+                                out("r4") select_le_or_be!(prev_lo, prev_hi),
+                                out("r5") select_le_or_be!(prev_hi, prev_lo),
                                 // new pair - must be even-numbered and not R14
-                                out("r8") _,
-                                out("r9") _,
+                                in("r8") select_le_or_be!(new.lo, new.hi),
+                                in("r9") select_le_or_be!(new.hi, new.lo),
                             )
                         };
                     }
@@ -834,8 +1015,6 @@ macro_rules! atomic64 {
                             asm_use_dmb!(
                                 // Do not use `preserves_flags` because CMP and ORRS modify the condition flags.
                                 options(nostack),
-                                "ldrd r2, r3, [{old}]",
-                                "ldrd r8, r9, [{new}]",
                                 // (atomic) CAS (LL/SC loop)
                                 "ldrexd r4, r5, [{dst}]",
                                 "eor {tmp}, r5, r3",
@@ -862,23 +1041,18 @@ macro_rules! atomic64 {
                                 "4:", // store succeed
                                     dmb!(), // acquire_success
                                 "5:",
-                                // store out pair to out
-                                "strd r4, r5, [{out}]",
-                                dst = inout(reg) dst => _,
-                                r = lateout(reg) r,
-                                old = in(reg) old,
-                                new = in(reg) new,
-                                out = inout(reg) out => _,
+                                dst = in(reg) dst,
+                                r = out(reg) r,
                                 tmp = out(reg) _,
                                 // old pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
-                                // out pair - must be even-numbered and not R14
-                                out("r4") _,
-                                out("r5") _,
+                                in("r2") select_le_or_be!(old.lo, old.hi),
+                                in("r3") select_le_or_be!(old.hi, old.lo),
+                                // prev pair - must be even-numbered and not R14
+                                out("r4") select_le_or_be!(prev_lo, prev_hi),
+                                out("r5") select_le_or_be!(prev_hi, prev_lo),
                                 // new pair - must be even-numbered and not R14
-                                out("r8") _,
-                                out("r9") _,
+                                in("r8") select_le_or_be!(new.lo, new.hi),
+                                in("r9") select_le_or_be!(new.hi, new.lo),
                             )
                         };
                     }
@@ -894,6 +1068,7 @@ macro_rules! atomic64 {
                         (AcqRel | SeqCst, _) => cmpxchg_acqrel!(dmb!()),
                         _ => unreachable!("{:?}, {:?}", success, failure),
                     }
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     // 0 if the store was successful, 1 if no store was performed
                     r == 0
@@ -916,14 +1091,15 @@ macro_rules! atomic64 {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
                     use core::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release, SeqCst};
+                    let old = load_pair::<Self, u32>(old);
+                    let new = load_pair::<Self, u32>(new);
                     let mut r: i32;
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
                     macro_rules! cmpxchg_weak {
                         ($asm:ident, $acquire:expr, $release:expr) => {
                             $asm!(
                                 // Do not use `preserves_flags` because ORRS modifies the condition flags.
                                 options(nostack),
-                                "ldrd r2, r3, [{old}]",
-                                "ldrd r8, r9, [{new}]",
                                 "ldrexd r4, r5, [{dst}]",
                                 "eor {tmp}, r5, r3",
                                 "eor {r}, r4, r2",
@@ -938,23 +1114,18 @@ macro_rules! atomic64 {
                                     clrex!(),
                                 "4:",
                                 $acquire,
-                                // store out pair to out
-                                "strd r4, r5, [{out}]",
-                                dst = inout(reg) dst => _,
-                                r = lateout(reg) r,
-                                old = in(reg) old,
-                                new = in(reg) new,
-                                out = inout(reg) out => _,
+                                dst = in(reg) dst,
+                                r = out(reg) r,
                                 tmp = out(reg) _,
                                 // old pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
-                                // out pair - must be even-numbered and not R14
-                                out("r4") _,
-                                out("r5") _,
+                                in("r2") select_le_or_be!(old.lo, old.hi),
+                                in("r3") select_le_or_be!(old.hi, old.lo),
+                                // prev pair - must be even-numbered and not R14
+                                out("r4") select_le_or_be!(prev_lo, prev_hi),
+                                out("r5") select_le_or_be!(prev_hi, prev_lo),
                                 // new pair - must be even-numbered and not R14
-                                out("r8") _,
-                                out("r9") _,
+                                in("r8") select_le_or_be!(new.lo, new.hi),
+                                in("r9") select_le_or_be!(new.hi, new.lo),
                             )
                         };
                     }
@@ -963,8 +1134,6 @@ macro_rules! atomic64 {
                             asm_use_dmb!(
                                 // Do not use `preserves_flags` because CMP and ORRS modify the condition flags.
                                 options(nostack),
-                                "ldrd r2, r3, [{old}]",
-                                "ldrd r8, r9, [{new}]",
                                 "ldrexd r4, r5, [{dst}]",
                                 "eor {tmp}, r5, r3",
                                 "eor {r}, r4, r2",
@@ -984,23 +1153,18 @@ macro_rules! atomic64 {
                                 "4:", // store succeed
                                     dmb!(), // acquire_success
                                 "5:",
-                                // store out pair to out
-                                "strd r4, r5, [{out}]",
-                                dst = inout(reg) dst => _,
-                                r = lateout(reg) r,
-                                old = in(reg) old,
-                                new = in(reg) new,
-                                out = inout(reg) out => _,
+                                dst = in(reg) dst,
+                                r = out(reg) r,
                                 tmp = out(reg) _,
                                 // old pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
-                                // out pair - must be even-numbered and not R14
-                                out("r4") _,
-                                out("r5") _,
+                                in("r2") select_le_or_be!(old.lo, old.hi),
+                                in("r3") select_le_or_be!(old.hi, old.lo),
+                                // prev pair - must be even-numbered and not R14
+                                out("r4") select_le_or_be!(prev_lo, prev_hi),
+                                out("r5") select_le_or_be!(prev_hi, prev_lo),
                                 // new pair - must be even-numbered and not R14
-                                out("r8") _,
-                                out("r9") _,
+                                in("r8") select_le_or_be!(new.lo, new.hi),
+                                in("r9") select_le_or_be!(new.hi, new.lo),
                             )
                         };
                     }
@@ -1009,8 +1173,6 @@ macro_rules! atomic64 {
                             asm_use_dmb!(
                                 // Do not use `preserves_flags` because CMP and ORRS modify the condition flags.
                                 options(nostack),
-                                "ldrd r2, r3, [{old}]",
-                                "ldrd r8, r9, [{new}]",
                                 "ldrexd r4, r5, [{dst}]",
                                 "eor {tmp}, r5, r3",
                                 "eor {r}, r4, r2",
@@ -1029,23 +1191,18 @@ macro_rules! atomic64 {
                                     "4:", // compare or store failed
                                     dmb!(), // acquire_failure
                                 "5:",
-                                // store out pair to out
-                                "strd r4, r5, [{out}]",
-                                dst = inout(reg) dst => _,
-                                r = lateout(reg) r,
-                                old = in(reg) old,
-                                new = in(reg) new,
-                                out = inout(reg) out => _,
+                                dst = in(reg) dst,
+                                r = out(reg) r,
                                 tmp = out(reg) _,
                                 // old pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
-                                // out pair - must be even-numbered and not R14
-                                out("r4") _,
-                                out("r5") _,
+                                in("r2") select_le_or_be!(old.lo, old.hi),
+                                in("r3") select_le_or_be!(old.hi, old.lo),
+                                // prev pair - must be even-numbered and not R14
+                                out("r4") select_le_or_be!(prev_lo, prev_hi),
+                                out("r5") select_le_or_be!(prev_hi, prev_lo),
                                 // new pair - must be even-numbered and not R14
-                                out("r8") _,
-                                out("r9") _,
+                                in("r8") select_le_or_be!(new.lo, new.hi),
+                                in("r9") select_le_or_be!(new.hi, new.lo),
                             )
                         };
                     }
@@ -1061,6 +1218,7 @@ macro_rules! atomic64 {
                         (AcqRel | SeqCst, _) => cmpxchg_weak!(asm_use_dmb, dmb!(), dmb!()),
                         _ => unreachable!("{:?}, {:?}", success, failure),
                     }
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     // 0 if the store was successful, 1 if no store was performed
                     r == 0
@@ -1073,6 +1231,49 @@ macro_rules! atomic64 {
 atomic64!(i64);
 atomic64!(u64);
 
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{CompareExchange, CompareExchangeWeak, Load, Store, Swap};
+        #[cfg(any(
+            target_feature = "v7",
+            atomic_maybe_uninit_target_feature = "v7",
+            target_feature = "mclass",
+            atomic_maybe_uninit_target_feature = "mclass",
+        ))]
+        let (load, store) = ("LDR with DMB", "STR with DMB");
+        #[cfg(not(any(
+            target_feature = "v7",
+            atomic_maybe_uninit_target_feature = "v7",
+            target_feature = "mclass",
+            atomic_maybe_uninit_target_feature = "mclass",
+        )))]
+        let (load, store) = ("LDR with CP15 barrier", "STR with CP15 barrier");
+        #[cfg(any(
+            any(target_feature = "v7", atomic_maybe_uninit_target_feature = "v7"),
+            not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")),
+        ))]
+        let (rmw, store) = (("LDREX/STREX loop", "LDREX/STREX"), store);
+        #[cfg(feature = "critical-section")]
+        #[cfg(not(any(target_feature = "v7", atomic_maybe_uninit_target_feature = "v7")))]
+        #[cfg(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass"))]
+        let (rmw, store) = (("critical section", "critical section"), "critical section");
+        #[cfg(not(feature = "critical-section"))]
+        #[cfg(not(any(target_feature = "v7", atomic_maybe_uninit_target_feature = "v7")))]
+        #[cfg(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass"))]
+        let (rmw, store) =
+            (("disabling interrupts (PRIMASK)", "disabling interrupts (PRIMASK)"), store);
+        Strategy::new(match (size, op) {
+            (8, Load) => "LDREXD",
+            (8, Store | Swap | CompareExchange) => "LDREXD/STREXD loop",
+            (8, CompareExchangeWeak) => "LDREXD/STREXD",
+            (_, Load) => load,
+            (_, Store) => store,
+            (_, Swap | CompareExchange) => rmw.0,
+            (_, CompareExchangeWeak) => rmw.1,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     test_atomic!(isize);
@@ -17,7 +17,11 @@ use core::{
     sync::atomic::Ordering,
 };
 
-use crate::raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap};
+use super::{from_reg, load_reg, Reg};
+use crate::{
+    info::Strategy,
+    raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap},
+};
 
 type XSize = usize;
 
@@ -52,6 +56,7 @@ macro_rules! blx {
 
 macro_rules! atomic_load_store {
     ($int_type:ident, $asm_suffix:tt) => {
+        impl_load_reg!($int_type, reg, concat!("ldr", $asm_suffix, " {v}, [{src}]"));
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
@@ -64,16 +69,14 @@ macro_rules! atomic_load_store {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let v: Reg<Self>;
                     match order {
                         Ordering::Relaxed => {
                             asm!(
-                                // (atomic) load from src to tmp
-                                concat!("ldr", $asm_suffix, " {tmp}, [{src}]"),
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
+                                // (atomic) load from src to v
+                                concat!("ldr", $asm_suffix, " {v}, [{src}]"),
                                 src = in(reg) src,
-                                out = inout(reg) out => _,
-                                tmp = lateout(reg) _,
+                                v = lateout(reg) v,
                                 options(nostack, preserves_flags),
                             );
                         }
@@ -81,14 +84,11 @@ macro_rules! atomic_load_store {
                         Ordering::Acquire | Ordering::SeqCst => {
                             debug_assert!(kuser_helper_version() >= 3);
                             asm!(
-                                // (atomic) load from src to tmp
-                                concat!("ldr", $asm_suffix, " {tmp}, [{src}]"),
+                                // (atomic) load from src to v
+                                concat!("ldr", $asm_suffix, " {v}, [{src}]"),
                                 blx!("{kuser_memory_barrier}"), // acquire fence
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
                                 src = in(reg) src,
-                                out = inout(reg) out => _,
-                                tmp = lateout(reg) _,
+                                v = lateout(reg) v,
                                 kuser_memory_barrier = inout(reg) KUSER_MEMORY_BARRIER => _,
                                 out("lr") _,
                                 options(nostack, preserves_flags),
@@ -96,6 +96,7 @@ macro_rules! atomic_load_store {
                         }
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_reg(v));
                 }
             }
         }
@@ -111,19 +112,17 @@ macro_rules! atomic_load_store {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
                     macro_rules! atomic_store_release {
                         ($acquire:expr) => {{
                             debug_assert!(kuser_helper_version() >= 3);
                             asm!(
-                                // load from val to tmp
-                                concat!("ldr", $asm_suffix, " {tmp}, [{val}]"),
-                                // (atomic) store tmp to dst
+                                // (atomic) store val to dst
                                 blx!("{kuser_memory_barrier}"), // release fence
-                                concat!("str", $asm_suffix, " {tmp}, [{dst}]"),
+                                concat!("str", $asm_suffix, " {val}, [{dst}]"),
                                 $acquire, // acquire fence
-                                dst = inout(reg) dst => _,
+                                dst = in(reg) dst,
                                 val = in(reg) val,
-                                tmp = lateout(reg) _,
                                 kuser_memory_barrier = inout(reg) KUSER_MEMORY_BARRIER => _,
                                 out("lr") _,
                                 options(nostack, preserves_flags),
@@ -133,13 +132,10 @@ macro_rules! atomic_load_store {
                     match order {
                         Ordering::Relaxed => {
                             asm!(
-                                // load from val to tmp
-                                concat!("ldr", $asm_suffix, " {tmp}, [{val}]"),
-                                // (atomic) store tmp to dst
-                                concat!("str", $asm_suffix, " {tmp}, [{dst}]"),
-                                dst = inout(reg) dst => _,
+                                // (atomic) store val to dst
+                                concat!("str", $asm_suffix, " {val}, [{dst}]"),
+                                dst = in(reg) dst,
                                 val = in(reg) val,
-                                tmp = lateout(reg) _,
                                 options(nostack, preserves_flags),
                             );
                         }
@@ -171,20 +167,18 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let prev: Reg<Self>;
                     asm!(
-                        "ldr r1, [r1]", // new_val
                         "2:",
                             "ldr r0, [r2]", // old_val
-                            "mov {out_tmp}, r0",
+                            "mov {prev}, r0",
                             blx!("{kuser_cmpxchg}"),
                             "cmp r0, #0",
                             "bne 2b",
-                        "str {out_tmp}, [{out}]",
-                        out = in(reg) out,
-                        out_tmp = out(reg) _,
+                        prev = out(reg) prev,
                         kuser_cmpxchg = in(reg) KUSER_CMPXCHG,
                         out("r0") _,
-                        inout("r1") val => _,
+                        inout("r1") load_reg(val) => _, // new_val
                         in("r2") dst, // ptr
                         out("r3") _,
                         out("ip") _,
@@ -192,6 +186,7 @@ macro_rules! atomic {
                         // Do not use `preserves_flags` because CMP and __kuser_cmpxchg modify the condition flags.
                         options(nostack),
                     );
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -214,12 +209,11 @@ macro_rules! atomic {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
                     let mut r: i32;
+                    let prev: Reg<Self>;
                     asm!(
-                        "ldr {old}, [{old}]",
-                        "ldr {new}, [{new}]",
                         "2:",
                             "ldr r0, [r2]", // old_val
-                            "mov {out_tmp}, r0",
+                            "mov {prev}, r0",
                             "cmp r0, {old}",
                             "bne 3f",
                             "mov r1, {new}", // new_val
@@ -235,11 +229,9 @@ macro_rules! atomic {
                             "bne 2b",
                             "mov r0, #1",
                         "4:",
-                        "str {out_tmp}, [{out}]",
-                        old = inout(reg) old => _,
-                        new = inout(reg) new => _,
-                        out = in(reg) out,
-                        out_tmp = out(reg) _,
+                        old = in(reg) load_reg(old),
+                        new = in(reg) load_reg(new),
+                        prev = out(reg) prev,
                         kuser_cmpxchg = in(reg) KUSER_CMPXCHG,
                         out("r0") r,
                         out("r1") _,
@@ -250,6 +242,7 @@ macro_rules! atomic {
                         // Do not use `preserves_flags` because CMP and __kuser_cmpxchg modify the condition flags.
                         options(nostack),
                     );
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     // 0 if the store was successful, 1 if no store was performed
                     r == 0
@@ -278,28 +271,26 @@ macro_rules! atomic_sub_word {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let prev: Reg<Self>;
                     asm!(
-                        concat!("ldr", $asm_suffix, " {val}, [{val}]"),
                         "lsl {mask}, {mask}, {shift}",
                         "lsl {val}, {val}, {shift}",
                         "and {val}, {val}, {mask}",
                         "mvn {inv_mask}, {mask}",
                         "2:",
                             "ldr r0, [r2]", // old_val
-                            "mov {out_tmp}, r0",
+                            "mov {prev}, r0",
                             "and r1, r0, {inv_mask}",
                             "orr r1, r1, {val}", // new_val
                             blx!("{kuser_cmpxchg}"),
                             "cmp r0, #0",
                             "bne 2b",
-                        "lsr {out_tmp}, {out_tmp}, {shift}",
-                        concat!("str", $asm_suffix, " {out_tmp}, [{out}]"),
-                        val = inout(reg) val => _,
-                        out = in(reg) out,
+                        "lsr {prev}, {prev}, {shift}",
+                        val = inout(reg) load_reg(val) => _,
                         shift = in(reg) shift,
                         mask = inout(reg) mask => _,
                         inv_mask = out(reg) _,
-                        out_tmp = out(reg) _,
+                        prev = out(reg) prev,
                         kuser_cmpxchg = in(reg) KUSER_CMPXCHG,
                         out("r0") _,
                         out("r1") _,
@@ -310,6 +301,7 @@ macro_rules! atomic_sub_word {
                         // Do not use `preserves_flags` because CMP and __kuser_cmpxchg modify the condition flags.
                         options(nostack),
                     );
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -333,9 +325,8 @@ macro_rules! atomic_sub_word {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
                     let mut r: i32;
+                    let prev: Reg<Self>;
                     asm!(
-                        concat!("ldr", $asm_suffix, " {old}, [{old}]"),
-                        concat!("ldr", $asm_suffix, " {new}, [{new}]"),
                         "lsl {mask}, {mask}, {shift}",
                         "lsl {old}, {old}, {shift}",
                         "lsl {new}, {new}, {shift}",
@@ -345,8 +336,8 @@ macro_rules! atomic_sub_word {
                         // "mvn {inv_mask}, {mask}",
                         "2:",
                             "ldr r0, [r2]", // old_val
-                            "and {out_tmp}, r0, {mask}",
-                            "cmp {out_tmp}, {old}",
+                            "and {prev}, r0, {mask}",
+                            "cmp {prev}, {old}",
                             "bne 3f",
                             "mvn r1, {mask}",
                             "and r1, r0, r1",
@@ -363,14 +354,12 @@ macro_rules! atomic_sub_word {
                             "bne 2b",
                             "mov r0, #1",
                         "4:",
-                        "lsr {out_tmp}, {out_tmp}, {shift}",
-                        concat!("str", $asm_suffix, " {out_tmp}, [{out}]"),
-                        old = inout(reg) old => _,
-                        new = inout(reg) new => _,
-                        out = in(reg) out,
+                        "lsr {prev}, {prev}, {shift}",
+                        old = inout(reg) load_reg(old) => _,
+                        new = inout(reg) load_reg(new) => _,
                         shift = in(reg) shift,
                         mask = inout(reg) mask => _,
-                        out_tmp = out(reg) _,
+                        prev = out(reg) prev,
                         kuser_cmpxchg = in(reg) KUSER_CMPXCHG,
                         out("r0") r,
                         out("r1") _,
@@ -381,6 +370,7 @@ macro_rules! atomic_sub_word {
                         // Do not use `preserves_flags` because CMP and __kuser_cmpxchg modify the condition flags.
                         options(nostack),
                     );
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     // 0 if the store was successful, 1 if no store was performed
                     r == 0
@@ -399,6 +389,8 @@ atomic!(u32);
 atomic!(isize);
 atomic!(usize);
 
+// __kuser_cmpxchg64 takes pointers to the old and new values, so 64-bit
+// values are passed to it through memory.
 macro_rules! atomic64 {
     ($int_type:ident) => {
         impl AtomicLoad for $int_type {
@@ -602,6 +594,19 @@ fn assert_has_kuser_cmpxchg64() {
     }
 }
 
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{Load, Store};
+        Strategy::new(match (size, op) {
+            (8, _) => "__kuser_cmpxchg64 loop (kernel user helper)",
+            (_, Load) => "LDR with __kuser_memory_barrier (kernel user helper)",
+            (_, Store) => "STR with __kuser_memory_barrier (kernel user helper)",
+            (1 | 2, _) => "masked __kuser_cmpxchg loop (kernel user helper)",
+            (_, _) => "__kuser_cmpxchg loop (kernel user helper)",
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -0,0 +1,440 @@
+// Pre-v6 ARM (except for Linux/Android)
+//
+// Pre-v6 ARM cores (ARMv4T and ARMv5TE, e.g., ARM7TDMI and ARM946E-S) don't
+// have memory barrier instructions, so load/store are plain LDR/STR (asm
+// without `nomem` works as a compiler fence).
+//
+// Swap uses SWP/SWPB in ARM mode (they are not available in Thumb mode, and
+// there is no halfword variant). Other swaps and CAS are implemented by
+// masking IRQ and FIQ in CPSR when `--cfg atomic_maybe_uninit_unsafe_assume_single_core`
+// is set (this requires ARM mode and privileged mode), or by using the
+// critical section when the `critical-section` feature is enabled (see cs.rs).
+// In the latter case, stores and all swaps are also routed through the
+// critical section, because systems with multiple pre-v6 cores sharing memory
+// (e.g., Nintendo DS) use it to synchronize between cores.
+//
+// Refs:
+// - ARM Architecture Reference Manual (ARMv5) https://developer.arm.com/documentation/ddi0100/latest
+// - portable-atomic https://github.com/taiki-e/portable-atomic
+//
+// See also arm_linux.rs for pre-v6 ARM Linux/Android.
+
+use core::{
+    arch::asm,
+    mem::{self, MaybeUninit},
+    sync::atomic::Ordering,
+};
+
+use super::{from_reg, load_reg, Reg};
+#[cfg(not(feature = "critical-section"))]
+use crate::raw::AtomicStore;
+use crate::{info::Strategy, raw::AtomicLoad};
+
+macro_rules! atomic {
+    ($int_type:ident, $asm_suffix:tt) => {
+        impl_load_reg!($int_type, reg, concat!("ldr", $asm_suffix, " {v}, [{src}]"));
+        impl AtomicLoad for $int_type {
+            #[inline]
+            unsafe fn atomic_load(
+                src: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(src as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let v: Reg<Self>;
+                    // atomic load is always SeqCst.
+                    asm!(
+                        // (atomic) load from src to v
+                        concat!("ldr", $asm_suffix, " {v}, [{src}]"),
+                        src = in(reg) src,
+                        v = lateout(reg) v,
+                        options(nostack, preserves_flags),
+                    );
+                    out.write(from_reg(v));
+                }
+            }
+        }
+        // See atomic_single_core.
+        #[cfg(not(feature = "critical-section"))]
+        impl AtomicStore for $int_type {
+            #[inline]
+            unsafe fn atomic_store(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    // atomic store is always SeqCst.
+                    asm!(
+                        // (atomic) store val to dst
+                        concat!("str", $asm_suffix, " {val}, [{dst}]"),
+                        dst = in(reg) dst,
+                        val = in(reg) load_reg(val),
+                        options(nostack, preserves_flags),
+                    );
+                }
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "critical-section"))]
+#[cfg(not(any(target_feature = "thumb-mode", atomic_maybe_uninit_target_feature = "thumb-mode")))]
+macro_rules! atomic_swp {
+    ($int_type:ident, $asm_suffix:tt) => {
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let prev: Reg<Self>;
+                    // atomic swap is always SeqCst.
+                    asm!(
+                        // (atomic) swap val and dst
+                        // Note: the destination register must be different from the
+                        // address register, so prev is not lateout.
+                        concat!("swp", $asm_suffix, " {prev}, {val}, [{dst}]"),
+                        dst = in(reg) dst,
+                        val = in(reg) load_reg(val),
+                        prev = out(reg) prev,
+                        options(nostack, preserves_flags),
+                    );
+                    out.write(from_reg(prev));
+                }
+            }
+        }
+    };
+}
+
+atomic!(i8, "b");
+atomic!(u8, "b");
+atomic!(i16, "h");
+atomic!(u16, "h");
+atomic!(i32, "");
+atomic!(u32, "");
+atomic!(isize, "");
+atomic!(usize, "");
+
+#[cfg(not(feature = "critical-section"))]
+#[cfg(not(any(target_feature = "thumb-mode", atomic_maybe_uninit_target_feature = "thumb-mode")))]
+mod swp {
+    use core::{
+        arch::asm,
+        mem::{self, MaybeUninit},
+        sync::atomic::Ordering,
+    };
+
+    use crate::{
+        arch::{from_reg, load_reg, Reg},
+        raw::AtomicSwap,
+    };
+
+    atomic_swp!(i8, "b");
+    atomic_swp!(u8, "b");
+    atomic_swp!(i32, "");
+    atomic_swp!(u32, "");
+    atomic_swp!(isize, "");
+    atomic_swp!(usize, "");
+}
+
+#[cfg(feature = "critical-section")]
+use super::cs::{disable, restore};
+// MRS/MSR are not available in Thumb mode on pre-v6 ARM.
+#[cfg(atomic_maybe_uninit_unsafe_assume_single_core)]
+#[cfg(not(feature = "critical-section"))]
+#[cfg(not(any(target_feature = "thumb-mode", atomic_maybe_uninit_target_feature = "thumb-mode")))]
+#[inline]
+fn disable() -> u32 {
+    let cpsr: u32;
+    // SAFETY: reading CPSR and disabling interrupts are safe.
+    unsafe {
+        // Do not use `nomem` and `readonly` because prevent subsequent memory accesses from being reordered before interrupts are disabled.
+        asm!(
+            "mrs {prev}, cpsr",
+            // set I (IRQ mask) and F (FIQ mask) bits
+            "orr {new}, {prev}, #0xC0",
+            "msr cpsr_c, {new}",
+            prev = out(reg) cpsr,
+            new = out(reg) _,
+            options(nostack, preserves_flags),
+        );
+    }
+    cpsr
+}
+#[cfg(atomic_maybe_uninit_unsafe_assume_single_core)]
+#[cfg(not(feature = "critical-section"))]
+#[cfg(not(any(target_feature = "thumb-mode", atomic_maybe_uninit_target_feature = "thumb-mode")))]
+#[inline]
+unsafe fn restore(cpsr: u32) {
+    // SAFETY: the caller must guarantee that the state was retrieved by the previous `disable`,
+    unsafe {
+        // This only writes the control field of CPSR (I/F/T bits and mode bits),
+        // and the condition flags are preserved.
+        //
+        // Do not use `nomem` and `readonly` because prevent preceding memory accesses from being reordered after interrupts are enabled.
+        asm!("msr cpsr_c, {0}", in(reg) cpsr, options(nostack, preserves_flags));
+    }
+}
+
+#[cfg(any(
+    feature = "critical-section",
+    all(
+        atomic_maybe_uninit_unsafe_assume_single_core,
+        not(any(
+            target_feature = "thumb-mode",
+            atomic_maybe_uninit_target_feature = "thumb-mode"
+        )),
+    ),
+))]
+macro_rules! atomic_single_core {
+    ($int_type:ident, $asm_suffix:tt, $cmp_extend:expr) => {
+        #[cfg(feature = "critical-section")]
+        impl crate::arch::NotLockFree for $int_type {}
+        #[cfg(feature = "critical-section")]
+        impl AtomicStore for $int_type {
+            #[inline]
+            unsafe fn atomic_store(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the critical section guarantees exclusive access to dst.
+                unsafe {
+                    let val = load_reg(val);
+                    let s = disable();
+                    asm!(
+                        // store val to dst
+                        concat!("str", $asm_suffix, " {val}, [{dst}]"),
+                        dst = in(reg) dst,
+                        val = in(reg) val,
+                        options(nostack, preserves_flags),
+                    );
+                    restore(s);
+                }
+            }
+        }
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the critical section guarantees exclusive access to dst.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let r: u32;
+                    let prev: Reg<Self>;
+                    let s = disable();
+                    asm!(
+                        $cmp_extend, // zero-extend old for comparison
+                        // load from dst to prev
+                        concat!("ldr", $asm_suffix, " {prev}, [{dst}]"),
+                        "movs {r}, #0",
+                        "cmp {prev}, {old}",
+                        "bne 2f", // jump if compare failed
+                        // store new to dst
+                        concat!("str", $asm_suffix, " {new}, [{dst}]"),
+                        "movs {r}, #1",
+                        "2:",
+                        dst = in(reg) dst,
+                        old = inout(reg) old => _,
+                        new = in(reg) new,
+                        prev = out(reg) prev,
+                        r = out(reg) r,
+                        // Do not use `preserves_flags` because MOVS, LSLS, LSRS, and CMP modify the condition flags.
+                        options(nostack),
+                    );
+                    restore(s);
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+        }
+    };
+}
+// Zero-extends sub-word old for comparison with the value loaded by LDRB/LDRH,
+// which zero-extends it. UXTB/UXTH are not available before ARMv6.
+#[cfg(any(
+    feature = "critical-section",
+    all(
+        atomic_maybe_uninit_unsafe_assume_single_core,
+        not(any(
+            target_feature = "thumb-mode",
+            atomic_maybe_uninit_target_feature = "thumb-mode"
+        )),
+    ),
+))]
+macro_rules! zero_extend {
+    ($shift:tt) => {
+        concat!("lsls {old}, {old}, #", $shift, "\n", "lsrs {old}, {old}, #", $shift)
+    };
+}
+// Swap for types that SWP/SWPB doesn't support.
+#[cfg(any(
+    feature = "critical-section",
+    all(
+        atomic_maybe_uninit_unsafe_assume_single_core,
+        not(any(
+            target_feature = "thumb-mode",
+            atomic_maybe_uninit_target_feature = "thumb-mode"
+        )),
+    ),
+))]
+macro_rules! atomic_swap_single_core {
+    ($int_type:ident, $asm_suffix:tt) => {
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the critical section guarantees exclusive access to dst.
+                unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
+                    let s = disable();
+                    asm!(
+                        // load from dst to prev
+                        concat!("ldr", $asm_suffix, " {prev}, [{dst}]"),
+                        // store val to dst
+                        concat!("str", $asm_suffix, " {val}, [{dst}]"),
+                        dst = in(reg) dst,
+                        val = in(reg) val,
+                        prev = out(reg) prev,
+                        options(nostack, preserves_flags),
+                    );
+                    restore(s);
+                    out.write(from_reg(prev));
+                }
+            }
+        }
+    };
+}
+
+#[cfg(any(
+    feature = "critical-section",
+    all(
+        atomic_maybe_uninit_unsafe_assume_single_core,
+        not(any(
+            target_feature = "thumb-mode",
+            atomic_maybe_uninit_target_feature = "thumb-mode"
+        )),
+    ),
+))]
+mod single_core {
+    use core::{
+        arch::asm,
+        mem::{self, MaybeUninit},
+        sync::atomic::Ordering,
+    };
+
+    use super::{disable, restore};
+    #[cfg(feature = "critical-section")]
+    use crate::raw::AtomicStore;
+    use crate::{
+        arch::{from_reg, load_reg, Reg},
+        raw::{AtomicCompareExchange, AtomicSwap},
+    };
+
+    atomic_single_core!(i8, "b", zero_extend!(24));
+    atomic_single_core!(u8, "b", zero_extend!(24));
+    atomic_single_core!(i16, "h", zero_extend!(16));
+    atomic_single_core!(u16, "h", zero_extend!(16));
+    atomic_single_core!(i32, "", "");
+    atomic_single_core!(u32, "", "");
+    atomic_single_core!(isize, "", "");
+    atomic_single_core!(usize, "", "");
+
+    atomic_swap_single_core!(i16, "h");
+    atomic_swap_single_core!(u16, "h");
+}
+
+// Swap for other types when SWP/SWPB are not used.
+#[cfg(feature = "critical-section")]
+mod single_core_swap {
+    use core::{
+        arch::asm,
+        mem::{self, MaybeUninit},
+        sync::atomic::Ordering,
+    };
+
+    use super::{disable, restore};
+    use crate::{
+        arch::{from_reg, load_reg, Reg},
+        raw::AtomicSwap,
+    };
+
+    atomic_swap_single_core!(i8, "b");
+    atomic_swap_single_core!(u8, "b");
+    atomic_swap_single_core!(i32, "");
+    atomic_swap_single_core!(u32, "");
+    atomic_swap_single_core!(isize, "");
+    atomic_swap_single_core!(usize, "");
+}
+
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{Load, Store, Swap};
+        #[cfg(feature = "critical-section")]
+        let (store, swap, rmw) = ("critical section", "critical section", "critical section");
+        #[cfg(not(feature = "critical-section"))]
+        let (store, swap, rmw) = ("STR", "SWP/SWPB", "disabling interrupts (CPSR.I/F)");
+        Strategy::new(match (size, op) {
+            (_, Load) => "LDR",
+            (_, Store) => store,
+            (1 | 4, Swap) => swap,
+            (_, _) => rmw,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    test_atomic_load_store!(isize);
+    test_atomic_load_store!(usize);
+    test_atomic_load_store!(i8);
+    test_atomic_load_store!(u8);
+    test_atomic_load_store!(i16);
+    test_atomic_load_store!(u16);
+    test_atomic_load_store!(i32);
+    test_atomic_load_store!(u32);
+}
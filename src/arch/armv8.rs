@@ -15,13 +15,23 @@
 // - armv8-m baseline https://godbolt.org/z/P51ezojjW
 // - armv8-m mainline https://godbolt.org/z/WdajnbYTr
 
+#[cfg(not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")))]
+#[path = "pair.rs"]
+mod pair;
+
 use core::{
     arch::asm,
     mem::{self, MaybeUninit},
     sync::atomic::Ordering,
 };
 
-use crate::raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap};
+#[cfg(not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")))]
+use self::pair::{from_pair, load_pair, Pair};
+use super::{from_reg, load_reg, Reg};
+use crate::{
+    info::Strategy,
+    raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap},
+};
 
 macro_rules! atomic_rmw {
     ($op:ident, $order:ident) => {
@@ -36,6 +46,23 @@ macro_rules! atomic_rmw {
     };
 }
 
+// Register pair operands of LDREXD/STREXD are in memory order: the first
+// register is the lower half on little-endian and the upper half on big-endian.
+#[cfg(not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")))]
+#[cfg(target_endian = "little")]
+macro_rules! select_le_or_be {
+    ($le:expr, $be:expr) => {
+        $le
+    };
+}
+#[cfg(not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")))]
+#[cfg(target_endian = "big")]
+macro_rules! select_le_or_be {
+    ($le:expr, $be:expr) => {
+        $be
+    };
+}
+
 // Adds S suffix if needed. We prefer instruction without S suffix,
 // but ARMv8-M Baseline doesn't support thumb2 instructions.
 #[cfg(not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")))]
@@ -52,7 +79,8 @@ macro_rules! s {
 }
 
 macro_rules! atomic {
-    ($int_type:ident, $asm_suffix:tt) => {
+    ($int_type:ident, $asm_suffix:tt, $cmp_extend:tt) => {
+        impl_load_reg!($int_type, reg, concat!("ldr", $asm_suffix, " {v}, [{src}]"));
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
@@ -65,16 +93,14 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let v: Reg<Self>;
                     macro_rules! atomic_load {
                         ($acquire:tt) => {
                             asm!(
-                                // (atomic) load from src to tmp
-                                concat!("ld", $acquire, $asm_suffix, " {tmp}, [{src}]"),
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
+                                // (atomic) load from src to v
+                                concat!("ld", $acquire, $asm_suffix, " {v}, [{src}]"),
                                 src = in(reg) src,
-                                out = inout(reg) out => _,
-                                tmp = lateout(reg) _,
+                                v = lateout(reg) v,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -85,6 +111,7 @@ macro_rules! atomic {
                         Ordering::Acquire | Ordering::SeqCst => atomic_load!("a"),
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_reg(v));
                 }
             }
         }
@@ -100,16 +127,14 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
                     macro_rules! atomic_store {
                         ($release:tt) => {
                             asm!(
-                                // load from val to tmp
-                                concat!("ldr", $asm_suffix, " {tmp}, [{val}]"),
-                                // (atomic) store tmp to dst
-                                concat!("st", $release, $asm_suffix, " {tmp}, [{dst}]"),
-                                dst = inout(reg) dst => _,
+                                // (atomic) store val to dst
+                                concat!("st", $release, $asm_suffix, " {val}, [{dst}]"),
+                                dst = in(reg) dst,
                                 val = in(reg) val,
-                                tmp = lateout(reg) _,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -137,33 +162,31 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     macro_rules! swap {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // load from val (ptr) to val (val)
-                                concat!("ldr", $asm_suffix, " {val}, [{val}]"),
                                 // (atomic) swap (LL/SC loop)
                                 "2:",
-                                    // load from dst to tmp
-                                    concat!("ld", $acquire, "ex", $asm_suffix, " {tmp}, [{dst}]"),
+                                    // load from dst to prev
+                                    concat!("ld", $acquire, "ex", $asm_suffix, " {prev}, [{dst}]"),
                                     // try to store val to dst
                                     concat!("st", $release, "ex", $asm_suffix, " {r}, {val}, [{dst}]"),
                                     // 0 if the store was successful, 1 if no store was performed
                                     "cmp {r}, 0x0",
                                     "bne 2b",
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
                                 dst = in(reg) dst,
-                                val = inout(reg) val => _,
-                                out = in(reg) out,
+                                val = in(reg) val,
                                 r = out(reg) _,
-                                tmp = out(reg) _,
+                                prev = out(reg) prev,
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
                             )
                         };
                     }
                     atomic_rmw!(swap, order);
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -185,18 +208,18 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
                     let mut r: i32;
+                    let prev: Reg<Self>;
                     macro_rules! cmpxchg {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // load from old/new (ptr) to old/new (val)
-                                concat!("ldr", $asm_suffix, " {old}, [{old}]"),
-                                concat!("ldr", $asm_suffix, " {new}, [{new}]"),
+                                $cmp_extend, // zero-extend old for comparison (ld{a}ex{b,h} zero-extends)
                                 // (atomic) CAS (LL/SC loop)
                                 "2:",
-                                    // load from dst to tmp
-                                    concat!("ld", $acquire, "ex", $asm_suffix, " {tmp}, [{dst}]"),
-                                    "cmp {tmp}, {old}",
+                                    // load from dst to prev
+                                    concat!("ld", $acquire, "ex", $asm_suffix, " {prev}, [{dst}]"),
+                                    "cmp {prev}, {old}",
                                     "bne 3f", // jump if compare failed
                                     // try to store val to dst
                                     concat!("st", $release, "ex", $asm_suffix, " {r}, {new}, [{dst}]"),
@@ -209,20 +232,18 @@ macro_rules! atomic {
                                     "clrex",
                                     s!("mov", "{r}, #1"),
                                 "4:",
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
                                 dst = in(reg) dst,
                                 old = inout(reg) old => _,
-                                new = inout(reg) new => _,
-                                out = in(reg) out,
+                                new = in(reg) new,
                                 r = out(reg) r,
-                                tmp = out(reg) _,
+                                prev = out(reg) prev,
                                 // Do not use `preserves_flags` because CMP and s! modify the condition flags.
                                 options(nostack),
                             )
                         };
                     }
                     atomic_rmw!(cmpxchg, order);
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     // 0 if the store was successful, 1 if no store was performed
                     r == 0
@@ -245,16 +266,16 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
                     let mut r: i32;
+                    let prev: Reg<Self>;
                     macro_rules! cmpxchg_weak {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // load from old/new (ptr) to old/new (val)
-                                concat!("ldr", $asm_suffix, " {old}, [{old}]"),
-                                concat!("ldr", $asm_suffix, " {new}, [{new}]"),
-                                // load from dst to tmp
-                                concat!("ld", $acquire, "ex", $asm_suffix, " {tmp}, [{dst}]"),
-                                "cmp {tmp}, {old}",
+                                $cmp_extend, // zero-extend old for comparison (ld{a}ex{b,h} zero-extends)
+                                // load from dst to prev
+                                concat!("ld", $acquire, "ex", $asm_suffix, " {prev}, [{dst}]"),
+                                "cmp {prev}, {old}",
                                 "bne 3f",
                                 // try to store new to dst
                                 concat!("st", $release, "ex", $asm_suffix, " {r}, {new}, [{dst}]"),
@@ -264,20 +285,18 @@ macro_rules! atomic {
                                     "clrex",
                                     s!("mov", "{r}, #1"),
                                 "4:",
-                                // store tmp to out
-                                concat!("str", $asm_suffix, " {tmp}, [{out}]"),
                                 dst = in(reg) dst,
                                 old = inout(reg) old => _,
-                                new = inout(reg) new => _,
-                                out = in(reg) out,
+                                new = in(reg) new,
                                 r = out(reg) r,
-                                tmp = out(reg) _,
+                                prev = out(reg) prev,
                                 // Do not use `preserves_flags` because CMP and s! modify the condition flags.
                                 options(nostack),
                             )
                         };
                     }
                     atomic_rmw!(cmpxchg_weak, order);
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     // 0 if the store was successful, 1 if no store was performed
                     r == 0
@@ -287,14 +306,14 @@ macro_rules! atomic {
     };
 }
 
-atomic!(i8, "b");
-atomic!(u8, "b");
-atomic!(i16, "h");
-atomic!(u16, "h");
-atomic!(i32, "");
-atomic!(u32, "");
-atomic!(isize, "");
-atomic!(usize, "");
+atomic!(i8, "b", "uxtb {old}, {old}");
+atomic!(u8, "b", "uxtb {old}, {old}");
+atomic!(i16, "h", "uxth {old}, {old}");
+atomic!(u16, "h", "uxth {old}, {old}");
+atomic!(i32, "", "");
+atomic!(u32, "", "");
+atomic!(isize, "", "");
+atomic!(usize, "", "");
 
 #[rustfmt::skip]
 macro_rules! atomic64 {
@@ -312,19 +331,17 @@ macro_rules! atomic64 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
                     macro_rules! atomic_load {
                         ($acquire:tt) => {
                             asm!(
-                                // (atomic) load from src to tmp pair
+                                // (atomic) load from src to prev pair
                                 concat!("ld", $acquire, "exd r2, r3, [{src}]"),
                                 "clrex",
-                                // store tmp pair to out
-                                "strd r2, r3, [{out}]",
                                 src = in(reg) src,
-                                out = in(reg) out,
-                                // tmp pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
+                                // prev pair - must be even-numbered and not R14
+                                lateout("r2") select_le_or_be!(prev_lo, prev_hi),
+                                lateout("r3") select_le_or_be!(prev_hi, prev_lo),
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -335,6 +352,7 @@ macro_rules! atomic64 {
                         Ordering::Acquire | Ordering::SeqCst => atomic_load!("a"),
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                 }
             }
         }
@@ -351,11 +369,10 @@ macro_rules! atomic64 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_pair::<Self, u32>(val);
                     macro_rules! store {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // load from val to val pair
-                                "ldrd r2, r3, [{val}]",
                                 // (atomic) store val pair to dst (LL/SC loop)
                                 "2:",
                                     // load from dst to tmp pair
@@ -365,12 +382,11 @@ macro_rules! atomic64 {
                                     // 0 if the store was successful, 1 if no store was performed
                                     "cmp {r}, 0x0",
                                     "bne 2b",
-                                dst = inout(reg) dst => _,
-                                val = in(reg) val,
-                                r = lateout(reg) _,
+                                dst = in(reg) dst,
+                                r = out(reg) _,
                                 // val pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
+                                in("r2") select_le_or_be!(val.lo, val.hi),
+                                in("r3") select_le_or_be!(val.hi, val.lo),
                                 // tmp pair - must be even-numbered and not R14
                                 out("r4") _,
                                 out("r5") _,
@@ -398,38 +414,35 @@ macro_rules! atomic64 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_pair::<Self, u32>(val);
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
                     macro_rules! swap {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // load from val to val pair
-                                "ldrd r2, r3, [{val}]",
                                 // (atomic) swap (LL/SC loop)
                                 "2:",
-                                    // load from dst to out pair
+                                    // load from dst to prev pair
                                     concat!("ld", $acquire, "exd r4, r5, [{dst}]"),
                                     // try to store val pair to dst
                                     concat!("st", $release, "exd {r}, r2, r3, [{dst}]"),
                                     // 0 if the store was successful, 1 if no store was performed
                                     "cmp {r}, 0x0",
                                     "bne 2b",
-                                // store out pair to out
-                                "strd r4, r5, [{out}]",
-                                dst = inout(reg) dst => _,
-                                val = in(reg) val,
-                                out = inout(reg) out => _,
-                                r = lateout(reg) _,
+                                dst = in(reg) dst,
+                                r = out(reg) _,
                                 // val pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
-                                // out pair - must be even-numbered and not R14
-                                out("r4") _,
-                                out("r5") _,
+                                in("r2") select_le_or_be!(val.lo, val.hi),
+                                in("r3") select_le_or_be!(val.hi, val.lo),
+                                // prev pair - must be even-numbered and not R14
+                                out("r4") select_le_or_be!(prev_lo, prev_hi),
+                                out("r5") select_le_or_be!(prev_hi, prev_lo),
                                 // Do not use `preserves_flags` because CMP modifies the condition flags.
                                 options(nostack),
                             )
                         };
                     }
                     atomic_rmw!(swap, order);
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                 }
             }
         }
@@ -452,12 +465,13 @@ macro_rules! atomic64 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let old = load_pair::<Self, u32>(old);
+                    let new = load_pair::<Self, u32>(new);
                     let mut r: i32;
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
                     macro_rules! cmpxchg {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                "ldrd r2, r3, [{old}]",
-                                "ldrd r8, r9, [{new}]",
                                 // (atomic) CAS (LL/SC loop)
                                 "2:",
                                     concat!("ld", $acquire, "exd r4, r5, [{dst}]"),
@@ -475,29 +489,25 @@ macro_rules! atomic64 {
                                     "clrex",
                                     s!("mov", "{r}, #1"),
                                 "4:",
-                                // store out pair to out
-                                "strd r4, r5, [{out}]",
-                                dst = inout(reg) dst => _,
-                                r = lateout(reg) r,
-                                old = in(reg) old,
-                                new = in(reg) new,
-                                out = inout(reg) out => _,
+                                dst = in(reg) dst,
+                                r = out(reg) r,
                                 tmp = out(reg) _,
                                 // old pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
-                                // out pair - must be even-numbered and not R14
-                                out("r4") _,
-                                out("r5") _,
+                                in("r2") select_le_or_be!(old.lo, old.hi),
+                                in("r3") select_le_or_be!(old.hi, old.lo),
+                                // prev pair - must be even-numbered and not R14
+                                out("r4") select_le_or_be!(prev_lo, prev_hi),
+                                out("r5") select_le_or_be!(prev_hi, prev_lo),
                                 // new pair - must be even-numbered and not R14
-                                out("r8") _,
-                                out("r9") _,
+                                in("r8") select_le_or_be!(new.lo, new.hi),
+                                in("r9") select_le_or_be!(new.hi, new.lo),
                                 // Do not use `preserves_flags` because CMP, ORRS, and s! modify the condition flags.
                                 options(nostack),
                             )
                         };
                     }
                     atomic_rmw!(cmpxchg, order);
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     // 0 if the store was successful, 1 if no store was performed
                     r == 0
@@ -520,12 +530,13 @@ macro_rules! atomic64 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let old = load_pair::<Self, u32>(old);
+                    let new = load_pair::<Self, u32>(new);
                     let mut r: i32;
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
                     macro_rules! cmpxchg_weak {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                "ldrd r2, r3, [{old}]",
-                                "ldrd r8, r9, [{new}]",
                                 concat!("ld", $acquire, "exd r4, r5, [{dst}]"),
                                 "eor {tmp}, r5, r3",
                                 "eor {r}, r4, r2",
@@ -538,29 +549,25 @@ macro_rules! atomic64 {
                                     "clrex",
                                     s!("mov", "{r}, #1"),
                                 "4:",
-                                // store out pair to out
-                                "strd r4, r5, [{out}]",
-                                dst = inout(reg) dst => _,
-                                r = lateout(reg) r,
-                                old = in(reg) old,
-                                new = in(reg) new,
-                                out = inout(reg) out => _,
+                                dst = in(reg) dst,
+                                r = out(reg) r,
                                 tmp = out(reg) _,
                                 // old pair - must be even-numbered and not R14
-                                out("r2") _,
-                                out("r3") _,
-                                // out pair - must be even-numbered and not R14
-                                out("r4") _,
-                                out("r5") _,
+                                in("r2") select_le_or_be!(old.lo, old.hi),
+                                in("r3") select_le_or_be!(old.hi, old.lo),
+                                // prev pair - must be even-numbered and not R14
+                                out("r4") select_le_or_be!(prev_lo, prev_hi),
+                                out("r5") select_le_or_be!(prev_hi, prev_lo),
                                 // new pair - must be even-numbered and not R14
-                                out("r8") _,
-                                out("r9") _,
+                                in("r8") select_le_or_be!(new.lo, new.hi),
+                                in("r9") select_le_or_be!(new.hi, new.lo),
                                 // Do not use `preserves_flags` because ORRS and s! modify the condition flags.
                                 options(nostack),
                             )
                         };
                     }
                     atomic_rmw!(cmpxchg_weak, order);
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     // 0 if the store was successful, 1 if no store was performed
                     r == 0
@@ -573,6 +580,23 @@ macro_rules! atomic64 {
 atomic64!(i64);
 atomic64!(u64);
 
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{CompareExchange, CompareExchangeWeak, Load, Store, Swap};
+        Strategy::new(match (size, op) {
+            (8, Load) => "LDREXD/LDAEXD",
+            (8, Store | Swap | CompareExchange) => {
+                "LDREXD/STREXD loop (with acquire/release variants)"
+            }
+            (8, CompareExchangeWeak) => "LDREXD/STREXD (with acquire/release variants)",
+            (_, Load) => "LDR/LDA",
+            (_, Store) => "STR/STL",
+            (_, Swap | CompareExchange) => "LDREX/STREX loop (with acquire/release variants)",
+            (_, CompareExchangeWeak) => "LDREX/STREX (with acquire/release variants)",
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     test_atomic!(isize);
@@ -3,11 +3,23 @@
 // Refs:
 // - portable-atomic https://github.com/taiki-e/portable-atomic
 
-use core::{arch::asm, mem::MaybeUninit, sync::atomic::Ordering};
+use core::{
+    arch::asm,
+    mem::{self, MaybeUninit},
+    sync::atomic::Ordering,
+};
 
-use crate::raw::{AtomicLoad, AtomicStore};
+// When the `critical-section` feature is enabled, the critical section provided
+// by the critical-section crate is used instead of disabling interrupts (see cs.rs).
+#[cfg(feature = "critical-section")]
+use super::cs::{disable, restore};
+use crate::{
+    info::Strategy,
+    raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap},
+};
 
 // See portable-atomic's interrupt module for more.
+#[cfg(not(feature = "critical-section"))]
 #[inline]
 fn disable() -> u8 {
     let sreg: u8;
@@ -24,11 +36,16 @@ fn disable() -> u8 {
     }
     sreg
 }
+#[cfg(not(feature = "critical-section"))]
 #[inline]
 unsafe fn restore(sreg: u8) {
     // SAFETY: the caller must guarantee that the state was retrieved by the previous `disable`,
     unsafe {
-        // This clobbers the entire status register. See msp430.rs to safety on this.
+        // This clobbers the entire status register, but we never explicitly modify
+        // flags within a critical session, and the only flags that may be changed
+        // within a critical session are the arithmetic flags that are changed as
+        // a side effect of arithmetic operations, etc., which LLVM recognizes,
+        // so it is safe to clobber them here.
         //
         // Do not use `nomem` and `readonly` because prevent preceding memory accesses from being reordered after interrupts are enabled.
         // Do not use `preserves_flags` because OUT modifies the status register (SREG).
@@ -36,8 +53,110 @@ unsafe fn restore(sreg: u8) {
     }
 }
 
+// Copies the value pointed to by `src` to `dst`.
+// Accesses to the atomic location in the critical section use this instead of
+// Rust reads/writes: the critical section serializes them with other accesses
+// at the hardware level, but they would still be data races in the Rust
+// abstract machine.
+#[inline]
+unsafe fn copy<T>(dst: *mut MaybeUninit<T>, src: *const MaybeUninit<T>) {
+    #[allow(clippy::cast_possible_truncation)]
+    let n = mem::size_of::<T>() as u8;
+    // SAFETY: the caller must guarantee that `src` is valid for reads and
+    // `dst` is valid for writes.
+    unsafe {
+        // Do not use `preserves_flags` because DEC modifies the status register (SREG).
+        asm!(
+            "2:",
+            "ld {t}, X+",
+            "st Z+, {t}",
+            "dec {n}",
+            // if n != 0, jump to 2
+            "brne 2b",
+            inout("X") src => _,
+            inout("Z") dst => _,
+            n = inout(reg) n => _,
+            t = out(reg) _,
+            options(nostack),
+        );
+    }
+}
+
+// Compares the values pointed to by `a` and `b` and returns true if they are equal.
+// The comparison is done in asm because the values may be uninitialized.
+#[inline]
+unsafe fn eq8(a: *const u8, b: *const u8) -> bool {
+    let r: u8;
+    // SAFETY: the caller must guarantee that `a` and `b` are valid for reads.
+    unsafe {
+        // Do not use `preserves_flags` because CP modifies the status register (SREG).
+        asm!(
+            "ld {a}, X",
+            "ld {b}, Z",
+            "clr {r}",
+            "cp {a}, {b}",
+            // if compare failed, jump to 2
+            "brne 2f",
+            "inc {r}",
+            "2:",
+            in("X") a,
+            in("Z") b,
+            a = out(reg) _,
+            b = out(reg) _,
+            r = out(reg) r,
+            options(nostack, readonly),
+        );
+    }
+    debug_assert!(r == 0 || r == 1, "r={}", r);
+    r != 0
+}
+#[inline]
+unsafe fn eq16(a: *const u8, b: *const u8) -> bool {
+    let r: u8;
+    // SAFETY: the caller must guarantee that `a` and `b` are valid for reads.
+    unsafe {
+        // Do not use `preserves_flags` because CP/CPC modifies the status register (SREG).
+        asm!(
+            "ld {a0}, X+",
+            "ld {b0}, Z+",
+            "ld {a1}, X",
+            "ld {b1}, Z",
+            "clr {r}",
+            "cp {a0}, {b0}",
+            "cpc {a1}, {b1}",
+            // if compare failed, jump to 2
+            "brne 2f",
+            "inc {r}",
+            "2:",
+            inout("X") a => _,
+            inout("Z") b => _,
+            a0 = out(reg) _,
+            b0 = out(reg) _,
+            a1 = out(reg) _,
+            b1 = out(reg) _,
+            r = out(reg) r,
+            options(nostack, readonly),
+        );
+    }
+    debug_assert!(r == 0 || r == 1, "r={}", r);
+    r != 0
+}
+
+#[inline]
+unsafe fn eq32(a: *const u8, b: *const u8) -> bool {
+    // SAFETY: the caller must guarantee that `a` and `b` are valid for reads.
+    unsafe { eq16(a, b) && eq16(a.add(2), b.add(2)) }
+}
+#[inline]
+unsafe fn eq64(a: *const u8, b: *const u8) -> bool {
+    // SAFETY: the caller must guarantee that `a` and `b` are valid for reads.
+    unsafe { eq32(a, b) && eq32(a.add(4), b.add(4)) }
+}
+
 macro_rules! atomic {
-    ($int_type:ident) => {
+    ($int_type:ident, $eq:ident) => {
+        #[cfg(feature = "critical-section")]
+        impl crate::arch::NotLockFree for $int_type {}
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
@@ -48,9 +167,8 @@ macro_rules! atomic {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
                     let s = disable();
-                    let v = src.read();
+                    copy(out, src);
                     restore(s);
-                    out.write(v);
                 }
             }
         }
@@ -65,20 +183,80 @@ macro_rules! atomic {
                 unsafe {
                     let v = val.read();
                     let s = disable();
-                    dst.write(v);
+                    copy(dst, &v);
                     restore(s);
                 }
             }
         }
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let v = val.read();
+                    let mut prev = MaybeUninit::<Self>::uninit();
+                    let s = disable();
+                    copy(&mut prev, dst);
+                    copy(dst, &v);
+                    restore(s);
+                    out.write(prev);
+                }
+            }
+        }
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let new = new.read();
+                    let mut prev = MaybeUninit::<Self>::uninit();
+                    let s = disable();
+                    copy(&mut prev, dst);
+                    let r = $eq(old.cast::<u8>(), (&prev as *const MaybeUninit<Self>).cast::<u8>());
+                    if r {
+                        copy(dst, &new);
+                    }
+                    restore(s);
+                    out.write(prev);
+                    r
+                }
+            }
+        }
     };
 }
 
-atomic!(i8);
-atomic!(u8);
-atomic!(i16);
-atomic!(u16);
-atomic!(isize);
-atomic!(usize);
+atomic!(i8, eq8);
+atomic!(u8, eq8);
+atomic!(i16, eq16);
+atomic!(u16, eq16);
+atomic!(isize, eq16);
+atomic!(usize, eq16);
+atomic!(i32, eq32);
+atomic!(u32, eq32);
+atomic!(i64, eq64);
+atomic!(u64, eq64);
+
+impl super::Describe for super::Native {
+    fn describe(_size: usize, _op: super::Op) -> Strategy {
+        #[cfg(feature = "critical-section")]
+        let description = "critical section";
+        #[cfg(not(feature = "critical-section"))]
+        let description = "disabling interrupts (SREG)";
+        Strategy::new(description)
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -88,4 +266,8 @@ mod tests {
     test_atomic!(u8);
     test_atomic!(i16);
     test_atomic!(u16);
+    test_atomic!(i32);
+    test_atomic!(u32);
+    test_atomic!(i64);
+    test_atomic!(u64);
 }
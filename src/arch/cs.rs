@@ -0,0 +1,140 @@
+// Critical section based on the critical-section crate.
+//
+// When the `critical-section` feature is enabled, this is used instead of
+// disabling interrupts to implement atomic operations that the native backend
+// lacks (swap/CAS on ARMv6-M, pre-v6 ARM, RISC-V without the A extension, and
+// x86 without CMPXCHG, and all operations that need a critical section on
+// MSP430 and AVR).
+//
+// Unlike disabling interrupts, this is sound on multi-core systems as long as
+// the critical-section implementation is (e.g., RP2040's hardware spinlock).
+// On multi-core systems, plain stores and native swaps can be interleaved with
+// operations in the critical section, so the ARMv6-M, pre-v6 ARM, RISC-V, and
+// x86 backends also route stores and all swaps through the critical section
+// when this is used. Loads are not affected because the critical section
+// writes `dst` by a single store instruction. (On AVR and MSP430, loads of
+// values wider than a single access also go through the critical section.)
+//
+// `dst` is always accessed by asm in the critical section, not by Rust
+// reads/writes, because the latter would be data races with accesses from
+// other threads in the Rust abstract machine.
+//
+// On x86_64, `--cfg atomic_maybe_uninit_test_critical_section` makes the x86
+// backend use the critical section for store/swap/CAS (as on i386), so that
+// the test suite can be run against this on the host. This cfg is only for
+// testing and is not public API.
+//
+// Refs:
+// - critical-section https://github.com/rust-embedded/critical-section
+// - portable-atomic https://github.com/taiki-e/portable-atomic
+
+pub(crate) use critical_section::RestoreState as State;
+
+// `disable` and `restore` are unused on targets where the native backend
+// provides all operations (unless the `fallback` feature is enabled).
+#[allow(dead_code)]
+#[inline]
+pub(crate) fn disable() -> State {
+    // SAFETY: the returned state is always passed to the subsequent `restore`.
+    unsafe { critical_section::acquire() }
+}
+#[allow(dead_code)]
+#[inline]
+pub(crate) unsafe fn restore(state: State) {
+    // SAFETY: the caller must guarantee that the state was retrieved by the previous `disable`,
+    // and critical sections are released in the reverse order of acquisition.
+    unsafe { critical_section::release(state) }
+}
+
+#[cfg(test)]
+#[allow(clippy::std_instead_of_core, clippy::undocumented_unsafe_blocks)]
+mod tests {
+    use std::{
+        cell::{Cell, UnsafeCell},
+        sync::atomic::{AtomicBool, Ordering},
+    };
+
+    use crossbeam_utils::thread;
+
+    use super::{disable, restore};
+
+    // A std-based critical-section implementation for testing on the host.
+    // Spin lock + thread-local nesting counter.
+    struct StdCriticalSection;
+    critical_section::set_impl!(StdCriticalSection);
+
+    static LOCKED: AtomicBool = AtomicBool::new(false);
+    std::thread_local! {
+        static DEPTH: Cell<usize> = const { Cell::new(0) };
+    }
+
+    // SAFETY: the lock is held until the outermost critical section is released.
+    unsafe impl critical_section::Impl for StdCriticalSection {
+        unsafe fn acquire() {
+            DEPTH.with(|depth| {
+                if depth.get() == 0 {
+                    while LOCKED
+                        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                        .is_err()
+                    {
+                        std::hint::spin_loop();
+                    }
+                }
+                depth.set(depth.get() + 1);
+            });
+        }
+        unsafe fn release((): ()) {
+            DEPTH.with(|depth| {
+                depth.set(depth.get() - 1);
+                if depth.get() == 0 {
+                    LOCKED.store(false, Ordering::Release);
+                }
+            });
+        }
+    }
+
+    struct Counter(UnsafeCell<u64>);
+    // SAFETY: all accesses are done in the critical section.
+    unsafe impl Sync for Counter {}
+
+    #[test]
+    fn mutual_exclusion() {
+        const THREADS: usize = 8;
+        const ITERATIONS: u64 = 10_000;
+        static COUNTER: Counter = Counter(UnsafeCell::new(0));
+        thread::scope(|s| {
+            for _ in 0..THREADS {
+                s.spawn(|_| {
+                    for _ in 0..ITERATIONS {
+                        let state = disable();
+                        // SAFETY: we are in the critical section.
+                        unsafe {
+                            let v = COUNTER.0.get().read_volatile();
+                            COUNTER.0.get().write_volatile(v + 1);
+                            restore(state);
+                        }
+                    }
+                });
+            }
+        })
+        .unwrap();
+        let state = disable();
+        // SAFETY: we are in the critical section.
+        unsafe {
+            assert_eq!(*COUNTER.0.get(), THREADS as u64 * ITERATIONS);
+            restore(state);
+        }
+    }
+
+    #[test]
+    fn nested() {
+        let outer = disable();
+        let inner = disable();
+        // SAFETY: states are restored in the reverse order.
+        unsafe {
+            restore(inner);
+            assert!(LOCKED.load(Ordering::Relaxed));
+            restore(outer);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+// User-provided backend.
+//
+// This is used on targets that are not supported natively, or when
+// `--cfg atomic_maybe_uninit_custom_backend` is passed.
+// Operations are delegated to the functions defined by the user (usually by
+// `set_backend!`). See the `backend` module for the protocol.
+//
+// Refs:
+// - critical-section https://github.com/rust-embedded/critical-section
+
+use core::{mem::MaybeUninit, sync::atomic::Ordering};
+
+use crate::{
+    info::Strategy,
+    raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap},
+};
+
+macro_rules! atomic {
+    ($int_type:ident, $backend_type:ident) => {
+        impl crate::arch::NotLockFree for $int_type {}
+        impl AtomicLoad for $int_type {
+            #[inline]
+            unsafe fn atomic_load(
+                src: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                extern "Rust" {
+                    #[link_name = concat!("_atomic_maybe_uninit_0_2_load_", stringify!($backend_type))]
+                    fn load(
+                        src: *const MaybeUninit<$backend_type>,
+                        out: *mut MaybeUninit<$backend_type>,
+                        order: Ordering,
+                    );
+                }
+                // SAFETY: the caller must uphold the safety contract, and
+                // the backend must implement it correctly.
+                unsafe { load(src.cast(), out.cast(), order) }
+            }
+        }
+        impl AtomicStore for $int_type {
+            #[inline]
+            unsafe fn atomic_store(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                extern "Rust" {
+                    #[link_name = concat!("_atomic_maybe_uninit_0_2_store_", stringify!($backend_type))]
+                    fn store(
+                        dst: *mut MaybeUninit<$backend_type>,
+                        val: *const MaybeUninit<$backend_type>,
+                        order: Ordering,
+                    );
+                }
+                // SAFETY: the caller must uphold the safety contract, and
+                // the backend must implement it correctly.
+                unsafe { store(dst.cast(), val.cast(), order) }
+            }
+        }
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                extern "Rust" {
+                    #[link_name = concat!("_atomic_maybe_uninit_0_2_swap_", stringify!($backend_type))]
+                    fn swap(
+                        dst: *mut MaybeUninit<$backend_type>,
+                        val: *const MaybeUninit<$backend_type>,
+                        out: *mut MaybeUninit<$backend_type>,
+                        order: Ordering,
+                    );
+                }
+                // SAFETY: the caller must uphold the safety contract, and
+                // the backend must implement it correctly.
+                unsafe { swap(dst.cast(), val.cast(), out.cast(), order) }
+            }
+        }
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                extern "Rust" {
+                    #[link_name = concat!(
+                        "_atomic_maybe_uninit_0_2_compare_exchange_",
+                        stringify!($backend_type)
+                    )]
+                    fn compare_exchange(
+                        dst: *mut MaybeUninit<$backend_type>,
+                        old: *const MaybeUninit<$backend_type>,
+                        new: *const MaybeUninit<$backend_type>,
+                        out: *mut MaybeUninit<$backend_type>,
+                        success: Ordering,
+                        failure: Ordering,
+                    ) -> bool;
+                }
+                // SAFETY: the caller must uphold the safety contract, and
+                // the backend must implement it correctly.
+                unsafe {
+                    compare_exchange(
+                        dst.cast(),
+                        old.cast(),
+                        new.cast(),
+                        out.cast(),
+                        success,
+                        failure,
+                    )
+                }
+            }
+            #[inline]
+            unsafe fn atomic_compare_exchange_weak(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                extern "Rust" {
+                    #[link_name = concat!(
+                        "_atomic_maybe_uninit_0_2_compare_exchange_weak_",
+                        stringify!($backend_type)
+                    )]
+                    fn compare_exchange_weak(
+                        dst: *mut MaybeUninit<$backend_type>,
+                        old: *const MaybeUninit<$backend_type>,
+                        new: *const MaybeUninit<$backend_type>,
+                        out: *mut MaybeUninit<$backend_type>,
+                        success: Ordering,
+                        failure: Ordering,
+                    ) -> bool;
+                }
+                // SAFETY: the caller must uphold the safety contract, and
+                // the backend must implement it correctly.
+                unsafe {
+                    compare_exchange_weak(
+                        dst.cast(),
+                        old.cast(),
+                        new.cast(),
+                        out.cast(),
+                        success,
+                        failure,
+                    )
+                }
+            }
+        }
+    };
+}
+
+atomic!(i8, u8);
+atomic!(u8, u8);
+atomic!(i16, u16);
+atomic!(u16, u16);
+atomic!(i32, u32);
+atomic!(u32, u32);
+atomic!(i64, u64);
+atomic!(u64, u64);
+atomic!(i128, u128);
+atomic!(u128, u128);
+#[cfg(target_pointer_width = "16")]
+atomic!(isize, u16);
+#[cfg(target_pointer_width = "16")]
+atomic!(usize, u16);
+#[cfg(target_pointer_width = "32")]
+atomic!(isize, u32);
+#[cfg(target_pointer_width = "32")]
+atomic!(usize, u32);
+#[cfg(target_pointer_width = "64")]
+atomic!(isize, u64);
+#[cfg(target_pointer_width = "64")]
+atomic!(usize, u64);
+
+impl super::Describe for super::Native {
+    fn describe(_size: usize, _op: super::Op) -> Strategy {
+        Strategy::new("user-provided backend")
+    }
+}
+
+#[cfg(test)]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[allow(clippy::std_instead_of_core, clippy::undocumented_unsafe_blocks)]
+mod tests {
+    use std::{
+        arch::asm,
+        mem::{self, MaybeUninit},
+        ptr,
+        sync::atomic::{AtomicBool, Ordering},
+    };
+
+    use crate::{backend::Backend, raw::Primitive};
+
+    // A backend that serializes all operations with a global lock.
+    struct TestBackend;
+    crate::set_backend!(TestBackend, u8, u16, u32, u64, u128);
+
+    static LOCKED: AtomicBool = AtomicBool::new(false);
+
+    struct Guard;
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            LOCKED.store(false, Ordering::Release);
+        }
+    }
+    fn lock() -> Guard {
+        while LOCKED
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            std::hint::spin_loop();
+        }
+        Guard
+    }
+
+    // Compares bytes in inline assembly, because comparing potentially
+    // uninitialized values in Rust is UB.
+    unsafe fn eq<T>(a: *const MaybeUninit<T>, b: *const MaybeUninit<T>) -> bool {
+        let r: u8;
+        unsafe {
+            asm!(
+                "repe cmpsb",
+                "sete {r}",
+                r = out(reg_byte) r,
+                inout("esi") a => _,
+                inout("edi") b => _,
+                inout("ecx") mem::size_of::<T>() => _,
+                options(nostack, readonly),
+            );
+        }
+        r != 0
+    }
+
+    unsafe impl<T: Primitive> Backend<T> for TestBackend {
+        unsafe fn atomic_load(src: *const MaybeUninit<T>, out: *mut MaybeUninit<T>, _: Ordering) {
+            let _guard = lock();
+            unsafe { ptr::copy_nonoverlapping(src, out, 1) }
+        }
+        unsafe fn atomic_store(dst: *mut MaybeUninit<T>, val: *const MaybeUninit<T>, _: Ordering) {
+            let _guard = lock();
+            unsafe { ptr::copy_nonoverlapping(val, dst, 1) }
+        }
+        unsafe fn atomic_swap(
+            dst: *mut MaybeUninit<T>,
+            val: *const MaybeUninit<T>,
+            out: *mut MaybeUninit<T>,
+            _: Ordering,
+        ) {
+            let _guard = lock();
+            unsafe {
+                ptr::copy_nonoverlapping(dst, out, 1);
+                ptr::copy_nonoverlapping(val, dst, 1);
+            }
+        }
+        unsafe fn atomic_compare_exchange(
+            dst: *mut MaybeUninit<T>,
+            current: *const MaybeUninit<T>,
+            new: *const MaybeUninit<T>,
+            out: *mut MaybeUninit<T>,
+            _: Ordering,
+            _: Ordering,
+        ) -> bool {
+            let _guard = lock();
+            unsafe {
+                ptr::copy_nonoverlapping(dst, out, 1);
+                let r = eq(dst, current);
+                if r {
+                    ptr::copy_nonoverlapping(new, dst, 1);
+                }
+                r
+            }
+        }
+    }
+
+    test_atomic!(isize);
+    test_atomic!(usize);
+    test_atomic!(i8);
+    test_atomic!(u8);
+    test_atomic!(i16);
+    test_atomic!(u16);
+    test_atomic!(i32);
+    test_atomic!(u32);
+    test_atomic!(i64);
+    test_atomic!(u64);
+    test_atomic!(i128);
+    test_atomic!(u128);
+    stress_test!(u8);
+    stress_test!(u16);
+    stress_test!(u32);
+    stress_test!(u64);
+    stress_test!(u128);
+}
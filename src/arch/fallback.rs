@@ -0,0 +1,291 @@
+// Lock-based fallback implementation for widths the native backend lacks.
+//
+// When the `fallback` feature is enabled, this implements 64-bit and 128-bit
+// atomics that the target doesn't support natively (e.g., 64-bit atomics on
+// riscv32 without Zacas and mips32, and 128-bit atomics on x86_64 without
+// CMPXCHG16B) by using a global table of spinlocks indexed by address
+// (striped locks). The spinlocks are built from 8-bit atomics, which are the
+// smallest native atomics on all targets.
+//
+// Operations implemented by this are NOT lock-free: a thread preempted while
+// holding a lock blocks other threads that access addresses mapped to the
+// same lock, and accessing these types from interrupt/signal handlers can
+// deadlock. When the `critical-section` feature is also enabled, the critical
+// section is used instead of the spinlock table.
+//
+// Comparing potentially uninitialized values in Rust is UB, so CAS compares
+// values by using native 32-bit CAS on each 32-bit chunk with the same
+// expected and desired values. (This never changes the value of the chunk.)
+//
+// Refs:
+// - portable-atomic https://github.com/taiki-e/portable-atomic/tree/HEAD/src/imp/fallback
+
+use core::{
+    mem::{self, MaybeUninit},
+    sync::atomic::Ordering,
+};
+
+#[cfg(not(feature = "critical-section"))]
+use crate::AtomicMaybeUninit;
+use crate::{
+    info::Strategy,
+    raw::{AtomicCompareExchange, Primitive},
+};
+
+// A prime number, so that addresses aligned to large powers of two are
+// distributed across all locks.
+#[cfg(not(feature = "critical-section"))]
+const LEN: usize = 67;
+
+// Avoid false sharing between locks on targets where memory is not scarce.
+#[cfg(not(feature = "critical-section"))]
+#[cfg_attr(target_pointer_width = "64", repr(align(64)))]
+struct Lock(AtomicMaybeUninit<u8>);
+
+#[cfg(not(feature = "critical-section"))]
+#[allow(clippy::declare_interior_mutable_const)] // only used to initialize LOCKS
+const UNLOCKED: Lock = Lock(AtomicMaybeUninit::<u8>::const_new(MaybeUninit::new(0)));
+#[cfg(not(feature = "critical-section"))]
+static LOCKS: [Lock; LEN] = [UNLOCKED; LEN];
+
+#[cfg(not(feature = "critical-section"))]
+struct Guard(&'static Lock);
+
+#[cfg(not(feature = "critical-section"))]
+impl Drop for Guard {
+    #[inline]
+    fn drop(&mut self) {
+        // Use SeqCst because operations on different addresses may use
+        // different locks, and SeqCst operations must still be totally ordered.
+        self.0 .0.store(MaybeUninit::new(0), Ordering::SeqCst);
+    }
+}
+
+// `lock`, `eq`, and `atomic!` are unused on targets where the native backend
+// provides all widths (there is no `imp` module below).
+#[cfg(not(feature = "critical-section"))]
+#[allow(dead_code)]
+#[inline]
+fn lock(addr: usize) -> Guard {
+    let lock = &LOCKS[(addr >> 3) % LEN];
+    // SAFETY: locks are always initialized.
+    while unsafe { lock.0.swap(MaybeUninit::new(1), Ordering::SeqCst).assume_init() } != 0 {
+        // SAFETY: locks are always initialized.
+        while unsafe { lock.0.load(Ordering::Relaxed).assume_init() } != 0 {
+            core::hint::spin_loop();
+        }
+    }
+    Guard(lock)
+}
+
+#[cfg(feature = "critical-section")]
+struct Guard(super::cs::State);
+
+#[cfg(feature = "critical-section")]
+impl Drop for Guard {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: the state was retrieved by `disable` in `lock`.
+        unsafe { super::cs::restore(self.0) }
+    }
+}
+
+#[cfg(feature = "critical-section")]
+#[allow(dead_code)]
+#[inline]
+fn lock(_addr: usize) -> Guard {
+    Guard(super::cs::disable())
+}
+
+// Returns `*a == *b`, without comparing potentially uninitialized values in Rust.
+//
+// Safety: the caller must hold the lock for `a`, `a` must be valid for reads
+// and writes and aligned to the size of `T`, and `b` must be valid for reads
+// and properly aligned.
+#[allow(dead_code)]
+#[inline]
+unsafe fn eq<T: Primitive>(a: *mut MaybeUninit<T>, b: *const MaybeUninit<T>) -> bool {
+    let a = a.cast::<MaybeUninit<u32>>();
+    let b = b.cast::<MaybeUninit<u32>>();
+    let mut out = MaybeUninit::<u32>::uninit();
+    for i in 0..mem::size_of::<T>() / mem::size_of::<u32>() {
+        // SAFETY: the caller must uphold the safety contract.
+        // Since the expected and desired values are the same, this never
+        // changes the value of `*a`.
+        let r = unsafe {
+            u32::atomic_compare_exchange(
+                a.add(i),
+                b.add(i),
+                b.add(i),
+                &mut out,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            )
+        };
+        if !r {
+            return false;
+        }
+    }
+    true
+}
+
+// See info.rs.
+pub(super) fn describe(_op: super::Op) -> Strategy {
+    #[cfg(not(feature = "critical-section"))]
+    let description = "spinlock table (fallback)";
+    #[cfg(feature = "critical-section")]
+    let description = "critical section (fallback)";
+    Strategy::new(description)
+}
+
+#[allow(unused_macros)]
+macro_rules! atomic {
+    ($int_type:ident) => {
+        impl crate::arch::NotLockFree for $int_type {}
+        impl crate::arch::Fallback for $int_type {}
+        impl AtomicLoad for $int_type {
+            #[inline]
+            unsafe fn atomic_load(
+                src: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(src as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the lock guarantees exclusive access to src.
+                unsafe {
+                    let guard = lock(src as usize);
+                    let v = src.read();
+                    drop(guard);
+                    out.write(v);
+                }
+            }
+        }
+        impl AtomicStore for $int_type {
+            #[inline]
+            unsafe fn atomic_store(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the lock guarantees exclusive access to dst.
+                unsafe {
+                    let v = val.read();
+                    let _guard = lock(dst as usize);
+                    dst.write(v);
+                }
+            }
+        }
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the lock guarantees exclusive access to dst.
+                unsafe {
+                    let v = val.read();
+                    let guard = lock(dst as usize);
+                    let prev = dst.read();
+                    dst.write(v);
+                    drop(guard);
+                    out.write(prev);
+                }
+            }
+        }
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the lock guarantees exclusive access to dst.
+                unsafe {
+                    let new = new.read();
+                    let guard = lock(dst as usize);
+                    let prev = dst.read();
+                    let r = eq(dst, old);
+                    if r {
+                        dst.write(new);
+                    }
+                    drop(guard);
+                    out.write(prev);
+                    r
+                }
+            }
+        }
+    };
+}
+
+// Implements the widths that the native backend lacks, under the same
+// conditions as the cfg_* macros (see cfgs.rs). Targets where 32-bit CAS is
+// not available natively (e.g., ARMv6-M without
+// `atomic_maybe_uninit_unsafe_assume_single_core` cfg or `critical-section`
+// feature) are not supported.
+cfg_native_cas_8_16_32! {
+    {
+        cfg_native_128! {
+            {}
+            {
+                mod imp {
+                    use core::{
+                        mem::{self, MaybeUninit},
+                        sync::atomic::Ordering,
+                    };
+
+                    use super::{eq, lock};
+                    use crate::raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap};
+
+                    cfg_native_64! {
+                        {}
+                        {
+                            atomic!(i64);
+                            atomic!(u64);
+                        }
+                    }
+                    atomic!(i128);
+                    atomic!(u128);
+
+                    #[cfg(test)]
+                    mod tests {
+                        cfg_native_64! {
+                            {}
+                            {
+                                test_atomic!(i64);
+                                test_atomic!(u64);
+                            }
+                        }
+                        test_atomic!(i128);
+                        test_atomic!(u128);
+                        #[cfg(target_arch = "x86_64")]
+                        stress_test!(u128);
+                    }
+                }
+            }
+        }
+    }
+    {}
+}
@@ -4,17 +4,25 @@
 // - Hexagon V5x Programmer's Reference Manual
 //   https://developer.qualcomm.com/download/hexagon/hexagon-v5x-programmers-reference-manual.pdf?referrer=node/6116
 
+#[path = "pair.rs"]
+mod pair;
 #[path = "partword.rs"]
 mod partword;
 
 use core::{arch::asm, mem::MaybeUninit, sync::atomic::Ordering};
 
-use crate::raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap};
+use self::pair::{from_pair, load_pair, Pair};
+use super::{from_reg, load_reg, Reg};
+use crate::{
+    info::Strategy,
+    raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap},
+};
 
 type XSize = usize;
 
 macro_rules! atomic_load_store {
     ($int_type:ident, $asm_suffix:tt, $asm_u_suffix:tt) => {
+        impl_load_reg!($int_type, reg, concat!("{v} = mem", $asm_u_suffix, $asm_suffix, "({src})"));
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
@@ -24,16 +32,15 @@ macro_rules! atomic_load_store {
             ) {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let v: Reg<Self>;
                     asm!(
-                        // (atomic) load from src to tmp
-                        concat!("{tmp} = mem", $asm_u_suffix, $asm_suffix, "({src})"),
-                        // store tmp to out
-                        concat!("mem", $asm_suffix, "({out}) = {tmp}"),
+                        // (atomic) load from src to v
+                        concat!("{v} = mem", $asm_u_suffix, $asm_suffix, "({src})"),
                         src = in(reg) src,
-                        out = inout(reg) out => _,
-                        tmp = lateout(reg) _,
+                        v = lateout(reg) v,
                         options(nostack, preserves_flags),
                     );
+                    out.write(from_reg(v));
                 }
             }
         }
@@ -46,14 +53,12 @@ macro_rules! atomic_load_store {
             ) {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
                     asm!(
-                        // load from val to tmp
-                        concat!("{tmp} = mem", $asm_u_suffix, $asm_suffix, "({val})"),
-                        // (atomic) store tmp to dst
-                        concat!("mem", $asm_suffix, "({dst}) = {tmp}"),
-                        dst = inout(reg) dst => _,
+                        // (atomic) store val to dst
+                        concat!("mem", $asm_suffix, "({dst}) = {val}"),
+                        dst = in(reg) dst,
                         val = in(reg) val,
-                        tmp = lateout(reg) _,
                         options(nostack, preserves_flags),
                     );
                 }
@@ -75,19 +80,19 @@ macro_rules! atomic {
             ) {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     asm!(
-                        "{val} = memw({val})",
                         "2:",
-                            "{tmp} = memw_locked({dst})",
+                            "{prev} = memw_locked({dst})",
                             "memw_locked({dst},p0) = {val}",
                             "if (!p0) jump 2b",
-                        "memw({out}) = {tmp}",
                         dst = in(reg) dst,
-                        val = inout(reg) val => _,
-                        out = in(reg) out,
-                        tmp = out(reg) _,
+                        val = in(reg) val,
+                        prev = out(reg) prev,
                         options(nostack),
                     );
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -103,13 +108,13 @@ macro_rules! atomic {
             ) -> bool {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let mut r: i32 = 1;
                     asm!(
-                        "{old} = memw({old})",
-                        "{new} = memw({new})",
                         "2:",
-                            "{tmp} = memw_locked({dst})",
-                            "{{ p0 = cmp.eq({tmp},{old})",
+                            "{prev} = memw_locked({dst})",
+                            "{{ p0 = cmp.eq({prev},{old})",
                                 "if (!p0.new) jump:nt 3f }}",
                             "memw_locked({dst},p0) = {new}",
                             "if (!p0) jump 2b",
@@ -117,15 +122,49 @@ macro_rules! atomic {
                         "3:",
                             "{r} = #0",
                         "4:",
-                        "memw({out}) = {tmp}",
                         dst = in(reg) dst,
-                        old = inout(reg) old => _,
-                        new = inout(reg) new => _,
-                        out = in(reg) out,
-                        tmp = out(reg) _,
+                        old = in(reg) old,
+                        new = in(reg) new,
+                        prev = out(reg) prev,
                         r = inout(reg) r,
                         options(nostack),
                     );
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+            #[inline(never)] // TODO: there is no way to mark p0 as clobbered
+            unsafe fn atomic_compare_exchange_weak(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let mut r: i32 = 1;
+                    asm!(
+                        "{prev} = memw_locked({dst})",
+                        "{{ p0 = cmp.eq({prev},{old})",
+                            "if (!p0.new) jump:nt 3f }}",
+                        "memw_locked({dst},p0) = {new}",
+                        "if (p0) jump 4f", // jump if store succeed
+                        "3:",
+                            "{r} = #0",
+                        "4:",
+                        dst = in(reg) dst,
+                        old = in(reg) old,
+                        new = in(reg) new,
+                        prev = out(reg) prev,
+                        r = inout(reg) r,
+                        options(nostack),
+                    );
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     r != 0
                 }
@@ -149,30 +188,30 @@ macro_rules! atomic_sub_word {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     asm!(
-                        concat!("{val} = mem", $asm_u_suffix, $asm_suffix, "({val})"),
                         "{mask} = asl({mask},{shift})",
                         "{val} = asl({val},{shift})",
                         "{val} = and({val},{mask})",
                         "{inv_mask} = not({mask})",
                         "2:",
-                            "{out_tmp} = memw_locked({dst})",
-                            "{tmp} = and({out_tmp},{inv_mask})",
+                            "{prev} = memw_locked({dst})",
+                            "{tmp} = and({prev},{inv_mask})",
                             "{tmp} = or({tmp},{val})",
                             "memw_locked({dst},p0) = {tmp}",
                             "if (!p0) jump 2b",
-                        "{out_tmp} = asr({out_tmp},{shift})",
-                        concat!("mem", $asm_suffix, "({out}) = {out_tmp}"),
+                        "{prev} = asr({prev},{shift})",
                         dst = in(reg) aligned_ptr,
                         val = inout(reg) val => _,
-                        out = in(reg) out,
                         shift = in(reg) shift,
                         mask = inout(reg) mask => _,
                         inv_mask = out(reg) _,
-                        out_tmp = out(reg) _,
+                        prev = out(reg) prev,
                         tmp = out(reg) _,
                         options(nostack),
                     );
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -190,10 +229,10 @@ macro_rules! atomic_sub_word {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let mut r: i32 = 1;
                     asm!(
-                        concat!("{old} = mem", $asm_u_suffix, $asm_suffix, "({old})"),
-                        concat!("{new} = mem", $asm_u_suffix, $asm_suffix, "({new})"),
                         "{mask} = asl({mask},{shift})",
                         "{old} = asl({old},{shift})",
                         "{new} = asl({new},{shift})",
@@ -202,8 +241,8 @@ macro_rules! atomic_sub_word {
                         "{inv_mask} = not({mask})",
                         "2:",
                             "{tmp} = memw_locked({dst})",
-                            "{out_tmp} = and({tmp},{mask})",
-                            "{{ p0 = cmp.eq({out_tmp},{old})",
+                            "{prev} = and({tmp},{mask})",
+                            "{{ p0 = cmp.eq({prev},{old})",
                                 "if (!p0.new) jump:nt 3f }}",
                             "{tmp} = and({tmp},{inv_mask})",
                             "{tmp} = or({tmp},{new})",
@@ -213,20 +252,70 @@ macro_rules! atomic_sub_word {
                         "3:",
                             "{r} = #0",
                         "4:",
-                        "{out_tmp} = asr({out_tmp},{shift})",
-                        concat!("mem", $asm_suffix, "({out}) = {out_tmp}"),
+                        "{prev} = asr({prev},{shift})",
+                        dst = in(reg) aligned_ptr,
+                        old = inout(reg) old => _,
+                        new = inout(reg) new => _,
+                        shift = in(reg) shift,
+                        mask = inout(reg) mask => _,
+                        inv_mask = out(reg) _,
+                        prev = out(reg) prev,
+                        tmp = out(reg) _,
+                        r = inout(reg) r,
+                        options(nostack),
+                    );
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+            #[inline(never)] // TODO: there is no way to mark p0 as clobbered
+            unsafe fn atomic_compare_exchange_weak(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                let (aligned_ptr, shift, mask) = partword::create_mask_values(dst);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let mut r: i32 = 1;
+                    asm!(
+                        "{mask} = asl({mask},{shift})",
+                        "{old} = asl({old},{shift})",
+                        "{new} = asl({new},{shift})",
+                        "{old} = and({old},{mask})",
+                        "{new} = and({new},{mask})",
+                        "{inv_mask} = not({mask})",
+                        "{tmp} = memw_locked({dst})",
+                        "{prev} = and({tmp},{mask})",
+                        "{{ p0 = cmp.eq({prev},{old})",
+                            "if (!p0.new) jump:nt 3f }}",
+                        "{tmp} = and({tmp},{inv_mask})",
+                        "{tmp} = or({tmp},{new})",
+                        "memw_locked({dst},p0) = {tmp}",
+                        "if (p0) jump 4f", // jump if store succeed
+                        "3:",
+                            "{r} = #0",
+                        "4:",
+                        "{prev} = asr({prev},{shift})",
                         dst = in(reg) aligned_ptr,
                         old = inout(reg) old => _,
                         new = inout(reg) new => _,
-                        out = in(reg) out,
                         shift = in(reg) shift,
                         mask = inout(reg) mask => _,
                         inv_mask = out(reg) _,
-                        out_tmp = out(reg) _,
+                        prev = out(reg) prev,
                         tmp = out(reg) _,
                         r = inout(reg) r,
                         options(nostack),
                     );
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     r != 0
                 }
@@ -255,17 +344,16 @@ macro_rules! atomic64 {
             ) {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (v_lo, v_hi): (Reg<u32>, Reg<u32>);
                     asm!(
-                        // (atomic) load from src to tmp pair
+                        // (atomic) load from src to v pair
                         "{{ r3:2 = memd({src}) }}",
-                        // store tmp pair to out
-                        "memd({out}) = r3:2",
                         src = in(reg) src,
-                        out = in(reg) out,
-                        out("r2") _, // tmp
-                        out("r3") _, // tmp
+                        out("r2") v_lo,
+                        out("r3") v_hi,
                         options(nostack, preserves_flags),
                     );
+                    out.write(from_pair(Pair { lo: v_lo, hi: v_hi }));
                 }
             }
         }
@@ -278,15 +366,13 @@ macro_rules! atomic64 {
             ) {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_pair::<Self, u32>(val);
                     asm!(
-                        // load from val to tmp pair
-                        "{{ r3:2 = memd({val}) }}",
-                        // (atomic) store tmp pair to dst
+                        // (atomic) store val pair to dst
                         "memd({dst}) = r3:2",
                         dst = in(reg) dst,
-                        val = in(reg) val,
-                        out("r2") _, // tmp
-                        out("r3") _, // tmp
+                        in("r2") val.lo,
+                        in("r3") val.hi,
                         options(nostack, preserves_flags),
                     );
                 }
@@ -302,22 +388,21 @@ macro_rules! atomic64 {
             ) {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_pair::<Self, u32>(val);
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
                     asm!(
-                        "{{ r3:2 = memd({val}) }}",
                         "2:",
                             "{{ r5:4 = memd_locked({dst}) }}",
                             "memd_locked({dst},p0) = r3:2",
                             "if (!p0) jump 2b",
-                        "memd({out}) = r5:4",
                         dst = in(reg) dst,
-                        val = in(reg) val,
-                        out = in(reg) out,
-                        out("r2") _, // val
-                        out("r3") _, // val
-                        out("r4") _, // tmp
-                        out("r5") _, // tmp
+                        in("r2") val.lo,
+                        in("r3") val.hi,
+                        out("r4") prev_lo,
+                        out("r5") prev_hi,
                         options(nostack),
                     );
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                 }
             }
         }
@@ -333,10 +418,11 @@ macro_rules! atomic64 {
             ) -> bool {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let old = load_pair::<Self, u32>(old);
+                    let new = load_pair::<Self, u32>(new);
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
                     let mut r: i32 = 1;
                     asm!(
-                        "{{ r3:2 = memd({old}) }}",
-                        "{{ r5:4 = memd({new}) }}",
                         "2:",
                             "{{ r7:6 = memd_locked({dst}) }}",
                             // TODO: merge two cmp?
@@ -350,20 +436,59 @@ macro_rules! atomic64 {
                         "3:",
                             "{r} = #0",
                         "4:",
-                        "memd({out}) = r7:6",
                         dst = in(reg) dst,
-                        old = in(reg) old,
-                        new = in(reg) new,
-                        out = in(reg) out,
-                        out("r2") _, // old
-                        out("r3") _, // old
-                        out("r4") _, // new
-                        out("r5") _, // new
-                        out("r6") _, // tmp
-                        out("r7") _, // tmp
+                        in("r2") old.lo,
+                        in("r3") old.hi,
+                        in("r4") new.lo,
+                        in("r5") new.hi,
+                        out("r6") prev_lo,
+                        out("r7") prev_hi,
                         r = inout(reg) r,
                         options(nostack),
                     );
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+            #[inline(never)] // TODO: there is no way to mark p0 as clobbered
+            unsafe fn atomic_compare_exchange_weak(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let old = load_pair::<Self, u32>(old);
+                    let new = load_pair::<Self, u32>(new);
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
+                    let mut r: i32 = 1;
+                    asm!(
+                        "{{ r7:6 = memd_locked({dst}) }}",
+                        // TODO: merge two cmp?
+                        "{{ p0 = cmp.eq(r6,r2)",
+                            "if (!p0.new) jump:nt 3f }}",
+                        "{{ p0 = cmp.eq(r7,r3)",
+                            "if (!p0.new) jump:nt 3f }}",
+                        "memd_locked({dst},p0) = r5:4",
+                        "if (p0) jump 4f", // jump if store succeed
+                        "3:",
+                            "{r} = #0",
+                        "4:",
+                        dst = in(reg) dst,
+                        in("r2") old.lo,
+                        in("r3") old.hi,
+                        in("r4") new.lo,
+                        in("r5") new.hi,
+                        out("r6") prev_lo,
+                        out("r7") prev_hi,
+                        r = inout(reg) r,
+                        options(nostack),
+                    );
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     r != 0
                 }
@@ -375,6 +500,22 @@ macro_rules! atomic64 {
 atomic64!(i64);
 atomic64!(u64);
 
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{CompareExchange, CompareExchangeWeak, Load, Store, Swap};
+        Strategy::new(match (size, op) {
+            (_, Load) => "MEMB/MEMH/MEMW/MEMD",
+            (_, Store) => "MEMB/MEMH/MEMW/MEMD",
+            (1 | 2, Swap | CompareExchange) => "masked MEMW_LOCKED loop",
+            (1 | 2, CompareExchangeWeak) => "masked MEMW_LOCKED",
+            (8, Swap | CompareExchange) => "MEMD_LOCKED loop",
+            (8, CompareExchangeWeak) => "MEMD_LOCKED",
+            (_, Swap | CompareExchange) => "MEMW_LOCKED loop",
+            (_, CompareExchangeWeak) => "MEMW_LOCKED",
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     test_atomic!(isize);
@@ -0,0 +1,153 @@
+// Model checking with loom.
+//
+// Under `cfg(loom)`, the value of each AtomicMaybeUninit is stored through a
+// loom atomic, and every operation is mapped to the operation of the loom
+// atomic with the memory ordering specified by the caller. So loom's
+// scheduler and memory model (including which store a load may read from)
+// apply in the same way as to loom's own atomic types.
+//
+// loom's atomics can only store initialized integers up to 64 bits, so the
+// loom atomic stores an index into a per-atomic table of values instead of
+// the value itself. Each store appends the value to the table, so values
+// (including uninitialized bytes) are returned exactly as they were stored.
+// CAS compares the values in the table by using the native implementation,
+// so the same padding behavior is exercised as without loom.
+//
+// compare_exchange_weak is mapped to loom's compare_exchange_weak, so loom
+// decides whether it fails spuriously.
+//
+// Refs:
+// - loom https://github.com/tokio-rs/loom
+
+use alloc::{vec, vec::Vec};
+use core::{mem::MaybeUninit, sync::atomic::Ordering};
+use std::sync::Mutex;
+
+use loom::sync::atomic::AtomicUsize;
+
+use crate::raw::{AtomicCompareExchange, Primitive};
+
+// An index that is never in the table.
+const NONE: usize = usize::MAX;
+
+pub(crate) struct Cell<T: Primitive> {
+    index: AtomicUsize,
+    values: Mutex<Vec<MaybeUninit<T>>>,
+}
+
+impl<T: Primitive> Cell<T> {
+    #[inline]
+    pub(crate) fn new(v: MaybeUninit<T>) -> Self {
+        Self { index: AtomicUsize::new(0), values: Mutex::new(vec![v]) }
+    }
+
+    #[inline]
+    pub(crate) fn get_mut(&mut self) -> &mut MaybeUninit<T> {
+        let i = self.index.with_mut(|i| *i);
+        &mut self.values.get_mut().unwrap()[i]
+    }
+
+    #[inline]
+    pub(crate) fn into_inner(self) -> MaybeUninit<T> {
+        let i = self.index.into_inner();
+        self.values.into_inner().unwrap()[i]
+    }
+
+    #[inline]
+    fn get(&self, i: usize) -> MaybeUninit<T> {
+        self.values.lock().unwrap()[i]
+    }
+
+    #[inline]
+    fn push(&self, v: MaybeUninit<T>) -> usize {
+        let mut values = self.values.lock().unwrap();
+        values.push(v);
+        values.len() - 1
+    }
+
+    #[inline]
+    pub(crate) fn load(&self, order: Ordering) -> MaybeUninit<T> {
+        self.get(self.index.load(order))
+    }
+
+    #[inline]
+    pub(crate) fn store(&self, val: MaybeUninit<T>, order: Ordering) {
+        let i = self.push(val);
+        self.index.store(i, order);
+    }
+
+    #[inline]
+    pub(crate) fn swap(&self, val: MaybeUninit<T>, order: Ordering) -> MaybeUninit<T> {
+        let i = self.push(val);
+        self.get(self.index.swap(i, order))
+    }
+
+    // Reads the latest index with the failure ordering, as a failed CAS does.
+    #[inline]
+    fn load_latest(&self, success: Ordering, failure: Ordering) -> usize {
+        self.index.compare_exchange(NONE, NONE, success, failure).unwrap_err()
+    }
+
+    #[inline]
+    pub(crate) fn compare_exchange(
+        &self,
+        current: MaybeUninit<T>,
+        new: MaybeUninit<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<MaybeUninit<T>, MaybeUninit<T>>
+    where
+        T: AtomicCompareExchange,
+    {
+        let new = self.push(new);
+        let mut i = self.load_latest(success, failure);
+        loop {
+            let prev = self.get(i);
+            if !eq(prev, current) {
+                return Err(prev);
+            }
+            // The same value may be stored at several indices, so retry with
+            // the latest index if it was changed.
+            match self.index.compare_exchange(i, new, success, failure) {
+                Ok(_) => return Ok(prev),
+                Err(next) => i = next,
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn compare_exchange_weak(
+        &self,
+        current: MaybeUninit<T>,
+        new: MaybeUninit<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<MaybeUninit<T>, MaybeUninit<T>>
+    where
+        T: AtomicCompareExchange,
+    {
+        let new = self.push(new);
+        let i = self.load_latest(success, failure);
+        let prev = self.get(i);
+        if !eq(prev, current) {
+            return Err(prev);
+        }
+        match self.index.compare_exchange_weak(i, new, success, failure) {
+            Ok(_) => Ok(prev),
+            Err(next) => Err(self.get(next)),
+        }
+    }
+}
+
+// Compares the values by using the native CAS, which compares them after
+// freezing uninitialized bytes.
+#[inline]
+fn eq<T: AtomicCompareExchange>(a: MaybeUninit<T>, b: MaybeUninit<T>) -> bool {
+    let mut a = a;
+    let mut out = MaybeUninit::<T>::uninit();
+    // SAFETY: all pointers are valid because we got them from references to
+    // local variables, which are not accessed by other threads.
+    unsafe {
+        T::atomic_compare_exchange(&mut a, &b, &b, &mut out, Ordering::Relaxed, Ordering::Relaxed)
+    }
+}
@@ -2,7 +2,16 @@
 //
 // Generated asm:
 // - loongarch64 https://godbolt.org/z/vTxfajT14
+//
+// Note: LoongArch v1.1 instructions are used when the corresponding target
+// features are enabled:
+// - amcas{,_db}.{b,h,w,d} for CAS (`lamcas`)
+// - amswap{,_db}.{b,h} for 8-bit and 16-bit swap (`lam-bh`)
 
+#[cfg(not(all(
+    any(target_feature = "lamcas", atomic_maybe_uninit_target_feature = "lamcas"),
+    any(target_feature = "lam-bh", atomic_maybe_uninit_target_feature = "lam-bh"),
+)))]
 #[path = "partword.rs"]
 mod partword;
 
@@ -12,14 +21,37 @@ use core::{
     sync::atomic::Ordering,
 };
 
-use crate::raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap};
+use super::{from_reg, load_reg, Reg};
+use crate::{
+    info::Strategy,
+    raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap},
+};
 
 #[cfg(target_arch = "loongarch64")]
 type XSize = u64;
 
+// Sign-extends old for comparison with the value loaded by LL.W or
+// AMCAS{,_DB}.{B,H,W}, which sign-extend it. The upper bits of register
+// operands narrower than 64 bits are undefined.
+macro_rules! sext {
+    ("b") => {
+        "ext.w.b {old}, {old}"
+    };
+    ("h") => {
+        "ext.w.h {old}, {old}"
+    };
+    ("w") => {
+        "addi.w {old}, {old}, 0"
+    };
+    ("d") => {
+        ""
+    };
+}
+
 #[rustfmt::skip]
 macro_rules! atomic_load {
     ($int_type:ident, $asm_suffix:tt) => {
+        impl_load_reg!($int_type, reg, concat!("ld.", $asm_suffix, " {v}, {src}, 0"));
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
@@ -32,17 +64,15 @@ macro_rules! atomic_load {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let v: Reg<Self>;
                     macro_rules! atomic_load {
                         ($acquire:tt) => {
                             asm!(
-                                // (atomic) load from src to tmp
-                                concat!("ld.", $asm_suffix, " {tmp}, {src}, 0"),
+                                // (atomic) load from src to v
+                                concat!("ld.", $asm_suffix, " {v}, {src}, 0"),
                                 $acquire,
-                                // store tmp to out
-                                concat!("st.", $asm_suffix, " {tmp}, {out}, 0"),
                                 src = in(reg) ptr_reg!(src),
-                                out = inout(reg) ptr_reg!(out) => _,
-                                tmp = lateout(reg) _,
+                                v = lateout(reg) v,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -53,6 +83,7 @@ macro_rules! atomic_load {
                         Ordering::Acquire | Ordering::SeqCst => atomic_load!("dbar 0"),
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_reg(v));
                 }
             }
         }
@@ -74,28 +105,23 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
                     match order {
                         Ordering::Relaxed => {
                             asm!(
-                                // load from val to tmp
-                                concat!("ld.", $asm_suffix, " {tmp}, {val}, 0"),
-                                // (atomic) store tmp to dst
-                                concat!("st.", $asm_suffix, " {tmp}, {dst}, 0"),
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                val = in(reg) ptr_reg!(val),
-                                tmp = lateout(reg) _,
+                                // (atomic) store val to dst
+                                concat!("st.", $asm_suffix, " {val}, {dst}, 0"),
+                                dst = in(reg) ptr_reg!(dst),
+                                val = in(reg) val,
                                 options(nostack, preserves_flags),
                             );
                         }
                         Ordering::Release | Ordering::SeqCst => {
                             asm!(
-                                // load from val to tmp
-                                concat!("ld.", $asm_suffix, " {tmp}, {val}, 0"),
-                                // (atomic) store tmp to dst
-                                concat!("amswap_db.", $asm_suffix, " $zero, {tmp}, {dst}"),
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                val = in(reg) ptr_reg!(val),
-                                tmp = lateout(reg) _,
+                                // (atomic) store val to dst
+                                concat!("amswap_db.", $asm_suffix, " $zero, {val}, {dst}"),
+                                dst = in(reg) ptr_reg!(dst),
+                                val = in(reg) val,
                                 options(nostack, preserves_flags),
                             )
                         }
@@ -118,21 +144,19 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     asm!(
-                        // load from val (ptr) to val (val)
-                        concat!("ld.", $asm_suffix, " {val}, {val}, 0"),
                         // (atomic) swap (AMO)
-                        // - load value from dst and store it to tmp
+                        // - load value from dst and store it to prev
                         // - store value of val to dst
-                        concat!("amswap_db.", $asm_suffix, " {tmp}, {val}, {dst}"),
-                        // store tmp to out
-                        concat!("st.", $asm_suffix, " {tmp}, {out}, 0"),
+                        concat!("amswap_db.", $asm_suffix, " {prev}, {val}, {dst}"),
                         dst = in(reg) ptr_reg!(dst),
-                        val = inout(reg) ptr_reg!(val) => _,
-                        out = inout(reg) ptr_reg!(out) => _,
-                        tmp = out(reg) _,
+                        val = in(reg) val,
+                        prev = out(reg) prev,
                         options(nostack, preserves_flags),
-                    )
+                    );
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -143,25 +167,55 @@ macro_rules! atomic {
                 old: *const MaybeUninit<Self>,
                 new: *const MaybeUninit<Self>,
                 out: *mut MaybeUninit<Self>,
-                _success: Ordering,
-                _failure: Ordering,
+                success: Ordering,
+                failure: Ordering,
             ) -> bool {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                #[cfg(not(any(target_feature = "lamcas", atomic_maybe_uninit_target_feature = "lamcas")))]
+                let _ = (success, failure);
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let mut r: XSize;
+                    #[cfg(any(target_feature = "lamcas", atomic_maybe_uninit_target_feature = "lamcas"))]
+                    {
+                        macro_rules! atomic_cmpxchg {
+                            ($db:tt) => {
+                                asm!(
+                                    sext!($asm_suffix),
+                                    "move {prev}, {old}",
+                                    // (atomic) CAS (AMO)
+                                    // - compare value of dst and prev, and store new to dst if equal
+                                    // - load previous value of dst to prev
+                                    concat!("amcas", $db, ".", $asm_suffix, " {prev}, {new}, {dst}"),
+                                    "xor {r}, {prev}, {old}",
+                                    "sltui {r}, {r}, 1",
+                                    dst = in(reg) ptr_reg!(dst),
+                                    old = inout(reg) old => _,
+                                    new = in(reg) new,
+                                    prev = out(reg) prev,
+                                    r = out(reg) r,
+                                    options(nostack, preserves_flags),
+                                )
+                            };
+                        }
+                        match crate::utils::upgrade_success_ordering(success, failure) {
+                            Ordering::Relaxed => atomic_cmpxchg!(""),
+                            _ => atomic_cmpxchg!("_db"),
+                        }
+                    }
+                    #[cfg(not(any(target_feature = "lamcas", atomic_maybe_uninit_target_feature = "lamcas")))]
                     asm!(
-                        // load from old/new (ptr) to old/new (val)
-                        concat!("ld.", $asm_suffix, " {old}, {old}, 0"),
-                        concat!("ld.", $asm_suffix, " {new}, {new}, 0"),
+                        sext!($asm_suffix),
                         // (atomic) CAS (LL/SC loop)
                         "2:",
-                            concat!("ll.", $asm_suffix, " {tmp}, {dst}, 0"),
-                            "bne {tmp}, {old}, 3f", // compare and jump if compare failed
+                            concat!("ll.", $asm_suffix, " {prev}, {dst}, 0"),
+                            "bne {prev}, {old}, 3f", // compare and jump if compare failed
                             "dbar 0",
                             "move {r}, {new}",
                             concat!("sc.", $asm_suffix, " {r}, {dst}, 0"),
@@ -170,18 +224,63 @@ macro_rules! atomic {
                         "3:",
                             "dbar 1792",
                         "4:",
-                        // store tmp to out
-                        concat!("st.", $asm_suffix, " {tmp}, {out}, 0"),
-                        "xor {r}, {tmp}, {old}",
+                        "xor {r}, {prev}, {old}",
                         "sltui {r}, {r}, 1",
                         dst = in(reg) ptr_reg!(dst),
-                        old = inout(reg) ptr_reg!(old) => _,
-                        new = inout(reg) ptr_reg!(new) => _,
-                        out = in(reg) ptr_reg!(out),
-                        tmp = out(reg) _,
+                        old = inout(reg) old => _,
+                        new = in(reg) new,
+                        prev = out(reg) prev,
+                        r = out(reg) r,
+                        options(nostack, preserves_flags),
+                    );
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+            #[cfg(not(any(target_feature = "lamcas", atomic_maybe_uninit_target_feature = "lamcas")))]
+            #[inline]
+            unsafe fn atomic_compare_exchange_weak(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let mut r: XSize;
+                    asm!(
+                        sext!($asm_suffix),
+                        // (atomic) CAS (LL/SC)
+                        concat!("ll.", $asm_suffix, " {prev}, {dst}, 0"),
+                        "bne {prev}, {old}, 3f", // compare and jump if compare failed
+                        "dbar 0",
+                        "move {r}, {new}",
+                        // 1 if the store was successful, 0 if no store was performed
+                        concat!("sc.", $asm_suffix, " {r}, {dst}, 0"),
+                        "b 4f",
+                        "3:",
+                            "dbar 1792",
+                            // compare failed, set r to 0
+                            "move {r}, $zero",
+                        "4:",
+                        dst = in(reg) ptr_reg!(dst),
+                        old = inout(reg) old => _,
+                        new = in(reg) new,
+                        prev = out(reg) prev,
                         r = out(reg) r,
                         options(nostack, preserves_flags),
                     );
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     r != 0
                 }
@@ -206,18 +305,16 @@ macro_rules! atomic_sub_word {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
                     macro_rules! atomic_store {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // load from val to tmp
-                                concat!("ld.", $asm_suffix, " {tmp}, {val}, 0"),
-                                // (atomic) store tmp to dst
+                                // (atomic) store val to dst
                                 $release,
-                                concat!("st.", $asm_suffix, " {tmp}, {dst}, 0"),
+                                concat!("st.", $asm_suffix, " {val}, {dst}, 0"),
                                 $acquire,
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                val = in(reg) ptr_reg!(val),
-                                tmp = lateout(reg) _,
+                                dst = in(reg) ptr_reg!(dst),
+                                val = in(reg) val,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -242,10 +339,39 @@ macro_rules! atomic_sub_word {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                #[cfg(not(any(target_feature = "lam-bh", atomic_maybe_uninit_target_feature = "lam-bh")))]
                 let (aligned_ptr, shift, mask) = partword::create_mask_values(dst);
 
                 // SAFETY: the caller must uphold the safety contract.
+                #[cfg(any(target_feature = "lam-bh", atomic_maybe_uninit_target_feature = "lam-bh"))]
+                unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
+                    macro_rules! atomic_swap {
+                        ($db:tt) => {
+                            asm!(
+                                // (atomic) swap (AMO)
+                                // - load value from dst and store it to prev
+                                // - store value of val to dst
+                                concat!("amswap", $db, ".", $asm_suffix, " {prev}, {val}, {dst}"),
+                                dst = in(reg) ptr_reg!(dst),
+                                val = in(reg) val,
+                                prev = out(reg) prev,
+                                options(nostack, preserves_flags),
+                            )
+                        };
+                    }
+                    match order {
+                        Ordering::Relaxed => atomic_swap!(""),
+                        _ => atomic_swap!("_db"),
+                    }
+                    out.write(from_reg(prev));
+                }
+                // SAFETY: the caller must uphold the safety contract.
+                #[cfg(not(any(target_feature = "lam-bh", atomic_maybe_uninit_target_feature = "lam-bh")))]
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     // Implement sub-word atomic operations using word-sized LL/SC loop.
                     // Based on assemblies generated by rustc/LLVM.
                     // See also partword.rs.
@@ -254,28 +380,25 @@ macro_rules! atomic_sub_word {
                             asm!(
                                 "sll.w {mask}, {mask}, {shift}",
                                 "addi.w {mask}, {mask}, 0",
-                                concat!("ld.", $asm_suffix, "u {val}, {val}, 0"),
                                 "sll.w {val}, {val}, {shift}",
                                 "addi.w {val}, {val}, 0",
                                 // (atomic) swap (LL/SC loop)
                                 "2:",
                                     $fence,
-                                    "ll.w {tmp1}, {dst}, 0",
-                                    "addi.w {tmp2}, {val}, 0",
-                                    "xor {tmp2}, {tmp1}, {tmp2}",
-                                    "and {tmp2}, {tmp2}, {mask}",
-                                    "xor {tmp2}, {tmp1}, {tmp2}",
-                                    "sc.w {tmp2}, {dst}, 0",
-                                    "beqz {tmp2}, 2b",
-                                "srl.w {tmp1}, {tmp1}, {shift}",
-                                concat!("st.", $asm_suffix, " {tmp1}, {out}, 0"),
+                                    "ll.w {prev}, {dst}, 0",
+                                    "addi.w {tmp}, {val}, 0",
+                                    "xor {tmp}, {prev}, {tmp}",
+                                    "and {tmp}, {tmp}, {mask}",
+                                    "xor {tmp}, {prev}, {tmp}",
+                                    "sc.w {tmp}, {dst}, 0",
+                                    "beqz {tmp}, 2b",
+                                "srl.w {prev}, {prev}, {shift}",
                                 dst = in(reg) ptr_reg!(aligned_ptr),
-                                val = inout(reg) ptr_reg!(val) => _,
-                                out = in(reg) ptr_reg!(out),
+                                val = inout(reg) val => _,
                                 shift = in(reg) shift,
                                 mask = inout(reg) mask => _,
-                                tmp1 = out(reg) _,
-                                tmp2 = out(reg) _,
+                                prev = out(reg) prev,
+                                tmp = out(reg) _,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -284,6 +407,7 @@ macro_rules! atomic_sub_word {
                         Ordering::Relaxed => atomic_swap!(""),
                         _ => atomic_swap!("dbar 0"),
                     }
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -294,30 +418,64 @@ macro_rules! atomic_sub_word {
                 old: *const MaybeUninit<Self>,
                 new: *const MaybeUninit<Self>,
                 out: *mut MaybeUninit<Self>,
-                _success: Ordering,
-                _failure: Ordering,
+                success: Ordering,
+                failure: Ordering,
             ) -> bool {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                #[cfg(not(any(target_feature = "lamcas", atomic_maybe_uninit_target_feature = "lamcas")))]
+                let _ = (success, failure);
+                #[cfg(not(any(target_feature = "lamcas", atomic_maybe_uninit_target_feature = "lamcas")))]
                 let (aligned_ptr, shift, mask) = partword::create_mask_values(dst);
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let mut r: XSize;
+                    #[cfg(any(target_feature = "lamcas", atomic_maybe_uninit_target_feature = "lamcas"))]
+                    {
+                        macro_rules! atomic_cmpxchg {
+                            ($db:tt) => {
+                                asm!(
+                                    sext!($asm_suffix),
+                                    "move {prev}, {old}",
+                                    // (atomic) CAS (AMO)
+                                    // - compare value of dst and prev, and store new to dst if equal
+                                    // - load previous value of dst to prev
+                                    concat!("amcas", $db, ".", $asm_suffix, " {prev}, {new}, {dst}"),
+                                    "xor {r}, {prev}, {old}",
+                                    "sltui {r}, {r}, 1",
+                                    dst = in(reg) ptr_reg!(dst),
+                                    old = inout(reg) old => _,
+                                    new = in(reg) new,
+                                    prev = out(reg) prev,
+                                    r = out(reg) r,
+                                    options(nostack, preserves_flags),
+                                )
+                            };
+                        }
+                        match crate::utils::upgrade_success_ordering(success, failure) {
+                            Ordering::Relaxed => atomic_cmpxchg!(""),
+                            _ => atomic_cmpxchg!("_db"),
+                        }
+                    }
                     // Implement sub-word atomic operations using word-sized LL/SC loop.
                     // Based on assemblies generated by rustc/LLVM.
                     // See also partword.rs.
+                    #[cfg(not(any(target_feature = "lamcas", atomic_maybe_uninit_target_feature = "lamcas")))]
                     asm!(
-                        concat!("ld.", $asm_suffix, "u {new}, {new}, 0"),
-                        concat!("ld.", $asm_suffix, "u {old}, {old}, 0"),
                         "sll.w {new}, {new}, {shift}",
                         "addi.w {new}, {new}, 0",
                         "sll.w {old}, {old}, {shift}",
                         "addi.w $a7, {old}, 0",
                         "sll.w {mask}, {mask}, {shift}",
                         "addi.w $a6, {mask}, 0",
+                        // The upper bits of old and new are undefined, so clear them.
+                        "and $a7, $a7, $a6",
+                        "and {new}, {new}, $a6",
                         // (atomic) CAS (LL/SC loop)
                         "2:",
                             "ll.w $t0, {dst}, 0",
@@ -332,18 +490,86 @@ macro_rules! atomic_sub_word {
                         "3:",
                             "dbar 1792",
                         "4:",
-                        "srl.w $a6, $t0, {shift}",
-                        concat!("st.", $asm_suffix, " $a6, {out}, 0"),
+                        "srl.w {prev}, $t0, {shift}",
                         "and {r}, $t0, {mask}",
                         "addi.w {r}, {r}, 0",
-                        "xor {r}, {old}, {r}",
+                        "xor {r}, $a7, {r}",
                         "sltui {r}, {r}, 1",
                         dst = in(reg) ptr_reg!(aligned_ptr),
-                        old = inout(reg) ptr_reg!(old) => _,
-                        new = inout(reg) ptr_reg!(new) => _,
-                        out = inout(reg) ptr_reg!(out) => _,
+                        old = inout(reg) old => _,
+                        new = inout(reg) new => _,
+                        shift = in(reg) shift,
+                        mask = inout(reg) mask => _,
+                        prev = out(reg) prev,
+                        r = lateout(reg) r,
+                        out("$a6") _,
+                        out("$a7") _,
+                        out("$t0") _,
+                        out("$t1") _,
+                        options(nostack, preserves_flags),
+                    );
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+            #[cfg(not(any(target_feature = "lamcas", atomic_maybe_uninit_target_feature = "lamcas")))]
+            #[inline]
+            unsafe fn atomic_compare_exchange_weak(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let (aligned_ptr, shift, mask) = partword::create_mask_values(dst);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let mut r: XSize;
+                    // Implement sub-word atomic operations using word-sized LL/SC.
+                    // Based on assemblies generated by rustc/LLVM.
+                    // See also partword.rs.
+                    asm!(
+                        "sll.w {new}, {new}, {shift}",
+                        "addi.w {new}, {new}, 0",
+                        "sll.w {old}, {old}, {shift}",
+                        "addi.w $a7, {old}, 0",
+                        "sll.w {mask}, {mask}, {shift}",
+                        "addi.w $a6, {mask}, 0",
+                        // The upper bits of old and new are undefined, so clear them.
+                        "and $a7, $a7, $a6",
+                        "and {new}, {new}, $a6",
+                        // (atomic) CAS (LL/SC)
+                        "ll.w $t0, {dst}, 0",
+                        "and $t1, $t0, $a6",
+                        "bne $t1, $a7, 3f",
+                        "dbar 0",
+                        "andn $t1, $t0, $a6",
+                        "or $t1, $t1, {new}",
+                        // 1 if the store was successful, 0 if no store was performed
+                        "sc.w $t1, {dst}, 0",
+                        "b 4f",
+                        "3:",
+                            "dbar 1792",
+                            // compare failed, set $t1 to 0
+                            "move $t1, $zero",
+                        "4:",
+                        "srl.w {prev}, $t0, {shift}",
+                        "move {r}, $t1",
+                        dst = in(reg) ptr_reg!(aligned_ptr),
+                        old = inout(reg) old => _,
+                        new = inout(reg) new => _,
                         shift = in(reg) shift,
                         mask = inout(reg) mask => _,
+                        prev = out(reg) prev,
                         r = lateout(reg) r,
                         out("$a6") _,
                         out("$a7") _,
@@ -351,6 +577,7 @@ macro_rules! atomic_sub_word {
                         out("$t1") _,
                         options(nostack, preserves_flags),
                     );
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     r != 0
                 }
@@ -378,6 +605,29 @@ atomic!(isize, "d");
 #[cfg(target_pointer_width = "64")]
 atomic!(usize, "d");
 
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{CompareExchange, CompareExchangeWeak, Load, Store, Swap};
+        let lamcas =
+            cfg!(any(target_feature = "lamcas", atomic_maybe_uninit_target_feature = "lamcas"));
+        let lam_bh =
+            cfg!(any(target_feature = "lam-bh", atomic_maybe_uninit_target_feature = "lam-bh"));
+        Strategy::new(match (size, op) {
+            (_, Load) => "LD with DBAR",
+            (1 | 2, Store) => "ST with DBAR",
+            (_, Store) => "ST, AMSWAP_DB for Release/SeqCst",
+            (1 | 2, Swap) if lam_bh => "AMSWAP_DB.B/AMSWAP_DB.H (lam-bh)",
+            (1 | 2, Swap) => "masked LL/SC loop",
+            (_, Swap) => "AMSWAP_DB",
+            (_, CompareExchange | CompareExchangeWeak) if lamcas => "AMCAS/AMCAS_DB (lamcas)",
+            (1 | 2, CompareExchange) => "masked LL/SC loop",
+            (1 | 2, CompareExchangeWeak) => "masked LL/SC",
+            (_, CompareExchange) => "LL/SC loop",
+            (_, CompareExchangeWeak) => "LL/SC",
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     test_atomic!(isize);
@@ -0,0 +1,446 @@
+// M68k
+//
+// Refs:
+// - M68000 Family Programmer's Reference Manual
+//   https://www.nxp.com/docs/en/reference-manual/M68000PRM.pdf
+// - M68060 User's Manual
+//   https://www.nxp.com/docs/en/data-sheet/MC68060UM.pdf
+// - Linux kernel's memory barriers (m68k uses asm-generic/barrier.h)
+//   https://github.com/torvalds/linux/blob/v6.6/include/asm-generic/barrier.h
+//
+// M68k processors don't reorder memory accesses as observed by other
+// processors (and there are no SMP m68k systems supported by Linux), so, like
+// the Linux kernel, we don't use any fence instructions and all orderings are
+// implemented in the same way. The asm! block acts as a compiler fence.
+//
+// CAS and CAS2 are available on 68020+ (isa-68020 target feature;
+// m68k-unknown-linux-gnu is 68020 by default). 64-bit atomics are implemented
+// by using CAS2 on the two 32-bit halves of the value; this also applies to
+// load and store because there is no 64-bit single-copy atomic load/store.
+//
+// Note that 68060 doesn't implement CAS2 in hardware. It traps as an
+// unimplemented integer instruction, and the OS needs to emulate it.
+//
+// LLVM's assembler doesn't support CAS2 yet, so we encode it by hand with
+// fixed registers. See the comment on the cas2 macro for the encoding.
+
+use core::{
+    arch::asm,
+    mem::{self, MaybeUninit},
+    sync::atomic::Ordering,
+};
+
+#[cfg(any(target_feature = "isa-68020", atomic_maybe_uninit_target_feature = "isa-68020"))]
+#[path = "pair.rs"]
+mod pair;
+
+#[cfg(any(target_feature = "isa-68020", atomic_maybe_uninit_target_feature = "isa-68020"))]
+use self::pair::{from_pair, load_pair, Pair};
+use super::{from_reg, load_reg, Reg};
+#[cfg(any(target_feature = "isa-68020", atomic_maybe_uninit_target_feature = "isa-68020"))]
+use crate::raw::{AtomicCompareExchange, AtomicSwap};
+use crate::{
+    info::Strategy,
+    raw::{AtomicLoad, AtomicStore},
+};
+
+#[rustfmt::skip]
+macro_rules! atomic {
+    ($int_type:ident, $size:tt) => {
+        // impl_load_reg! can't be used because MOVE modifies the condition codes
+        // and the address must be in an address register.
+        #[cfg(atomic_maybe_uninit_no_asm_maybe_uninit)]
+        impl super::LoadReg for $int_type {
+            #[inline]
+            unsafe fn load_reg(src: *const MaybeUninit<Self>) -> Self {
+                let v;
+                // SAFETY: the caller must guarantee that `src` is valid for reads.
+                unsafe {
+                    asm!(
+                        concat!("move", $size, " ({src}), {v}"),
+                        src = in(reg_addr) src,
+                        v = lateout(reg_data) v,
+                        // Do not use `preserves_flags` because MOVE modifies the condition codes.
+                        options(nostack, readonly),
+                    );
+                }
+                v
+            }
+        }
+        impl AtomicLoad for $int_type {
+            #[inline]
+            unsafe fn atomic_load(
+                src: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(src as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let v: Reg<Self>;
+                    // atomic load is always SeqCst.
+                    asm!(
+                        // (atomic) load from src to v
+                        concat!("move", $size, " ({src}), {v}"),
+                        src = in(reg_addr) src,
+                        v = lateout(reg_data) v,
+                        // Do not use `preserves_flags` because MOVE modifies the condition codes.
+                        options(nostack),
+                    );
+                    out.write(from_reg(v));
+                }
+            }
+        }
+        impl AtomicStore for $int_type {
+            #[inline]
+            unsafe fn atomic_store(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let val = load_reg(val);
+                    // atomic store is always SeqCst.
+                    asm!(
+                        // (atomic) store val to dst
+                        concat!("move", $size, " {val}, ({dst})"),
+                        dst = in(reg_addr) dst,
+                        val = in(reg_data) val,
+                        // Do not use `preserves_flags` because MOVE modifies the condition codes.
+                        options(nostack),
+                    );
+                }
+            }
+        }
+        #[cfg(any(target_feature = "isa-68020", atomic_maybe_uninit_target_feature = "isa-68020"))]
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
+                    // atomic swap is always SeqCst.
+                    asm!(
+                        // (atomic) swap (CAS loop)
+                        concat!("move", $size, " ({dst}), {prev}"),
+                        "2:",
+                            // on failure, CAS loads the current value to prev
+                            concat!("cas", $size, " {prev}, {val}, ({dst})"),
+                            "bne 2b",
+                        dst = in(reg_addr) dst,
+                        val = in(reg_data) val,
+                        prev = out(reg_data) prev,
+                        // Do not use `preserves_flags` because CAS modifies the condition codes.
+                        options(nostack),
+                    );
+                    out.write(from_reg(prev));
+                }
+            }
+        }
+        #[cfg(any(target_feature = "isa-68020", atomic_maybe_uninit_target_feature = "isa-68020"))]
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let r: u8;
+                    // compare_exchange is always SeqCst.
+                    asm!(
+                        // (atomic) CAS
+                        // on failure, CAS loads the current value to old (prev)
+                        concat!("cas", $size, " {old}, {new}, ({dst})"),
+                        // r = 1 if the CAS succeeded (Z flag is set)
+                        "seq {r}",
+                        "neg.b {r}",
+                        dst = in(reg_addr) dst,
+                        old = inout(reg_data) old => prev,
+                        new = in(reg_data) new,
+                        r = out(reg_data) r,
+                        // Do not use `preserves_flags` because CAS modifies the condition codes.
+                        options(nostack),
+                    );
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+        }
+    };
+}
+
+// CAS2.L %d0:%d1, %d2:%d3, (%a0):(%a1)
+//
+// Encoding:
+// - 0x0EFC: CAS2 with size = long
+// - 0x8080: Rn1 = %a0, Du1 = %d2, Dc1 = %d0
+// - 0x90C1: Rn2 = %a1, Du2 = %d3, Dc2 = %d1
+//
+// If (%a0) == %d0 and (%a1) == %d1, stores %d2 to (%a0) and %d3 to (%a1)
+// and sets the Z flag. Otherwise, loads (%a0) to %d0 and (%a1) to %d1 and
+// clears the Z flag.
+#[cfg(any(target_feature = "isa-68020", atomic_maybe_uninit_target_feature = "isa-68020"))]
+macro_rules! cas2 {
+    () => {
+        ".short 0x0efc, 0x8080, 0x90c1"
+    };
+}
+
+#[cfg(any(target_feature = "isa-68020", atomic_maybe_uninit_target_feature = "isa-68020"))]
+#[rustfmt::skip]
+macro_rules! atomic64 {
+    ($int_type:ident) => {
+        impl AtomicLoad for $int_type {
+            #[inline]
+            unsafe fn atomic_load(
+                src: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(src as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (v_hi, v_lo): (Reg<u32>, Reg<u32>);
+                    // atomic load is always SeqCst.
+                    asm!(
+                        "lea (4, %a0), %a1",
+                        // set old arg of CAS2 to 0
+                        "moveq #0, %d0",
+                        "moveq #0, %d1",
+                        // (atomic) load by CAS2(0, 0)
+                        cas2!(),
+                        in("a0") src,
+                        out("a1") _,
+                        out("d0") v_hi,
+                        out("d1") v_lo,
+                        // set new arg of CAS2 to 0
+                        in("d2") 0_u32,
+                        in("d3") 0_u32,
+                        // Do not use `preserves_flags` because CAS2 modifies the condition codes.
+                        options(nostack),
+                    );
+                    out.write(from_pair(Pair { lo: v_lo, hi: v_hi }));
+                }
+            }
+        }
+        impl AtomicStore for $int_type {
+            #[inline]
+            unsafe fn atomic_store(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let val = load_pair::<Self, u32>(val);
+                    // atomic store is always SeqCst.
+                    asm!(
+                        "lea (4, %a0), %a1",
+                        // (atomic) store (CAS2 loop)
+                        // the first (non-atomic) load is fixed up by CAS2 if it is torn
+                        "move.l (%a0), %d0",
+                        "move.l (%a1), %d1",
+                        "2:",
+                            cas2!(),
+                            "bne 2b",
+                        in("a0") dst,
+                        out("a1") _,
+                        out("d0") _,
+                        out("d1") _,
+                        in("d2") val.hi,
+                        in("d3") val.lo,
+                        // Do not use `preserves_flags` because CAS2 modifies the condition codes.
+                        options(nostack),
+                    );
+                }
+            }
+        }
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let val = load_pair::<Self, u32>(val);
+                    let (prev_hi, prev_lo): (Reg<u32>, Reg<u32>);
+                    // atomic swap is always SeqCst.
+                    asm!(
+                        "lea (4, %a0), %a1",
+                        // (atomic) swap (CAS2 loop)
+                        // the first (non-atomic) load is fixed up by CAS2 if it is torn
+                        "move.l (%a0), %d0",
+                        "move.l (%a1), %d1",
+                        "2:",
+                            cas2!(),
+                            "bne 2b",
+                        in("a0") dst,
+                        out("a1") _,
+                        out("d0") prev_hi,
+                        out("d1") prev_lo,
+                        in("d2") val.hi,
+                        in("d3") val.lo,
+                        // Do not use `preserves_flags` because CAS2 modifies the condition codes.
+                        options(nostack),
+                    );
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
+                }
+            }
+        }
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let old = load_pair::<Self, u32>(old);
+                    let new = load_pair::<Self, u32>(new);
+                    let (prev_hi, prev_lo): (Reg<u32>, Reg<u32>);
+                    let r: u8;
+                    // compare_exchange is always SeqCst.
+                    asm!(
+                        "lea (4, %a0), %a1",
+                        // (atomic) CAS
+                        // on failure, CAS2 loads the current value to d0-d1
+                        cas2!(),
+                        // r = 1 if the CAS succeeded (Z flag is set)
+                        "seq {r}",
+                        "neg.b {r}",
+                        r = out(reg_data) r,
+                        in("a0") dst,
+                        out("a1") _,
+                        inout("d0") old.hi => prev_hi,
+                        inout("d1") old.lo => prev_lo,
+                        in("d2") new.hi,
+                        in("d3") new.lo,
+                        // Do not use `preserves_flags` because CAS2 modifies the condition codes.
+                        options(nostack),
+                    );
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+        }
+    };
+}
+
+atomic!(i8, ".b");
+atomic!(u8, ".b");
+atomic!(i16, ".w");
+atomic!(u16, ".w");
+atomic!(i32, ".l");
+atomic!(u32, ".l");
+atomic!(isize, ".l");
+atomic!(usize, ".l");
+#[cfg(any(target_feature = "isa-68020", atomic_maybe_uninit_target_feature = "isa-68020"))]
+atomic64!(i64);
+#[cfg(any(target_feature = "isa-68020", atomic_maybe_uninit_target_feature = "isa-68020"))]
+atomic64!(u64);
+
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{Load, Store, Swap};
+        Strategy::new(match (size, op) {
+            (8, Swap | Store) => "CAS2 loop",
+            (8, _) => "CAS2",
+            (_, Load | Store) => "MOVE",
+            (_, Swap) => "CAS loop",
+            (_, _) => "CAS",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(any(target_feature = "isa-68020", atomic_maybe_uninit_target_feature = "isa-68020"))]
+    mod cas {
+        test_atomic!(isize);
+        test_atomic!(usize);
+        test_atomic!(i8);
+        test_atomic!(u8);
+        test_atomic!(i16);
+        test_atomic!(u16);
+        test_atomic!(i32);
+        test_atomic!(u32);
+        test_atomic!(i64);
+        test_atomic!(u64);
+
+        // load/store/swap implementation is not affected by signedness, so it is
+        // enough to test only unsigned types.
+        stress_test!(u8);
+        stress_test!(u16);
+        stress_test!(u32);
+        stress_test!(u64);
+    }
+    #[cfg(not(any(
+        target_feature = "isa-68020",
+        atomic_maybe_uninit_target_feature = "isa-68020",
+    )))]
+    mod load_store {
+        test_atomic_load_store!(isize);
+        test_atomic_load_store!(usize);
+        test_atomic_load_store!(i8);
+        test_atomic_load_store!(u8);
+        test_atomic_load_store!(i16);
+        test_atomic_load_store!(u16);
+        test_atomic_load_store!(i32);
+        test_atomic_load_store!(u32);
+
+        stress_test_load_store!(u8);
+        stress_test_load_store!(u16);
+        stress_test_load_store!(u32);
+    }
+}
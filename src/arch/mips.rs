@@ -15,7 +15,11 @@ use core::{
     sync::atomic::Ordering,
 };
 
-use crate::raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap};
+use super::{from_reg, load_reg, Reg};
+use crate::{
+    info::Strategy,
+    raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap},
+};
 
 // TODO: we can add options(preserves_flags) to some of asm!
 
@@ -37,9 +41,29 @@ type XSize = u32;
 #[cfg(any(target_arch = "mips64", target_arch = "mips64r6"))]
 type XSize = u64;
 
+// Sign-extends 32-bit old for comparison with the value loaded by LL, which
+// sign-extends it on MIPS64. The upper bits of register operands narrower
+// than 64 bits are undefined.
+#[cfg(any(target_arch = "mips", target_arch = "mips32r6"))]
+macro_rules! sext {
+    ("w") => {
+        ""
+    };
+}
+#[cfg(any(target_arch = "mips64", target_arch = "mips64r6"))]
+macro_rules! sext {
+    ("w") => {
+        "sll {old}, {old}, 0"
+    };
+    ("d") => {
+        ""
+    };
+}
+
 #[rustfmt::skip]
 macro_rules! atomic_load_store {
-    ($int_type:ident, $asm_suffix:tt, $l_u_suffix:tt) => {
+    ($int_type:ident, $asm_suffix:tt) => {
+        impl_load_reg!($int_type, reg, concat!("l", $asm_suffix, " {v}, 0({src})"));
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
@@ -52,20 +76,18 @@ macro_rules! atomic_load_store {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let v: Reg<Self>;
                     macro_rules! atomic_load {
                         ($acquire:tt) => {
                             asm!(
                                 ".set push",
                                 ".set noat",
-                                // (atomic) load from src to tmp
-                                concat!("l", $asm_suffix, " {tmp}, 0({src})"),
+                                // (atomic) load from src to v
+                                concat!("l", $asm_suffix, " {v}, 0({src})"),
                                 $acquire,
-                                // store tmp to out
-                                concat!("s", $asm_suffix, " {tmp}, 0({out})"),
                                 ".set pop",
                                 src = in(reg) ptr_reg!(src),
-                                out = in(reg) ptr_reg!(out),
-                                tmp = out(reg) _,
+                                v = lateout(reg) v,
                                 options(nostack),
                             )
                         };
@@ -76,6 +98,7 @@ macro_rules! atomic_load_store {
                         Ordering::Acquire | Ordering::SeqCst => atomic_load!("sync"),
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_reg(v));
                 }
             }
         }
@@ -91,21 +114,19 @@ macro_rules! atomic_load_store {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
                     macro_rules! store {
                         ($acquire:tt, $release:tt) => {
                             asm!(
                                 ".set push",
                                 ".set noat",
-                                // load from val to tmp
-                                concat!("l", $asm_suffix, $l_u_suffix, " {tmp}, 0({val})"),
-                                // (atomic) store tmp to dst
+                                // (atomic) store val to dst
                                 $release, // release fence
-                                concat!("s", $asm_suffix, " {tmp}, 0({dst})"),
+                                concat!("s", $asm_suffix, " {val}, 0({dst})"),
                                 $acquire, // acquire fence
                                 ".set pop",
                                 dst = in(reg) ptr_reg!(dst),
-                                val = in(reg) ptr_reg!(val),
-                                tmp = out(reg) _,
+                                val = in(reg) val,
                                 options(nostack),
                             )
                         };
@@ -120,7 +141,7 @@ macro_rules! atomic_load_store {
 #[rustfmt::skip]
 macro_rules! atomic {
     ($int_type:ident, $asm_suffix:tt, $ll_sc_suffix:tt) => {
-        atomic_load_store!($int_type, $asm_suffix, "");
+        atomic_load_store!($int_type, $asm_suffix);
         impl AtomicSwap for $int_type {
             #[inline]
             unsafe fn atomic_swap(
@@ -135,38 +156,35 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     macro_rules! swap {
                         ($acquire:tt, $release:tt) => {
                             asm!(
                                 ".set push",
                                 ".set noat",
-                                // load from val to val_tmp
-                                concat!("l", $asm_suffix, " {val_tmp}, 0({val})"),
                                 // (atomic) swap (LL/SC loop)
                                 $release, // release fence
                                 "2:",
-                                    // load from dst to out_tmp
-                                    concat!("ll", $ll_sc_suffix, " {out_tmp}, 0({dst})"),
-                                    "move {r}, {val_tmp}",
+                                    // load from dst to prev
+                                    concat!("ll", $ll_sc_suffix, " {prev}, 0({dst})"),
+                                    "move {r}, {val}",
                                     // try to store val to dst
                                     concat!("sc", $ll_sc_suffix, " {r}, 0({dst})"),
                                     // 1 if the store was successful, 0 if no store was performed
                                     "beqz {r}, 2b",
                                 $acquire, // acquire fence
-                                // store out_tmp to out
-                                concat!("s", $asm_suffix, " {out_tmp}, 0({out})"),
                                 ".set pop",
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                val = in(reg) ptr_reg!(val),
-                                out = inout(reg) ptr_reg!(out) => _,
-                                val_tmp = out(reg) _,
-                                out_tmp = out(reg) _,
-                                r = lateout(reg) _,
+                                dst = in(reg) ptr_reg!(dst),
+                                val = in(reg) val,
+                                prev = out(reg) prev,
+                                r = out(reg) _,
                                 options(nostack),
                             )
                         };
                     }
                     atomic_rmw!(swap, order);
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -188,46 +206,99 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let mut r: XSize;
                     macro_rules! cmpxchg {
                         ($acquire:tt, $release:tt) => {
                             asm!(
                                 ".set push",
                                 ".set noat",
-                                // load from old/new to old_tmp/new_tmp
-                                concat!("l", $asm_suffix, " {old_tmp}, 0({old})"),
-                                concat!("l", $asm_suffix, " {new_tmp}, 0({new})"),
+                                sext!($asm_suffix),
                                 // (atomic) CAS (LL/SC loop)
                                 $release, // release fence
                                 "2:",
-                                    // load from dst to out_tmp
-                                    concat!("ll", $ll_sc_suffix, " {out_tmp}, 0({dst})"),
-                                    "bne {out_tmp}, {old_tmp}, 3f", // compare and jump if compare failed
-                                    "move {r}, {new_tmp}",
+                                    // load from dst to prev
+                                    concat!("ll", $ll_sc_suffix, " {prev}, 0({dst})"),
+                                    "bne {prev}, {old}, 3f", // compare and jump if compare failed
+                                    "move {r}, {new}",
                                     // try to store new to dst
                                     concat!("sc", $ll_sc_suffix, " {r}, 0({dst})"),
                                     // 1 if the store was successful, 0 if no store was performed
                                     "beqz {r}, 2b", // continue loop if store failed
                                 "3:",
                                 $acquire, // acquire fence
-                                "xor {new_tmp}, {out_tmp}, {old_tmp}",
-                                // store out_tmp to out
-                                concat!("s", $asm_suffix, " {out_tmp}, 0({out})"),
-                                "sltiu {r}, {new_tmp}, 1",
+                                "xor {r}, {prev}, {old}",
+                                "sltiu {r}, {r}, 1",
+                                ".set pop",
+                                dst = in(reg) ptr_reg!(dst),
+                                old = inout(reg) old => _,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
+                                r = out(reg) r,
+                                options(nostack),
+                            )
+                        };
+                    }
+                    atomic_rmw!(cmpxchg, order);
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+            #[inline]
+            unsafe fn atomic_compare_exchange_weak(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let order = crate::utils::upgrade_success_ordering(success, failure);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let mut r: XSize;
+                    macro_rules! cmpxchg {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                ".set push",
+                                ".set noat",
+                                sext!($asm_suffix),
+                                // (atomic) CAS (LL/SC)
+                                $release, // release fence
+                                // load from dst to prev
+                                concat!("ll", $ll_sc_suffix, " {prev}, 0({dst})"),
+                                "bne {prev}, {old}, 3f", // compare and jump if compare failed
+                                "move {r}, {new}",
+                                // try to store new to dst
+                                concat!("sc", $ll_sc_suffix, " {r}, 0({dst})"),
+                                // 1 if the store was successful, 0 if no store was performed
+                                "b 4f",
+                                "3:",
+                                    // compare failed, set r to 0
+                                    "move {r}, $zero",
+                                "4:",
+                                $acquire, // acquire fence
                                 ".set pop",
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                old = in(reg) ptr_reg!(old),
-                                new = in(reg) ptr_reg!(new),
-                                out = inout(reg) ptr_reg!(out) => _,
-                                new_tmp = out(reg) _,
-                                old_tmp = out(reg) _,
-                                out_tmp = out(reg) _,
-                                r = lateout(reg) r,
+                                dst = in(reg) ptr_reg!(dst),
+                                old = inout(reg) old => _,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
+                                r = out(reg) r,
                                 options(nostack),
                             )
                         };
                     }
                     atomic_rmw!(cmpxchg, order);
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     r != 0
                 }
@@ -239,7 +310,7 @@ macro_rules! atomic {
 #[rustfmt::skip]
 macro_rules! atomic_sub_word {
     ($int_type:ident, $asm_suffix:tt, $max:tt) => {
-        atomic_load_store!($int_type, $asm_suffix, "u");
+        atomic_load_store!($int_type, $asm_suffix);
         impl AtomicSwap for $int_type {
             #[inline]
             unsafe fn atomic_swap(
@@ -255,6 +326,8 @@ macro_rules! atomic_sub_word {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     // Implement sub-word atomic operations using word-sized LL/SC loop.
                     // Based on assemblies generated by rustc/LLVM.
                     // See also partword.rs.
@@ -263,9 +336,8 @@ macro_rules! atomic_sub_word {
                             asm!(
                                 ".set push",
                                 ".set noat",
-                                concat!("l", $asm_suffix, "u {tmp}, 0($5)"),
                                 "sllv {mask}, {mask}, {shift}",
-                                "sllv $7, {tmp}, {shift}",
+                                "sllv $7, $7, {shift}",
                                 "nor $5, $zero, {mask}",
                                 // (atomic) swap (LL/SC loop)
                                 $release,
@@ -276,19 +348,16 @@ macro_rules! atomic_sub_word {
                                     "or $10, $10, $9",
                                     "sc $10, 0({dst})",
                                     "beqz $10, 2b",
-                                "and {tmp}, $8, {mask}",
-                                "srlv {tmp}, {tmp}, {shift}",
-                                concat!("se", $asm_suffix, " {tmp}, {tmp}"),
+                                "and {prev}, $8, {mask}",
+                                "srlv {prev}, {prev}, {shift}",
                                 $acquire,
-                                concat!("s", $asm_suffix, " {tmp}, 0({out})"),
                                 ".set pop",
                                 dst = in(reg) ptr_reg!(aligned_ptr),
-                                out = in(reg) ptr_reg!(out),
                                 shift = in(reg) shift,
                                 mask = inout(reg) mask => _,
-                                tmp = out(reg) _,
-                                inout("$5") ptr_reg!(val) => _, // val => inv_mask
-                                out("$7") _,
+                                prev = out(reg) prev,
+                                out("$5") _, // inv_mask
+                                inout("$7") val => _,
                                 out("$8") _,
                                 out("$9") _,
                                 out("$10") _,
@@ -297,6 +366,7 @@ macro_rules! atomic_sub_word {
                         };
                     }
                     atomic_rmw!(swap, order);
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -319,6 +389,8 @@ macro_rules! atomic_sub_word {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let mut r: XSize;
                     // Implement sub-word atomic operations using word-sized LL/SC loop.
                     // Based on assemblies generated by rustc/LLVM.
@@ -328,11 +400,12 @@ macro_rules! atomic_sub_word {
                             asm!(
                                 ".set push",
                                 ".set noat",
-                                concat!("l", $asm_suffix, "u $2, 0($6)"), // new
-                                concat!("l", $asm_suffix, " {tmp}, 0($5)"),  // old
+                                // The upper bits of old are undefined, so sign-extend it
+                                // for the comparison with prev below.
+                                concat!("se", $asm_suffix, " {old}, {old}"),
                                 concat!("ori $5, $zero, ", $max),
-                                concat!("andi $8, {tmp}, ", $max),
-                                concat!("andi $2, $2, ", $max),
+                                concat!("andi $8, {old}, ", $max),
+                                concat!("andi $2, {new}, ", $max),
                                 "sllv $5, $5, {shift}",
                                 "sllv $8, $8, {shift}",
                                 "sllv $9, $2, {shift}",
@@ -348,20 +421,20 @@ macro_rules! atomic_sub_word {
                                     "sc $10, 0({dst})",
                                     "beqz $10, 2b",
                                 "3:",
-                                "srlv $2, $11, {shift}",
-                                concat!("se", $asm_suffix, " $2, $2"),
+                                "srlv {prev}, $11, {shift}",
+                                concat!("se", $asm_suffix, " {prev}, {prev}"),
                                 $acquire,
-                                "xor {tmp}, $2, {tmp}",
-                                concat!("s", $asm_suffix, " $2, 0({out})"),
-                                "sltiu $2, {tmp}, 1",
+                                "xor {old}, {prev}, {old}",
+                                "sltiu $2, {old}, 1",
                                 ".set pop",
                                 dst = in(reg) ptr_reg!(aligned_ptr),
-                                out = in(reg) ptr_reg!(out),
                                 shift = in(reg) shift,
-                                tmp = out(reg) _,
+                                old = inout(reg) old => _,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
                                 out("$2") r,
-                                inout("$5") ptr_reg!(old) => _, // old => mask
-                                inout("$6") ptr_reg!(new) => _, // new => inv_mask
+                                out("$5") _, // mask
+                                out("$6") _, // inv_mask
                                 out("$8") _,
                                 out("$9") _,
                                 out("$10") _,
@@ -371,6 +444,83 @@ macro_rules! atomic_sub_word {
                         };
                     }
                     atomic_rmw!(cmpxchg, order);
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+            #[inline]
+            unsafe fn atomic_compare_exchange_weak(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let order = crate::utils::upgrade_success_ordering(success, failure);
+                let (aligned_ptr, shift, _mask) = partword::create_mask_values(dst);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let mut r: XSize;
+                    // Implement sub-word atomic operations using word-sized LL/SC.
+                    // Based on assemblies generated by rustc/LLVM.
+                    // See also partword.rs.
+                    macro_rules! cmpxchg {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                ".set push",
+                                ".set noat",
+                                concat!("ori $5, $zero, ", $max),
+                                concat!("andi $8, {old}, ", $max),
+                                concat!("andi $2, {new}, ", $max),
+                                "sllv $5, $5, {shift}",
+                                "sllv $8, $8, {shift}",
+                                "sllv $9, $2, {shift}",
+                                "nor $6, $zero, $5",
+                                // (atomic) CAS (LL/SC)
+                                $release,
+                                "ll $10, 0({dst})",
+                                "and $11, $10, $5",
+                                "bne $11, $8, 3f",
+                                "and $10, $10, $6",
+                                "or $10, $10, $9",
+                                // 1 if the store was successful, 0 if no store was performed
+                                "sc $10, 0({dst})",
+                                "b 4f",
+                                "3:",
+                                    // compare failed, set $10 to 0
+                                    "move $10, $zero",
+                                "4:",
+                                "srlv {prev}, $11, {shift}",
+                                $acquire,
+                                "move $2, $10",
+                                ".set pop",
+                                dst = in(reg) ptr_reg!(aligned_ptr),
+                                shift = in(reg) shift,
+                                old = in(reg) old,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
+                                out("$2") r,
+                                out("$5") _, // mask
+                                out("$6") _, // inv_mask
+                                out("$8") _,
+                                out("$9") _,
+                                out("$10") _,
+                                out("$11") _,
+                                options(nostack),
+                            )
+                        };
+                    }
+                    atomic_rmw!(cmpxchg, order);
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     r != 0
                 }
@@ -398,6 +548,22 @@ atomic!(isize, "d", "d");
 #[cfg(target_pointer_width = "64")]
 atomic!(usize, "d", "d");
 
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{CompareExchange, CompareExchangeWeak, Load, Store, Swap};
+        Strategy::new(match (size, op) {
+            (_, Load) => "L* with SYNC",
+            (_, Store) => "S* with SYNC",
+            (1 | 2, Swap | CompareExchange) => "masked LL/SC loop",
+            (1 | 2, CompareExchangeWeak) => "masked LL/SC",
+            (8, Swap | CompareExchange) => "LLD/SCD loop",
+            (8, CompareExchangeWeak) => "LLD/SCD",
+            (_, Swap | CompareExchange) => "LL/SC loop",
+            (_, CompareExchangeWeak) => "LL/SC",
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     test_atomic!(isize);
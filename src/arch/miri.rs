@@ -0,0 +1,232 @@
+// Miri
+//
+// Miri cannot run inline assembly, so this implements atomic operations by
+// using operations that Miri understands: plain (non-atomic) copies of
+// MaybeUninit<T> in a critical section of a spinlock built from core's
+// atomics. Locks are indexed by address (striped locks) so that operations on
+// different addresses rarely synchronize with each other.
+//
+// This preserves uninitialized bytes, so Miri still detects uses of
+// uninitialized values loaded from atomics, and still detects data races
+// between atomic operations and non-atomic accesses.
+//
+// Limitations:
+// - Operations are stronger than the specified memory ordering (every
+//   operation on the same address synchronizes with the previous one), so
+//   Miri cannot detect bugs caused by insufficient ordering on these atomics.
+// - CAS compares values byte by byte and stops at the first differing byte,
+//   so values that differ in an initialized byte before any uninitialized one
+//   are compared as in the native implementations. However, Miri has no way
+//   to freeze uninitialized bytes, so reaching an uninitialized byte (e.g.,
+//   padding of otherwise equal values) is reported as UB, while the native
+//   implementations compare it after freezing. (Valgrind also reports it.)
+
+use core::{
+    mem,
+    mem::MaybeUninit,
+    sync::atomic::{self, AtomicBool, Ordering},
+};
+
+use crate::{
+    info::Strategy,
+    raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap},
+};
+
+// A prime number, so that addresses aligned to large powers of two are
+// distributed across all locks.
+const LEN: usize = 67;
+
+#[allow(clippy::declare_interior_mutable_const)] // only used to initialize LOCKS
+const UNLOCKED: AtomicBool = AtomicBool::new(false);
+static LOCKS: [AtomicBool; LEN] = [UNLOCKED; LEN];
+
+struct Guard(&'static AtomicBool);
+
+impl Drop for Guard {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+#[inline]
+fn lock(addr: usize, order: Ordering) -> Guard {
+    // Operations on different addresses may use different locks, so
+    // SeqCst operations need a fence to be totally ordered.
+    if order == Ordering::SeqCst {
+        atomic::fence(Ordering::SeqCst);
+    }
+    let lock = &LOCKS[(addr >> 3) % LEN];
+    while lock.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {
+        core::hint::spin_loop();
+    }
+    Guard(lock)
+}
+
+/// Compares `a` and `b` byte by byte, stopping at the first differing byte.
+///
+/// # Safety
+///
+/// `a` and `b` must be valid for reads, and the bytes up to and including the
+/// first differing byte (or all bytes if there is none) must be initialized.
+#[inline]
+unsafe fn eq<T>(a: &MaybeUninit<T>, b: &MaybeUninit<T>) -> bool {
+    let a: *const MaybeUninit<T> = a;
+    let b: *const MaybeUninit<T> = b;
+    let (a, b) = (a.cast::<MaybeUninit<u8>>(), b.cast::<MaybeUninit<u8>>());
+    for i in 0..mem::size_of::<T>() {
+        // SAFETY: the caller must uphold the safety contract.
+        if unsafe { a.add(i).read().assume_init() != b.add(i).read().assume_init() } {
+            return false;
+        }
+    }
+    true
+}
+
+macro_rules! atomic {
+    ($int_type:ident) => {
+        impl crate::arch::NotLockFree for $int_type {}
+        impl AtomicLoad for $int_type {
+            #[inline]
+            unsafe fn atomic_load(
+                src: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                debug_assert!(src as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the lock guarantees exclusive access to src.
+                unsafe {
+                    let guard = lock(src as usize, order);
+                    let v = src.read();
+                    drop(guard);
+                    out.write(v);
+                }
+            }
+        }
+        impl AtomicStore for $int_type {
+            #[inline]
+            unsafe fn atomic_store(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the lock guarantees exclusive access to dst.
+                unsafe {
+                    let v = val.read();
+                    let _guard = lock(dst as usize, order);
+                    dst.write(v);
+                }
+            }
+        }
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the lock guarantees exclusive access to dst.
+                unsafe {
+                    let v = val.read();
+                    let guard = lock(dst as usize, order);
+                    let prev = dst.read();
+                    dst.write(v);
+                    drop(guard);
+                    out.write(prev);
+                }
+            }
+        }
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                let order = if success == Ordering::SeqCst || failure == Ordering::SeqCst {
+                    Ordering::SeqCst
+                } else {
+                    success
+                };
+                // SAFETY: the caller must uphold the safety contract, and
+                // the lock guarantees exclusive access to dst.
+                // Reaching uninitialized bytes in the comparison is reported by
+                // Miri (see the comment at the top of this file).
+                unsafe {
+                    let old = old.read();
+                    let new = new.read();
+                    let guard = lock(dst as usize, order);
+                    let prev = dst.read();
+                    let r = eq(&prev, &old);
+                    if r {
+                        dst.write(new);
+                    }
+                    drop(guard);
+                    out.write(prev);
+                    r
+                }
+            }
+        }
+    };
+}
+
+atomic!(i8);
+atomic!(u8);
+atomic!(i16);
+atomic!(u16);
+atomic!(i32);
+atomic!(u32);
+atomic!(i64);
+atomic!(u64);
+atomic!(i128);
+atomic!(u128);
+atomic!(isize);
+atomic!(usize);
+
+impl super::Describe for super::Native {
+    fn describe(_size: usize, _op: super::Op) -> Strategy {
+        Strategy::new("spinlock table (Miri)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    test_atomic!(isize);
+    test_atomic!(usize);
+    test_atomic!(i8);
+    test_atomic!(u8);
+    test_atomic!(i16);
+    test_atomic!(u16);
+    test_atomic!(i32);
+    test_atomic!(u32);
+    test_atomic!(i64);
+    test_atomic!(u64);
+    test_atomic!(i128);
+    test_atomic!(u128);
+    stress_test!(u8);
+    stress_test!(u16);
+    stress_test!(u32);
+    stress_test!(u64);
+    stress_test!(u128);
+}
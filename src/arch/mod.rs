@@ -7,37 +7,110 @@
 // - LLVM LangRef: https://llvm.org/docs/LangRef.html#inline-assembler-expressions
 // - inline assembly related issues in rust-lang/rust: https://github.com/rust-lang/rust/labels/A-inline-assembly
 
-#[cfg(not(any(
-    target_arch = "x86",
-    target_arch = "x86_64",
-    all(
-        target_arch = "arm",
-        any(
-            target_feature = "v6",
-            atomic_maybe_uninit_target_feature = "v6",
-            target_os = "linux",
-            target_os = "android",
-        ),
-    ),
-    target_arch = "aarch64",
-    target_arch = "riscv32",
-    target_arch = "riscv64",
-    target_arch = "loongarch64",
-    target_arch = "avr",
-    target_arch = "hexagon",
-    target_arch = "mips",
-    target_arch = "mips32r6",
-    target_arch = "mips64",
-    target_arch = "mips64r6",
-    target_arch = "msp430",
-    target_arch = "powerpc",
-    target_arch = "powerpc64",
-    target_arch = "s390x",
-)))]
-compile_error!("this target is not supported yet");
+cfg_native! {
+    {}
+    {
+        #[cfg(not(miri))]
+        mod custom;
+    }
+}
+
+// The native backends pass values to and from asm! in registers, as operands
+// of type `Reg<T>`.
+//
+// On Rust 1.74+, MaybeUninit<T> can be used as a register operand, so
+// potentially uninitialized values are passed as is. On older compilers,
+// `Reg<T>` is `T`: input values are loaded by a separate asm! (see `impl_load_reg!`),
+// and outputs are returned as integers. This is sound even if the value
+// contains uninitialized bytes, because values returned from asm! are always
+// initialized. build.rs also selects the latter when MemorySanitizer is enabled,
+// because it reports uninitialized bytes in register operands of asm!.
+cfg_native! {
+    {
+        use core::mem::MaybeUninit;
+
+        #[cfg(not(atomic_maybe_uninit_no_asm_maybe_uninit))]
+        pub(crate) type Reg<T> = MaybeUninit<T>;
+        #[cfg(atomic_maybe_uninit_no_asm_maybe_uninit)]
+        pub(crate) type Reg<T> = T;
+
+        /// Loads the value at `src` to pass it to asm! as a register operand.
+        ///
+        /// # Safety
+        ///
+        /// `src` must be valid for reads and properly aligned.
+        #[cfg(not(atomic_maybe_uninit_no_asm_maybe_uninit))]
+        #[inline]
+        pub(crate) unsafe fn load_reg<T: Copy>(src: *const MaybeUninit<T>) -> Reg<T> {
+            // SAFETY: the caller must uphold the safety contract.
+            unsafe { src.read() }
+        }
+        /// Loads the value at `src` to pass it to asm! as a register operand.
+        ///
+        /// # Safety
+        ///
+        /// `src` must be valid for reads and properly aligned.
+        #[cfg(atomic_maybe_uninit_no_asm_maybe_uninit)]
+        #[inline]
+        pub(crate) unsafe fn load_reg<T: LoadReg>(src: *const MaybeUninit<T>) -> Reg<T> {
+            // SAFETY: the caller must uphold the safety contract.
+            unsafe { T::load_reg(src) }
+        }
+
+        /// Converts a register operand returned from asm! to `MaybeUninit<T>`.
+        #[inline]
+        pub(crate) fn from_reg<T: Copy>(v: Reg<T>) -> MaybeUninit<T> {
+            #[cfg(not(atomic_maybe_uninit_no_asm_maybe_uninit))]
+            {
+                v
+            }
+            #[cfg(atomic_maybe_uninit_no_asm_maybe_uninit)]
+            {
+                MaybeUninit::new(v)
+            }
+        }
+
+        // Implemented in each backend, usually by impl_load_reg!.
+        #[cfg(atomic_maybe_uninit_no_asm_maybe_uninit)]
+        pub(crate) trait LoadReg: Copy {
+            unsafe fn load_reg(src: *const MaybeUninit<Self>) -> Self;
+        }
+
+        // Implements `LoadReg` for `$int_type` with the given instructions that
+        // load the value at `{src}` into `{v}` of the register class `$reg`.
+        // Additional operands (e.g., clobbers) can be given after `;`.
+        // Only used on compilers that don't support MaybeUninit<T> register
+        // operands.
+        macro_rules! impl_load_reg {
+            ($int_type:ident, $reg:tt, $($template:expr),+ $(; $($operand:tt)*)?) => {
+                #[cfg(atomic_maybe_uninit_no_asm_maybe_uninit)]
+                impl super::LoadReg for $int_type {
+                    #[inline]
+                    unsafe fn load_reg(src: *const MaybeUninit<Self>) -> Self {
+                        let v;
+                        // SAFETY: the caller must guarantee that `src` is valid for reads.
+                        unsafe {
+                            core::arch::asm!(
+                                $($template),+,
+                                src = in(reg) ptr_reg!(src),
+                                v = lateout($reg) v,
+                                $($($operand)*,)?
+                                options(nostack, preserves_flags, readonly),
+                            );
+                        }
+                        v
+                    }
+                }
+            };
+        }
+    }
+    {}
+}
 
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
 #[cfg(target_arch = "aarch64")]
 mod aarch64;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
 #[cfg(target_arch = "arm")]
 #[cfg(all(
     any(target_feature = "v6", atomic_maybe_uninit_target_feature = "v6"),
@@ -49,12 +122,21 @@ mod aarch64;
     )),
 ))]
 mod arm;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
 #[cfg(target_arch = "arm")]
 #[cfg(all(
     any(target_os = "linux", target_os = "android"),
     not(any(target_feature = "v6", atomic_maybe_uninit_target_feature = "v6")),
 ))]
 mod arm_linux;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
+#[cfg(target_arch = "arm")]
+#[cfg(all(
+    not(any(target_os = "linux", target_os = "android")),
+    not(any(target_feature = "v6", atomic_maybe_uninit_target_feature = "v6")),
+))]
+mod arm_pre_v6;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
 #[cfg(target_arch = "arm")]
 #[cfg(any(
     target_feature = "v8",
@@ -63,12 +145,26 @@ mod arm_linux;
     atomic_maybe_uninit_target_feature = "v8m",
 ))]
 mod armv8;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
 #[cfg(target_arch = "avr")]
 mod avr;
+#[cfg(feature = "critical-section")]
+mod cs;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
+#[cfg(feature = "fallback")]
+mod fallback;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
 #[cfg(target_arch = "hexagon")]
 mod hexagon;
+#[cfg(loom)]
+pub(crate) mod loom;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
 #[cfg(target_arch = "loongarch64")]
 mod loongarch;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
+#[cfg(target_arch = "m68k")]
+mod m68k;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
 #[cfg(any(
     target_arch = "mips",
     target_arch = "mips32r6",
@@ -76,13 +172,68 @@ mod loongarch;
     target_arch = "mips64r6",
 ))]
 mod mips;
+#[cfg(miri)]
+mod miri;
+#[cfg(atomic_maybe_uninit_sanitize_memory)]
+pub(crate) mod msan;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
 #[cfg(target_arch = "msp430")]
 mod msp430;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
 #[cfg(any(target_arch = "powerpc", target_arch = "powerpc64"))]
 mod powerpc;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
 #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
 mod riscv;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
 #[cfg(target_arch = "s390x")]
 mod s390x;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
+#[cfg(any(target_arch = "sparc", target_arch = "sparc64"))]
+mod sparc;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
+#[cfg(any(target_arch = "aarch64", target_arch = "x86", target_arch = "x86_64"))]
+mod tsan;
+#[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 mod x86;
+
+// Implemented for types whose operations are (or may be) implemented using locks,
+// i.e., types for which `AtomicMaybeUninit::IS_ALWAYS_LOCK_FREE` is false.
+// Disabling interrupts on single-core systems is considered lock-free, but
+// the critical section provided by the critical-section crate may be a lock
+// (e.g., RP2040's hardware spinlock).
+pub(crate) trait NotLockFree {}
+
+// Implemented for types implemented by fallback.rs.
+pub(crate) trait Fallback {}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Op {
+    Load,
+    Store,
+    Swap,
+    CompareExchange,
+    CompareExchangeWeak,
+}
+
+// Describes the strategy used for `op` on values of `size` bytes (see info.rs).
+// Exactly one backend module (the native module of the current architecture,
+// custom.rs, or miri.rs) implements this for `Native`, next to the cfgs that
+// select the instructions.
+pub(crate) trait Describe {
+    fn describe(size: usize, op: Op) -> crate::info::Strategy;
+}
+pub(crate) enum Native {}
+
+pub(crate) fn describe(size: usize, op: Op, fallback: bool) -> crate::info::Strategy {
+    #[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
+    #[cfg(feature = "fallback")]
+    {
+        if fallback {
+            return self::fallback::describe(op);
+        }
+    }
+    let _ = fallback;
+    <Native as Describe>::describe(size, op)
+}
@@ -0,0 +1,157 @@
+// MemorySanitizer support.
+//
+// MSan cannot see memory accesses in inline assembly, so the shadow (which
+// tracks which bits are uninitialized) of the values read and written by
+// atomic operations implemented in inline assembly is not updated: values
+// loaded from atomics would be considered fully initialized (or keep a stale
+// shadow), hiding real uses of uninitialized bytes such as padding.
+//
+// To avoid this, when `-Z sanitizer=memory` is used, AtomicMaybeUninit (and
+// the exported libcalls) copies the shadow along with the value by using
+// `__msan_copy_shadow`, so partially initialized values keep their shadow bits.
+// This is not done by the functions of the raw traits: they take pointers
+// provided by the caller, and the shadow is copied around them.
+//
+// Note that the shadow is updated separately from the atomic operation itself,
+// so if operations on the same atomic race with each other, the shadow of the
+// result may not exactly match the value.
+//
+// Refs:
+// - https://github.com/llvm/llvm-project/blob/llvmorg-17.0.0/compiler-rt/include/sanitizer/msan_interface.h
+
+use core::{ffi::c_void, mem, mem::MaybeUninit};
+
+extern "C" {
+    fn __msan_copy_shadow(dst: *const c_void, src: *const c_void, size: usize);
+}
+
+/// Copies the shadow of `src` to `dst`.
+///
+/// # Safety
+///
+/// `dst` and `src` must be valid for reads and writes of `T`.
+#[inline]
+pub(crate) unsafe fn copy_shadow<T>(dst: *mut MaybeUninit<T>, src: *const MaybeUninit<T>) {
+    // SAFETY: the caller must uphold the safety contract.
+    // MSan only accesses the shadow memory of `dst` and `src`.
+    unsafe { __msan_copy_shadow(dst as *const c_void, src.cast::<c_void>(), mem::size_of::<T>()) }
+}
+
+/// Saves the shadow of `src` to a new value, to be copied to the result of
+/// a read-modify-write operation.
+///
+/// # Safety
+///
+/// `src` must be valid for reads of `T`.
+#[inline]
+pub(crate) unsafe fn save_shadow<T>(src: *const MaybeUninit<T>) -> MaybeUninit<T> {
+    let mut shadow = MaybeUninit::<T>::uninit();
+    // SAFETY: the caller must uphold the safety contract.
+    unsafe { copy_shadow(&mut shadow, src) }
+    shadow
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{
+        ffi::c_void,
+        mem::{self, MaybeUninit},
+        sync::atomic::Ordering,
+    };
+
+    use crate::AtomicMaybeUninit;
+
+    extern "C" {
+        fn __msan_test_shadow(x: *const c_void, size: usize) -> isize;
+    }
+
+    // Returns the offset of the first uninitialized byte of `v`, or -1 if `v`
+    // is fully initialized.
+    fn first_uninit<T>(v: &MaybeUninit<T>) -> isize {
+        let v: *const MaybeUninit<T> = v;
+        // SAFETY: `v` is valid for reads of `T`, and MSan only accesses its shadow.
+        unsafe { __msan_test_shadow(v.cast::<c_void>(), mem::size_of::<T>()) }
+    }
+
+    #[derive(Clone, Copy)]
+    #[repr(C, align(4))]
+    struct Padded(u8, u16);
+
+    // A value whose byte at offset 1 is uninitialized.
+    fn padded() -> MaybeUninit<u32> {
+        // SAFETY: MaybeUninit<u32> can hold any bytes of the same size.
+        unsafe { mem::transmute::<Padded, MaybeUninit<u32>>(Padded(1, 1)) }
+    }
+
+    #[test]
+    fn load_store() {
+        let a = AtomicMaybeUninit::new(padded());
+        assert_eq!(first_uninit(&a.load(Ordering::Relaxed)), 1);
+        a.store(MaybeUninit::new(0), Ordering::Relaxed);
+        assert_eq!(first_uninit(&a.load(Ordering::Relaxed)), -1);
+        a.store(padded(), Ordering::Relaxed);
+        assert_eq!(first_uninit(&a.load(Ordering::Relaxed)), 1);
+    }
+
+    #[test]
+    fn swap() {
+        let a = AtomicMaybeUninit::new(padded());
+        assert_eq!(first_uninit(&a.swap(MaybeUninit::new(0), Ordering::Relaxed)), 1);
+        assert_eq!(first_uninit(&a.swap(padded(), Ordering::Relaxed)), -1);
+        assert_eq!(first_uninit(&a.load(Ordering::Relaxed)), 1);
+    }
+
+    #[test]
+    fn compare_exchange() {
+        let a = AtomicMaybeUninit::new(padded());
+        let prev = a
+            .compare_exchange(MaybeUninit::new(0), padded(), Ordering::Relaxed, Ordering::Relaxed)
+            .unwrap_err();
+        assert_eq!(first_uninit(&prev), 1);
+        a.store(MaybeUninit::new(0), Ordering::Relaxed);
+        let prev = a
+            .compare_exchange(MaybeUninit::new(0), padded(), Ordering::Relaxed, Ordering::Relaxed)
+            .unwrap();
+        assert_eq!(first_uninit(&prev), -1);
+        assert_eq!(first_uninit(&a.load(Ordering::Relaxed)), 1);
+        let prev = a
+            .compare_exchange_weak(
+                padded(),
+                MaybeUninit::new(0),
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            )
+            .unwrap_or_else(|prev| prev);
+        assert_eq!(first_uninit(&prev), 1);
+    }
+
+    #[cfg(feature = "libcalls")]
+    #[test]
+    fn libcalls() {
+        extern "C" {
+            fn __atomic_load_4(src: *const MaybeUninit<u32>, order: i32) -> MaybeUninit<u32>;
+            fn __atomic_store_4(dst: *mut MaybeUninit<u32>, val: MaybeUninit<u32>, order: i32);
+            fn __atomic_exchange_4(
+                dst: *mut MaybeUninit<u32>,
+                val: MaybeUninit<u32>,
+                order: i32,
+            ) -> MaybeUninit<u32>;
+            fn __sync_val_compare_and_swap_4(
+                dst: *mut MaybeUninit<u32>,
+                old: MaybeUninit<u32>,
+                new: MaybeUninit<u32>,
+            ) -> MaybeUninit<u32>;
+        }
+        // SAFETY: `v` is valid and aligned.
+        unsafe {
+            let mut v = padded();
+            assert_eq!(first_uninit(&__atomic_load_4(&v, 0)), 1);
+            __atomic_store_4(&mut v, MaybeUninit::new(0), 0);
+            assert_eq!(first_uninit(&__atomic_load_4(&v, 0)), -1);
+            assert_eq!(first_uninit(&__atomic_exchange_4(&mut v, padded(), 0)), -1);
+            assert_eq!(first_uninit(&__atomic_load_4(&v, 0)), 1);
+            let prev = __sync_val_compare_and_swap_4(&mut v, MaybeUninit::new(0), padded());
+            assert_eq!(first_uninit(&prev), 1);
+        }
+    }
+}
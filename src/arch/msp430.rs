@@ -1,11 +1,72 @@
 // MSP430
+//
+// Refs:
+// - MSP430x5xx and MSP430x6xx Family User's Guide https://www.ti.com/lit/ug/slau208q/slau208q.pdf
+// - portable-atomic https://github.com/taiki-e/portable-atomic
+//
+// MSP430 is single-core and has no atomic read-modify-write instructions, so
+// swap and CAS are implemented by disabling interrupts. 32-bit and 64-bit
+// atomics (which cannot be accessed by a single instruction) are also
+// implemented by disabling interrupts, in the same way as avr.rs.
 
-use core::{arch::asm, mem::MaybeUninit, sync::atomic::Ordering};
+use core::{
+    arch::asm,
+    mem::{self, MaybeUninit},
+    sync::atomic::Ordering,
+};
 
-use crate::raw::{AtomicLoad, AtomicStore};
+// When the `critical-section` feature is enabled, the critical section provided
+// by the critical-section crate is used instead of disabling interrupts (see cs.rs).
+#[cfg(feature = "critical-section")]
+use super::cs::{disable, restore};
+use super::{from_reg, load_reg, Reg};
+use crate::{
+    info::Strategy,
+    raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap},
+};
+
+// See portable-atomic's interrupt module for more.
+#[cfg(not(feature = "critical-section"))]
+#[inline]
+fn disable() -> u16 {
+    let sr: u16;
+    // SAFETY: reading the status register and disabling interrupts are safe.
+    unsafe {
+        // Do not use `nomem` and `readonly` because prevent subsequent memory accesses from being reordered before interrupts are disabled.
+        // Do not use `preserves_flags` because DINT modifies the GIE (global interrupt enable) bit of the status register.
+        asm!(
+            "mov r2, {0}",
+            "dint",
+            // A NOP is required after DINT (see the user's guide).
+            "nop",
+            out(reg) sr,
+            options(nostack),
+        );
+    }
+    sr
+}
+#[cfg(not(feature = "critical-section"))]
+#[inline]
+unsafe fn restore(sr: u16) {
+    // GIE (global interrupt enable) bit
+    const GIE: u16 = 0x8;
+    // Only re-enable interrupts if they were enabled before the critical section.
+    if sr & GIE != 0 {
+        // SAFETY: the caller must guarantee that the state was retrieved by the previous `disable`,
+        unsafe {
+            // Do not use `nomem` and `readonly` because prevent preceding memory accesses from being reordered after interrupts are enabled.
+            // Do not use `preserves_flags` because EINT modifies the GIE (global interrupt enable) bit of the status register.
+            // NOPs around EINT are required on MSP430X (see the user's guide).
+            asm!("nop", "eint", "nop", options(nostack));
+        }
+    }
+}
 
 macro_rules! atomic {
     ($int_type:ident, $asm_suffix:tt) => {
+        #[cfg(feature = "critical-section")]
+        impl crate::arch::NotLockFree for $int_type {}
+        impl_load_reg!($int_type, reg, concat!("mov", $asm_suffix, " @{src}, {v}"));
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
@@ -15,18 +76,16 @@ macro_rules! atomic {
             ) {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let v: Reg<Self>;
                     // atomic load is always SeqCst.
                     asm!(
-                        // TODO: use mem to mem mov?
-                        // (atomic) load from src to tmp
-                        concat!("mov", $asm_suffix, " @{src}, {tmp}"),
-                        // store tmp to out
-                        concat!("mov", $asm_suffix, " {tmp}, 0({out})"),
+                        // (atomic) load from src to v
+                        concat!("mov", $asm_suffix, " @{src}, {v}"),
                         src = in(reg) src,
-                        out = inout(reg) out => _,
-                        tmp = lateout(reg) _,
+                        v = lateout(reg) v,
                         options(nostack, preserves_flags),
                     );
+                    out.write(from_reg(v));
                 }
             }
         }
@@ -39,21 +98,238 @@ macro_rules! atomic {
             ) {
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
                     // atomic store is always SeqCst.
                     asm!(
-                        // TODO: use mem to mem mov?
-                        // load from val to tmp
-                        concat!("mov", $asm_suffix, " @{val}, {tmp}"),
-                        // (atomic) store tmp to dst
-                        concat!("mov", $asm_suffix, " {tmp}, 0({dst})"),
-                        dst = inout(reg) dst => _,
+                        // (atomic) store val to dst
+                        concat!("mov", $asm_suffix, " {val}, 0({dst})"),
+                        dst = in(reg) dst,
                         val = in(reg) val,
-                        tmp = lateout(reg) _,
                         options(nostack, preserves_flags),
                     );
                 }
             }
         }
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
+                    let s = disable();
+                    asm!(
+                        // load from dst to prev
+                        concat!("mov", $asm_suffix, " @{dst}, {prev}"),
+                        // store val to dst
+                        concat!("mov", $asm_suffix, " {val}, 0({dst})"),
+                        dst = in(reg) dst,
+                        val = in(reg) val,
+                        prev = out(reg) prev,
+                        options(nostack, preserves_flags),
+                    );
+                    restore(s);
+                    out.write(from_reg(prev));
+                }
+            }
+        }
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let r: u16;
+                    let s = disable();
+                    // Do not use `preserves_flags` because CMP modifies the status register.
+                    asm!(
+                        // load from dst to prev
+                        concat!("mov", $asm_suffix, " @{dst}, {prev}"),
+                        "clr {r}",
+                        // compare old and prev
+                        concat!("cmp", $asm_suffix, " {old}, {prev}"),
+                        // if compare failed, jump to 2
+                        "jne 2f",
+                        // store new to dst
+                        concat!("mov", $asm_suffix, " {new}, 0({dst})"),
+                        "mov #1, {r}",
+                        "2:",
+                        dst = in(reg) dst,
+                        old = in(reg) old,
+                        new = in(reg) new,
+                        prev = out(reg) prev,
+                        r = out(reg) r,
+                        options(nostack),
+                    );
+                    restore(s);
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+        }
+    };
+}
+
+// Copies the value pointed to by `src` to `dst` word by word.
+// Accesses to the atomic location in the critical section use this instead of
+// Rust reads/writes: the critical section serializes them with other accesses
+// at the hardware level, but they would still be data races in the Rust
+// abstract machine.
+#[inline]
+unsafe fn copy<T>(dst: *mut MaybeUninit<T>, src: *const MaybeUninit<T>) {
+    #[allow(clippy::cast_possible_truncation)]
+    let n = (mem::size_of::<T>() / 2) as u16;
+    // SAFETY: the caller must guarantee that `src` is valid for reads and
+    // `dst` is valid for writes, and both are 2-byte aligned.
+    unsafe {
+        // Do not use `preserves_flags` because DEC modifies the status register.
+        asm!(
+            "2:",
+            "mov @{src}, 0({dst})",
+            "incd {src}",
+            "incd {dst}",
+            "dec {n}",
+            // if n != 0, jump to 2
+            "jnz 2b",
+            src = inout(reg) src => _,
+            dst = inout(reg) dst => _,
+            n = inout(reg) n => _,
+            options(nostack),
+        );
+    }
+}
+
+// Compares the values pointed to by `a` and `b` and returns true if they are equal.
+// The comparison is done in asm because the values may be uninitialized.
+#[inline]
+unsafe fn eq16(a: *const u16, b: *const u16) -> bool {
+    let r: u16;
+    // SAFETY: the caller must guarantee that `a` and `b` are valid for reads.
+    unsafe {
+        // Do not use `preserves_flags` because CMP modifies the status register.
+        asm!(
+            "clr {r}",
+            "cmp @{a}, 0({b})",
+            // if compare failed, jump to 2
+            "jne 2f",
+            "mov #1, {r}",
+            "2:",
+            a = in(reg) a,
+            b = in(reg) b,
+            r = out(reg) r,
+            options(nostack, readonly),
+        );
+    }
+    debug_assert!(r == 0 || r == 1, "r={}", r);
+    r != 0
+}
+#[inline]
+unsafe fn eq32(a: *const u16, b: *const u16) -> bool {
+    // SAFETY: the caller must guarantee that `a` and `b` are valid for reads.
+    unsafe { eq16(a, b) && eq16(a.add(1), b.add(1)) }
+}
+#[inline]
+unsafe fn eq64(a: *const u16, b: *const u16) -> bool {
+    // SAFETY: the caller must guarantee that `a` and `b` are valid for reads.
+    unsafe { eq32(a, b) && eq32(a.add(2), b.add(2)) }
+}
+
+macro_rules! atomic_multi_word {
+    ($int_type:ident, $eq:ident) => {
+        #[cfg(feature = "critical-section")]
+        impl crate::arch::NotLockFree for $int_type {}
+        impl AtomicLoad for $int_type {
+            #[inline]
+            unsafe fn atomic_load(
+                src: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let s = disable();
+                    copy(out, src);
+                    restore(s);
+                }
+            }
+        }
+        impl AtomicStore for $int_type {
+            #[inline]
+            unsafe fn atomic_store(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let v = val.read();
+                    let s = disable();
+                    copy(dst, &v);
+                    restore(s);
+                }
+            }
+        }
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let v = val.read();
+                    let mut prev = MaybeUninit::<Self>::uninit();
+                    let s = disable();
+                    copy(&mut prev, dst);
+                    copy(dst, &v);
+                    restore(s);
+                    out.write(prev);
+                }
+            }
+        }
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let new = new.read();
+                    let mut prev = MaybeUninit::<Self>::uninit();
+                    let s = disable();
+                    copy(&mut prev, dst);
+                    let r =
+                        $eq(old.cast::<u16>(), (&prev as *const MaybeUninit<Self>).cast::<u16>());
+                    if r {
+                        copy(dst, &new);
+                    }
+                    restore(s);
+                    out.write(prev);
+                    r
+                }
+            }
+        }
     };
 }
 
@@ -63,6 +339,24 @@ atomic!(i16, ".w");
 atomic!(u16, ".w");
 atomic!(isize, ".w");
 atomic!(usize, ".w");
+atomic_multi_word!(i32, eq32);
+atomic_multi_word!(u32, eq32);
+atomic_multi_word!(i64, eq64);
+atomic_multi_word!(u64, eq64);
+
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{Load, Store};
+        #[cfg(feature = "critical-section")]
+        let description = "critical section";
+        #[cfg(not(feature = "critical-section"))]
+        let description = "disabling interrupts (GIE)";
+        Strategy::new(match (size, op) {
+            (1 | 2, Load | Store) => "MOV",
+            (_, _) => description,
+        })
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -72,4 +366,8 @@ mod tests {
     test_atomic!(u8);
     test_atomic!(i16);
     test_atomic!(u16);
+    test_atomic!(i32);
+    test_atomic!(u32);
+    test_atomic!(i64);
+    test_atomic!(u64);
 }
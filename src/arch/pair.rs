@@ -0,0 +1,58 @@
+// Helper for passing double-width values to asm! as a pair of registers.
+
+use core::mem::{self, MaybeUninit};
+
+use crate::arch::{from_reg, Reg};
+
+// The halves of a double-width value, in memory order.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub(crate) struct Pair<H: Copy> {
+    #[cfg(target_endian = "little")]
+    pub(crate) lo: H,
+    pub(crate) hi: H,
+    #[cfg(target_endian = "big")]
+    pub(crate) lo: H,
+}
+
+// Loads the value at `src` to pass its halves to asm! as register operands.
+#[cfg(not(atomic_maybe_uninit_no_asm_maybe_uninit))]
+#[inline]
+pub(crate) unsafe fn load_pair<T, H: Copy>(src: *const MaybeUninit<T>) -> Pair<Reg<H>> {
+    debug_assert!(mem::size_of::<T>() == mem::size_of::<H>() * 2);
+    // SAFETY: the caller must guarantee that `src` is valid for reads and
+    // properly aligned, and T has the same size as the pair of H.
+    unsafe { src.cast::<Pair<MaybeUninit<H>>>().read() }
+}
+// Loads the value at `src` to pass its halves to asm! as register operands.
+#[cfg(atomic_maybe_uninit_no_asm_maybe_uninit)]
+#[inline]
+pub(crate) unsafe fn load_pair<T, H: crate::arch::LoadReg>(
+    src: *const MaybeUninit<T>,
+) -> Pair<Reg<H>> {
+    debug_assert!(mem::size_of::<T>() == mem::size_of::<H>() * 2);
+    let src = src.cast::<Pair<MaybeUninit<H>>>();
+    // SAFETY: the caller must guarantee that `src` is valid for reads and
+    // properly aligned, and T has the same size as the pair of H.
+    unsafe {
+        Pair {
+            lo: crate::arch::load_reg(core::ptr::addr_of!((*src).lo)),
+            hi: crate::arch::load_reg(core::ptr::addr_of!((*src).hi)),
+        }
+    }
+}
+
+// Converts a pair of register operands returned from asm! to `MaybeUninit<T>`.
+#[inline]
+pub(crate) fn from_pair<T, H: Copy>(p: Pair<Reg<H>>) -> MaybeUninit<T> {
+    debug_assert!(mem::size_of::<T>() == mem::size_of::<H>() * 2);
+    let mut out = MaybeUninit::<T>::uninit();
+    // SAFETY: T has the same size as the pair of H, and MaybeUninit<T> is
+    // at least as aligned as MaybeUninit<H>.
+    unsafe {
+        out.as_mut_ptr()
+            .cast::<Pair<MaybeUninit<H>>>()
+            .write(Pair { lo: from_reg(p.lo), hi: from_reg(p.hi) });
+    }
+    out
+}
@@ -23,58 +23,31 @@
 #[path = "partword.rs"]
 mod partword;
 
+#[cfg(target_arch = "powerpc64")]
+#[cfg(any(
+    target_feature = "quadword-atomics",
+    atomic_maybe_uninit_target_feature = "quadword-atomics",
+))]
+#[path = "pair.rs"]
+mod pair;
+
 use core::{
     arch::asm,
     mem::{self, MaybeUninit},
     sync::atomic::Ordering,
 };
 
-use crate::raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap};
-
-#[cfg(target_arch = "powerpc64")]
-#[cfg(any(
-    target_feature = "quadword-atomics",
-    atomic_maybe_uninit_target_feature = "quadword-atomics",
-))]
-#[cfg(target_endian = "big")]
-macro_rules! p128h {
-    () => {
-        "0"
-    };
-}
-#[cfg(target_arch = "powerpc64")]
-#[cfg(any(
-    target_feature = "quadword-atomics",
-    atomic_maybe_uninit_target_feature = "quadword-atomics",
-))]
-#[cfg(target_endian = "big")]
-macro_rules! p128l {
-    () => {
-        "8"
-    };
-}
 #[cfg(target_arch = "powerpc64")]
 #[cfg(any(
     target_feature = "quadword-atomics",
     atomic_maybe_uninit_target_feature = "quadword-atomics",
 ))]
-#[cfg(target_endian = "little")]
-macro_rules! p128h {
-    () => {
-        "8"
-    };
-}
-#[cfg(target_arch = "powerpc64")]
-#[cfg(any(
-    target_feature = "quadword-atomics",
-    atomic_maybe_uninit_target_feature = "quadword-atomics",
-))]
-#[cfg(target_endian = "little")]
-macro_rules! p128l {
-    () => {
-        "0"
-    };
-}
+use self::pair::{from_pair, load_pair, Pair};
+use super::{from_reg, load_reg, Reg};
+use crate::{
+    info::Strategy,
+    raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap},
+};
 
 macro_rules! atomic_rmw {
     ($op:ident, $order:ident) => {
@@ -101,8 +74,28 @@ fn extract_cr0(r: Cr) -> bool {
     r & 0x20000000 != 0
 }
 
+// Zero-extends sub-word old for comparison with the value loaded by
+// LBARX/LHARX, which zero-extend it. The upper bits of register operands
+// narrower than 32 bits are undefined (CMPW only compares the lower 32 bits).
+macro_rules! zext {
+    ("b") => {
+        "clrlwi {old}, {old}, 24"
+    };
+    ("h") => {
+        "clrlwi {old}, {old}, 16"
+    };
+    ("w") => {
+        ""
+    };
+    ("d") => {
+        ""
+    };
+}
+
 macro_rules! atomic_load_store {
     ($int_type:ident, $l_suffix:tt, $asm_suffix:tt) => {
+        // Use the indexed form because {src} may be r0, which is read as zero in RA.
+        impl_load_reg!($int_type, reg, concat!("l", $l_suffix, "x {v}, 0, {src}"));
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
@@ -116,23 +109,21 @@ macro_rules! atomic_load_store {
                 #[cfg(target_arch = "powerpc64")]
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let v: Reg<Self>;
                     macro_rules! atomic_load_acquire {
                         ($release:tt) => {
                             asm!(
                                 $release,
-                                // (atomic) load from src to tmp
-                                concat!("l", $l_suffix, " {tmp}, 0({src})"),
+                                // (atomic) load from src to v
+                                concat!("l", $l_suffix, " {v}, 0({src})"),
                                 // Lightweight acquire sync
                                 // Refs: https://github.com/boostorg/atomic/blob/boost-1.79.0/include/boost/atomic/detail/core_arch_ops_gcc_ppc.hpp#L47-L62
-                                "cmpd %cr7, {tmp}, {tmp}",
+                                "cmpd %cr7, {v}, {v}",
                                 "bne- %cr7, 2f",
                                 "2:",
                                 "isync",
-                                // store tmp to out
-                                concat!("st", $asm_suffix, " {tmp}, 0({out})"),
                                 src = in(reg_nonzero) ptr_reg!(src),
-                                out = inout(reg_nonzero) ptr_reg!(out) => _,
-                                tmp = lateout(reg_nonzero) _,
+                                v = lateout(reg_nonzero) v,
                                 out("cr7") _,
                                 options(nostack, preserves_flags),
                             )
@@ -141,13 +132,10 @@ macro_rules! atomic_load_store {
                     match order {
                         Ordering::Relaxed => {
                             asm!(
-                                // (atomic) load from src to tmp
-                                concat!("l", $l_suffix, " {tmp}, 0({src})"),
-                                // store tmp to out
-                                concat!("st", $asm_suffix, " {tmp}, 0({out})"),
+                                // (atomic) load from src to v
+                                concat!("l", $l_suffix, " {v}, 0({src})"),
                                 src = in(reg_nonzero) ptr_reg!(src),
-                                out = inout(reg_nonzero) ptr_reg!(out) => _,
-                                tmp = lateout(reg_nonzero) _,
+                                v = lateout(reg_nonzero) v,
                                 options(nostack, preserves_flags),
                             )
                         }
@@ -155,22 +143,21 @@ macro_rules! atomic_load_store {
                         Ordering::SeqCst =>  atomic_load_acquire!("sync"),
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_reg(v));
                 }
                 #[cfg(target_arch = "powerpc")]
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let v: Reg<Self>;
                     macro_rules! atomic_load {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // (atomic) load from src to tmp
+                                // (atomic) load from src to v
                                 $release,
-                                concat!("l", $l_suffix, " {tmp}, 0({src})"),
+                                concat!("l", $l_suffix, " {v}, 0({src})"),
                                 $acquire,
-                                // store tmp to out
-                                concat!("st", $asm_suffix, " {tmp}, 0({out})"),
                                 src = in(reg_nonzero) ptr_reg!(src),
-                                out = inout(reg_nonzero) ptr_reg!(out) => _,
-                                tmp = lateout(reg_nonzero) _,
+                                v = lateout(reg_nonzero) v,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -181,6 +168,7 @@ macro_rules! atomic_load_store {
                         Ordering::SeqCst => atomic_load!("lwsync", "sync"),
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_reg(v));
                 }
             }
         }
@@ -196,17 +184,15 @@ macro_rules! atomic_load_store {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
                     macro_rules! atomic_store {
                         ($release:tt) => {
                             asm!(
-                                // load from val to tmp
-                                concat!("l", $l_suffix, " {tmp}, 0({val})"),
-                                // (atomic) store tmp to dst
+                                // (atomic) store val to dst
                                 $release,
-                                concat!("st", $asm_suffix, " {tmp}, 0({dst})"),
-                                dst = inout(reg_nonzero) ptr_reg!(dst) => _,
-                                val = in(reg_nonzero) ptr_reg!(val),
-                                tmp = lateout(reg_nonzero) _,
+                                concat!("st", $asm_suffix, " {val}, 0({dst})"),
+                                dst = in(reg_nonzero) ptr_reg!(dst),
+                                val = in(reg_nonzero) val,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -241,32 +227,30 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     macro_rules! swap {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // load from val (ptr) to val (val)
-                                concat!("l", $l_suffix, " {val}, 0({val})"),
                                 // (atomic) swap (LL/SC loop)
                                 $release,
                                 "2:",
-                                    // load from dst to tmp
-                                    concat!("l", $asm_suffix, "arx {tmp}, 0, {dst}"),
+                                    // load from dst to prev
+                                    concat!("l", $asm_suffix, "arx {prev}, 0, {dst}"),
                                     // try to store val to dst
                                     concat!("st", $asm_suffix, "cx. {val}, 0, {dst}"),
                                     "bne %cr0, 2b",
                                 $acquire,
-                                // store tmp to out
-                                concat!("st", $asm_suffix, " {tmp}, 0({out})"),
                                 dst = in(reg_nonzero) ptr_reg!(dst),
-                                val = inout(reg_nonzero) ptr_reg!(val) => _,
-                                out = in(reg_nonzero) ptr_reg!(out),
-                                tmp = out(reg_nonzero) _,
+                                val = in(reg_nonzero) val,
+                                prev = out(reg_nonzero) prev,
                                 out("cr0") _,
                                 options(nostack, preserves_flags),
                             )
                         };
                     }
                     atomic_rmw!(swap, order);
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -288,18 +272,18 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let mut r: Cr;
                     macro_rules! cmpxchg {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // load from old/new (ptr) to old/new (val)
-                                concat!("l", $l_suffix, " {old}, 0({old})"),
-                                concat!("l", $l_suffix, " {new}, 0({new})"),
+                                zext!($asm_suffix),
                                 // (atomic) CAS (LL/SC loop)
                                 $release,
                                 "2:",
-                                    concat!("l", $asm_suffix, "arx {tmp}, 0, {dst}"),
-                                    concat!("cmp", $cmp_suffix, " {old}, {tmp}"),
+                                    concat!("l", $asm_suffix, "arx {prev}, 0, {dst}"),
+                                    concat!("cmp", $cmp_suffix, " {old}, {prev}"),
                                     "bne %cr0, 3f", // jump if compare failed
                                     concat!("st", $asm_suffix, "cx. {new}, 0, {dst}"),
                                     "bne %cr0, 2b", // continue loop if store failed
@@ -307,13 +291,10 @@ macro_rules! atomic {
                                 // if compare failed EQ bit is cleared, if stqcx succeeds EQ bit is set.
                                 "mfcr {r}",
                                 $acquire,
-                                // store tmp to out
-                                concat!("st", $asm_suffix, " {tmp}, 0({out})"),
                                 dst = in(reg_nonzero) ptr_reg!(dst),
-                                old = inout(reg_nonzero) ptr_reg!(old) => _,
-                                new = inout(reg_nonzero) ptr_reg!(new) => _,
-                                out = inout(reg_nonzero) ptr_reg!(out) => _,
-                                tmp = out(reg_nonzero) _,
+                                old = inout(reg_nonzero) old => _,
+                                new = in(reg_nonzero) new,
+                                prev = out(reg_nonzero) prev,
                                 r = lateout(reg_nonzero) r,
                                 out("cr0") _,
                                 options(nostack, preserves_flags),
@@ -321,6 +302,56 @@ macro_rules! atomic {
                         };
                     }
                     atomic_rmw!(cmpxchg, order);
+                    out.write(from_reg(prev));
+                    extract_cr0(r)
+                }
+            }
+            #[inline]
+            unsafe fn atomic_compare_exchange_weak(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let order = crate::utils::upgrade_success_ordering(success, failure);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let mut r: Cr;
+                    macro_rules! cmpxchg {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                zext!($asm_suffix),
+                                // (atomic) CAS (LL/SC)
+                                $release,
+                                concat!("l", $asm_suffix, "arx {prev}, 0, {dst}"),
+                                concat!("cmp", $cmp_suffix, " {old}, {prev}"),
+                                "bne %cr0, 3f", // jump if compare failed
+                                concat!("st", $asm_suffix, "cx. {new}, 0, {dst}"),
+                                "3:",
+                                // if compare or store failed EQ bit is cleared, if stqcx succeeds EQ bit is set.
+                                "mfcr {r}",
+                                $acquire,
+                                dst = in(reg_nonzero) ptr_reg!(dst),
+                                old = inout(reg_nonzero) old => _,
+                                new = in(reg_nonzero) new,
+                                prev = out(reg_nonzero) prev,
+                                r = lateout(reg_nonzero) r,
+                                out("cr0") _,
+                                options(nostack, preserves_flags),
+                            )
+                        };
+                    }
+                    atomic_rmw!(cmpxchg, order);
+                    out.write(from_reg(prev));
                     extract_cr0(r)
                 }
             }
@@ -351,40 +382,40 @@ macro_rules! atomic_sub_word {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     // Implement sub-word atomic operations using word-sized LL/SC loop.
                     // Based on assemblies generated by rustc/LLVM.
                     // See also partword.rs.
                     macro_rules! swap {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                concat!("l", $l_suffix, " {val}, 0({val})"),
                                 "slw {mask}, {mask}, {shift}",
                                 "slw {val}, {val}, {shift}",
                                 "and {val}, {val}, {mask}",
                                 // (atomic) swap (LL/SC loop)
                                 $release,
                                 "2:",
-                                    "lwarx {tmp1}, 0, {dst}",
-                                    "andc {tmp2}, {tmp1}, {mask}",
-                                    "or {tmp2}, {val}, {tmp2}",
-                                    "stwcx. {tmp2}, 0, {dst}",
+                                    "lwarx {prev}, 0, {dst}",
+                                    "andc {tmp}, {prev}, {mask}",
+                                    "or {tmp}, {val}, {tmp}",
+                                    "stwcx. {tmp}, 0, {dst}",
                                     "bne %cr0, 2b",
-                                "srw {tmp1}, {tmp1}, {shift}",
+                                "srw {prev}, {prev}, {shift}",
                                 $acquire,
-                                concat!("st", $asm_suffix, " {tmp1}, 0({out})"),
                                 dst = in(reg_nonzero) ptr_reg!(aligned_ptr),
-                                val = inout(reg_nonzero) ptr_reg!(val) => _,
-                                out = in(reg_nonzero) ptr_reg!(out),
+                                val = inout(reg_nonzero) val => _,
                                 shift = in(reg_nonzero) shift,
                                 mask = inout(reg_nonzero) mask => _,
-                                tmp1 = out(reg_nonzero) _,
-                                tmp2 = out(reg_nonzero) _,
+                                prev = out(reg_nonzero) prev,
+                                tmp = out(reg_nonzero) _,
                                 out("cr0") _,
                                 options(nostack, preserves_flags),
                             )
                         };
                     }
                     atomic_rmw!(swap, order);
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -407,6 +438,8 @@ macro_rules! atomic_sub_word {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let mut r: Cr;
                     // Implement sub-word atomic operations using word-sized LL/SC loop.
                     // Based on assemblies generated by rustc/LLVM.
@@ -414,8 +447,6 @@ macro_rules! atomic_sub_word {
                     macro_rules! cmpxchg {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                concat!("l", $l_suffix, " {old}, 0({old})"),
-                                concat!("l", $l_suffix, " {new}, 0({new})"),
                                 "slw {mask}, {mask}, {shift}",
                                 "slw {old}, {old}, {shift}",
                                 "slw {new}, {new}, {shift}",
@@ -424,35 +455,98 @@ macro_rules! atomic_sub_word {
                                 // (atomic) CAS (LL/SC loop)
                                 $release,
                                 "2:",
-                                    "lwarx {tmp2}, 0, {dst}",
-                                    "and {tmp1}, {tmp2}, {mask}",
-                                    "cmpw {tmp1}, {old}",
+                                    "lwarx {tmp}, 0, {dst}",
+                                    "and {prev}, {tmp}, {mask}",
+                                    "cmpw {prev}, {old}",
                                     "bne %cr0, 3f",
-                                    "andc {tmp2}, {tmp2}, {mask}",
-                                    "or {tmp2}, {tmp2}, {new}",
-                                    "stwcx. {tmp2}, 0, {dst}",
+                                    "andc {tmp}, {tmp}, {mask}",
+                                    "or {tmp}, {tmp}, {new}",
+                                    "stwcx. {tmp}, 0, {dst}",
                                     "bne %cr0, 2b",
                                 "3:",
-                                "srw {tmp1}, {tmp1}, {shift}",
+                                "srw {prev}, {prev}, {shift}",
                                 // if compare failed EQ bit is cleared, if stqcx succeeds EQ bit is set.
                                 "mfcr {r}",
                                 $acquire,
-                                concat!("st", $asm_suffix, " {tmp1}, 0({out})"),
                                 dst = in(reg_nonzero) ptr_reg!(aligned_ptr),
-                                old = inout(reg_nonzero) ptr_reg!(old) => _,
-                                new = inout(reg_nonzero) ptr_reg!(new) => _,
-                                out = inout(reg_nonzero) ptr_reg!(out) => _,
+                                old = inout(reg_nonzero) old => _,
+                                new = inout(reg_nonzero) new => _,
+                                shift = in(reg_nonzero) shift,
+                                mask = inout(reg_nonzero) mask => _,
+                                r = lateout(reg_nonzero) r,
+                                prev = out(reg_nonzero) prev,
+                                tmp = out(reg_nonzero) _,
+                                out("cr0") _,
+                                options(nostack, preserves_flags),
+                            )
+                        };
+                    }
+                    atomic_rmw!(cmpxchg, order);
+                    out.write(from_reg(prev));
+                    extract_cr0(r)
+                }
+            }
+            #[inline]
+            unsafe fn atomic_compare_exchange_weak(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let order = crate::utils::upgrade_success_ordering(success, failure);
+                let (aligned_ptr, shift, mask) = partword::create_mask_values(dst);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let mut r: Cr;
+                    // Implement sub-word atomic operations using word-sized LL/SC.
+                    // Based on assemblies generated by rustc/LLVM.
+                    // See also partword.rs.
+                    macro_rules! cmpxchg {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                "slw {mask}, {mask}, {shift}",
+                                "slw {old}, {old}, {shift}",
+                                "slw {new}, {new}, {shift}",
+                                "and {old}, {old}, {mask}",
+                                "and {new}, {new}, {mask}",
+                                // (atomic) CAS (LL/SC)
+                                $release,
+                                "lwarx {tmp}, 0, {dst}",
+                                "and {prev}, {tmp}, {mask}",
+                                "cmpw {prev}, {old}",
+                                "bne %cr0, 3f",
+                                "andc {tmp}, {tmp}, {mask}",
+                                "or {tmp}, {tmp}, {new}",
+                                "stwcx. {tmp}, 0, {dst}",
+                                "3:",
+                                "srw {prev}, {prev}, {shift}",
+                                // if compare or store failed EQ bit is cleared, if stqcx succeeds EQ bit is set.
+                                "mfcr {r}",
+                                $acquire,
+                                dst = in(reg_nonzero) ptr_reg!(aligned_ptr),
+                                old = inout(reg_nonzero) old => _,
+                                new = inout(reg_nonzero) new => _,
                                 shift = in(reg_nonzero) shift,
                                 mask = inout(reg_nonzero) mask => _,
                                 r = lateout(reg_nonzero) r,
-                                tmp1 = out(reg_nonzero) _,
-                                tmp2 = out(reg_nonzero) _,
+                                prev = out(reg_nonzero) prev,
+                                tmp = out(reg_nonzero) _,
                                 out("cr0") _,
                                 options(nostack, preserves_flags),
                             )
                         };
                     }
                     atomic_rmw!(cmpxchg, order);
+                    out.write(from_reg(prev));
                     extract_cr0(r)
                 }
             }
@@ -553,10 +647,11 @@ macro_rules! atomic128 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (v_hi, v_lo): (Reg<u64>, Reg<u64>);
                     macro_rules! atomic_load_acquire {
                         ($release:tt) => {
                             asm!(
-                                // (atomic) load from src to out pair
+                                // (atomic) load from src to v pair
                                 $release,
                                 "lq %r4, 0({src})",
                                 // Refs: https://github.com/boostorg/atomic/blob/boost-1.79.0/include/boost/atomic/detail/core_arch_ops_gcc_ppc.hpp#L47-L62
@@ -564,15 +659,12 @@ macro_rules! atomic128 {
                                 "bne- %cr7, 2f",
                                 "2:",
                                 "isync",
-                                // store out pair to out
-                                concat!("std %r4, ", p128h!(), "({out})"),
-                                concat!("std %r5, ", p128l!(), "({out})"),
                                 src = in(reg_nonzero) ptr_reg!(src),
-                                out = inout(reg_nonzero) ptr_reg!(out) => _,
                                 // Quadword atomic instructions work with even/odd pair of specified register and subsequent register.
                                 // We cannot use r1 (sp) and r2 (system reserved), so start with r4 or grater.
-                                out("r4") _, // out (hi)
-                                out("r5") _, // out (lo)
+                                // The even register holds the high-order doubleword regardless of endianness.
+                                out("r4") v_hi,
+                                out("r5") v_lo,
                                 out("cr7") _,
                                 options(nostack, preserves_flags),
                             )
@@ -581,17 +673,14 @@ macro_rules! atomic128 {
                     match order {
                         Ordering::Relaxed => {
                             asm!(
-                                // (atomic) load from src to out pair
+                                // (atomic) load from src to v pair
                                 "lq %r4, 0({src})",
-                                // store out pair to out
-                                concat!("std %r4, ", p128h!(), "({out})"),
-                                concat!("std %r5, ", p128l!(), "({out})"),
                                 src = in(reg_nonzero) ptr_reg!(src),
-                                out = inout(reg_nonzero) ptr_reg!(out) => _,
                                 // Quadword atomic instructions work with even/odd pair of specified register and subsequent register.
                                 // We cannot use r1 (sp) and r2 (system reserved), so start with r4 or grater.
-                                out("r4") _, // out (hi)
-                                out("r5") _, // out (lo)
+                                // The even register holds the high-order doubleword regardless of endianness.
+                                out("r4") v_hi,
+                                out("r5") v_lo,
                                 options(nostack, preserves_flags),
                             )
                         }
@@ -599,6 +688,7 @@ macro_rules! atomic128 {
                         Ordering::SeqCst =>  atomic_load_acquire!("sync"),
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_pair(Pair { lo: v_lo, hi: v_hi }));
                 }
             }
         }
@@ -614,21 +704,19 @@ macro_rules! atomic128 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_pair::<Self, u64>(val);
                     macro_rules! atomic_store {
                         ($release:tt) => {
                             asm!(
-                                // load from val to val pair
-                                concat!("ld %r4, ", p128h!(), "({val})"),
-                                concat!("ld %r5, ", p128l!(), "({val})"),
                                 // (atomic) store val pair to dst
                                 $release,
                                 "stq %r4, 0({dst})",
-                                dst = inout(reg_nonzero) ptr_reg!(dst) => _,
-                                val = in(reg_nonzero) ptr_reg!(val),
+                                dst = in(reg_nonzero) ptr_reg!(dst),
                                 // Quadword atomic instructions work with even/odd pair of specified register and subsequent register.
                                 // We cannot use r1 (sp) and r2 (system reserved), so start with r4 or grater.
-                                out("r4") _, // val (hi)
-                                lateout("r5") _, // val (lo)
+                                // The even register holds the high-order doubleword regardless of endianness.
+                                in("r4") val.hi,
+                                in("r5") val.lo,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -656,39 +744,35 @@ macro_rules! atomic128 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_pair::<Self, u64>(val);
+                    let (prev_hi, prev_lo): (Reg<u64>, Reg<u64>);
                     macro_rules! swap {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // load from val to val pair
-                                concat!("ld %r4, ", p128h!(), "({val})"),
-                                concat!("ld %r5, ", p128l!(), "({val})"),
                                 // (atomic) swap (LL/SC loop)
                                 $release,
                                 "2:",
-                                    // load from dst to out pair
+                                    // load from dst to prev pair
                                     "lqarx %r6, 0, {dst}",
                                     // try to store val pair to dst
                                     "stqcx. %r4, 0, {dst}",
                                     "bne %cr0, 2b",
                                 $acquire,
-                                // store out pair to out
-                                concat!("std %r6, ", p128h!(), "({out})"),
-                                concat!("std %r7, ", p128l!(), "({out})"),
-                                dst = inout(reg_nonzero) ptr_reg!(dst) => _,
-                                val = in(reg_nonzero) ptr_reg!(val),
-                                out = inout(reg_nonzero) ptr_reg!(out) => _,
+                                dst = in(reg_nonzero) ptr_reg!(dst),
                                 // Quadword atomic instructions work with even/odd pair of specified register and subsequent register.
                                 // We cannot use r1 (sp) and r2 (system reserved), so start with r4 or grater.
-                                out("r4") _, // val (hi)
-                                lateout("r5") _, // val (lo)
-                                out("r6") _, // out (hi)
-                                out("r7") _, // out (lo)
+                                // The even register holds the high-order doubleword regardless of endianness.
+                                in("r4") val.hi,
+                                in("r5") val.lo,
+                                out("r6") prev_hi,
+                                out("r7") prev_lo,
                                 out("cr0") _,
                                 options(nostack, preserves_flags),
                             )
                         };
                     }
                     atomic_rmw!(swap, order);
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                 }
             }
         }
@@ -710,15 +794,13 @@ macro_rules! atomic128 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let old = load_pair::<Self, u64>(old);
+                    let new = load_pair::<Self, u64>(new);
+                    let (prev_hi, prev_lo): (Reg<u64>, Reg<u64>);
                     let mut r: Cr;
                     macro_rules! cmpxchg {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // load from old/new to old/new pairs
-                                concat!("ld %r4, ", p128h!(), "({old})"),
-                                concat!("ld %r5, ", p128l!(), "({old})"),
-                                concat!("ld %r6, ", p128h!(), "({new})"),
-                                concat!("ld %r7, ", p128l!(), "({new})"),
                                 // (atomic) CAS (LL/SC loop)
                                 $release,
                                 "2:",
@@ -733,29 +815,83 @@ macro_rules! atomic128 {
                                 // if compare failed EQ bit is cleared, if stqcx succeeds EQ bit is set.
                                 "mfcr {tmp_lo}",
                                 $acquire,
-                                // store out pair to out
-                                concat!("std %r8, ", p128h!(), "({out})"),
-                                concat!("std %r9, ", p128l!(), "({out})"),
-                                dst = inout(reg_nonzero) ptr_reg!(dst) => _,
-                                old = in(reg_nonzero) ptr_reg!(old),
-                                new = in(reg_nonzero) ptr_reg!(new),
-                                out = inout(reg_nonzero) ptr_reg!(out) => _,
+                                dst = in(reg_nonzero) ptr_reg!(dst),
                                 tmp_hi = out(reg_nonzero) _,
                                 tmp_lo = out(reg_nonzero) r,
                                 // Quadword atomic instructions work with even/odd pair of specified register and subsequent register.
                                 // We cannot use r1 (sp) and r2 (system reserved), so start with r4 or grater.
-                                out("r4") _, // old (hi)
-                                out("r5") _, // old (lo)
-                                out("r6") _, // new (hi)
-                                lateout("r7") _, // new (lo)
-                                lateout("r8") _, // out (hi)
-                                lateout("r9") _, // out (lo)
+                                // The even register holds the high-order doubleword regardless of endianness.
+                                in("r4") old.hi,
+                                in("r5") old.lo,
+                                in("r6") new.hi,
+                                in("r7") new.lo,
+                                out("r8") prev_hi,
+                                out("r9") prev_lo,
                                 out("cr0") _,
                                 options(nostack, preserves_flags),
                             )
                         };
                     }
                     atomic_rmw!(cmpxchg, order);
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
+                    extract_cr0(r)
+                }
+            }
+            #[inline]
+            unsafe fn atomic_compare_exchange_weak(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let order = crate::utils::upgrade_success_ordering(success, failure);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let old = load_pair::<Self, u64>(old);
+                    let new = load_pair::<Self, u64>(new);
+                    let (prev_hi, prev_lo): (Reg<u64>, Reg<u64>);
+                    let mut r: Cr;
+                    macro_rules! cmpxchg {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                // (atomic) CAS (LL/SC)
+                                $release,
+                                "lqarx %r8, 0, {dst}",
+                                "xor {tmp_lo}, %r9, %r5",
+                                "xor {tmp_hi}, %r8, %r4",
+                                "or. {tmp_lo}, {tmp_lo}, {tmp_hi}",
+                                "bne %cr0, 3f", // jump if compare failed
+                                "stqcx. %r6, 0, {dst}",
+                                "3:",
+                                // if compare or store failed EQ bit is cleared, if stqcx succeeds EQ bit is set.
+                                "mfcr {tmp_lo}",
+                                $acquire,
+                                dst = in(reg_nonzero) ptr_reg!(dst),
+                                tmp_hi = out(reg_nonzero) _,
+                                tmp_lo = out(reg_nonzero) r,
+                                // Quadword atomic instructions work with even/odd pair of specified register and subsequent register.
+                                // We cannot use r1 (sp) and r2 (system reserved), so start with r4 or grater.
+                                // The even register holds the high-order doubleword regardless of endianness.
+                                in("r4") old.hi,
+                                in("r5") old.lo,
+                                in("r6") new.hi,
+                                in("r7") new.lo,
+                                out("r8") prev_hi,
+                                out("r9") prev_lo,
+                                out("cr0") _,
+                                options(nostack, preserves_flags),
+                            )
+                        };
+                    }
+                    atomic_rmw!(cmpxchg, order);
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                     extract_cr0(r)
                 }
             }
@@ -776,6 +912,35 @@ atomic128!(i128);
 ))]
 atomic128!(u128);
 
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{CompareExchange, CompareExchangeWeak, Load, Store, Swap};
+        let partword = cfg!(all(
+            target_arch = "powerpc64",
+            any(
+                target_feature = "partword-atomics",
+                atomic_maybe_uninit_target_feature = "partword-atomics",
+            ),
+        ));
+        Strategy::new(match (size, op) {
+            (16, Load) => "LQ with SYNC/LWSYNC (quadword-atomics)",
+            (16, Store) => "STQ with SYNC/LWSYNC (quadword-atomics)",
+            (16, Swap | CompareExchange) => "LQARX/STQCX. loop (quadword-atomics)",
+            (16, CompareExchangeWeak) => "LQARX/STQCX. (quadword-atomics)",
+            (_, Load) => "L* with SYNC/LWSYNC",
+            (_, Store) => "ST* with SYNC/LWSYNC",
+            (1 | 2, Swap | CompareExchange) if partword => "LBARX/STBCX. or LHARX/STHCX. loop",
+            (1 | 2, CompareExchangeWeak) if partword => "LBARX/STBCX. or LHARX/STHCX.",
+            (1 | 2, Swap | CompareExchange) => "masked LWARX/STWCX. loop",
+            (1 | 2, CompareExchangeWeak) => "masked LWARX/STWCX.",
+            (8, Swap | CompareExchange) => "LDARX/STDCX. loop",
+            (8, CompareExchangeWeak) => "LDARX/STDCX.",
+            (_, Swap | CompareExchange) => "LWARX/STWCX. loop",
+            (_, CompareExchangeWeak) => "LWARX/STWCX.",
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     test_atomic!(isize);
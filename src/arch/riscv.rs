@@ -9,20 +9,57 @@
 // Generated asm:
 // - riscv64gc https://godbolt.org/z/nW3Po8n4K
 // - riscv32imac https://godbolt.org/z/51nPPMYze
+//
+// Note: Zacas extension (amocas.{d,q}) is used for 64-bit atomics on riscv32
+// and 128-bit atomics on riscv64 when the `zacas` target feature is enabled.
+// Zabha extension (amoswap.{b,h}, and amocas.{b,h} with Zacas) is used for
+// 8-bit and 16-bit atomics when the `zabha` target feature is enabled.
+// Zacas is also used for 32-bit and 64-bit CAS instead of LR/SC loop.
+//
+// On Linux/Android, when `--cfg atomic_maybe_uninit_outline_atomics` is set,
+// Zacas/Zabha are also detected at run-time and dispatched between LR/SC loop
+// and these instructions. See riscv_detect.rs for details.
 
 #[cfg(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a"))]
 #[path = "partword.rs"]
 mod partword;
 
+#[cfg(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a"))]
+#[cfg(atomic_maybe_uninit_outline_atomics)]
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[path = "riscv_detect.rs"]
+mod detect;
+
+#[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+#[path = "pair.rs"]
+mod pair;
+
 use core::{
     arch::asm,
     mem::{self, MaybeUninit},
     sync::atomic::Ordering,
 };
 
-#[cfg(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a"))]
+#[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+use self::pair::{from_pair, load_pair, Pair};
+use super::{from_reg, load_reg, Reg};
+#[cfg(any(
+    not(all(
+        feature = "critical-section",
+        not(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a")),
+    )),
+    target_feature = "zacas",
+    atomic_maybe_uninit_target_feature = "zacas",
+))]
+use crate::raw::AtomicStore;
+#[cfg(any(
+    target_feature = "a",
+    atomic_maybe_uninit_target_feature = "a",
+    target_feature = "zacas",
+    atomic_maybe_uninit_target_feature = "zacas",
+))]
 use crate::raw::{AtomicCompareExchange, AtomicSwap};
-use crate::raw::{AtomicLoad, AtomicStore};
+use crate::{info::Strategy, raw::AtomicLoad};
 
 #[cfg(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a"))]
 #[cfg(target_arch = "riscv32")]
@@ -39,7 +76,91 @@ macro_rules! w {
     };
 }
 
+// Sign-extends old for comparison with the value loaded by L{B,H,W}, LR.W, or
+// AMOCAS.{B,H,W}, which sign-extend it. The upper bits of register operands
+// narrower than XLEN are undefined.
+#[cfg(any(
+    target_feature = "a",
+    atomic_maybe_uninit_target_feature = "a",
+    atomic_maybe_uninit_unsafe_assume_single_core,
+    feature = "critical-section",
+))]
+#[cfg(target_arch = "riscv32")]
+macro_rules! sext {
+    ("b") => {
+        "slli {old}, {old}, 24\nsrai {old}, {old}, 24"
+    };
+    ("h") => {
+        "slli {old}, {old}, 16\nsrai {old}, {old}, 16"
+    };
+    ("w") => {
+        ""
+    };
+}
+#[cfg(any(
+    target_feature = "a",
+    atomic_maybe_uninit_target_feature = "a",
+    atomic_maybe_uninit_unsafe_assume_single_core,
+    feature = "critical-section",
+))]
+#[cfg(target_arch = "riscv64")]
+macro_rules! sext {
+    ("b") => {
+        "slli {old}, {old}, 56\nsrai {old}, {old}, 56"
+    };
+    ("h") => {
+        "slli {old}, {old}, 48\nsrai {old}, {old}, 48"
+    };
+    ("w") => {
+        "sext.w {old}, {old}"
+    };
+    ("d") => {
+        ""
+    };
+}
+
+// has_zacas!()/has_zabha!() return whether the extension is available.
+// If the extension is enabled at compile-time, they always return true, so
+// the fallback LR/SC loop will be removed as dead code.
 #[cfg(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a"))]
+#[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+macro_rules! has_zacas {
+    () => {
+        true
+    };
+}
+#[cfg(not(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas")))]
+#[cfg(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a"))]
+#[cfg(atomic_maybe_uninit_outline_atomics)]
+#[cfg(any(target_os = "linux", target_os = "android"))]
+macro_rules! has_zacas {
+    () => {
+        detect::detect().has_zacas()
+    };
+}
+#[cfg(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a"))]
+#[cfg(any(target_feature = "zabha", atomic_maybe_uninit_target_feature = "zabha"))]
+macro_rules! has_zabha {
+    () => {
+        true
+    };
+}
+#[cfg(not(any(target_feature = "zabha", atomic_maybe_uninit_target_feature = "zabha")))]
+#[cfg(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a"))]
+#[cfg(atomic_maybe_uninit_outline_atomics)]
+#[cfg(any(target_os = "linux", target_os = "android"))]
+macro_rules! has_zabha {
+    () => {
+        detect::detect().has_zabha()
+    };
+}
+
+#[cfg(any(
+    target_feature = "a",
+    atomic_maybe_uninit_target_feature = "a",
+    target_feature = "zacas",
+    atomic_maybe_uninit_target_feature = "zacas",
+))]
 macro_rules! atomic_rmw_amo {
     ($op:ident, $order:ident) => {
         match $order {
@@ -76,6 +197,7 @@ type XSize = u64;
 #[rustfmt::skip]
 macro_rules! atomic_load_store {
     ($int_type:ident, $asm_suffix:tt) => {
+        impl_load_reg!($int_type, reg, concat!("l", $asm_suffix, " {v}, 0({src})"));
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
@@ -88,18 +210,16 @@ macro_rules! atomic_load_store {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let v: Reg<Self>;
                     macro_rules! atomic_load {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // (atomic) load from src to tmp
+                                // (atomic) load from src to v
                                 $release,
-                                concat!("l", $asm_suffix, " {tmp}, 0({src})"),
+                                concat!("l", $asm_suffix, " {v}, 0({src})"),
                                 $acquire,
-                                // store tmp to out
-                                concat!("s", $asm_suffix, " {tmp}, 0({out})"),
                                 src = in(reg) ptr_reg!(src),
-                                out = inout(reg) ptr_reg!(out) => _,
-                                tmp = lateout(reg) _,
+                                v = lateout(reg) v,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -110,9 +230,15 @@ macro_rules! atomic_load_store {
                         Ordering::SeqCst => atomic_load!("fence r, rw", "fence rw, rw"),
                         _ => unreachable!("{:?}", order),
                     }
+                    out.write(from_reg(v));
                 }
             }
         }
+        // See atomic_single_core.
+        #[cfg(not(all(
+            feature = "critical-section",
+            not(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a")),
+        )))]
         impl AtomicStore for $int_type {
             #[inline]
             unsafe fn atomic_store(
@@ -125,17 +251,15 @@ macro_rules! atomic_load_store {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
                     macro_rules! atomic_store {
                         ($release:tt) => {
                             asm!(
-                                // load from val to tmp
-                                concat!("l", $asm_suffix, " {tmp}, 0({val})"),
-                                // (atomic) store tmp to dst
+                                // (atomic) store val to dst
                                 $release,
-                                concat!("s", $asm_suffix, " {tmp}, 0({dst})"),
-                                dst = inout(reg) ptr_reg!(dst) => _,
-                                val = in(reg) ptr_reg!(val),
-                                tmp = lateout(reg) _,
+                                concat!("s", $asm_suffix, " {val}, 0({dst})"),
+                                dst = in(reg) ptr_reg!(dst),
+                                val = in(reg) val,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -170,26 +294,24 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     macro_rules! swap {
                         ($order:tt) => {
                             asm!(
-                                // load from val (ptr) to val (val)
-                                concat!("l", $asm_suffix, " {val}, 0({val})"),
                                 // (atomic) swap (AMO)
-                                // - load value from dst and store it to tmp
+                                // - load value from dst and store it to prev
                                 // - store value of val to dst
-                                concat!("amoswap.", $asm_suffix, $order, " {tmp}, {val}, 0({dst})"),
-                                // store tmp to out
-                                concat!("s", $asm_suffix, " {tmp}, 0({out})"),
+                                concat!("amoswap.", $asm_suffix, $order, " {prev}, {val}, 0({dst})"),
                                 dst = in(reg) ptr_reg!(dst),
-                                val = inout(reg) ptr_reg!(val) => _,
-                                out = inout(reg) ptr_reg!(out) => _,
-                                tmp = lateout(reg) _,
+                                val = in(reg) val,
+                                prev = lateout(reg) prev,
                                 options(nostack, preserves_flags),
                             )
                         };
                     }
                     atomic_rmw_amo!(swap, order);
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -212,35 +334,135 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let mut r: XSize;
+                    #[cfg(any(
+                        target_feature = "zacas",
+                        atomic_maybe_uninit_target_feature = "zacas",
+                        all(
+                            atomic_maybe_uninit_outline_atomics,
+                            any(target_os = "linux", target_os = "android"),
+                        ),
+                    ))]
+                    {
+                        if has_zacas!() {
+                            macro_rules! cmpxchg_amocas {
+                                ($order:tt) => {
+                                    asm!(
+                                        ".option push",
+                                        ".option arch, +zacas",
+                                        sext!($asm_suffix),
+                                        "mv {prev}, {old}",
+                                        // (atomic) CAS (AMO)
+                                        // - compare value of dst and prev, and store new to dst if equal
+                                        // - load previous value of dst to prev
+                                        concat!("amocas.", $asm_suffix, $order, " {prev}, {new}, 0({dst})"),
+                                        ".option pop",
+                                        "xor {r}, {prev}, {old}",
+                                        "seqz {r}, {r}",
+                                        dst = in(reg) ptr_reg!(dst),
+                                        old = inout(reg) old => _,
+                                        new = in(reg) new,
+                                        prev = out(reg) prev,
+                                        r = lateout(reg) r,
+                                        options(nostack, preserves_flags),
+                                    )
+                                };
+                            }
+                            atomic_rmw_amo!(cmpxchg_amocas, order);
+                            out.write(from_reg(prev));
+                            debug_assert!(r == 0 || r == 1, "r={}", r);
+                            return r != 0;
+                        }
+                    }
                     macro_rules! cmpxchg {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                // load from old/new (ptr) to old/new (val)
-                                concat!("l", $asm_suffix, " {old}, 0({old})"),
-                                concat!("l", $asm_suffix, " {new}, 0({new})"),
+                                sext!($asm_suffix),
                                 // (atomic) CAS (LR/SC loop)
                                 "2:",
-                                    concat!("lr.", $asm_suffix, $acquire, " {tmp}, 0({dst})"),
-                                    "bne {tmp}, {old}, 3f", // compare and jump if compare failed
+                                    concat!("lr.", $asm_suffix, $acquire, " {prev}, 0({dst})"),
+                                    "bne {prev}, {old}, 3f", // compare and jump if compare failed
                                     concat!("sc.", $asm_suffix, $release, " {r}, {new}, 0({dst})"),
                                     "bnez {r}, 2b", // continue loop if store failed
                                 "3:",
-                                "xor {r}, {tmp}, {old}",
+                                "xor {r}, {prev}, {old}",
                                 "seqz {r}, {r}",
-                                // store tmp to out
-                                concat!("s", $asm_suffix, " {tmp}, 0({out})"),
                                 dst = in(reg) ptr_reg!(dst),
-                                old = inout(reg) ptr_reg!(old) => _,
-                                new = inout(reg) ptr_reg!(new) => _,
-                                out = in(reg) ptr_reg!(out),
-                                tmp = out(reg) _,
+                                old = inout(reg) old => _,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
                                 r = out(reg) r,
                                 options(nostack, preserves_flags),
                             )
                         };
                     }
                     atomic_rmw_lr_sc!(cmpxchg, order);
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+            #[cfg(not(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas")))]
+            #[inline]
+            unsafe fn atomic_compare_exchange_weak(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let order = crate::utils::upgrade_success_ordering(success, failure);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let mut r: XSize;
+                    #[cfg(all(
+                        atomic_maybe_uninit_outline_atomics,
+                        any(target_os = "linux", target_os = "android"),
+                    ))]
+                    {
+                        if has_zacas!() {
+                            // The instruction-based CAS never fails spuriously.
+                            return Self::atomic_compare_exchange(
+                                dst, old, new, out, success, failure,
+                            );
+                        }
+                    }
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    macro_rules! cmpxchg {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                sext!($asm_suffix),
+                                // (atomic) CAS (LR/SC)
+                                concat!("lr.", $asm_suffix, $acquire, " {prev}, 0({dst})"),
+                                "bne {prev}, {old}, 3f", // compare and jump if compare failed
+                                // 0 if the store was successful, non-zero if no store was performed
+                                concat!("sc.", $asm_suffix, $release, " {r}, {new}, 0({dst})"),
+                                "seqz {r}, {r}",
+                                "j 4f",
+                                "3:",
+                                    // compare failed, set r to 0
+                                    "li {r}, 0",
+                                "4:",
+                                dst = in(reg) ptr_reg!(dst),
+                                old = inout(reg) old => _,
+                                new = in(reg) new,
+                                prev = out(reg) prev,
+                                r = out(reg) r,
+                                options(nostack, preserves_flags),
+                            )
+                        };
+                    }
+                    atomic_rmw_lr_sc!(cmpxchg, order);
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     r != 0
                 }
@@ -269,38 +491,70 @@ macro_rules! atomic_sub_word {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
+                    #[cfg(any(
+                        target_feature = "zabha",
+                        atomic_maybe_uninit_target_feature = "zabha",
+                        all(
+                            atomic_maybe_uninit_outline_atomics,
+                            any(target_os = "linux", target_os = "android"),
+                        ),
+                    ))]
+                    {
+                        if has_zabha!() {
+                            macro_rules! swap_amo {
+                                ($order:tt) => {
+                                    asm!(
+                                        ".option push",
+                                        ".option arch, +zabha",
+                                        // (atomic) swap (AMO)
+                                        // - load value from dst and store it to prev
+                                        // - store value of val to dst
+                                        concat!("amoswap.", $asm_suffix, $order, " {prev}, {val}, 0({dst})"),
+                                        ".option pop",
+                                        dst = in(reg) ptr_reg!(dst),
+                                        val = in(reg) val,
+                                        prev = lateout(reg) prev,
+                                        options(nostack, preserves_flags),
+                                    )
+                                };
+                            }
+                            atomic_rmw_amo!(swap_amo, order);
+                            out.write(from_reg(prev));
+                            return;
+                        }
+                    }
                     // Implement sub-word atomic operations using word-sized LL/SC loop.
                     // Based on assemblies generated by rustc/LLVM.
                     // See also partword.rs.
                     macro_rules! swap {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                concat!("l", $asm_suffix, "u {val}, 0({val})"),
                                 concat!("sll", w!(), " {mask}, {mask}, {shift}"),
                                 concat!("sll", w!(), " {val}, {val}, {shift}"),
                                 // (atomic) swap (LR/SC loop)
                                 "2:",
-                                    concat!("lr.w", $acquire, " {tmp1}, 0({dst})"),
-                                    "mv {tmp2}, {val}",
-                                    "xor {tmp2}, {tmp2}, {tmp1}",
-                                    "and {tmp2}, {tmp2}, {mask}",
-                                    "xor {tmp2}, {tmp2}, {tmp1}",
-                                    concat!("sc.w", $release, " {tmp2}, {tmp2}, 0({dst})"),
-                                    "bnez {tmp2}, 2b",
-                                concat!("srl", w!(), " {tmp1}, {tmp1}, {shift}"),
-                                concat!("s", $asm_suffix, " {tmp1}, 0({out})"),
+                                    concat!("lr.w", $acquire, " {prev}, 0({dst})"),
+                                    "mv {tmp}, {val}",
+                                    "xor {tmp}, {tmp}, {prev}",
+                                    "and {tmp}, {tmp}, {mask}",
+                                    "xor {tmp}, {tmp}, {prev}",
+                                    concat!("sc.w", $release, " {tmp}, {tmp}, 0({dst})"),
+                                    "bnez {tmp}, 2b",
+                                concat!("srl", w!(), " {prev}, {prev}, {shift}"),
                                 dst = in(reg) ptr_reg!(aligned_ptr),
-                                val = inout(reg) ptr_reg!(val) => _,
-                                out = in(reg) ptr_reg!(out),
+                                val = inout(reg) val => _,
                                 shift = in(reg) shift,
                                 mask = inout(reg) mask => _,
-                                tmp1 = out(reg) _,
-                                tmp2 = out(reg) _,
+                                prev = out(reg) prev,
+                                tmp = out(reg) _,
                                 options(nostack, preserves_flags),
                             )
                         };
                     }
                     atomic_rmw_lr_sc!(swap, order);
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -324,48 +578,172 @@ macro_rules! atomic_sub_word {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let mut r: XSize;
+                    #[cfg(any(
+                        all(
+                            any(target_feature = "zabha", atomic_maybe_uninit_target_feature = "zabha"),
+                            any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"),
+                        ),
+                        all(
+                            atomic_maybe_uninit_outline_atomics,
+                            any(target_os = "linux", target_os = "android"),
+                        ),
+                    ))]
+                    {
+                        if has_zabha!() && has_zacas!() {
+                            macro_rules! cmpxchg_amocas {
+                                ($order:tt) => {
+                                    asm!(
+                                        ".option push",
+                                        ".option arch, +zabha, +zacas",
+                                        sext!($asm_suffix),
+                                        "mv {prev}, {old}",
+                                        // (atomic) CAS (AMO)
+                                        // - compare value of dst and prev, and store new to dst if equal
+                                        // - load previous value of dst to prev
+                                        concat!("amocas.", $asm_suffix, $order, " {prev}, {new}, 0({dst})"),
+                                        ".option pop",
+                                        "xor {r}, {prev}, {old}",
+                                        "seqz {r}, {r}",
+                                        dst = in(reg) ptr_reg!(dst),
+                                        old = inout(reg) old => _,
+                                        new = in(reg) new,
+                                        prev = out(reg) prev,
+                                        r = lateout(reg) r,
+                                        options(nostack, preserves_flags),
+                                    )
+                                };
+                            }
+                            atomic_rmw_amo!(cmpxchg_amocas, order);
+                            out.write(from_reg(prev));
+                            debug_assert!(r == 0 || r == 1, "r={}", r);
+                            return r != 0;
+                        }
+                    }
                     // Implement sub-word atomic operations using word-sized LL/SC loop.
                     // Based on assemblies generated by rustc/LLVM.
                     // See also partword.rs.
                     macro_rules! cmpxchg {
                         ($acquire:tt, $release:tt) => {
                             asm!(
-                                concat!("l", $asm_suffix, "u {old}, 0({old})"),
-                                concat!("l", $asm_suffix, "u {new}, 0({new})"),
                                 concat!("sll", w!(), " {mask}, {mask}, {shift}"),
                                 concat!("sll", w!(), " {old}, {old}, {shift}"),
                                 concat!("sll", w!(), " {new}, {new}, {shift}"),
+                                // The upper bits of old are undefined, so clear them.
+                                "and {old}, {old}, {mask}",
                                 // (atomic) CAS (LR/SC loop)
                                 "2:",
-                                    concat!("lr.w", $acquire, " {tmp1}, 0({dst})"),
-                                    "and {tmp2}, {tmp1}, {mask}",
-                                    "bne {tmp2}, {old}, 3f",
-                                    "xor {tmp2}, {tmp1}, {new}",
-                                    "and {tmp2}, {tmp2}, {mask}",
-                                    "xor {tmp2}, {tmp2}, {tmp1}",
-                                    concat!("sc.w", $release, " {tmp2}, {tmp2}, 0({dst})"),
-                                    "bnez {tmp2}, 2b",
+                                    concat!("lr.w", $acquire, " {tmp}, 0({dst})"),
+                                    "and {prev}, {tmp}, {mask}",
+                                    "bne {prev}, {old}, 3f",
+                                    "xor {prev}, {tmp}, {new}",
+                                    "and {prev}, {prev}, {mask}",
+                                    "xor {prev}, {prev}, {tmp}",
+                                    concat!("sc.w", $release, " {prev}, {prev}, 0({dst})"),
+                                    "bnez {prev}, 2b",
                                 "3:",
-                                concat!("srl", w!(), " {tmp2}, {tmp1}, {shift}"),
-                                "and {tmp1}, {tmp1}, {mask}",
-                                "xor {r}, {old}, {tmp1}",
+                                concat!("srl", w!(), " {prev}, {tmp}, {shift}"),
+                                "and {tmp}, {tmp}, {mask}",
+                                "xor {r}, {old}, {tmp}",
                                 "seqz {r}, {r}",
-                                concat!("s", $asm_suffix, " {tmp2}, 0({out})"),
                                 dst = in(reg) ptr_reg!(aligned_ptr),
-                                old = inout(reg) ptr_reg!(old) => _,
-                                new = inout(reg) ptr_reg!(new) => _,
-                                out = inout(reg) ptr_reg!(out) => _,
+                                old = inout(reg) old => _,
+                                new = inout(reg) new => _,
                                 shift = in(reg) shift,
                                 mask = inout(reg) mask => _,
                                 r = lateout(reg) r,
-                                tmp1 = out(reg) _,
-                                tmp2 = out(reg) _,
+                                prev = out(reg) prev,
+                                tmp = out(reg) _,
                                 options(nostack, preserves_flags),
                             )
                         };
                     }
                     atomic_rmw_lr_sc!(cmpxchg, order);
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+            #[cfg(not(all(
+                any(target_feature = "zabha", atomic_maybe_uninit_target_feature = "zabha"),
+                any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"),
+            )))]
+            #[inline]
+            unsafe fn atomic_compare_exchange_weak(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let order = crate::utils::upgrade_success_ordering(success, failure);
+                let (aligned_ptr, shift, mask) = partword::create_mask_values(dst);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let mut r: XSize;
+                    #[cfg(all(
+                        atomic_maybe_uninit_outline_atomics,
+                        any(target_os = "linux", target_os = "android"),
+                    ))]
+                    {
+                        if has_zabha!() && has_zacas!() {
+                            // The instruction-based CAS never fails spuriously.
+                            return Self::atomic_compare_exchange(
+                                dst, old, new, out, success, failure,
+                            );
+                        }
+                    }
+                    // Implement sub-word atomic operations using word-sized LL/SC.
+                    // Based on assemblies generated by rustc/LLVM.
+                    // See also partword.rs.
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    macro_rules! cmpxchg {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                concat!("sll", w!(), " {mask}, {mask}, {shift}"),
+                                concat!("sll", w!(), " {old}, {old}, {shift}"),
+                                concat!("sll", w!(), " {new}, {new}, {shift}"),
+                                // The upper bits of old are undefined, so clear them.
+                                "and {old}, {old}, {mask}",
+                                // (atomic) CAS (LR/SC)
+                                concat!("lr.w", $acquire, " {tmp}, 0({dst})"),
+                                "and {prev}, {tmp}, {mask}",
+                                "bne {prev}, {old}, 3f",
+                                "xor {prev}, {tmp}, {new}",
+                                "and {prev}, {prev}, {mask}",
+                                "xor {prev}, {prev}, {tmp}",
+                                // 0 if the store was successful, non-zero if no store was performed
+                                concat!("sc.w", $release, " {r}, {prev}, 0({dst})"),
+                                "seqz {r}, {r}",
+                                "j 4f",
+                                "3:",
+                                    // compare failed, set r to 0
+                                    "li {r}, 0",
+                                "4:",
+                                concat!("srl", w!(), " {prev}, {tmp}, {shift}"),
+                                dst = in(reg) ptr_reg!(aligned_ptr),
+                                old = inout(reg) old => _,
+                                new = inout(reg) new => _,
+                                shift = in(reg) shift,
+                                mask = inout(reg) mask => _,
+                                r = out(reg) r,
+                                prev = out(reg) prev,
+                                tmp = out(reg) _,
+                                options(nostack, preserves_flags),
+                            )
+                        };
+                    }
+                    atomic_rmw_lr_sc!(cmpxchg, order);
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     r != 0
                 }
@@ -393,6 +771,557 @@ atomic!(isize, "d");
 #[cfg(target_pointer_width = "64")]
 atomic!(usize, "d");
 
+// Without the A extension, swap/CAS are implemented by disabling interrupts
+// when `--cfg atomic_maybe_uninit_unsafe_assume_single_core` is set.
+// This is only sound on single-core systems and requires machine mode
+// (mstatus.MIE is used).
+//
+// When the `critical-section` feature is enabled, the critical section provided
+// by the critical-section crate is used instead (see cs.rs), and stores are
+// also routed through it.
+//
+// See also avr.rs and portable-atomic's interrupt module.
+#[cfg(feature = "critical-section")]
+#[cfg(not(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a")))]
+use super::cs::{disable, restore};
+#[cfg(atomic_maybe_uninit_unsafe_assume_single_core)]
+#[cfg(not(feature = "critical-section"))]
+#[cfg(not(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a")))]
+#[inline]
+fn disable() -> usize {
+    let mstatus: usize;
+    // SAFETY: reading mstatus and disabling interrupts are safe.
+    unsafe {
+        // Do not use `nomem` and `readonly` because prevent subsequent memory accesses from being reordered before interrupts are disabled.
+        // csrrci: read mstatus and clear the MIE bit (0x8)
+        asm!("csrrci {0}, mstatus, 0x8", out(reg) mstatus, options(nostack, preserves_flags));
+    }
+    mstatus
+}
+#[cfg(atomic_maybe_uninit_unsafe_assume_single_core)]
+#[cfg(not(feature = "critical-section"))]
+#[cfg(not(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a")))]
+#[inline]
+unsafe fn restore(mstatus: usize) {
+    if mstatus & 0x8 != 0 {
+        // SAFETY: the caller must guarantee that the state was retrieved by the previous `disable`,
+        // and the MIE bit was set in that state.
+        unsafe {
+            // Do not use `nomem` and `readonly` because prevent preceding memory accesses from being reordered after interrupts are enabled.
+            asm!("csrsi mstatus, 0x8", options(nostack, preserves_flags));
+        }
+    }
+}
+
+#[cfg(any(atomic_maybe_uninit_unsafe_assume_single_core, feature = "critical-section"))]
+#[cfg(not(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a")))]
+#[rustfmt::skip]
+macro_rules! atomic_single_core {
+    ($int_type:ident, $asm_suffix:tt) => {
+        #[cfg(feature = "critical-section")]
+        impl crate::arch::NotLockFree for $int_type {}
+        #[cfg(feature = "critical-section")]
+        impl AtomicStore for $int_type {
+            #[inline]
+            unsafe fn atomic_store(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the critical section guarantees exclusive access to dst.
+                unsafe {
+                    let val = load_reg(val);
+                    let s = disable();
+                    asm!(
+                        // store val to dst
+                        concat!("s", $asm_suffix, " {val}, 0({dst})"),
+                        dst = in(reg) ptr_reg!(dst),
+                        val = in(reg) val,
+                        options(nostack, preserves_flags),
+                    );
+                    restore(s);
+                }
+            }
+        }
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the critical section guarantees exclusive access to dst.
+                unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
+                    let s = disable();
+                    asm!(
+                        // load from dst to prev
+                        concat!("l", $asm_suffix, " {prev}, 0({dst})"),
+                        // store val to dst
+                        concat!("s", $asm_suffix, " {val}, 0({dst})"),
+                        dst = in(reg) ptr_reg!(dst),
+                        val = in(reg) val,
+                        prev = out(reg) prev,
+                        options(nostack, preserves_flags),
+                    );
+                    restore(s);
+                    out.write(from_reg(prev));
+                }
+            }
+        }
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                _success: Ordering,
+                _failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the critical section guarantees exclusive access to dst.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let r: usize;
+                    let s = disable();
+                    asm!(
+                        sext!($asm_suffix),
+                        // load from dst to prev
+                        concat!("l", $asm_suffix, " {prev}, 0({dst})"),
+                        "bne {prev}, {old}, 2f", // jump if compare failed
+                            // store new to dst
+                            concat!("s", $asm_suffix, " {new}, 0({dst})"),
+                        "2:",
+                        // r = 1 if prev == old
+                        "xor {r}, {prev}, {old}",
+                        "seqz {r}, {r}",
+                        dst = in(reg) ptr_reg!(dst),
+                        old = inout(reg) old => _,
+                        new = in(reg) new,
+                        prev = out(reg) prev,
+                        r = lateout(reg) r,
+                        options(nostack, preserves_flags),
+                    );
+                    restore(s);
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+        }
+    };
+}
+
+#[cfg(any(atomic_maybe_uninit_unsafe_assume_single_core, feature = "critical-section"))]
+#[cfg(not(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a")))]
+mod single_core {
+    use core::{
+        arch::asm,
+        mem::{self, MaybeUninit},
+        sync::atomic::Ordering,
+    };
+
+    use super::{disable, restore};
+    #[cfg(feature = "critical-section")]
+    use crate::raw::AtomicStore;
+    use crate::{
+        arch::{from_reg, load_reg, Reg},
+        raw::{AtomicCompareExchange, AtomicSwap},
+    };
+
+    atomic_single_core!(i8, "b");
+    atomic_single_core!(u8, "b");
+    atomic_single_core!(i16, "h");
+    atomic_single_core!(u16, "h");
+    atomic_single_core!(i32, "w");
+    atomic_single_core!(u32, "w");
+    #[cfg(target_arch = "riscv64")]
+    atomic_single_core!(i64, "d");
+    #[cfg(target_arch = "riscv64")]
+    atomic_single_core!(u64, "d");
+    #[cfg(target_pointer_width = "32")]
+    atomic_single_core!(isize, "w");
+    #[cfg(target_pointer_width = "32")]
+    atomic_single_core!(usize, "w");
+    #[cfg(target_pointer_width = "64")]
+    atomic_single_core!(isize, "d");
+    #[cfg(target_pointer_width = "64")]
+    atomic_single_core!(usize, "d");
+}
+
+// Double-width atomics using amocas.{d,q} (Zacas extension).
+//
+// amocas.d on riscv32 and amocas.q on riscv64 operate on even-odd register
+// pairs: rd holds the expected value (and receives the previous value) and rs2
+// holds the desired value. The lower half is in the even register.
+//
+// If the value at `dst` and rd pair are equal, the rs2 pair is stored to `dst`.
+// In either case, the value at `dst` before the operation is loaded to rd pair.
+//
+// Refs:
+// - RISC-V Zacas extension https://github.com/riscv/riscv-zacas
+// - portable-atomic https://github.com/taiki-e/portable-atomic
+#[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+#[rustfmt::skip]
+macro_rules! atomic_cas_dw {
+    ($int_type:ident, $half_type:ident, $asm_suffix:tt, $half_suffix:tt, $half_size:tt) => {
+        impl AtomicLoad for $int_type {
+            #[inline]
+            unsafe fn atomic_load(
+                src: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                debug_assert!(src as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must guarantee that `src` is valid for both writes and
+                // reads, properly aligned, and that there are no concurrent non-atomic operations.
+                // cfg guarantees that the CPU supports Zacas.
+                unsafe {
+                    let (v_lo, v_hi): (Reg<$half_type>, Reg<$half_type>);
+                    macro_rules! atomic_load {
+                        ($order:tt) => {
+                            asm!(
+                                // set old/new args of amocas to 0
+                                "li a2, 0",
+                                "li a3, 0",
+                                // (atomic) load by amocas(0, 0)
+                                concat!("amocas.", $asm_suffix, $order, " a2, a2, 0({src})"),
+                                src = in(reg) ptr_reg!(src),
+                                out("a2") v_lo,
+                                out("a3") v_hi,
+                                options(nostack, preserves_flags),
+                            )
+                        };
+                    }
+                    atomic_rmw_amo!(atomic_load, order);
+                    out.write(from_pair(Pair { lo: v_lo, hi: v_hi }));
+                }
+            }
+        }
+        impl AtomicStore for $int_type {
+            #[inline]
+            unsafe fn atomic_store(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must guarantee that `dst` is valid for both writes and
+                // reads, properly aligned, and that there are no concurrent non-atomic operations.
+                // cfg guarantees that the CPU supports Zacas.
+                unsafe {
+                    let val = load_pair::<Self, $half_type>(val);
+                    macro_rules! atomic_store {
+                        ($order:tt) => {
+                            asm!(
+                                // This is not single-copy atomic reads, but this is ok because subsequent
+                                // CAS will check for consistency.
+                                concat!("l", $half_suffix, " a2, 0({dst})"),
+                                concat!("l", $half_suffix, " a3, ", $half_size, "({dst})"),
+                                // (atomic) store (CAS loop)
+                                "2:",
+                                    "mv {tmp0}, a2",
+                                    "mv {tmp1}, a3",
+                                    concat!("amocas.", $asm_suffix, $order, " a2, a4, 0({dst})"),
+                                    "xor {tmp0}, {tmp0}, a2",
+                                    "xor {tmp1}, {tmp1}, a3",
+                                    "or {tmp0}, {tmp0}, {tmp1}",
+                                    "bnez {tmp0}, 2b", // continue loop if compare failed
+                                dst = in(reg) ptr_reg!(dst),
+                                tmp0 = out(reg) _,
+                                tmp1 = out(reg) _,
+                                out("a2") _,
+                                out("a3") _,
+                                in("a4") val.lo,
+                                in("a5") val.hi,
+                                options(nostack, preserves_flags),
+                            )
+                        };
+                    }
+                    atomic_rmw_amo!(atomic_store, order);
+                }
+            }
+        }
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must guarantee that `dst` is valid for both writes and
+                // reads, properly aligned, and that there are no concurrent non-atomic operations.
+                // cfg guarantees that the CPU supports Zacas.
+                unsafe {
+                    let val = load_pair::<Self, $half_type>(val);
+                    let (prev_lo, prev_hi): (Reg<$half_type>, Reg<$half_type>);
+                    macro_rules! swap {
+                        ($order:tt) => {
+                            asm!(
+                                // This is not single-copy atomic reads, but this is ok because subsequent
+                                // CAS will check for consistency.
+                                concat!("l", $half_suffix, " a2, 0({dst})"),
+                                concat!("l", $half_suffix, " a3, ", $half_size, "({dst})"),
+                                // (atomic) swap (CAS loop)
+                                "2:",
+                                    "mv {tmp0}, a2",
+                                    "mv {tmp1}, a3",
+                                    concat!("amocas.", $asm_suffix, $order, " a2, a4, 0({dst})"),
+                                    "xor {tmp0}, {tmp0}, a2",
+                                    "xor {tmp1}, {tmp1}, a3",
+                                    "or {tmp0}, {tmp0}, {tmp1}",
+                                    "bnez {tmp0}, 2b", // continue loop if compare failed
+                                dst = in(reg) ptr_reg!(dst),
+                                tmp0 = out(reg) _,
+                                tmp1 = out(reg) _,
+                                out("a2") prev_lo,
+                                out("a3") prev_hi,
+                                in("a4") val.lo,
+                                in("a5") val.hi,
+                                options(nostack, preserves_flags),
+                            )
+                        };
+                    }
+                    atomic_rmw_amo!(swap, order);
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
+                }
+            }
+        }
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let order = crate::utils::upgrade_success_ordering(success, failure);
+
+                // SAFETY: the caller must guarantee that `dst` is valid for both writes and
+                // reads, properly aligned, and that there are no concurrent non-atomic operations.
+                // cfg guarantees that the CPU supports Zacas.
+                unsafe {
+                    let old = load_pair::<Self, $half_type>(old);
+                    let new = load_pair::<Self, $half_type>(new);
+                    let (prev_lo, prev_hi): (Reg<$half_type>, Reg<$half_type>);
+                    let r: usize;
+                    macro_rules! cmpxchg {
+                        ($order:tt) => {
+                            asm!(
+                                // (atomic) CAS
+                                concat!("amocas.", $asm_suffix, $order, " a2, a4, 0({dst})"),
+                                "xor {old_lo}, {old_lo}, a2",
+                                "xor {old_hi}, {old_hi}, a3",
+                                "or {r}, {old_lo}, {old_hi}",
+                                "seqz {r}, {r}",
+                                dst = in(reg) ptr_reg!(dst),
+                                old_lo = inout(reg) old.lo => _,
+                                old_hi = inout(reg) old.hi => _,
+                                r = lateout(reg) r,
+                                inout("a2") old.lo => prev_lo,
+                                inout("a3") old.hi => prev_hi,
+                                in("a4") new.lo,
+                                in("a5") new.hi,
+                                options(nostack, preserves_flags),
+                            )
+                        };
+                    }
+                    atomic_rmw_amo!(cmpxchg, order);
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+        }
+    };
+}
+
+#[cfg(target_arch = "riscv32")]
+#[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+macro_rules! atomic64 {
+    ($int_type:ident) => {
+        atomic_cas_dw!($int_type, u32, "d", "w", "4");
+    };
+}
+#[cfg(target_arch = "riscv64")]
+#[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+macro_rules! atomic128 {
+    ($int_type:ident) => {
+        atomic_cas_dw!($int_type, u64, "q", "d", "8");
+    };
+}
+
+#[cfg(target_arch = "riscv32")]
+#[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+atomic64!(i64);
+#[cfg(target_arch = "riscv32")]
+#[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+atomic64!(u64);
+#[cfg(target_arch = "riscv64")]
+#[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+atomic128!(i128);
+#[cfg(target_arch = "riscv64")]
+#[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+atomic128!(u128);
+
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{CompareExchange, CompareExchangeWeak, Load, Store, Swap};
+        #[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+        {
+            if size == mem::size_of::<usize>() * 2 {
+                return Strategy::new(match op {
+                    Load | CompareExchange | CompareExchangeWeak => "double-width AMOCAS (Zacas)",
+                    Store | Swap => "double-width AMOCAS loop (Zacas)",
+                });
+            }
+        }
+        #[cfg(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a"))]
+        {
+            // Returns whether the extension is available (see has_zacas!/has_zabha!).
+            #[inline]
+            fn zacas() -> bool {
+                #[cfg(any(
+                    target_feature = "zacas",
+                    atomic_maybe_uninit_target_feature = "zacas",
+                    all(
+                        atomic_maybe_uninit_outline_atomics,
+                        any(target_os = "linux", target_os = "android"),
+                    ),
+                ))]
+                {
+                    has_zacas!()
+                }
+                #[cfg(not(any(
+                    target_feature = "zacas",
+                    atomic_maybe_uninit_target_feature = "zacas",
+                    all(
+                        atomic_maybe_uninit_outline_atomics,
+                        any(target_os = "linux", target_os = "android"),
+                    ),
+                )))]
+                {
+                    false
+                }
+            }
+            #[inline]
+            fn zabha() -> bool {
+                #[cfg(any(
+                    target_feature = "zabha",
+                    atomic_maybe_uninit_target_feature = "zabha",
+                    all(
+                        atomic_maybe_uninit_outline_atomics,
+                        any(target_os = "linux", target_os = "android"),
+                    ),
+                ))]
+                {
+                    has_zabha!()
+                }
+                #[cfg(not(any(
+                    target_feature = "zabha",
+                    atomic_maybe_uninit_target_feature = "zabha",
+                    all(
+                        atomic_maybe_uninit_outline_atomics,
+                        any(target_os = "linux", target_os = "android"),
+                    ),
+                )))]
+                {
+                    false
+                }
+            }
+            let detect = cfg!(all(
+                atomic_maybe_uninit_outline_atomics,
+                any(target_os = "linux", target_os = "android"),
+            ));
+            let static_zacas =
+                cfg!(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"));
+            let static_zabha =
+                cfg!(any(target_feature = "zabha", atomic_maybe_uninit_target_feature = "zabha"));
+            let (description, detected) = match (size, op) {
+                (_, Load) => ("L* with FENCE", false),
+                (_, Store) => ("S* with FENCE", false),
+                (1 | 2, Swap) => (
+                    if zabha() { "AMOSWAP.B/AMOSWAP.H (Zabha)" } else { "masked LR/SC loop" },
+                    detect && !static_zabha,
+                ),
+                (1 | 2, CompareExchange | CompareExchangeWeak) => (
+                    if zabha() && zacas() {
+                        "AMOCAS.B/AMOCAS.H (Zabha, Zacas)"
+                    } else if matches!(op, CompareExchange) {
+                        "masked LR/SC loop"
+                    } else {
+                        "masked LR/SC"
+                    },
+                    detect && !(static_zabha && static_zacas),
+                ),
+                (_, Swap) => ("AMOSWAP", false),
+                (_, CompareExchange | CompareExchangeWeak) => (
+                    if zacas() {
+                        "AMOCAS (Zacas)"
+                    } else if matches!(op, CompareExchange) {
+                        "LR/SC loop"
+                    } else {
+                        "LR/SC"
+                    },
+                    detect && !static_zacas,
+                ),
+            };
+            if detected {
+                Strategy::detected(description)
+            } else {
+                Strategy::new(description)
+            }
+        }
+        #[cfg(not(any(target_feature = "a", atomic_maybe_uninit_target_feature = "a")))]
+        {
+            Strategy::new(match op {
+                Load => "L* with FENCE",
+                #[cfg(feature = "critical-section")]
+                Store | Swap | CompareExchange | CompareExchangeWeak => "critical section",
+                #[cfg(not(feature = "critical-section"))]
+                Store => "S* with FENCE",
+                #[cfg(not(feature = "critical-section"))]
+                Swap | CompareExchange | CompareExchangeWeak => {
+                    "disabling interrupts (mstatus.MIE)"
+                }
+            })
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     test_atomic!(isize);
@@ -403,16 +1332,37 @@ mod tests {
     test_atomic!(u16);
     test_atomic!(i32);
     test_atomic!(u32);
-    #[cfg(target_arch = "riscv64")]
+    #[cfg(any(
+        target_arch = "riscv64",
+        target_feature = "zacas",
+        atomic_maybe_uninit_target_feature = "zacas",
+    ))]
     test_atomic!(i64);
-    #[cfg(target_arch = "riscv64")]
+    #[cfg(any(
+        target_arch = "riscv64",
+        target_feature = "zacas",
+        atomic_maybe_uninit_target_feature = "zacas",
+    ))]
     test_atomic!(u64);
+    #[cfg(target_arch = "riscv64")]
+    #[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+    test_atomic!(i128);
+    #[cfg(target_arch = "riscv64")]
+    #[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+    test_atomic!(u128);
 
     // load/store/swap implementation is not affected by signedness, so it is
     // enough to test only unsigned types.
     stress_test!(u8);
     stress_test!(u16);
     stress_test!(u32);
-    #[cfg(target_arch = "riscv64")]
+    #[cfg(any(
+        target_arch = "riscv64",
+        target_feature = "zacas",
+        atomic_maybe_uninit_target_feature = "zacas",
+    ))]
     stress_test!(u64);
+    #[cfg(target_arch = "riscv64")]
+    #[cfg(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"))]
+    stress_test!(u128);
 }
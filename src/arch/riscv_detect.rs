@@ -0,0 +1,152 @@
+// Run-time detection of RISC-V extensions on Linux/Android.
+//
+// This is enabled by `--cfg atomic_maybe_uninit_outline_atomics`.
+//
+// Refs:
+// - RISC-V Hardware Probing Interface https://docs.kernel.org/arch/riscv/hwprobe.html
+// - https://github.com/torvalds/linux/blob/v6.12/arch/riscv/include/uapi/asm/hwprobe.h
+// - portable-atomic https://github.com/taiki-e/portable-atomic
+//
+// We use the riscv_hwprobe syscall (Linux 6.4+). If it is not available, Zacas
+// and Zabha are treated as unavailable, and the LR/SC loop is used, which is the
+// same as without run-time detection. (AT_HWCAP in auxv is not used as a
+// fallback, because it only represents single-letter extensions and cannot
+// report Zacas or Zabha.)
+//
+// Note: Ztso is also reported by riscv_hwprobe, but it is not used here because
+// code generated assuming RVWMO is also correct under RVTSO.
+
+use core::{
+    arch::asm,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+#[derive(Clone, Copy)]
+pub(crate) struct CpuInfo(u32);
+
+impl CpuInfo {
+    const INIT: u32 = 0;
+    const HAS_ZACAS: u32 = 1;
+    const HAS_ZABHA: u32 = 2;
+
+    #[inline]
+    fn set(&mut self, bit: u32) {
+        self.0 |= 1 << bit;
+    }
+    #[inline]
+    fn test(self, bit: u32) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+    #[inline]
+    pub(crate) fn has_zacas(self) -> bool {
+        self.test(CpuInfo::HAS_ZACAS)
+    }
+    #[inline]
+    pub(crate) fn has_zabha(self) -> bool {
+        self.test(CpuInfo::HAS_ZABHA)
+    }
+}
+
+#[inline]
+pub(crate) fn detect() -> CpuInfo {
+    static CACHE: AtomicU32 = AtomicU32::new(0);
+    let mut info = CpuInfo(CACHE.load(Ordering::Relaxed));
+    if info.0 != 0 {
+        return info;
+    }
+    info.set(CpuInfo::INIT);
+    // Note: detect_slow is cold, so the fast path above is usually inlined into callers.
+    detect_slow(&mut info);
+    CACHE.store(info.0, Ordering::Relaxed);
+    info
+}
+
+// Defined in https://github.com/torvalds/linux/blob/v6.12/arch/riscv/include/uapi/asm/hwprobe.h
+#[allow(non_camel_case_types)]
+#[repr(C)]
+struct riscv_hwprobe {
+    key: i64,
+    value: u64,
+}
+#[allow(non_upper_case_globals)]
+const __NR_riscv_hwprobe: usize = 258;
+const RISCV_HWPROBE_KEY_IMA_EXT_0: i64 = 4;
+const RISCV_HWPROBE_EXT_ZACAS: u64 = 1 << 34;
+const RISCV_HWPROBE_EXT_ZABHA: u64 = 1 << 58;
+
+#[cold]
+fn detect_slow(info: &mut CpuInfo) {
+    let mut pairs = [riscv_hwprobe { key: RISCV_HWPROBE_KEY_IMA_EXT_0, value: 0 }];
+    // SAFETY: `pairs` is valid for writes of `pairs.len()` elements, and
+    // the syscall does not access other memory when cpusetsize is 0.
+    let res = unsafe { hwprobe(pairs.as_mut_ptr(), pairs.len(), 0, core::ptr::null_mut(), 0) };
+    // If the kernel doesn't support riscv_hwprobe (Linux < 6.4), it returns -ENOSYS.
+    // In that case, treat the extensions as unavailable. (see comments at the top of this file)
+    // If the kernel doesn't know the key, it sets the key to -1.
+    if res != 0 || pairs[0].key != RISCV_HWPROBE_KEY_IMA_EXT_0 {
+        return;
+    }
+    let value = pairs[0].value;
+    if value & RISCV_HWPROBE_EXT_ZACAS != 0 {
+        info.set(CpuInfo::HAS_ZACAS);
+    }
+    if value & RISCV_HWPROBE_EXT_ZABHA != 0 {
+        info.set(CpuInfo::HAS_ZABHA);
+    }
+}
+
+// riscv_hwprobe(struct riscv_hwprobe *pairs, size_t pair_count,
+//               size_t cpusetsize, cpu_set_t *cpus, unsigned int flags)
+//
+// We call the syscall directly instead of via libc's syscall function, because
+// this crate doesn't depend on libc.
+unsafe fn hwprobe(
+    pairs: *mut riscv_hwprobe,
+    pair_count: usize,
+    cpu_set_size: usize,
+    cpus: *mut usize,
+    flags: u32,
+) -> isize {
+    // SAFETY: the caller must uphold the safety contract.
+    unsafe {
+        let r: isize;
+        asm!(
+            "ecall",
+            in("a7") __NR_riscv_hwprobe,
+            inout("a0") pairs => r,
+            in("a1") pair_count,
+            in("a2") cpu_set_size,
+            in("a3") cpus,
+            in("a4") flags as usize,
+            options(nostack, preserves_flags),
+        );
+        r
+    }
+}
+
+#[allow(
+    clippy::alloc_instead_of_core,
+    clippy::std_instead_of_alloc,
+    clippy::std_instead_of_core,
+    clippy::undocumented_unsafe_blocks,
+    clippy::wildcard_imports
+)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let info = detect();
+        assert!(info.test(CpuInfo::INIT));
+        // The result must be cached.
+        assert_eq!(detect().0, info.0);
+        // If the extension is enabled at compile-time, it must be detected at run-time.
+        if cfg!(any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas")) {
+            assert!(info.has_zacas());
+        }
+        if cfg!(any(target_feature = "zabha", atomic_maybe_uninit_target_feature = "zabha")) {
+            assert!(info.has_zabha());
+        }
+    }
+}
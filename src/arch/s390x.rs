@@ -9,6 +9,8 @@
 // - s390x https://godbolt.org/z/qv8s6o13G
 // - s390x (z196) https://godbolt.org/z/jW67E4YEq
 
+#[path = "pair.rs"]
+mod pair;
 #[cfg(not(atomic_maybe_uninit_no_s390x_asm_cc_clobbered))]
 #[path = "partword.rs"]
 mod partword;
@@ -19,9 +21,14 @@ use core::{
     sync::atomic::Ordering,
 };
 
+use self::pair::{from_pair, load_pair, Pair};
+use super::{from_reg, load_reg, Reg};
 #[cfg(not(atomic_maybe_uninit_no_s390x_asm_cc_clobbered))]
 use crate::raw::{AtomicCompareExchange, AtomicSwap};
-use crate::raw::{AtomicLoad, AtomicStore};
+use crate::{
+    info::Strategy,
+    raw::{AtomicLoad, AtomicStore},
+};
 
 #[cfg(not(atomic_maybe_uninit_no_s390x_asm_cc_clobbered))]
 type XSize = u64;
@@ -43,6 +50,8 @@ fn complement(v: u32) -> u32 {
 
 macro_rules! atomic_load_store {
     ($int_type:ident, $l_suffix:tt, $asm_suffix:tt) => {
+        // r0 is read as zero when used as a base register, so keep {src} out of it.
+        impl_load_reg!($int_type, reg, concat!("l", $l_suffix, " {v}, 0({src})"); out("r0") _);
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
@@ -55,17 +64,16 @@ macro_rules! atomic_load_store {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let v: Reg<Self>;
                     // atomic load is always SeqCst.
                     asm!(
                         // (atomic) load from src to r0
                         concat!("l", $l_suffix, " %r0, 0({src})"),
-                        // store r0 to out
-                        concat!("st", $asm_suffix, " %r0, 0({out})"),
                         src = in(reg) ptr_reg!(src),
-                        out = in(reg) ptr_reg!(out),
-                        out("r0") _,
+                        out("r0") v,
                         options(nostack, preserves_flags),
                     );
+                    out.write(from_reg(v));
                 }
             }
         }
@@ -81,17 +89,15 @@ macro_rules! atomic_load_store {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
                     macro_rules! atomic_store {
                         ($fence:tt) => {
                             asm!(
-                                // load from val to r0
-                                concat!("l", $l_suffix, " %r0, 0({val})"),
                                 // (atomic) store r0 to dst
                                 concat!("st", $asm_suffix, " %r0, 0({dst})"),
                                 $fence,
                                 dst = in(reg) ptr_reg!(dst),
-                                val = in(reg) ptr_reg!(val),
-                                out("r0") _,
+                                in("r0") val,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -136,25 +142,22 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     // atomic swap is always SeqCst.
                     asm!(
-                        // load from val to val_tmp
-                        concat!("l", $asm_suffix, " {val_tmp}, 0({val})"),
                         // (atomic) swap (CAS loop)
                         concat!("l", $asm_suffix, " %r0, 0({dst})"),
                         "2:",
-                            concat!("cs", $asm_suffix, " %r0, {val_tmp}, 0({dst})"),
+                            concat!("cs", $asm_suffix, " %r0, {val}, 0({dst})"),
                             "jl 2b",
-                        // store r0 to out
-                        concat!("st", $asm_suffix, " %r0, 0({out})"),
                         dst = in(reg) ptr_reg!(dst),
-                        val = in(reg) ptr_reg!(val),
-                        val_tmp = out(reg) _,
-                        out = in(reg) ptr_reg!(out),
-                        out("r0") _,
+                        val = in(reg) val,
+                        out("r0") prev,
                         // Do not use `preserves_flags` because CS modifies the condition code.
                         options(nostack),
                     );
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -176,27 +179,23 @@ macro_rules! atomic {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let mut r: i64;
                     // compare_exchange is always SeqCst.
                     asm!(
-                        // load from old/new to r0/tmp
-                        concat!("l", $asm_suffix, " %r0, 0({old})"),
-                        concat!("l", $asm_suffix, " {tmp}, 0({new})"),
                         // (atomic) CAS
-                        concat!("cs", $asm_suffix, " %r0, {tmp}, 0({dst})"),
+                        concat!("cs", $asm_suffix, " %r0, {new}, 0({dst})"),
                         // store condition code
-                        "ipm {tmp}",
-                        // store r0 to out
-                        concat!("st", $asm_suffix, " %r0, 0({out})"),
+                        "ipm {r}",
                         dst = in(reg) ptr_reg!(dst),
-                        old = in(reg) ptr_reg!(old),
-                        new = in(reg) ptr_reg!(new),
-                        tmp = out(reg) r,
-                        out = in(reg) ptr_reg!(out),
-                        out("r0") _,
+                        new = in(reg) new,
+                        r = lateout(reg) r,
+                        inout("r0") old => prev,
                         // Do not use `preserves_flags` because CS modifies the condition code.
                         options(nostack),
                     );
+                    out.write(from_reg(prev));
                     extract_cc(r)
                 }
             }
@@ -223,11 +222,12 @@ macro_rules! atomic_sub_word {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
                     // Implement sub-word atomic operations using word-sized CAS loop.
                     // Based on assemblies generated by rustc/LLVM.
                     // See also partword.rs.
                     asm!(
-                        concat!("l", $l_suffix, " %r0, 0(%r3)"),
                         "l %r3, 0({dst})",
                         "2:",
                             "rll %r14, %r3, 0({shift})",
@@ -236,17 +236,16 @@ macro_rules! atomic_sub_word {
                             "cs %r3, %r14, 0({dst})",
                             "jl 2b",
                         concat!("rll %r0, %r3, ", $bits ,"({shift})"),
-                        concat!("st", $asm_suffix, " %r0, 0({out})"),
                         dst = in(reg) ptr_reg!(aligned_ptr),
-                        out = in(reg) ptr_reg!(out),
                         shift = in(reg) shift as u32,
                         shift_c = in(reg) complement(shift as u32),
-                        out("r0") _,
-                        inout("r3") ptr_reg!(val) => _,
+                        inout("r0") val => prev,
+                        out("r3") _,
                         out("r14") _,
                         // Do not use `preserves_flags` because CS modifies the condition code.
                         options(nostack),
                     );
+                    out.write(from_reg(prev));
                 }
             }
         }
@@ -269,13 +268,16 @@ macro_rules! atomic_sub_word {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
                     let mut r: i64;
                     // Implement sub-word atomic operations using word-sized CAS loop.
                     // Based on assemblies generated by rustc/LLVM.
                     // See also partword.rs.
                     asm!(
-                        concat!("ll", $asm_suffix, " %r0, 0(%r3)"),
-                        concat!("l", $l_suffix, " %r1, 0(%r4)"),
+                        // The upper bits of old are undefined, so zero-extend it
+                        // for comparison with the zero-extended loaded value.
+                        concat!("ll", $asm_suffix, "r %r0, %r0"),
                         "l %r4, 0({dst})",
                         "2:",
                             concat!("rll %r13, %r4, ", $bits ,"({shift})"),
@@ -288,20 +290,20 @@ macro_rules! atomic_sub_word {
                             "jl 2b",
                         "3:",
                         // store condition code
-                        "ipm %r0",
-                        concat!("st", $asm_suffix, " %r13, 0({out})"),
+                        "ipm {r}",
                         dst = in(reg) ptr_reg!(aligned_ptr),
-                        out = in(reg) ptr_reg!(out),
                         shift = in(reg) shift as u32,
                         shift_c = in(reg) complement(shift as u32),
-                        out("r0") r,
-                        out("r1") _,
-                        inout("r3") ptr_reg!(old) => _,
-                        inout("r4") ptr_reg!(new) => _,
-                        out("r13") _,
+                        r = lateout(reg) r,
+                        inout("r0") old => _,
+                        inout("r1") new => _,
+                        out("r3") _,
+                        out("r4") _,
+                        out("r13") prev,
                         // Do not use `preserves_flags` because CS modifies the condition code.
                         options(nostack),
                     );
+                    out.write(from_reg(prev));
                     extract_cc(r)
                 }
             }
@@ -335,20 +337,18 @@ macro_rules! atomic128 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let (v_hi, v_lo): (Reg<u64>, Reg<u64>);
                     // atomic load is always SeqCst.
                     asm!(
-                        // (atomic) load from src to out pair
+                        // (atomic) load from src to v pair
                         "lpq %r0, 0({src})",
-                        // store out pair to out
-                        "stg %r1, 8({out})",
-                        "stg %r0, 0({out})",
                         src = in(reg) ptr_reg!(src),
-                        out = in(reg) ptr_reg!(out),
                         // Quadword atomic instructions work with even/odd pair of specified register and subsequent register.
-                        out("r0") _, // out (hi)
-                        out("r1") _, // out (lo)
+                        out("r0") v_hi,
+                        out("r1") v_lo,
                         options(nostack, preserves_flags),
                     );
+                    out.write(from_pair(Pair { lo: v_lo, hi: v_hi }));
                 }
             }
         }
@@ -364,20 +364,17 @@ macro_rules! atomic128 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_pair::<Self, u64>(val);
                     macro_rules! atomic_store {
                         ($fence:tt) => {
                             asm!(
-                                // load from val to val pair
-                                "lg %r1, 8({val})",
-                                "lg %r0, 0({val})",
                                 // (atomic) store val pair to dst
                                 "stpq %r0, 0({dst})",
                                 $fence,
                                 dst = in(reg) ptr_reg!(dst),
-                                val = in(reg) ptr_reg!(val),
                                 // Quadword atomic instructions work with even/odd pair of specified register and subsequent register.
-                                out("r0") _, // val (hi)
-                                out("r1") _, // val (lo)
+                                in("r0") val.hi,
+                                in("r1") val.lo,
                                 options(nostack, preserves_flags),
                             )
                         };
@@ -416,30 +413,25 @@ macro_rules! atomic128 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_pair::<Self, u64>(val);
+                    let (prev_hi, prev_lo): (Reg<u64>, Reg<u64>);
                     // atomic swap is always SeqCst.
                     asm!(
-                        // load from val to val pair
-                        "lg %r1, 8({val})",
-                        "lg %r0, 0({val})",
                         // (atomic) swap (CAS loop)
                         "lpq %r2, 0({dst})",
                         "2:",
                             "cdsg %r2, %r0, 0({dst})",
                             "jl 2b",
-                        // store out pair to out
-                        "stg %r3, 8({out})",
-                        "stg %r2, 0({out})",
-                        dst = inout(reg) ptr_reg!(dst) => _,
-                        val = in(reg) ptr_reg!(val),
-                        out = inout(reg) ptr_reg!(out) => _,
+                        dst = in(reg) ptr_reg!(dst),
                         // Quadword atomic instructions work with even/odd pair of specified register and subsequent register.
-                        out("r0") _, // val (hi)
-                        out("r1") _, // val (lo)
-                        lateout("r2") _, // out (hi)
-                        lateout("r3") _, // out (lo)
+                        in("r0") val.hi,
+                        in("r1") val.lo,
+                        out("r2") prev_hi,
+                        out("r3") prev_lo,
                         // Do not use `preserves_flags` because CDSG modifies the condition code.
                         options(nostack),
                     );
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                 }
             }
         }
@@ -461,34 +453,27 @@ macro_rules! atomic128 {
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let old = load_pair::<Self, u64>(old);
+                    let new = load_pair::<Self, u64>(new);
+                    let (prev_hi, prev_lo): (Reg<u64>, Reg<u64>);
                     let mut r: i64;
                     // compare_exchange is always SeqCst.
                     asm!(
-                        // load from old/new to old/new pairs
-                        "lg %r1, 8({old})",
-                        "lg %r0, 0({old})",
-                        "lg %r13, 8({new})",
-                        "lg %r12, 0({new})",
                         // (atomic) CAS
                         "cdsg %r0, %r12, 0({dst})",
                         // store condition code
                         "ipm {r}",
-                        // store out pair to out
-                        "stg %r1, 8({out})",
-                        "stg %r0, 0({out})",
                         dst = in(reg) ptr_reg!(dst),
-                        old = in(reg) ptr_reg!(old),
-                        new = in(reg) ptr_reg!(new),
-                        out = inout(reg) ptr_reg!(out) => _,
                         r = lateout(reg) r,
                         // Quadword atomic instructions work with even/odd pair of specified register and subsequent register.
-                        out("r0") _, // old (hi) -> out (hi)
-                        out("r1") _, // old (lo) -> out (lo)
-                        out("r12") _, // new (hi)
-                        out("r13") _, // new (hi)
+                        inout("r0") old.hi => prev_hi,
+                        inout("r1") old.lo => prev_lo,
+                        in("r12") new.hi,
+                        in("r13") new.lo,
                         // Do not use `preserves_flags` because CDSG modifies the condition code.
                         options(nostack),
                     );
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                     extract_cc(r)
                 }
             }
@@ -499,6 +484,42 @@ macro_rules! atomic128 {
 atomic128!(i128);
 atomic128!(u128);
 
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{Load, Store, Swap};
+        Strategy::new(match (size, op) {
+            (16, Load) => "LPQ",
+            (_, Load) => "LB/LH/L/LG",
+            // bcr 14,0 (fast-BCR-serialization) requires z196 or later.
+            #[cfg(any(
+                target_feature = "fast-serialization",
+                atomic_maybe_uninit_target_feature = "fast-serialization",
+            ))]
+            (16, Store) => "STPQ, BCR 14,0 for SeqCst (fast-BCR-serialization)",
+            #[cfg(any(
+                target_feature = "fast-serialization",
+                atomic_maybe_uninit_target_feature = "fast-serialization",
+            ))]
+            (_, Store) => "STC/STH/ST/STG, BCR 14,0 for SeqCst (fast-BCR-serialization)",
+            #[cfg(not(any(
+                target_feature = "fast-serialization",
+                atomic_maybe_uninit_target_feature = "fast-serialization",
+            )))]
+            (16, Store) => "STPQ, BCR 15,0 for SeqCst",
+            #[cfg(not(any(
+                target_feature = "fast-serialization",
+                atomic_maybe_uninit_target_feature = "fast-serialization",
+            )))]
+            (_, Store) => "STC/STH/ST/STG, BCR 15,0 for SeqCst",
+            (16, Swap) => "CDSG loop",
+            (16, _) => "CDSG",
+            (1 | 2, _) => "masked CS loop",
+            (_, Swap) => "CS/CSG loop",
+            (_, _) => "CS/CSG",
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[cfg(not(atomic_maybe_uninit_no_s390x_asm_cc_clobbered))]
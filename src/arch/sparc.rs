@@ -0,0 +1,712 @@
+// SPARC and SPARC64
+//
+// Refs:
+// - The SPARC Architecture Manual, Version 8
+//   https://sparc.org/wp-content/uploads/2014/01/v8.pdf.gz
+// - The SPARC Architecture Manual, Version 9
+//   https://sparc.org/wp-content/uploads/2014/01/SPARCV9.pdf.gz
+// - GRLIB IP Core User's Manual (LEON3/LEON4)
+//   https://www.gaisler.com/products/grlib/grip.pdf
+// - Linux kernel's memory barriers (arch/sparc/include/asm/barrier_64.h)
+//   https://github.com/torvalds/linux/blob/v6.6/arch/sparc/include/asm/barrier_64.h
+//
+// SPARC-V9 has three memory models: TSO (Total Store Order), PSO (Partial
+// Store Order), and RMO (Relaxed Memory Order), selected by PSTATE.MM.
+// Linux and Solaris run user-space programs in TSO, but we don't rely on it:
+// the membar masks used here are the ones required under RMO, which is the
+// weakest, so they are also correct under PSO and TSO. (On TSO, only
+// #StoreLoad actually has an effect, and the others are cheap.)
+//
+// SPARC-V8 doesn't have MEMBAR, so STBAR is used as a fence on 32-bit SPARC
+// without V9 instructions. However, STBAR only orders stores against stores
+// and cannot prevent a later load from passing a store in the store buffer,
+// so SeqCst stores are followed by LDSTUB to a dummy stack slot below %sp,
+// like GCC does for V8: atomic load-store instructions (LDSTUB, SWAP, CASA)
+// wait for the store buffer to drain, so they also act as a full barrier.
+//
+// On 32-bit SPARC, 32-bit swap is always available (SWAP is a V8
+// instruction), and other swap/CAS are available if V9 instructions are
+// available (V8+, e.g., sparc-unknown-linux-gnu) or the CPU has LEON's CASA
+// extension (leoncasa target feature; LEON4 and some LEON3). Note that LLVM
+// doesn't enable leoncasa by default on LEON3 because CASA is optional on
+// LEON3, so `-C target-feature=+leoncasa` is needed to enable them on it.
+//
+// Note that SPARC has branch delay slots; the instruction after a branch is
+// executed regardless of whether the branch is taken.
+
+#[cfg(any(
+    target_arch = "sparc64",
+    target_feature = "v9",
+    atomic_maybe_uninit_target_feature = "v9",
+    target_feature = "leoncasa",
+    atomic_maybe_uninit_target_feature = "leoncasa",
+))]
+#[path = "partword.rs"]
+mod partword;
+
+use core::{
+    arch::asm,
+    mem::{self, MaybeUninit},
+    sync::atomic::Ordering,
+};
+
+use super::{from_reg, load_reg, Reg};
+#[cfg(any(
+    target_arch = "sparc64",
+    target_feature = "v9",
+    atomic_maybe_uninit_target_feature = "v9",
+    target_feature = "leoncasa",
+    atomic_maybe_uninit_target_feature = "leoncasa",
+))]
+use crate::raw::AtomicCompareExchange;
+use crate::{
+    info::Strategy,
+    raw::{AtomicLoad, AtomicStore, AtomicSwap},
+};
+
+#[cfg(any(
+    target_arch = "sparc64",
+    target_feature = "v9",
+    atomic_maybe_uninit_target_feature = "v9",
+))]
+macro_rules! load_order {
+    ($op:ident, $order:ident) => {
+        match $order {
+            Ordering::Relaxed => $op!(""),
+            // Acquire and SeqCst loads are equivalent
+            // (SeqCst stores have a trailing #StoreLoad).
+            Ordering::Acquire | Ordering::SeqCst => $op!("membar #LoadLoad | #LoadStore"),
+            _ => unreachable!("{:?}", $order),
+        }
+    };
+}
+#[cfg(any(
+    target_arch = "sparc64",
+    target_feature = "v9",
+    atomic_maybe_uninit_target_feature = "v9",
+))]
+macro_rules! store_order {
+    ($op:ident, $order:ident) => {
+        match $order {
+            Ordering::Relaxed => $op!("", ""),
+            Ordering::Release => $op!("", "membar #LoadStore | #StoreStore"),
+            Ordering::SeqCst => $op!("membar #StoreLoad", "membar #LoadStore | #StoreStore"),
+            _ => unreachable!("{:?}", $order),
+        }
+    };
+}
+#[cfg(any(
+    target_arch = "sparc64",
+    target_feature = "v9",
+    atomic_maybe_uninit_target_feature = "v9",
+))]
+macro_rules! atomic_rmw {
+    ($op:ident, $order:ident) => {
+        match $order {
+            Ordering::Relaxed => $op!("", ""),
+            Ordering::Acquire => $op!("membar #LoadLoad | #LoadStore", ""),
+            Ordering::Release => $op!("", "membar #LoadStore | #StoreStore"),
+            Ordering::AcqRel => {
+                $op!("membar #LoadLoad | #LoadStore", "membar #LoadStore | #StoreStore")
+            }
+            Ordering::SeqCst => $op!(
+                "membar #LoadLoad | #LoadStore | #StoreLoad",
+                "membar #LoadStore | #StoreStore"
+            ),
+            _ => unreachable!("{:?}", $order),
+        }
+    };
+}
+#[cfg(not(any(
+    target_arch = "sparc64",
+    target_feature = "v9",
+    atomic_maybe_uninit_target_feature = "v9",
+)))]
+macro_rules! load_order {
+    ($op:ident, $order:ident) => {
+        match $order {
+            Ordering::Relaxed => $op!(""),
+            Ordering::Acquire | Ordering::SeqCst => $op!("stbar"),
+            _ => unreachable!("{:?}", $order),
+        }
+    };
+}
+#[cfg(not(any(
+    target_arch = "sparc64",
+    target_feature = "v9",
+    atomic_maybe_uninit_target_feature = "v9",
+)))]
+macro_rules! store_order {
+    ($op:ident, $order:ident) => {
+        match $order {
+            Ordering::Relaxed => $op!("", ""),
+            Ordering::Release => $op!("", "stbar"),
+            Ordering::SeqCst => $op!("ldstub [%sp - 1], %g0", "stbar"),
+            _ => unreachable!("{:?}", $order),
+        }
+    };
+}
+#[cfg(not(any(
+    target_arch = "sparc64",
+    target_feature = "v9",
+    atomic_maybe_uninit_target_feature = "v9",
+)))]
+macro_rules! atomic_rmw {
+    ($op:ident, $order:ident) => {
+        match $order {
+            Ordering::Relaxed => $op!("", ""),
+            Ordering::Acquire => $op!("stbar", ""),
+            Ordering::Release => $op!("", "stbar"),
+            // AcqRel and SeqCst RMWs are equivalent.
+            Ordering::AcqRel | Ordering::SeqCst => $op!("stbar", "stbar"),
+            _ => unreachable!("{:?}", $order),
+        }
+    };
+}
+
+// The address space identifier (ASI) used for CASA.
+// On V9, `cas` is an alias of `casa` with ASI_PRIMARY (0x80).
+#[cfg(any(
+    target_arch = "sparc64",
+    target_feature = "v9",
+    atomic_maybe_uninit_target_feature = "v9",
+))]
+macro_rules! asi {
+    () => {
+        "0x80"
+    };
+}
+// LEON's CASA only supports ASI 0xA (user data) and 0xB (supervisor data).
+// LLVM uses 0xA, which also works in supervisor mode.
+#[cfg(not(any(
+    target_arch = "sparc64",
+    target_feature = "v9",
+    atomic_maybe_uninit_target_feature = "v9",
+)))]
+#[cfg(any(target_feature = "leoncasa", atomic_maybe_uninit_target_feature = "leoncasa"))]
+macro_rules! asi {
+    () => {
+        "10"
+    };
+}
+
+#[cfg(target_arch = "sparc")]
+type XSize = u32;
+#[cfg(target_arch = "sparc64")]
+type XSize = u64;
+
+#[rustfmt::skip]
+macro_rules! atomic_load_store {
+    ($int_type:ident, $ld_suffix:tt, $st_suffix:tt) => {
+        impl_load_reg!($int_type, reg, concat!("ld", $ld_suffix, " [{src}], {v}"));
+        impl AtomicLoad for $int_type {
+            #[inline]
+            unsafe fn atomic_load(
+                src: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                debug_assert!(src as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let v: Reg<Self>;
+                    macro_rules! atomic_load {
+                        ($acquire:tt) => {
+                            asm!(
+                                // (atomic) load from src to v
+                                concat!("ld", $ld_suffix, " [{src}], {v}"),
+                                $acquire, // acquire fence
+                                src = in(reg) ptr_reg!(src),
+                                v = lateout(reg) v,
+                                options(nostack, preserves_flags),
+                            )
+                        };
+                    }
+                    load_order!(atomic_load, order);
+                    out.write(from_reg(v));
+                }
+            }
+        }
+        impl AtomicStore for $int_type {
+            #[inline]
+            unsafe fn atomic_store(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let val = load_reg(val);
+                    macro_rules! atomic_store {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                // (atomic) store val to dst
+                                $release, // release fence
+                                concat!("st", $st_suffix, " {val}, [{dst}]"),
+                                $acquire, // acquire fence
+                                dst = in(reg) ptr_reg!(dst),
+                                val = in(reg) val,
+                                options(nostack, preserves_flags),
+                            )
+                        };
+                    }
+                    store_order!(atomic_store, order);
+                }
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+macro_rules! atomic {
+    ($int_type:ident) => {
+        atomic_load_store!($int_type, "", "");
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
+                    macro_rules! swap {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                // (atomic) swap
+                                $release, // release fence
+                                "swap [{dst}], {val}",
+                                $acquire, // acquire fence
+                                dst = in(reg) ptr_reg!(dst),
+                                val = inout(reg) val => prev,
+                                options(nostack, preserves_flags),
+                            )
+                        };
+                    }
+                    atomic_rmw!(swap, order);
+                    out.write(from_reg(prev));
+                }
+            }
+        }
+        #[cfg(any(
+            target_arch = "sparc64",
+            target_feature = "v9",
+            atomic_maybe_uninit_target_feature = "v9",
+            target_feature = "leoncasa",
+            atomic_maybe_uninit_target_feature = "leoncasa",
+        ))]
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let order = crate::utils::upgrade_success_ordering(success, failure);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let r: XSize;
+                    macro_rules! cmpxchg {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                // (atomic) CAS
+                                $release, // release fence
+                                concat!("casa [{dst}] ", asi!(), ", {old}, {new}"),
+                                $acquire, // acquire fence
+                                // r = 1 if new (prev) == old
+                                "xor {new}, {old}, {r}",
+                                "subcc %g0, {r}, %g0",
+                                "subx %g0, -1, {r}",
+                                dst = in(reg) ptr_reg!(dst),
+                                old = in(reg) old,
+                                new = inout(reg) new => prev,
+                                r = out(reg) r,
+                                // Do not use `preserves_flags` because SUBCC modifies the condition codes.
+                                options(nostack),
+                            )
+                        };
+                    }
+                    atomic_rmw!(cmpxchg, order);
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+        }
+    };
+}
+
+#[cfg(target_arch = "sparc64")]
+#[rustfmt::skip]
+macro_rules! atomic64 {
+    ($int_type:ident) => {
+        atomic_load_store!($int_type, "x", "x");
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
+                    macro_rules! swap {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                // (atomic) swap (CAS loop)
+                                $release, // release fence
+                                "ldx [{dst}], {prev}",
+                                "2:",
+                                    "mov {val}, {tmp}",
+                                    concat!("casxa [{dst}] ", asi!(), ", {prev}, {tmp}"),
+                                    "cmp {tmp}, {prev}",
+                                    "bne %xcc, 2b",
+                                    "mov {tmp}, {prev}", // (delay slot)
+                                $acquire, // acquire fence
+                                dst = in(reg) ptr_reg!(dst),
+                                val = in(reg) val,
+                                prev = out(reg) prev,
+                                tmp = out(reg) _,
+                                // Do not use `preserves_flags` because CMP modifies the condition codes.
+                                options(nostack),
+                            )
+                        };
+                    }
+                    atomic_rmw!(swap, order);
+                    out.write(from_reg(prev));
+                }
+            }
+        }
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let order = crate::utils::upgrade_success_ordering(success, failure);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let mut r: XSize = 0;
+                    macro_rules! cmpxchg {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                // (atomic) CAS
+                                $release, // release fence
+                                concat!("casxa [{dst}] ", asi!(), ", {old}, {new}"),
+                                $acquire, // acquire fence
+                                // r = 1 if new (prev) == old
+                                "cmp {new}, {old}",
+                                "move %xcc, 1, {r}",
+                                dst = in(reg) ptr_reg!(dst),
+                                old = in(reg) old,
+                                new = inout(reg) new => prev,
+                                r = inout(reg) r,
+                                // Do not use `preserves_flags` because CMP modifies the condition codes.
+                                options(nostack),
+                            )
+                        };
+                    }
+                    atomic_rmw!(cmpxchg, order);
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+macro_rules! atomic_sub_word {
+    ($int_type:ident, $ld_suffix:tt, $st_suffix:tt) => {
+        atomic_load_store!($int_type, $ld_suffix, $st_suffix);
+        #[cfg(any(
+            target_arch = "sparc64",
+            target_feature = "v9",
+            atomic_maybe_uninit_target_feature = "v9",
+            target_feature = "leoncasa",
+            atomic_maybe_uninit_target_feature = "leoncasa",
+        ))]
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let (aligned_ptr, shift, mask) = partword::create_mask_values(dst);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
+                    // Implement sub-word atomic operations using word-sized CAS loop.
+                    // See also partword.rs.
+                    macro_rules! swap {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                "sll {val}, {shift}, {val}",
+                                "sll {mask}, {shift}, {mask}",
+                                // The upper bits of val are undefined, so clear them.
+                                "and {val}, {mask}, {val}",
+                                // (atomic) swap (CAS loop)
+                                $release, // release fence
+                                "ld [{dst}], {prev}",
+                                "2:",
+                                    "andn {prev}, {mask}, {tmp}",
+                                    "or {tmp}, {val}, {tmp}",
+                                    concat!("casa [{dst}] ", asi!(), ", {prev}, {tmp}"),
+                                    "cmp {tmp}, {prev}",
+                                    "bne 2b",
+                                    "mov {tmp}, {prev}", // (delay slot)
+                                $acquire, // acquire fence
+                                "and {prev}, {mask}, {prev}",
+                                "srl {prev}, {shift}, {prev}",
+                                dst = in(reg) ptr_reg!(aligned_ptr),
+                                val = inout(reg) val => _,
+                                shift = in(reg) shift,
+                                mask = inout(reg) mask => _,
+                                prev = out(reg) prev,
+                                tmp = out(reg) _,
+                                // Do not use `preserves_flags` because CMP modifies the condition codes.
+                                options(nostack),
+                            )
+                        };
+                    }
+                    atomic_rmw!(swap, order);
+                    out.write(from_reg(prev));
+                }
+            }
+        }
+        #[cfg(any(
+            target_arch = "sparc64",
+            target_feature = "v9",
+            atomic_maybe_uninit_target_feature = "v9",
+            target_feature = "leoncasa",
+            atomic_maybe_uninit_target_feature = "leoncasa",
+        ))]
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let order = crate::utils::upgrade_success_ordering(success, failure);
+                let (aligned_ptr, shift, mask) = partword::create_mask_values(dst);
+
+                // SAFETY: the caller must uphold the safety contract.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let prev: Reg<Self>;
+                    let r: XSize;
+                    // Implement sub-word atomic operations using word-sized CAS loop.
+                    // See also partword.rs.
+                    macro_rules! cmpxchg {
+                        ($acquire:tt, $release:tt) => {
+                            asm!(
+                                "sll {old}, {shift}, {old}",
+                                "sll {new}, {shift}, {new}",
+                                "sll {mask}, {shift}, {mask}",
+                                // The upper bits of old/new are undefined, so clear them.
+                                "and {old}, {mask}, {old}",
+                                "and {new}, {mask}, {new}",
+                                // (atomic) CAS (CAS loop)
+                                $release, // release fence
+                                // rest = the bits of the word other than the target
+                                "ld [{dst}], {rest}",
+                                "andn {rest}, {mask}, {rest}",
+                                "2:",
+                                    "or {rest}, {old}, {tmp}",
+                                    "or {rest}, {new}, {prev}",
+                                    concat!("casa [{dst}] ", asi!(), ", {tmp}, {prev}"),
+                                    "cmp {prev}, {tmp}",
+                                    "be 3f",
+                                    "andn {prev}, {mask}, {tmp}", // (delay slot)
+                                    // CAS failed; retry if only the other bits have been changed
+                                    "cmp {tmp}, {rest}",
+                                    "bne 2b",
+                                    "mov {tmp}, {rest}", // (delay slot)
+                                "3:",
+                                $acquire, // acquire fence
+                                "and {prev}, {mask}, {prev}",
+                                // r = 1 if prev == old
+                                "xor {prev}, {old}, {r}",
+                                "subcc %g0, {r}, %g0",
+                                "subx %g0, -1, {r}",
+                                "srl {prev}, {shift}, {prev}",
+                                dst = in(reg) ptr_reg!(aligned_ptr),
+                                old = inout(reg) old => _,
+                                new = inout(reg) new => _,
+                                shift = in(reg) shift,
+                                mask = inout(reg) mask => _,
+                                prev = out(reg) prev,
+                                rest = out(reg) _,
+                                tmp = out(reg) _,
+                                r = out(reg) r,
+                                // Do not use `preserves_flags` because CMP/SUBCC modify the condition codes.
+                                options(nostack),
+                            )
+                        };
+                    }
+                    atomic_rmw!(cmpxchg, order);
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+        }
+    };
+}
+
+atomic_sub_word!(i8, "ub", "b");
+atomic_sub_word!(u8, "ub", "b");
+atomic_sub_word!(i16, "uh", "h");
+atomic_sub_word!(u16, "uh", "h");
+atomic!(i32);
+atomic!(u32);
+#[cfg(target_arch = "sparc")]
+atomic!(isize);
+#[cfg(target_arch = "sparc")]
+atomic!(usize);
+#[cfg(target_arch = "sparc64")]
+atomic64!(i64);
+#[cfg(target_arch = "sparc64")]
+atomic64!(u64);
+#[cfg(target_arch = "sparc64")]
+atomic64!(isize);
+#[cfg(target_arch = "sparc64")]
+atomic64!(usize);
+
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{Load, Store, Swap};
+        #[cfg(any(
+            target_arch = "sparc64",
+            target_feature = "v9",
+            atomic_maybe_uninit_target_feature = "v9",
+        ))]
+        let (load, store, cas) = ("LD* with MEMBAR", "ST* with MEMBAR", "CAS (V9)");
+        #[cfg(not(any(
+            target_arch = "sparc64",
+            target_feature = "v9",
+            atomic_maybe_uninit_target_feature = "v9",
+        )))]
+        let (load, store, cas) =
+            ("LD* with STBAR", "ST* with STBAR, LDSTUB for SeqCst", "CASA (leoncasa)");
+        Strategy::new(match (size, op) {
+            (_, Load) => load,
+            (_, Store) => store,
+            (1 | 2, _) => "masked CAS loop",
+            (8, Swap) => "CASX loop",
+            (8, _) => "CASX",
+            (_, Swap) => "SWAP",
+            (_, _) => cas,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(any(
+        target_arch = "sparc64",
+        target_feature = "v9",
+        atomic_maybe_uninit_target_feature = "v9",
+        target_feature = "leoncasa",
+        atomic_maybe_uninit_target_feature = "leoncasa",
+    ))]
+    mod cas {
+        test_atomic!(isize);
+        test_atomic!(usize);
+        test_atomic!(i8);
+        test_atomic!(u8);
+        test_atomic!(i16);
+        test_atomic!(u16);
+        test_atomic!(i32);
+        test_atomic!(u32);
+        #[cfg(target_arch = "sparc64")]
+        test_atomic!(i64);
+        #[cfg(target_arch = "sparc64")]
+        test_atomic!(u64);
+
+        // load/store/swap implementation is not affected by signedness, so it is
+        // enough to test only unsigned types.
+        stress_test!(u8);
+        stress_test!(u16);
+        stress_test!(u32);
+        #[cfg(target_arch = "sparc64")]
+        stress_test!(u64);
+    }
+    #[cfg(not(any(
+        target_arch = "sparc64",
+        target_feature = "v9",
+        atomic_maybe_uninit_target_feature = "v9",
+        target_feature = "leoncasa",
+        atomic_maybe_uninit_target_feature = "leoncasa",
+    )))]
+    mod no_cas {
+        test_atomic_load_store_swap!(isize);
+        test_atomic_load_store_swap!(usize);
+        test_atomic_load_store!(i8);
+        test_atomic_load_store!(u8);
+        test_atomic_load_store!(i16);
+        test_atomic_load_store!(u16);
+        test_atomic_load_store_swap!(i32);
+        test_atomic_load_store_swap!(u32);
+
+        stress_test_load_store!(u8);
+        stress_test_load_store!(u16);
+        stress_test_load_store!(u32);
+    }
+}
@@ -0,0 +1,94 @@
+// ThreadSanitizer support.
+//
+// TSan cannot see memory accesses in inline assembly, so programs that use
+// atomic operations implemented in inline assembly for synchronization would
+// get false data race reports under `-Z sanitizer=thread`. To avoid this, the
+// x86 and AArch64 backends tell TSan about the happens-before edges that each
+// operation creates: `__tsan_release` is called before the operation if the
+// ordering has release semantics, and `__tsan_acquire` is called after the
+// operation if the ordering has acquire semantics.
+//
+// For CAS, whether the operation succeeds is not known when the hooks are
+// called, so the union of the success and failure orderings is used. This may
+// record more happens-before edges than the operation creates (which can hide
+// races), but never fewer (which would report false races).
+//
+// When the thread sanitizer is not enabled, this is a no-op.
+//
+// Refs:
+// - https://github.com/llvm/llvm-project/blob/llvmorg-17.0.0/compiler-rt/include/sanitizer/tsan_interface.h
+
+use core::sync::atomic::Ordering;
+
+#[cfg(atomic_maybe_uninit_sanitize_thread)]
+extern "C" {
+    fn __tsan_acquire(addr: *mut core::ffi::c_void);
+    fn __tsan_release(addr: *mut core::ffi::c_void);
+}
+
+/// Calls `__tsan_acquire` on drop if needed.
+pub(crate) struct Hooks {
+    #[cfg(atomic_maybe_uninit_sanitize_thread)]
+    addr: *mut core::ffi::c_void,
+    #[cfg(atomic_maybe_uninit_sanitize_thread)]
+    acquire: bool,
+}
+
+impl Hooks {
+    /// Creates hooks for an operation with the given ordering.
+    #[inline]
+    pub(crate) fn new<T>(addr: *const T, order: Ordering) -> Self {
+        Self::with(addr, has_release(order), has_acquire(order))
+    }
+
+    /// Creates hooks for a compare_exchange operation with the given orderings.
+    #[inline]
+    pub(crate) fn compare_exchange<T>(
+        addr: *const T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Self {
+        Self::with(addr, has_release(success), has_acquire(success) || has_acquire(failure))
+    }
+
+    #[inline]
+    fn with<T>(addr: *const T, release: bool, acquire: bool) -> Self {
+        #[cfg(atomic_maybe_uninit_sanitize_thread)]
+        {
+            let addr = addr as *mut core::ffi::c_void;
+            if release {
+                // SAFETY: TSan only uses the address to identify the
+                // synchronization object, and never dereferences it.
+                unsafe { __tsan_release(addr) }
+            }
+            Self { addr, acquire }
+        }
+        #[cfg(not(atomic_maybe_uninit_sanitize_thread))]
+        {
+            let _ = (addr, release, acquire);
+            Self {}
+        }
+    }
+}
+
+#[cfg(atomic_maybe_uninit_sanitize_thread)]
+impl Drop for Hooks {
+    #[inline]
+    fn drop(&mut self) {
+        if self.acquire {
+            // SAFETY: TSan only uses the address to identify the
+            // synchronization object, and never dereferences it.
+            unsafe { __tsan_acquire(self.addr) }
+        }
+    }
+}
+
+#[inline]
+fn has_release(order: Ordering) -> bool {
+    matches!(order, Ordering::Release | Ordering::AcqRel | Ordering::SeqCst)
+}
+
+#[inline]
+fn has_acquire(order: Ordering) -> bool {
+    matches!(order, Ordering::Acquire | Ordering::AcqRel | Ordering::SeqCst)
+}
@@ -14,13 +14,35 @@
 // - x86 (i486) https://godbolt.org/z/nPaGY4oEM
 // - x86 (i386) https://godbolt.org/z/YWEc63Kac
 
+#[cfg(any(
+    all(target_arch = "x86", not(atomic_maybe_uninit_no_cmpxchg8b)),
+    all(
+        target_arch = "x86_64",
+        any(target_feature = "cmpxchg16b", atomic_maybe_uninit_target_feature = "cmpxchg16b"),
+    ),
+))]
+#[path = "pair.rs"]
+mod pair;
+
 use core::{
     arch::asm,
     mem::{self, MaybeUninit},
     sync::atomic::Ordering,
 };
 
-use crate::raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap};
+#[cfg(any(
+    all(target_arch = "x86", not(atomic_maybe_uninit_no_cmpxchg8b)),
+    all(
+        target_arch = "x86_64",
+        any(target_feature = "cmpxchg16b", atomic_maybe_uninit_target_feature = "cmpxchg16b"),
+    ),
+))]
+use self::pair::{from_pair, load_pair, Pair};
+use super::{from_reg, load_reg, Reg};
+use crate::{
+    info::Strategy,
+    raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap},
+};
 
 #[cfg(target_pointer_width = "32")]
 macro_rules! ptr_modifier {
@@ -35,53 +57,36 @@ macro_rules! ptr_modifier {
     };
 }
 
-#[cfg(target_arch = "x86")]
-#[cfg(not(atomic_maybe_uninit_no_cmpxchg8b))]
-#[cfg(target_feature = "sse")]
-#[cfg(target_feature = "sse2")]
-macro_rules! if_sse2 {
-    ($then:expr, $else:expr) => {
-        $then
-    };
-}
-#[cfg(target_arch = "x86")]
-#[cfg(not(atomic_maybe_uninit_no_cmpxchg8b))]
-#[cfg(target_feature = "sse")]
-#[cfg(not(target_feature = "sse2"))]
-macro_rules! if_sse2 {
-    ($then:expr, $else:expr) => {
-        $else
-    };
-}
-
 macro_rules! atomic {
     ($int_type:ident, $val_reg:tt, $val_modifier:tt, $ptr_size:tt, $cmpxchg_cmp_reg:tt) => {
+        impl_load_reg!($int_type, $val_reg, concat!("mov {v", $val_modifier, "}, ", $ptr_size, " ptr [{src}]"));
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
                 src: *const MaybeUninit<Self>,
                 out: *mut MaybeUninit<Self>,
-                _order: Ordering,
+                order: Ordering,
             ) {
                 debug_assert!(src as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(src, order);
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let v: Reg<Self>;
                     // atomic load is always SeqCst.
                     asm!(
-                        // (atomic) load from src to tmp
-                        concat!("mov {tmp", $val_modifier, "}, ", $ptr_size, " ptr [{src", ptr_modifier!(), "}]"),
-                        // store tmp to out
-                        concat!("mov ", $ptr_size, " ptr [{out", ptr_modifier!(), "}], {tmp", $val_modifier, "}"),
+                        // (atomic) load from src to v
+                        concat!("mov {v", $val_modifier, "}, ", $ptr_size, " ptr [{src", ptr_modifier!(), "}]"),
                         src = in(reg) src,
-                        out = inout(reg) out => _,
-                        tmp = lateout($val_reg) _,
+                        v = lateout($val_reg) v,
                         options(nostack, preserves_flags),
                     );
+                    out.write(from_reg(v));
                 }
             }
         }
+        #[cfg(not(all(feature = "critical-section", any(all(target_arch = "x86", atomic_maybe_uninit_no_cmpxchg), atomic_maybe_uninit_test_critical_section))))]
         impl AtomicStore for $int_type {
             #[inline]
             unsafe fn atomic_store(
@@ -91,32 +96,28 @@ macro_rules! atomic {
             ) {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(dst, order);
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let val = load_reg(val);
                     match order {
                         // Relaxed and Release stores are equivalent.
                         Ordering::Relaxed | Ordering::Release => {
                             asm!(
-                                // load from val to tmp
-                                concat!("mov {tmp", $val_modifier, "}, ", $ptr_size, " ptr [{val", ptr_modifier!(), "}]"),
-                                // (atomic) store tmp to dst
-                                concat!("mov ", $ptr_size, " ptr [{dst", ptr_modifier!(), "}], {tmp", $val_modifier, "}"),
-                                dst = inout(reg) dst => _,
-                                val = in(reg) val,
-                                tmp = lateout($val_reg) _,
+                                // (atomic) store val to dst
+                                concat!("mov ", $ptr_size, " ptr [{dst", ptr_modifier!(), "}], {val", $val_modifier, "}"),
+                                dst = in(reg) dst,
+                                val = in($val_reg) val,
                                 options(nostack, preserves_flags),
                             );
                         }
                         Ordering::SeqCst => {
                             asm!(
-                                // load from val to tmp
-                                concat!("mov {tmp", $val_modifier, "}, ", $ptr_size, " ptr [{val", ptr_modifier!(), "}]"),
-                                // (atomic) store tmp to dst (SeqCst store is xchg, not mov)
-                                concat!("xchg ", $ptr_size, " ptr [{dst", ptr_modifier!(), "}], {tmp", $val_modifier, "}"),
-                                dst = inout(reg) dst => _,
-                                val = in(reg) val,
-                                tmp = lateout($val_reg) _,
+                                // (atomic) store val to dst (SeqCst store is xchg, not mov)
+                                concat!("xchg ", $ptr_size, " ptr [{dst", ptr_modifier!(), "}], {val", $val_modifier, "}"),
+                                dst = in(reg) dst,
+                                val = inout($val_reg) val => _,
                                 options(nostack, preserves_flags),
                             );
                         }
@@ -125,38 +126,39 @@ macro_rules! atomic {
                 }
             }
         }
+        #[cfg(not(all(feature = "critical-section", any(all(target_arch = "x86", atomic_maybe_uninit_no_cmpxchg), atomic_maybe_uninit_test_critical_section))))]
         impl AtomicSwap for $int_type {
             #[inline]
             unsafe fn atomic_swap(
                 dst: *mut MaybeUninit<Self>,
                 val: *const MaybeUninit<Self>,
                 out: *mut MaybeUninit<Self>,
-                _order: Ordering,
+                order: Ordering,
             ) {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(dst, order);
 
                 // SAFETY: the caller must uphold the safety contract.
                 unsafe {
+                    let prev: Reg<Self>;
                     // atomic swap is always SeqCst.
                     asm!(
-                        // load from val to tmp
-                        concat!("mov {tmp", $val_modifier, "}, ", $ptr_size, " ptr [{val", ptr_modifier!(), "}]"),
-                        // (atomic) swap tmp and dst
-                        concat!("xchg ", $ptr_size, " ptr [{dst", ptr_modifier!(), "}], {tmp", $val_modifier, "}"),
-                        // store tmp to out
-                        concat!("mov ", $ptr_size, " ptr [{out", ptr_modifier!(), "}], {tmp", $val_modifier, "}"),
-                        dst = inout(reg) dst => _,
-                        val = in(reg) val,
-                        out = inout(reg) out => _,
-                        tmp = lateout($val_reg) _,
+                        // (atomic) swap val and dst
+                        concat!("xchg ", $ptr_size, " ptr [{dst", ptr_modifier!(), "}], {val", $val_modifier, "}"),
+                        dst = in(reg) dst,
+                        val = inout($val_reg) load_reg(val) => prev,
                         options(nostack, preserves_flags),
                     );
+                    out.write(from_reg(prev));
                 }
             }
         }
-        #[cfg(not(all(target_arch = "x86", atomic_maybe_uninit_no_cmpxchg)))]
+        #[cfg(not(any(
+            all(target_arch = "x86", atomic_maybe_uninit_no_cmpxchg),
+            all(feature = "critical-section", atomic_maybe_uninit_test_critical_section),
+        )))]
         impl AtomicCompareExchange for $int_type {
             #[inline]
             unsafe fn atomic_compare_exchange(
@@ -164,43 +166,170 @@ macro_rules! atomic {
                 old: *const MaybeUninit<Self>,
                 new: *const MaybeUninit<Self>,
                 out: *mut MaybeUninit<Self>,
-                _success: Ordering,
-                _failure: Ordering,
+                success: Ordering,
+                failure: Ordering,
             ) -> bool {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::compare_exchange(dst, success, failure);
 
                 // SAFETY: the caller must uphold the safety contract.
                 //
                 // Refs: https://www.felixcloutier.com/x86/cmpxchg
                 unsafe {
                     let r: u8;
+                    let prev: Reg<Self>;
                     // compare_exchange is always SeqCst.
                     asm!(
-                        // load from old/new to $cmpxchg_cmp_reg/tmp_new
-                        concat!("mov ", $cmpxchg_cmp_reg, ", ", $ptr_size, " ptr [{old", ptr_modifier!(), "}]"),
-                        concat!("mov {tmp_new", $val_modifier, "}, ", $ptr_size, " ptr [{new", ptr_modifier!(), "}]"),
                         // (atomic) CAS
                         // - Compare $cmpxchg_cmp_reg with dst.
-                        // - If equal, ZF is set and tmp_new is loaded into dst.
+                        // - If equal, ZF is set and new is loaded into dst.
                         // - Else, clear ZF and load dst into $cmpxchg_cmp_reg.
-                        concat!("lock cmpxchg ", $ptr_size, " ptr [{dst", ptr_modifier!(), "}], {tmp_new", $val_modifier, "}"),
+                        concat!("lock cmpxchg ", $ptr_size, " ptr [{dst", ptr_modifier!(), "}], {new", $val_modifier, "}"),
                         // load ZF to r
                         "sete {r}",
-                        // store $cmpxchg_cmp_reg to out
-                        concat!("mov ", $ptr_size, " ptr [{out", ptr_modifier!(), "}], ", $cmpxchg_cmp_reg, ""),
                         dst = in(reg) dst,
-                        old = in(reg) old,
-                        new = in(reg) new,
-                        out = in(reg) out,
-                        tmp_new = out($val_reg) _,
-                        r = out(reg_byte) r,
-                        out($cmpxchg_cmp_reg) _,
+                        new = in($val_reg) load_reg(new),
+                        r = lateout(reg_byte) r,
+                        inout($cmpxchg_cmp_reg) load_reg(old) => prev,
                         // Do not use `preserves_flags` because CMPXCHG modifies the ZF flag.
                         options(nostack),
                     );
+                    out.write(from_reg(prev));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
+                }
+            }
+        }
+        // i386 doesn't have CMPXCHG, so CAS is implemented by using critical section
+        // when the `critical-section` feature is enabled. Stores and swaps also go
+        // through the critical section, otherwise they can be interleaved with
+        // CAS on multi-core systems. See cs.rs for details.
+        #[cfg(all(
+            feature = "critical-section",
+            any(
+                all(target_arch = "x86", atomic_maybe_uninit_no_cmpxchg),
+                atomic_maybe_uninit_test_critical_section,
+            ),
+        ))]
+        atomic_cs!($int_type, $val_reg, $val_modifier, $ptr_size);
+    };
+}
+
+#[cfg(all(
+    feature = "critical-section",
+    any(
+        all(target_arch = "x86", atomic_maybe_uninit_no_cmpxchg),
+        atomic_maybe_uninit_test_critical_section,
+    ),
+))]
+macro_rules! atomic_cs {
+    ($int_type:ident, $val_reg:tt, $val_modifier:tt, $ptr_size:tt) => {
+        impl crate::arch::NotLockFree for $int_type {}
+        impl AtomicStore for $int_type {
+            #[inline]
+            unsafe fn atomic_store(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(dst, order);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the critical section guarantees exclusive access to dst.
+                unsafe {
+                    let val = load_reg(val);
+                    let s = super::cs::disable();
+                    asm!(
+                        // store val to dst
+                        concat!("mov ", $ptr_size, " ptr [{dst", ptr_modifier!(), "}], {val", $val_modifier, "}"),
+                        dst = in(reg) dst,
+                        val = in($val_reg) val,
+                        options(nostack, preserves_flags),
+                    );
+                    super::cs::restore(s);
+                }
+            }
+        }
+        impl AtomicSwap for $int_type {
+            #[inline]
+            unsafe fn atomic_swap(
+                dst: *mut MaybeUninit<Self>,
+                val: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                order: Ordering,
+            ) {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(dst, order);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the critical section guarantees exclusive access to dst.
+                unsafe {
+                    let val = load_reg(val);
+                    let prev: Reg<Self>;
+                    let s = super::cs::disable();
+                    asm!(
+                        // swap val and dst
+                        concat!("xchg ", $ptr_size, " ptr [{dst", ptr_modifier!(), "}], {val", $val_modifier, "}"),
+                        dst = in(reg) dst,
+                        val = inout($val_reg) val => prev,
+                        options(nostack, preserves_flags),
+                    );
+                    super::cs::restore(s);
+                    out.write(from_reg(prev));
+                }
+            }
+        }
+        impl AtomicCompareExchange for $int_type {
+            #[inline]
+            unsafe fn atomic_compare_exchange(
+                dst: *mut MaybeUninit<Self>,
+                old: *const MaybeUninit<Self>,
+                new: *const MaybeUninit<Self>,
+                out: *mut MaybeUninit<Self>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> bool {
+                debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
+                debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
+                debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::compare_exchange(dst, success, failure);
+
+                // SAFETY: the caller must uphold the safety contract, and
+                // the critical section guarantees exclusive access to dst.
+                unsafe {
+                    let (old, new) = (load_reg(old), load_reg(new));
+                    let r: u8;
+                    let prev: Reg<Self>;
+                    let s = super::cs::disable();
+                    asm!(
+                        // load from dst to prev
+                        concat!("mov {prev", $val_modifier, "}, ", $ptr_size, " ptr [{dst", ptr_modifier!(), "}]"),
+                        // compare prev and old
+                        concat!("cmp {prev", $val_modifier, "}, {old", $val_modifier, "}"),
+                        "jne 2f", // jump if compare failed
+                        // store new to dst
+                        concat!("mov ", $ptr_size, " ptr [{dst", ptr_modifier!(), "}], {new", $val_modifier, "}"),
+                        "2:",
+                        // load ZF to r (MOV doesn't modify the flags)
+                        "sete {r}",
+                        dst = in(reg) dst,
+                        old = in($val_reg) old,
+                        new = in($val_reg) new,
+                        prev = out($val_reg) prev,
+                        r = lateout(reg_byte) r,
+                        // Do not use `preserves_flags` because CMP modifies the flags.
+                        options(nostack),
+                    );
+                    super::cs::restore(s);
+                    out.write(from_reg(prev));
                     debug_assert!(r == 0 || r == 1, "r={}", r);
                     r != 0
                 }
@@ -234,58 +363,50 @@ atomic!(usize, reg, "", "qword", "rax");
 #[cfg(not(atomic_maybe_uninit_no_cmpxchg8b))]
 macro_rules! atomic64 {
     ($int_type:ident) => {
+        #[cfg(target_feature = "sse2")]
+        impl_load_reg!($int_type, xmm_reg, "movq {v}, qword ptr [{src}]");
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
                 src: *const MaybeUninit<Self>,
                 out: *mut MaybeUninit<Self>,
-                _order: Ordering,
+                order: Ordering,
             ) {
                 debug_assert!(src as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(src, order);
 
                 #[cfg(target_feature = "sse")]
                 // SAFETY: the caller must uphold the safety contract.
                 // cfg guarantees that the CPU supports SSE.
+                //
+                // Refs:
+                // - https://www.felixcloutier.com/x86/movq (SSE2)
+                // - https://www.felixcloutier.com/x86/movlps (SSE)
                 unsafe {
                     #[cfg(target_feature = "sse2")]
                     {
+                        let v: Reg<Self>;
                         // atomic load is always SeqCst.
                         asm!(
-                            // Refs:
-                            // - https://www.felixcloutier.com/x86/movq (SSE2)
-                            // - https://www.felixcloutier.com/x86/movd:movq (SSE2)
-                            // - https://www.felixcloutier.com/x86/pshufd (SSE2)
-                            // (atomic) load from src to tmp0
-                            "movq {tmp0}, qword ptr [{src}]",
-                            // extract lower 64-bits
-                            "pshufd {tmp1}, {tmp0}, 85",
-                            // store tmp0/tmp1 to out
-                            "movd dword ptr [{out}], {tmp0}",
-                            "movd dword ptr [{out} + 4], {tmp1}",
+                            // (atomic) load from src to v
+                            "movq {v}, qword ptr [{src}]",
                             src = in(reg) src,
-                            out = in(reg) out,
-                            tmp0 = out(xmm_reg) _,
-                            tmp1 = out(xmm_reg) _,
+                            v = lateout(xmm_reg) v,
                             options(nostack, preserves_flags),
                         );
+                        out.write(from_reg(v));
                     }
+                    // Without SSE2, XMM registers can't hold 64-bit integers,
+                    // so the value is copied to out in asm.
                     #[cfg(not(target_feature = "sse2"))]
                     {
                         // atomic load is always SeqCst.
                         asm!(
-                            // Refs:
-                            // - https://www.felixcloutier.com/x86/xorps (SSE)
-                            // - https://www.felixcloutier.com/x86/movlps (SSE)
-                            // - https://www.felixcloutier.com/x86/movss (SSE)
-                            // - https://www.felixcloutier.com/x86/shufps (SSE)
-                            "xorps {tmp}, {tmp}",
                             // (atomic) load from src to tmp
                             "movlps {tmp}, qword ptr [{src}]",
                             // store tmp to out
-                            "movss dword ptr [{out}], {tmp}",
-                            "shufps {tmp}, {tmp}, 85",
-                            "movss dword ptr [{out} + 4], {tmp}",
+                            "movlps qword ptr [{out}], {tmp}",
                             src = in(reg) src,
                             out = in(reg) out,
                             tmp = out(xmm_reg) _,
@@ -298,26 +419,21 @@ macro_rules! atomic64 {
                 //
                 // Refs: https://www.felixcloutier.com/x86/cmpxchg8b:cmpxchg16b
                 unsafe {
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
                     // atomic load is always SeqCst.
                     asm!(
-                        // esi is reserved by LLVM
-                        "xchg {esi_tmp}, esi",
                         // (atomic) load by cmpxchg(0, 0)
                         "lock cmpxchg8b qword ptr [edi]",
-                        // store current value to out
-                        "mov dword ptr [esi], eax",
-                        "mov dword ptr [esi + 4], edx",
-                        "mov esi, {esi_tmp}", // restore esi
-                        esi_tmp = inout(reg) out => _,
                         // set old/new args of cmpxchg8b to 0
-                        inout("eax") 0_u32 => _,
-                        inout("edx") 0_u32 => _,
+                        inout("eax") 0_u32 => prev_lo,
+                        inout("edx") 0_u32 => prev_hi,
                         in("ebx") 0_u32,
                         in("ecx") 0_u32,
                         in("edi") src,
                         // Do not use `preserves_flags` because CMPXCHG8B modifies the ZF flag.
                         options(nostack),
                     );
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                 }
             }
         }
@@ -330,6 +446,7 @@ macro_rules! atomic64 {
             ) {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(dst, order);
 
                 #[cfg(target_feature = "sse")]
                 // SAFETY: the caller must uphold the safety contract.
@@ -337,38 +454,38 @@ macro_rules! atomic64 {
                 //
                 // Refs:
                 // - https://www.felixcloutier.com/x86/movlps (SSE)
-                // - https://www.felixcloutier.com/x86/xorps (SSE)
-                // - https://www.felixcloutier.com/x86/movsd (SSE2)
                 // - https://www.felixcloutier.com/x86/lock
                 // - https://www.felixcloutier.com/x86/or
                 unsafe {
+                    #[cfg(target_feature = "sse2")]
+                    asm!(
+                        // (atomic) store val to dst
+                        "movlps qword ptr [{dst}], {val}",
+                        dst = in(reg) dst,
+                        val = in(xmm_reg) load_reg(val),
+                        options(nostack, preserves_flags),
+                    );
+                    // Without SSE2, XMM registers can't hold 64-bit integers,
+                    // so the value is loaded from val in asm.
+                    #[cfg(not(target_feature = "sse2"))]
+                    asm!(
+                        // load from val to tmp
+                        "xorps {tmp}, {tmp}",
+                        "movlps {tmp}, qword ptr [{val}]",
+                        // (atomic) store tmp to dst
+                        "movlps qword ptr [{dst}], {tmp}",
+                        dst = in(reg) dst,
+                        val = in(reg) val,
+                        tmp = out(xmm_reg) _,
+                        options(nostack, preserves_flags),
+                    );
                     match order {
                         // Relaxed and Release stores are equivalent.
-                        Ordering::Relaxed | Ordering::Release => {
-                            asm!(
-                                if_sse2!("", "xorps {tmp}, {tmp}"),
-                                // load from val to tmp
-                                if_sse2!("movsd {tmp}, qword ptr [{val}]", "movlps {tmp}, qword ptr [{val}]"),
-                                // (atomic) store tmp to dst
-                                "movlps qword ptr [{dst}], {tmp}",
-                                dst = in(reg) dst,
-                                val = in(reg) val,
-                                tmp = out(xmm_reg) _,
-                                options(nostack, preserves_flags),
-                            );
-                        }
+                        Ordering::Relaxed | Ordering::Release => {}
                         Ordering::SeqCst => {
                             let p = core::cell::UnsafeCell::new(0_u32);
                             asm!(
-                                // load from val to tmp
-                                if_sse2!("", "xorps {tmp}, {tmp}"),
-                                if_sse2!("movsd {tmp}, qword ptr [{val}]", "movlps {tmp}, qword ptr [{val}]"),
-                                // (atomic) store tmp to dst
-                                "movlps qword ptr [{dst}], {tmp}",
                                 "lock or dword ptr [{p}], 0", // equivalent to mfence, but doesn't require SSE2
-                                dst = in(reg) dst,
-                                val = in(reg) val,
-                                tmp = out(xmm_reg) _,
                                 p = in(reg) p.get(),
                                 // Do not use `preserves_flags` because OR modifies the OF, CF, SF, ZF, and PF flags.
                                 options(nostack),
@@ -382,11 +499,10 @@ macro_rules! atomic64 {
                 //
                 // Refs: https://www.felixcloutier.com/x86/cmpxchg8b:cmpxchg16b
                 unsafe {
+                    let val = load_pair::<Self, u32>(val);
                     // atomic store is always SeqCst.
                     let _ = order;
                     asm!(
-                        "mov ebx, dword ptr [eax]",
-                        "mov ecx, dword ptr [eax + 4]",
                         // This is based on the code generated for the first load in DW RMWs by LLVM,
                         // but it is interesting that they generate code that does mixed-sized atomic access.
                         //
@@ -398,10 +514,10 @@ macro_rules! atomic64 {
                         "2:",
                             "lock cmpxchg8b qword ptr [edi]",
                             "jne 2b",
-                        inout("eax") val => _,
+                        out("eax") _,
                         out("edx") _,
-                        out("ebx") _,
-                        out("ecx") _,
+                        in("ebx") val.lo,
+                        in("ecx") val.hi,
                         in("edi") dst,
                         // Do not use `preserves_flags` because CMPXCHG8B modifies the ZF flag.
                         options(nostack),
@@ -415,22 +531,21 @@ macro_rules! atomic64 {
                 dst: *mut MaybeUninit<Self>,
                 val: *const MaybeUninit<Self>,
                 out: *mut MaybeUninit<Self>,
-                _order: Ordering,
+                order: Ordering,
             ) {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(dst, order);
 
                 // SAFETY: the caller must uphold the safety contract.
                 //
                 // Refs: https://www.felixcloutier.com/x86/cmpxchg8b:cmpxchg16b
                 unsafe {
-                    // atomic store is always SeqCst.
+                    let val = load_pair::<Self, u32>(val);
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
+                    // atomic swap is always SeqCst.
                     asm!(
-                        // esi is reserved by LLVM
-                        "xchg {esi_tmp}, esi",
-                        "mov ebx, dword ptr [eax]",
-                        "mov ecx, dword ptr [eax + 4]",
                         // This is based on the code generated for the first load in DW RMWs by LLVM,
                         // but it is interesting that they generate code that does mixed-sized atomic access.
                         //
@@ -442,19 +557,15 @@ macro_rules! atomic64 {
                         "2:",
                             "lock cmpxchg8b qword ptr [edi]",
                             "jne 2b",
-                        // store previous value to out
-                        "mov dword ptr [esi], eax",
-                        "mov dword ptr [esi + 4], edx",
-                        "mov esi, {esi_tmp}", // restore esi
-                        esi_tmp = inout(reg) out => _,
-                        inout("eax") val => _,
-                        out("edx") _,
-                        out("ebx") _,
-                        out("ecx") _,
+                        out("eax") prev_lo,
+                        out("edx") prev_hi,
+                        in("ebx") val.lo,
+                        in("ecx") val.hi,
                         in("edi") dst,
                         // Do not use `preserves_flags` because CMPXCHG8B modifies the ZF flag.
                         options(nostack),
                     );
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                 }
             }
         }
@@ -465,45 +576,41 @@ macro_rules! atomic64 {
                 old: *const MaybeUninit<Self>,
                 new: *const MaybeUninit<Self>,
                 out: *mut MaybeUninit<Self>,
-                _success: Ordering,
-                _failure: Ordering,
+                success: Ordering,
+                failure: Ordering,
             ) -> bool {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::compare_exchange(dst, success, failure);
 
                 // SAFETY: the caller must uphold the safety contract.
                 //
-                // Refs: https://www.felixcloutier.com/x86/cmpxchg
+                // Refs: https://www.felixcloutier.com/x86/cmpxchg8b:cmpxchg16b
                 unsafe {
-                    let mut r: u32;
+                    let old = load_pair::<Self, u32>(old);
+                    let new = load_pair::<Self, u32>(new);
+                    let (prev_lo, prev_hi): (Reg<u32>, Reg<u32>);
+                    let r: u8;
                     // compare_exchange is always SeqCst.
                     asm!(
-                        // esi is reserved by LLVM
-                        "xchg {esi_tmp}, esi",
-                        "mov eax, dword ptr [edx]",
-                        "mov edx, dword ptr [edx + 4]",
-                        "mov ebx, dword ptr [ecx]",
-                        "mov ecx, dword ptr [ecx + 4]",
                         // (atomic) CAS
                         "lock cmpxchg8b qword ptr [edi]",
-                        "sete cl",
-                        // store previous value to out
-                        "mov dword ptr [esi], eax",
-                        "mov dword ptr [esi + 4], edx",
-                        "mov esi, {esi_tmp}", // restore esi
-                        esi_tmp = inout(reg) out => _,
-                        out("eax") _,
-                        inout("edx") old => _,
-                        out("ebx") _,
-                        inout("ecx") new => r,
+                        // load ZF to r
+                        "sete {r}",
+                        r = lateout(reg_byte) r,
+                        inout("eax") old.lo => prev_lo,
+                        inout("edx") old.hi => prev_hi,
+                        in("ebx") new.lo,
+                        in("ecx") new.hi,
                         in("edi") dst,
                         // Do not use `preserves_flags` because CMPXCHG8B modifies the ZF flag.
                         options(nostack),
                     );
-                    debug_assert!(r as u8 == 0 || r as u8 == 1, "r={}", r as u8);
-                    r as u8 != 0
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
                 }
             }
         }
@@ -521,21 +628,16 @@ atomic64!(u64);
 #[cfg(any(target_feature = "cmpxchg16b", atomic_maybe_uninit_target_feature = "cmpxchg16b"))]
 macro_rules! atomic128 {
     ($int_type:ident) => {
-        #[cfg(target_pointer_width = "32")]
-        atomic128!($int_type, "edi", "esi", "r8d", "edx");
-        #[cfg(target_pointer_width = "64")]
-        atomic128!($int_type, "rdi", "rsi", "r8", "rdx");
-    };
-    ($int_type:ident, $rdi:tt, $rsi:tt, $r8:tt, $rdx:tt) => {
         impl AtomicLoad for $int_type {
             #[inline]
             unsafe fn atomic_load(
                 src: *const MaybeUninit<Self>,
                 out: *mut MaybeUninit<Self>,
-                _order: Ordering,
+                order: Ordering,
             ) {
                 debug_assert!(src as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(src, order);
 
                 // SAFETY: the caller must guarantee that `src` is valid for both writes and
                 // reads, 16-byte aligned, and that there are no concurrent non-atomic operations.
@@ -550,27 +652,25 @@ macro_rules! atomic128 {
                 //
                 // Refs: https://www.felixcloutier.com/x86/cmpxchg8b:cmpxchg16b
                 unsafe {
+                    let (prev_lo, prev_hi): (Reg<u64>, Reg<u64>);
                     // atomic load is always SeqCst.
                     asm!(
                         // rbx is reserved by LLVM
                         "mov {rbx_tmp}, rbx",
                         "xor rbx, rbx", // zeroed rbx
                         // (atomic) load by cmpxchg(0, 0)
-                        concat!("lock cmpxchg16b xmmword ptr [", $rdi, "]"),
-                        // store current value to out
-                        concat!("mov qword ptr [", $rsi, "], rax"),
-                        concat!("mov qword ptr [", $rsi, " + 8], rdx"),
+                        concat!("lock cmpxchg16b xmmword ptr [{src", ptr_modifier!(), "}]"),
                         "mov rbx, {rbx_tmp}", // restore rbx
+                        src = in(reg) src,
                         // set old/new args of cmpxchg16b to 0 (rbx is zeroed after saved to rbx_tmp, to avoid xchg)
                         rbx_tmp = out(reg) _,
                         in("rcx") 0_u64,
-                        inout("rax") 0_u64 => _,
-                        inout("rdx") 0_u64 => _,
-                        in($rdi) src,
-                        in($rsi) out,
+                        inout("rax") 0_u64 => prev_lo,
+                        inout("rdx") 0_u64 => prev_hi,
                         // Do not use `preserves_flags` because CMPXCHG16B modifies the ZF flag.
                         options(nostack),
                     );
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                 }
             }
         }
@@ -579,10 +679,11 @@ macro_rules! atomic128 {
             unsafe fn atomic_store(
                 dst: *mut MaybeUninit<Self>,
                 val: *const MaybeUninit<Self>,
-                _order: Ordering,
+                order: Ordering,
             ) {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(dst, order);
 
                 // SAFETY: the caller must guarantee that `dst` is valid for both writes and
                 // reads, 16-byte aligned, and that there are no concurrent non-atomic operations.
@@ -597,30 +698,28 @@ macro_rules! atomic128 {
                 //
                 // Refs: https://www.felixcloutier.com/x86/cmpxchg8b:cmpxchg16b
                 unsafe {
+                    let val = load_pair::<Self, u64>(val);
                     // atomic store is always SeqCst.
                     asm!(
                         // rbx is reserved by LLVM
-                        "mov {rbx_tmp}, rbx",
-                        concat!("mov rbx, qword ptr [", $rsi, "]"),
-                        concat!("mov rcx, qword ptr [", $rsi, " + 8]"),
+                        "xchg {rbx_tmp}, rbx",
                         // This is based on the code generated for the first load in DW RMWs by LLVM,
                         // but it is interesting that they generate code that does mixed-sized atomic access.
                         //
                         // This is not single-copy atomic reads, but this is ok because subsequent
                         // CAS will check for consistency.
-                        concat!("mov rax, qword ptr [", $rdi, "]"),
-                        concat!("mov rdx, qword ptr [", $rdi, " + 8]"),
+                        concat!("mov rax, qword ptr [{dst", ptr_modifier!(), "}]"),
+                        concat!("mov rdx, qword ptr [{dst", ptr_modifier!(), "} + 8]"),
                         // (atomic) store (CAS loop)
                         "2:",
-                            concat!("lock cmpxchg16b xmmword ptr [", $rdi, "]"),
+                            concat!("lock cmpxchg16b xmmword ptr [{dst", ptr_modifier!(), "}]"),
                             "jne 2b",
                         "mov rbx, {rbx_tmp}", // restore rbx
-                        rbx_tmp = out(reg) _,
+                        dst = in(reg) dst,
+                        rbx_tmp = inout(reg) val.lo => _,
+                        in("rcx") val.hi,
                         out("rax") _,
-                        out("rcx") _,
                         out("rdx") _,
-                        in($rdi) dst,
-                        in($rsi) val,
                         // Do not use `preserves_flags` because CMPXCHG16B modifies the ZF flag.
                         options(nostack),
                     );
@@ -633,11 +732,12 @@ macro_rules! atomic128 {
                 dst: *mut MaybeUninit<Self>,
                 val: *const MaybeUninit<Self>,
                 out: *mut MaybeUninit<Self>,
-                _order: Ordering,
+                order: Ordering,
             ) {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(val as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::new(dst, order);
 
                 // SAFETY: the caller must guarantee that `dst` is valid for both writes and
                 // reads, 16-byte aligned, and that there are no concurrent non-atomic operations.
@@ -652,37 +752,33 @@ macro_rules! atomic128 {
                 //
                 // Refs: https://www.felixcloutier.com/x86/cmpxchg8b:cmpxchg16b
                 unsafe {
+                    let val = load_pair::<Self, u64>(val);
+                    let (prev_lo, prev_hi): (Reg<u64>, Reg<u64>);
                     // atomic swap is always SeqCst.
                     asm!(
                         // rbx is reserved by LLVM
-                        "mov {rbx_tmp}, rbx",
-                        concat!("mov rbx, qword ptr [", $rsi, "]"),
-                        concat!("mov rcx, qword ptr [", $rsi, " + 8]"),
+                        "xchg {rbx_tmp}, rbx",
                         // This is based on the code generated for the first load in DW RMWs by LLVM,
                         // but it is interesting that they generate code that does mixed-sized atomic access.
                         //
                         // This is not single-copy atomic reads, but this is ok because subsequent
                         // CAS will check for consistency.
-                        concat!("mov rax, qword ptr [", $rdi, "]"),
-                        concat!("mov rdx, qword ptr [", $rdi, " + 8]"),
+                        concat!("mov rax, qword ptr [{dst", ptr_modifier!(), "}]"),
+                        concat!("mov rdx, qword ptr [{dst", ptr_modifier!(), "} + 8]"),
                         // (atomic) swap (CAS loop)
                         "2:",
-                            concat!("lock cmpxchg16b xmmword ptr [", $rdi, "]"),
+                            concat!("lock cmpxchg16b xmmword ptr [{dst", ptr_modifier!(), "}]"),
                             "jne 2b",
-                        // store previous value to out
-                        concat!("mov qword ptr [", $r8, "], rax"),
-                        concat!("mov qword ptr [", $r8, " + 8], rdx"),
                         "mov rbx, {rbx_tmp}", // restore rbx
-                        rbx_tmp = out(reg) _,
-                        out("rax") _,
-                        out("rcx") _,
-                        out("rdx") _,
-                        in($rdi) dst,
-                        in($rsi) val,
-                        in($r8) out,
+                        dst = in(reg) dst,
+                        rbx_tmp = inout(reg) val.lo => _,
+                        in("rcx") val.hi,
+                        out("rax") prev_lo,
+                        out("rdx") prev_hi,
                         // Do not use `preserves_flags` because CMPXCHG16B modifies the ZF flag.
                         options(nostack),
                     );
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
                 }
             }
         }
@@ -693,13 +789,14 @@ macro_rules! atomic128 {
                 old: *const MaybeUninit<Self>,
                 new: *const MaybeUninit<Self>,
                 out: *mut MaybeUninit<Self>,
-                _success: Ordering,
-                _failure: Ordering,
+                success: Ordering,
+                failure: Ordering,
             ) -> bool {
                 debug_assert!(dst as usize % mem::size_of::<$int_type>() == 0);
                 debug_assert!(old as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(new as usize % mem::align_of::<$int_type>() == 0);
                 debug_assert!(out as usize % mem::align_of::<$int_type>() == 0);
+                let _tsan = super::tsan::Hooks::compare_exchange(dst, success, failure);
 
                 // SAFETY: the caller must guarantee that `dst` is valid for both writes and
                 // reads, 16-byte aligned, and that there are no concurrent non-atomic operations.
@@ -714,37 +811,31 @@ macro_rules! atomic128 {
                 //
                 // Refs: https://www.felixcloutier.com/x86/cmpxchg8b:cmpxchg16b
                 unsafe {
-                    let mut r: u64;
+                    let old = load_pair::<Self, u64>(old);
+                    let new = load_pair::<Self, u64>(new);
+                    let (prev_lo, prev_hi): (Reg<u64>, Reg<u64>);
+                    let r: u8;
                     // compare_exchange is always SeqCst.
                     asm!(
                         // rbx is reserved by LLVM
-                        "mov {rbx_tmp}, rbx",
-                        concat!("mov rax, qword ptr [", $rsi, "]"),
-                        concat!("mov rsi, qword ptr [", $rsi, " + 8]"),
-                        concat!("mov rbx, qword ptr [", $rdx, "]"),
-                        concat!("mov rcx, qword ptr [", $rdx, " + 8]"),
-                        "mov rdx, rsi",
+                        "xchg {rbx_tmp}, rbx",
                         // (atomic) CAS
-                        concat!("lock cmpxchg16b xmmword ptr [", $rdi, "]"),
-                        "sete cl",
-                        // store previous value to out
-                        concat!("mov qword ptr [", $r8, "], rax"),
-                        concat!("mov qword ptr [", $r8, " + 8], rdx"),
+                        concat!("lock cmpxchg16b xmmword ptr [{dst", ptr_modifier!(), "}]"),
+                        // load ZF to r
+                        "sete {r}",
                         "mov rbx, {rbx_tmp}", // restore rbx
-                        rbx_tmp = out(reg) _,
-                        out("rax") _,
-                        out("rcx") r,
-                        lateout("rdx") _,
-                        lateout("rsi") _,
-                        in($rdi) dst,
-                        in($rsi) old,
-                        in($rdx) new,
-                        in($r8) out,
+                        dst = in(reg) dst,
+                        rbx_tmp = inout(reg) new.lo => _,
+                        r = lateout(reg_byte) r,
+                        in("rcx") new.hi,
+                        inout("rax") old.lo => prev_lo,
+                        inout("rdx") old.hi => prev_hi,
                         // Do not use `preserves_flags` because CMPXCHG16B modifies the ZF flag.
                         options(nostack),
                     );
-                    debug_assert!(r as u8 == 0 || r as u8 == 1, "r={}", r as u8);
-                    r as u8 != 0
+                    out.write(from_pair(Pair { lo: prev_lo, hi: prev_hi }));
+                    debug_assert!(r == 0 || r == 1, "r={}", r);
+                    r != 0
                 }
             }
         }
@@ -758,6 +849,75 @@ atomic128!(i128);
 #[cfg(any(target_feature = "cmpxchg16b", atomic_maybe_uninit_target_feature = "cmpxchg16b"))]
 atomic128!(u128);
 
+impl super::Describe for super::Native {
+    fn describe(size: usize, op: super::Op) -> Strategy {
+        use super::Op::{CompareExchange, CompareExchangeWeak, Load, Store, Swap};
+        Strategy::new(match (size, op) {
+            #[cfg(target_arch = "x86")]
+            (8, Load) => {
+                if cfg!(target_feature = "sse2") {
+                    "MOVQ (SSE2)"
+                } else if cfg!(target_feature = "sse") {
+                    "MOVLPS (SSE)"
+                } else {
+                    "LOCK CMPXCHG8B"
+                }
+            }
+            #[cfg(target_arch = "x86")]
+            (8, Store) => {
+                if cfg!(target_feature = "sse") {
+                    "MOVLPS (SSE), LOCK OR for SeqCst"
+                } else {
+                    "LOCK CMPXCHG8B loop"
+                }
+            }
+            #[cfg(target_arch = "x86")]
+            (8, Swap) => "LOCK CMPXCHG8B loop",
+            #[cfg(target_arch = "x86")]
+            (8, _) => "LOCK CMPXCHG8B",
+            #[cfg(target_arch = "x86_64")]
+            (16, Load | CompareExchange | CompareExchangeWeak) => "LOCK CMPXCHG16B",
+            #[cfg(target_arch = "x86_64")]
+            (16, _) => "LOCK CMPXCHG16B loop",
+            (_, Load) => "MOV",
+            #[cfg(not(all(
+                feature = "critical-section",
+                any(
+                    all(target_arch = "x86", atomic_maybe_uninit_no_cmpxchg),
+                    atomic_maybe_uninit_test_critical_section,
+                ),
+            )))]
+            (_, Store) => "MOV, XCHG for SeqCst",
+            #[cfg(not(all(
+                feature = "critical-section",
+                any(
+                    all(target_arch = "x86", atomic_maybe_uninit_no_cmpxchg),
+                    atomic_maybe_uninit_test_critical_section,
+                ),
+            )))]
+            (_, Swap) => "XCHG",
+            #[cfg(all(
+                feature = "critical-section",
+                any(
+                    all(target_arch = "x86", atomic_maybe_uninit_no_cmpxchg),
+                    atomic_maybe_uninit_test_critical_section,
+                ),
+            ))]
+            (_, Store | Swap) => "critical section",
+            #[cfg(not(any(
+                all(target_arch = "x86", atomic_maybe_uninit_no_cmpxchg),
+                all(feature = "critical-section", atomic_maybe_uninit_test_critical_section),
+            )))]
+            (_, CompareExchange | CompareExchangeWeak) => "LOCK CMPXCHG",
+            #[cfg(any(
+                all(target_arch = "x86", atomic_maybe_uninit_no_cmpxchg),
+                all(feature = "critical-section", atomic_maybe_uninit_test_critical_section),
+            ))]
+            (_, CompareExchange | CompareExchangeWeak) => "critical section",
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     test_atomic!(isize);
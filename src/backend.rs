@@ -0,0 +1,207 @@
+//! User-provided backend.
+//!
+//! On targets that atomic-maybe-uninit does not support natively (or when
+//! `--cfg atomic_maybe_uninit_custom_backend` is passed in `RUSTFLAGS`),
+//! atomic operations are delegated to a backend provided by the user.
+//!
+//! To provide a backend, implement [`Backend`] for each unsigned integer type
+//! you want to support and register it with [`set_backend!`](crate::set_backend).
+//! Signed integers use the backend of the unsigned integer of the same size,
+//! and `isize`/`usize` use the backend of the unsigned integer of the pointer width.
+//!
+//! ```ignore
+//! use core::{mem::MaybeUninit, sync::atomic::Ordering};
+//!
+//! use atomic_maybe_uninit::backend::Backend;
+//!
+//! struct MyBackend;
+//! unsafe impl Backend<u32> for MyBackend {
+//!     unsafe fn atomic_load(
+//!         src: *const MaybeUninit<u32>,
+//!         out: *mut MaybeUninit<u32>,
+//!         order: Ordering,
+//!     ) {
+//!         // ...
+//!     }
+//!     // ...
+//! }
+//! atomic_maybe_uninit::set_backend!(MyBackend, u32);
+//! ```
+//!
+//! Only one backend can be registered in the whole program. Using an integer
+//! type whose backend is not registered results in a link error.
+//!
+//! The backend is called through `extern "Rust"` functions named
+//! `_atomic_maybe_uninit_0_2_{load,store,swap,compare_exchange,compare_exchange_weak}_{u8,u16,u32,u64,u128}`,
+//! similar to the protocol of the [critical-section] crate.
+//! [`set_backend!`](crate::set_backend) defines them, but they can also be
+//! defined manually (e.g., in assembly).
+//!
+//! [critical-section]: https://github.com/rust-embedded/critical-section
+
+#[cfg(doc)]
+use core::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release, SeqCst};
+use core::{mem::MaybeUninit, sync::atomic::Ordering};
+
+use crate::raw::Primitive;
+#[cfg(doc)]
+use crate::raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap};
+
+/// Backend that implements atomic operations on `T`.
+///
+/// `T` is one of `u8`, `u16`, `u32`, `u64`, and `u128`.
+///
+/// # Safety
+///
+/// Each function must behave as described in the corresponding function of
+/// the traits in the [`raw`](crate::raw) module, under their safety contracts.
+/// In particular:
+///
+/// - All operations on the same address must be atomic with respect to each
+///   other, and must respect the given memory ordering.
+/// - Values may contain uninitialized bytes, so they must not be read as
+///   integers in Rust (e.g., use inline assembly or copy them under a lock).
+pub unsafe trait Backend<T: Primitive> {
+    /// See [`AtomicLoad::atomic_load`].
+    ///
+    /// # Safety
+    ///
+    /// See [`AtomicLoad::atomic_load`].
+    unsafe fn atomic_load(src: *const MaybeUninit<T>, out: *mut MaybeUninit<T>, order: Ordering);
+
+    /// See [`AtomicStore::atomic_store`].
+    ///
+    /// # Safety
+    ///
+    /// See [`AtomicStore::atomic_store`].
+    unsafe fn atomic_store(dst: *mut MaybeUninit<T>, val: *const MaybeUninit<T>, order: Ordering);
+
+    /// See [`AtomicSwap::atomic_swap`].
+    ///
+    /// # Safety
+    ///
+    /// See [`AtomicSwap::atomic_swap`].
+    unsafe fn atomic_swap(
+        dst: *mut MaybeUninit<T>,
+        val: *const MaybeUninit<T>,
+        out: *mut MaybeUninit<T>,
+        order: Ordering,
+    );
+
+    /// See [`AtomicCompareExchange::atomic_compare_exchange`].
+    ///
+    /// # Safety
+    ///
+    /// See [`AtomicCompareExchange::atomic_compare_exchange`].
+    unsafe fn atomic_compare_exchange(
+        dst: *mut MaybeUninit<T>,
+        current: *const MaybeUninit<T>,
+        new: *const MaybeUninit<T>,
+        out: *mut MaybeUninit<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> bool;
+
+    /// See [`AtomicCompareExchange::atomic_compare_exchange_weak`].
+    ///
+    /// # Safety
+    ///
+    /// See [`AtomicCompareExchange::atomic_compare_exchange_weak`].
+    #[inline]
+    unsafe fn atomic_compare_exchange_weak(
+        dst: *mut MaybeUninit<T>,
+        current: *const MaybeUninit<T>,
+        new: *const MaybeUninit<T>,
+        out: *mut MaybeUninit<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> bool {
+        // SAFETY: the caller must uphold the safety contract.
+        unsafe { Self::atomic_compare_exchange(dst, current, new, out, success, failure) }
+    }
+}
+
+/// Registers a [`Backend`] implementation.
+///
+/// The first argument is the type that implements [`Backend`], and the rest
+/// are the unsigned integer types it implements [`Backend`] for.
+///
+/// ```ignore
+/// atomic_maybe_uninit::set_backend!(MyBackend, u8, u16, u32);
+/// ```
+///
+/// See the [`backend`](crate::backend) module for details.
+#[macro_export]
+macro_rules! set_backend {
+    ($backend:ty $(, $int_type:ident)+ $(,)?) => {
+        $(
+            const _: () = {
+                use ::core::{mem::MaybeUninit, sync::atomic::Ordering};
+
+                use $crate::backend::Backend;
+
+                #[export_name = concat!("_atomic_maybe_uninit_0_2_load_", stringify!($int_type))]
+                unsafe fn atomic_load(
+                    src: *const MaybeUninit<$int_type>,
+                    out: *mut MaybeUninit<$int_type>,
+                    order: Ordering,
+                ) {
+                    unsafe { <$backend as Backend<$int_type>>::atomic_load(src, out, order) }
+                }
+                #[export_name = concat!("_atomic_maybe_uninit_0_2_store_", stringify!($int_type))]
+                unsafe fn atomic_store(
+                    dst: *mut MaybeUninit<$int_type>,
+                    val: *const MaybeUninit<$int_type>,
+                    order: Ordering,
+                ) {
+                    unsafe { <$backend as Backend<$int_type>>::atomic_store(dst, val, order) }
+                }
+                #[export_name = concat!("_atomic_maybe_uninit_0_2_swap_", stringify!($int_type))]
+                unsafe fn atomic_swap(
+                    dst: *mut MaybeUninit<$int_type>,
+                    val: *const MaybeUninit<$int_type>,
+                    out: *mut MaybeUninit<$int_type>,
+                    order: Ordering,
+                ) {
+                    unsafe { <$backend as Backend<$int_type>>::atomic_swap(dst, val, out, order) }
+                }
+                #[export_name = concat!(
+                    "_atomic_maybe_uninit_0_2_compare_exchange_",
+                    stringify!($int_type)
+                )]
+                unsafe fn atomic_compare_exchange(
+                    dst: *mut MaybeUninit<$int_type>,
+                    current: *const MaybeUninit<$int_type>,
+                    new: *const MaybeUninit<$int_type>,
+                    out: *mut MaybeUninit<$int_type>,
+                    success: Ordering,
+                    failure: Ordering,
+                ) -> bool {
+                    unsafe {
+                        <$backend as Backend<$int_type>>::atomic_compare_exchange(
+                            dst, current, new, out, success, failure,
+                        )
+                    }
+                }
+                #[export_name = concat!(
+                    "_atomic_maybe_uninit_0_2_compare_exchange_weak_",
+                    stringify!($int_type)
+                )]
+                unsafe fn atomic_compare_exchange_weak(
+                    dst: *mut MaybeUninit<$int_type>,
+                    current: *const MaybeUninit<$int_type>,
+                    new: *const MaybeUninit<$int_type>,
+                    out: *mut MaybeUninit<$int_type>,
+                    success: Ordering,
+                    failure: Ordering,
+                ) -> bool {
+                    unsafe {
+                        <$backend as Backend<$int_type>>::atomic_compare_exchange_weak(
+                            dst, current, new, out, success, failure,
+                        )
+                    }
+                }
+            };
+        )+
+    };
+}
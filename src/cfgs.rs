@@ -0,0 +1,845 @@
+// Macros for conditional compilation based on the operations available on the
+// current target. See the "Available operations" section in the crate-level
+// documentation.
+//
+// Each condition is written once, in the internal cfg_* macros in the second
+// half of this file. They are used to define the hidden selector macros that
+// the public cfg_{has,no}_atomic_* macros dispatch to, to gate the
+// implementations in fallback.rs, and within this crate (e.g., libcalls.rs).
+// (Macros defined by macro expansion cannot be referred to by path within
+// the same crate, so this crate uses the internal macros instead of the public
+// ones.)
+//
+// - Load/store of 8-bit, 16-bit, 32-bit, and pointer-sized integers are always
+//   available.
+// - Swap/CAS of 8-bit, 16-bit, and 32-bit integers are available under the same
+//   conditions on all targets, except that swap of some widths is available
+//   without CAS on pre-v6 ARM (SWP/SWPB), i386 (XCHG), and 32-bit SPARC (SWAP).
+// - When the `fallback` feature is enabled, all operations of 64-bit and 128-bit
+//   integers are available on targets where 32-bit CAS is available natively
+//   (see arch/fallback.rs).
+// - Miri and user-provided backends (see backend.rs) support all operations.
+
+/// Expands the given items if load and store of `i8`/`u8` are available on the current target.
+///
+/// See also [`cfg_no_atomic_8!`](crate::cfg_no_atomic_8) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_8 {
+    ($($tt:tt)*) => {
+        $($tt)*
+    };
+}
+/// Expands the given items if load and store of `i8`/`u8` are not available on the current target.
+///
+/// See also [`cfg_has_atomic_8!`](crate::cfg_has_atomic_8) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_8 {
+    ($($tt:tt)*) => {};
+}
+
+/// Expands the given items if load and store of `i16`/`u16` are available on the current target.
+///
+/// See also [`cfg_no_atomic_16!`](crate::cfg_no_atomic_16) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_16 {
+    ($($tt:tt)*) => {
+        $($tt)*
+    };
+}
+/// Expands the given items if load and store of `i16`/`u16` are not available on the current target.
+///
+/// See also [`cfg_has_atomic_16!`](crate::cfg_has_atomic_16) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_16 {
+    ($($tt:tt)*) => {};
+}
+
+/// Expands the given items if load and store of `i32`/`u32` are available on the current target.
+///
+/// See also [`cfg_no_atomic_32!`](crate::cfg_no_atomic_32) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_32 {
+    ($($tt:tt)*) => {
+        $($tt)*
+    };
+}
+/// Expands the given items if load and store of `i32`/`u32` are not available on the current target.
+///
+/// See also [`cfg_has_atomic_32!`](crate::cfg_has_atomic_32) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_32 {
+    ($($tt:tt)*) => {};
+}
+
+/// Expands the given items if load and store of `i64`/`u64` are available on the current target.
+///
+/// See also [`cfg_no_atomic_64!`](crate::cfg_no_atomic_64) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_64 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_64! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if load and store of `i64`/`u64` are not available on the current target.
+///
+/// See also [`cfg_has_atomic_64!`](crate::cfg_has_atomic_64) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_64 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_64! { {} { $($tt)* } }
+    };
+}
+
+/// Expands the given items if load and store of `i128`/`u128` are available on the current target.
+///
+/// See also [`cfg_no_atomic_128!`](crate::cfg_no_atomic_128) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_128 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_128! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if load and store of `i128`/`u128` are not available on the current target.
+///
+/// See also [`cfg_has_atomic_128!`](crate::cfg_has_atomic_128) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_128 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_128! { {} { $($tt)* } }
+    };
+}
+
+/// Expands the given items if load and store of `isize`/`usize` are available on the current target.
+///
+/// See also [`cfg_no_atomic_ptr!`](crate::cfg_no_atomic_ptr) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_ptr {
+    ($($tt:tt)*) => {
+        $($tt)*
+    };
+}
+/// Expands the given items if load and store of `isize`/`usize` are not available on the current target.
+///
+/// See also [`cfg_has_atomic_ptr!`](crate::cfg_has_atomic_ptr) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_ptr {
+    ($($tt:tt)*) => {};
+}
+
+/// Expands the given items if swap of `i8`/`u8` is available on the current target.
+///
+/// See also [`cfg_no_atomic_swap_8!`](crate::cfg_no_atomic_swap_8) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_swap_8 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_swap_8! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if swap of `i8`/`u8` is not available on the current target.
+///
+/// See also [`cfg_has_atomic_swap_8!`](crate::cfg_has_atomic_swap_8) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_swap_8 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_swap_8! { {} { $($tt)* } }
+    };
+}
+
+/// Expands the given items if swap of `i16`/`u16` is available on the current target.
+///
+/// See also [`cfg_no_atomic_swap_16!`](crate::cfg_no_atomic_swap_16) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_swap_16 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_swap_16! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if swap of `i16`/`u16` is not available on the current target.
+///
+/// See also [`cfg_has_atomic_swap_16!`](crate::cfg_has_atomic_swap_16) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_swap_16 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_swap_16! { {} { $($tt)* } }
+    };
+}
+
+/// Expands the given items if swap of `i32`/`u32` is available on the current target.
+///
+/// See also [`cfg_no_atomic_swap_32!`](crate::cfg_no_atomic_swap_32) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_swap_32 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_swap_32! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if swap of `i32`/`u32` is not available on the current target.
+///
+/// See also [`cfg_has_atomic_swap_32!`](crate::cfg_has_atomic_swap_32) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_swap_32 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_swap_32! { {} { $($tt)* } }
+    };
+}
+
+/// Expands the given items if swap of `i64`/`u64` is available on the current target.
+///
+/// See also [`cfg_no_atomic_swap_64!`](crate::cfg_no_atomic_swap_64) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_swap_64 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_64! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if swap of `i64`/`u64` is not available on the current target.
+///
+/// See also [`cfg_has_atomic_swap_64!`](crate::cfg_has_atomic_swap_64) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_swap_64 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_64! { {} { $($tt)* } }
+    };
+}
+
+/// Expands the given items if swap of `i128`/`u128` is available on the current target.
+///
+/// See also [`cfg_no_atomic_swap_128!`](crate::cfg_no_atomic_swap_128) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_swap_128 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_128! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if swap of `i128`/`u128` is not available on the current target.
+///
+/// See also [`cfg_has_atomic_swap_128!`](crate::cfg_has_atomic_swap_128) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_swap_128 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_128! { {} { $($tt)* } }
+    };
+}
+
+/// Expands the given items if swap of `isize`/`usize` is available on the current target.
+///
+/// See also [`cfg_no_atomic_swap_ptr!`](crate::cfg_no_atomic_swap_ptr) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_swap_ptr {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_swap_ptr! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if swap of `isize`/`usize` is not available on the current target.
+///
+/// See also [`cfg_has_atomic_swap_ptr!`](crate::cfg_has_atomic_swap_ptr) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_swap_ptr {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_swap_ptr! { {} { $($tt)* } }
+    };
+}
+
+/// Expands the given items if swap and CAS of `i8`/`u8` are available on the current target.
+///
+/// See also [`cfg_no_atomic_cas_8!`](crate::cfg_no_atomic_cas_8) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_cas_8 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_8_16_32! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if CAS of `i8`/`u8` is not available (swap may still be available) on the current target.
+///
+/// See also [`cfg_has_atomic_cas_8!`](crate::cfg_has_atomic_cas_8) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_cas_8 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_8_16_32! { {} { $($tt)* } }
+    };
+}
+
+/// Expands the given items if swap and CAS of `i16`/`u16` are available on the current target.
+///
+/// See also [`cfg_no_atomic_cas_16!`](crate::cfg_no_atomic_cas_16) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_cas_16 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_8_16_32! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if CAS of `i16`/`u16` is not available (swap may still be available) on the current target.
+///
+/// See also [`cfg_has_atomic_cas_16!`](crate::cfg_has_atomic_cas_16) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_cas_16 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_8_16_32! { {} { $($tt)* } }
+    };
+}
+
+/// Expands the given items if swap and CAS of `i32`/`u32` are available on the current target.
+///
+/// See also [`cfg_no_atomic_cas_32!`](crate::cfg_no_atomic_cas_32) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_cas_32 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_8_16_32! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if CAS of `i32`/`u32` is not available (swap may still be available) on the current target.
+///
+/// See also [`cfg_has_atomic_cas_32!`](crate::cfg_has_atomic_cas_32) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_cas_32 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_8_16_32! { {} { $($tt)* } }
+    };
+}
+
+/// Expands the given items if swap and CAS of `i64`/`u64` are available on the current target.
+///
+/// See also [`cfg_no_atomic_cas_64!`](crate::cfg_no_atomic_cas_64) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_cas_64 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_64! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if CAS of `i64`/`u64` is not available (swap may still be available) on the current target.
+///
+/// See also [`cfg_has_atomic_cas_64!`](crate::cfg_has_atomic_cas_64) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_cas_64 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_64! { {} { $($tt)* } }
+    };
+}
+
+/// Expands the given items if swap and CAS of `i128`/`u128` are available on the current target.
+///
+/// See also [`cfg_no_atomic_cas_128!`](crate::cfg_no_atomic_cas_128) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_cas_128 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_128! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if CAS of `i128`/`u128` is not available (swap may still be available) on the current target.
+///
+/// See also [`cfg_has_atomic_cas_128!`](crate::cfg_has_atomic_cas_128) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_cas_128 {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_128! { {} { $($tt)* } }
+    };
+}
+
+/// Expands the given items if swap and CAS of `isize`/`usize` are available on the current target.
+///
+/// See also [`cfg_no_atomic_cas_ptr!`](crate::cfg_no_atomic_cas_ptr) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_has_atomic_cas_ptr {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_ptr! { { $($tt)* } {} }
+    };
+}
+/// Expands the given items if CAS of `isize`/`usize` is not available (swap may still be available) on the current target.
+///
+/// See also [`cfg_has_atomic_cas_ptr!`](crate::cfg_has_atomic_cas_ptr) and the [crate-level documentation](crate#available-operations).
+#[macro_export]
+macro_rules! cfg_no_atomic_cas_ptr {
+    ($($tt:tt)*) => {
+        $crate::__atomic_maybe_uninit_cfg_cas_ptr! { {} { $($tt)* } }
+    };
+}
+
+// Expands the first group of items if the condition is true, and the second
+// group otherwise.
+macro_rules! cfg_sel {
+    ([$cond:meta] { $($has:item)* } { $($no:item)* }) => {
+        $(#[cfg($cond)] $has)*
+        $(#[cfg(not($cond))] $no)*
+    };
+}
+
+// The native backends in arch/*.rs are used, i.e., neither Miri nor a
+// user-provided backend (see backend.rs), which support all operations.
+macro_rules! cfg_native {
+    ($($tt:tt)*) => {
+        cfg_sel! {
+            [not(any(
+                miri,
+                atomic_maybe_uninit_custom_backend,
+                not(any(
+                    target_arch = "x86",
+                    target_arch = "x86_64",
+                    target_arch = "arm",
+                    target_arch = "aarch64",
+                    target_arch = "riscv32",
+                    target_arch = "riscv64",
+                    target_arch = "loongarch64",
+                    target_arch = "avr",
+                    target_arch = "hexagon",
+                    target_arch = "m68k",
+                    target_arch = "mips",
+                    target_arch = "mips32r6",
+                    target_arch = "mips64",
+                    target_arch = "mips64r6",
+                    target_arch = "msp430",
+                    target_arch = "powerpc",
+                    target_arch = "powerpc64",
+                    target_arch = "s390x",
+                    target_arch = "sparc",
+                    target_arch = "sparc64",
+                )),
+            ))]
+            $($tt)*
+        }
+    };
+}
+
+// The following macros are only meaningful when the native backends are used
+// (and are unused otherwise).
+
+// The native backend provides swap/CAS of 8-bit, 16-bit, and 32-bit integers.
+#[allow(unused_macros)]
+macro_rules! cfg_native_cas_8_16_32 {
+    ($($tt:tt)*) => {
+        cfg_sel! {
+            [any(
+                target_arch = "x86_64",
+                all(
+                    target_arch = "x86",
+                    any(not(atomic_maybe_uninit_no_cmpxchg), feature = "critical-section"),
+                ),
+                target_arch = "aarch64",
+                all(
+                    target_arch = "arm",
+                    any(
+                        target_feature = "v7",
+                        atomic_maybe_uninit_target_feature = "v7",
+                        target_feature = "v8",
+                        atomic_maybe_uninit_target_feature = "v8",
+                        target_feature = "v8m",
+                        atomic_maybe_uninit_target_feature = "v8m",
+                        all(
+                            any(target_feature = "v6", atomic_maybe_uninit_target_feature = "v6"),
+                            not(any(
+                                target_feature = "mclass",
+                                atomic_maybe_uninit_target_feature = "mclass"
+                            )),
+                        ),
+                        target_os = "linux",
+                        target_os = "android",
+                        feature = "critical-section",
+                        all(
+                            atomic_maybe_uninit_unsafe_assume_single_core,
+                            any(
+                                target_feature = "v6",
+                                atomic_maybe_uninit_target_feature = "v6",
+                                not(any(
+                                    target_feature = "thumb-mode",
+                                    atomic_maybe_uninit_target_feature = "thumb-mode"
+                                )),
+                            ),
+                        ),
+                    ),
+                ),
+                all(
+                    any(target_arch = "riscv32", target_arch = "riscv64"),
+                    any(
+                        target_feature = "a",
+                        atomic_maybe_uninit_target_feature = "a",
+                        atomic_maybe_uninit_unsafe_assume_single_core,
+                        feature = "critical-section",
+                    ),
+                ),
+                target_arch = "loongarch64",
+                target_arch = "avr",
+                target_arch = "hexagon",
+                all(
+                    target_arch = "m68k",
+                    any(
+                        target_feature = "isa-68020",
+                        atomic_maybe_uninit_target_feature = "isa-68020"
+                    ),
+                ),
+                target_arch = "mips",
+                target_arch = "mips32r6",
+                target_arch = "mips64",
+                target_arch = "mips64r6",
+                target_arch = "msp430",
+                target_arch = "powerpc",
+                target_arch = "powerpc64",
+                all(target_arch = "s390x", not(atomic_maybe_uninit_no_s390x_asm_cc_clobbered)),
+                all(
+                    any(target_arch = "sparc", target_arch = "sparc64"),
+                    any(
+                        target_arch = "sparc64",
+                        target_feature = "v9",
+                        atomic_maybe_uninit_target_feature = "v9",
+                        target_feature = "leoncasa",
+                        atomic_maybe_uninit_target_feature = "leoncasa",
+                    ),
+                ),
+            )]
+            $($tt)*
+        }
+    };
+}
+
+// The native backend provides load/store of 64-bit integers.
+#[allow(unused_macros)]
+macro_rules! cfg_native_64 {
+    ($($tt:tt)*) => {
+        cfg_sel! {
+            [any(
+                target_arch = "x86_64",
+                all(target_arch = "x86", not(atomic_maybe_uninit_no_cmpxchg8b)),
+                target_arch = "aarch64",
+                all(
+                    target_arch = "arm",
+                    not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")),
+                    any(
+                        target_feature = "v6",
+                        atomic_maybe_uninit_target_feature = "v6",
+                        target_os = "linux",
+                        target_os = "android",
+                    ),
+                ),
+                all(
+                    target_arch = "riscv32",
+                    any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"),
+                ),
+                target_arch = "riscv64",
+                target_arch = "loongarch64",
+                target_arch = "avr",
+                target_arch = "hexagon",
+                all(
+                    target_arch = "m68k",
+                    any(
+                        target_feature = "isa-68020",
+                        atomic_maybe_uninit_target_feature = "isa-68020"
+                    ),
+                ),
+                target_arch = "mips64",
+                target_arch = "mips64r6",
+                target_arch = "msp430",
+                target_arch = "powerpc64",
+                target_arch = "s390x",
+                target_arch = "sparc64",
+            )]
+            $($tt)*
+        }
+    };
+}
+
+// The native backend provides swap/CAS of 64-bit integers.
+#[allow(unused_macros)]
+macro_rules! cfg_native_cas_64 {
+    ($($tt:tt)*) => {
+        cfg_sel! {
+            [any(
+                target_arch = "x86_64",
+                all(target_arch = "x86", not(atomic_maybe_uninit_no_cmpxchg8b)),
+                target_arch = "aarch64",
+                all(
+                    target_arch = "arm",
+                    not(any(target_feature = "mclass", atomic_maybe_uninit_target_feature = "mclass")),
+                    any(
+                        target_feature = "v6",
+                        atomic_maybe_uninit_target_feature = "v6",
+                        target_os = "linux",
+                        target_os = "android",
+                    ),
+                ),
+                all(
+                    target_arch = "riscv32",
+                    any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"),
+                ),
+                all(
+                    target_arch = "riscv64",
+                    any(
+                        target_feature = "a",
+                        atomic_maybe_uninit_target_feature = "a",
+                        atomic_maybe_uninit_unsafe_assume_single_core,
+                        feature = "critical-section",
+                    ),
+                ),
+                target_arch = "loongarch64",
+                target_arch = "avr",
+                target_arch = "hexagon",
+                all(
+                    target_arch = "m68k",
+                    any(
+                        target_feature = "isa-68020",
+                        atomic_maybe_uninit_target_feature = "isa-68020"
+                    ),
+                ),
+                target_arch = "mips64",
+                target_arch = "mips64r6",
+                target_arch = "msp430",
+                target_arch = "powerpc64",
+                all(target_arch = "s390x", not(atomic_maybe_uninit_no_s390x_asm_cc_clobbered)),
+                target_arch = "sparc64",
+            )]
+            $($tt)*
+        }
+    };
+}
+
+// The native backend provides load/store of 128-bit integers.
+#[allow(unused_macros)]
+macro_rules! cfg_native_128 {
+    ($($tt:tt)*) => {
+        cfg_sel! {
+            [any(
+                all(
+                    target_arch = "x86_64",
+                    any(
+                        target_feature = "cmpxchg16b",
+                        atomic_maybe_uninit_target_feature = "cmpxchg16b"
+                    ),
+                ),
+                target_arch = "aarch64",
+                all(
+                    target_arch = "riscv64",
+                    any(target_feature = "zacas", atomic_maybe_uninit_target_feature = "zacas"),
+                ),
+                all(
+                    target_arch = "powerpc64",
+                    any(
+                        target_feature = "quadword-atomics",
+                        atomic_maybe_uninit_target_feature = "quadword-atomics"
+                    ),
+                ),
+                target_arch = "s390x",
+            )]
+            $($tt)*
+        }
+    };
+}
+
+// The native backend provides swap/CAS of 128-bit integers.
+#[allow(unused_macros)]
+macro_rules! cfg_native_cas_128 {
+    ({ $($has:item)* } { $($no:item)* }) => {
+        cfg_native_128! {
+            {
+                cfg_sel! {
+                    [not(all(target_arch = "s390x", atomic_maybe_uninit_no_s390x_asm_cc_clobbered))]
+                    { $($has)* }
+                    { $($no)* }
+                }
+            }
+            { $($no)* }
+        }
+    };
+}
+
+// The widths that the native backend lacks are provided by fallback.rs.
+#[allow(unused_macros)]
+macro_rules! cfg_fallback {
+    ({ $($has:item)* } { $($no:item)* }) => {
+        cfg_sel! {
+            [feature = "fallback"]
+            { cfg_native_cas_8_16_32! { { $($has)* } { $($no)* } } }
+            { $($no)* }
+        }
+    };
+}
+
+// The conditions for each operation, including Miri, user-provided backends,
+// and fallback.rs.
+
+macro_rules! cfg_cas_8_16_32 {
+    ({ $($has:item)* } { $($no:item)* }) => {
+        cfg_native! {
+            { cfg_native_cas_8_16_32! { { $($has)* } { $($no)* } } }
+            { $($has)* }
+        }
+    };
+}
+// SWP/SWPB on pre-v6 ARM in ARM mode and XCHG on i386 don't need CAS.
+macro_rules! cfg_swap_8 {
+    ({ $($has:item)* } { $($no:item)* }) => {
+        cfg_sel! {
+            [any(
+                target_arch = "x86",
+                all(
+                    target_arch = "arm",
+                    not(any(
+                        target_feature = "v6",
+                        atomic_maybe_uninit_target_feature = "v6",
+                        target_os = "linux",
+                        target_os = "android",
+                        target_feature = "thumb-mode",
+                        atomic_maybe_uninit_target_feature = "thumb-mode",
+                    )),
+                ),
+            )]
+            { $($has)* }
+            { cfg_cas_8_16_32! { { $($has)* } { $($no)* } } }
+        }
+    };
+}
+// SWAP on 32-bit SPARC doesn't need CAS either.
+macro_rules! cfg_swap_32 {
+    ({ $($has:item)* } { $($no:item)* }) => {
+        cfg_sel! {
+            [target_arch = "sparc"]
+            { $($has)* }
+            { cfg_swap_8! { { $($has)* } { $($no)* } } }
+        }
+    };
+}
+macro_rules! cfg_swap_16 {
+    ({ $($has:item)* } { $($no:item)* }) => {
+        cfg_sel! {
+            [target_arch = "x86"]
+            { $($has)* }
+            { cfg_cas_8_16_32! { { $($has)* } { $($no)* } } }
+        }
+    };
+}
+macro_rules! cfg_64 {
+    ({ $($has:item)* } { $($no:item)* }) => {
+        cfg_native! {
+            { cfg_native_64! { { $($has)* } { cfg_fallback! { { $($has)* } { $($no)* } } } } }
+            { $($has)* }
+        }
+    };
+}
+macro_rules! cfg_cas_64 {
+    ({ $($has:item)* } { $($no:item)* }) => {
+        cfg_native! {
+            { cfg_native_cas_64! { { $($has)* } { cfg_fallback! { { $($has)* } { $($no)* } } } } }
+            { $($has)* }
+        }
+    };
+}
+macro_rules! cfg_128 {
+    ({ $($has:item)* } { $($no:item)* }) => {
+        cfg_native! {
+            { cfg_native_128! { { $($has)* } { cfg_fallback! { { $($has)* } { $($no)* } } } } }
+            { $($has)* }
+        }
+    };
+}
+macro_rules! cfg_cas_128 {
+    ({ $($has:item)* } { $($no:item)* }) => {
+        cfg_native! {
+            { cfg_native_cas_128! { { $($has)* } { cfg_fallback! { { $($has)* } { $($no)* } } } } }
+            { $($has)* }
+        }
+    };
+}
+macro_rules! cfg_swap_ptr {
+    ({ $($has:item)* } { $($no:item)* }) => {
+        cfg_sel! {
+            [target_pointer_width = "16"]
+            { cfg_swap_16! { { $($has)* } { $($no)* } } }
+            {
+                cfg_sel! {
+                    [target_pointer_width = "32"]
+                    { cfg_swap_32! { { $($has)* } { $($no)* } } }
+                    { cfg_cas_64! { { $($has)* } { $($no)* } } }
+                }
+            }
+        }
+    };
+}
+macro_rules! cfg_cas_ptr {
+    ({ $($has:item)* } { $($no:item)* }) => {
+        cfg_sel! {
+            [target_pointer_width = "64"]
+            { cfg_cas_64! { { $($has)* } { $($no)* } } }
+            { cfg_cas_8_16_32! { { $($has)* } { $($no)* } } }
+        }
+    };
+}
+
+// Defines the hidden selector macro used by the public macros from the given
+// internal macro. (`$d` is `$`, which cannot be written directly in the
+// definition of a macro defined by a macro.)
+macro_rules! selector {
+    ($d:tt $name:ident, $cfg:ident) => {
+        $cfg! {
+            {
+                #[doc(hidden)]
+                #[macro_export]
+                macro_rules! $name {
+                    ({ $d($d has:tt)* } { $d($d no:tt)* }) => {
+                        $d($d has)*
+                    };
+                }
+            }
+            {
+                #[doc(hidden)]
+                #[macro_export]
+                macro_rules! $name {
+                    ({ $d($d has:tt)* } { $d($d no:tt)* }) => {
+                        $d($d no)*
+                    };
+                }
+            }
+        }
+    };
+}
+selector!($ __atomic_maybe_uninit_cfg_cas_8_16_32, cfg_cas_8_16_32);
+selector!($ __atomic_maybe_uninit_cfg_swap_8, cfg_swap_8);
+selector!($ __atomic_maybe_uninit_cfg_swap_32, cfg_swap_32);
+selector!($ __atomic_maybe_uninit_cfg_swap_16, cfg_swap_16);
+selector!($ __atomic_maybe_uninit_cfg_64, cfg_64);
+selector!($ __atomic_maybe_uninit_cfg_cas_64, cfg_cas_64);
+selector!($ __atomic_maybe_uninit_cfg_128, cfg_128);
+selector!($ __atomic_maybe_uninit_cfg_cas_128, cfg_cas_128);
+selector!($ __atomic_maybe_uninit_cfg_swap_ptr, cfg_swap_ptr);
+selector!($ __atomic_maybe_uninit_cfg_cas_ptr, cfg_cas_ptr);
+
+// Check that the conditions above match the implementations of the native
+// backends. (fallback.rs is gated by the conditions above, so it cannot
+// drift.) This only catches drift on the combinations of target, cfgs, and
+// features that are actually built; tools/build.sh builds each native backend
+// with the feature powerset and the target-specific cfgs and target features.
+macro_rules! check {
+    ($cfg:ident, $op:ident, $($ty:ident),+) => {
+        $cfg! {
+            {
+                $(static_assert!(
+                    <$ty as crate::private::PrimitivePriv>::$op,
+                    concat!(stringify!($cfg), " does not match the implementations for ", stringify!($ty))
+                );)+
+            }
+            {
+                $(static_assert!(
+                    !<$ty as crate::private::PrimitivePriv>::$op,
+                    concat!(stringify!($cfg), " does not match the implementations for ", stringify!($ty))
+                );)+
+            }
+        }
+    };
+}
+// Expands the given items unconditionally.
+macro_rules! cfg_always {
+    ({ $($has:item)* } { $($no:item)* }) => {
+        $($has)*
+    };
+}
+check!(cfg_always, HAS_LOAD, i8, u8, i16, u16, i32, u32, isize, usize);
+check!(cfg_always, HAS_STORE, i8, u8, i16, u16, i32, u32, isize, usize);
+check!(cfg_64, HAS_LOAD, i64, u64);
+check!(cfg_64, HAS_STORE, i64, u64);
+check!(cfg_128, HAS_LOAD, i128, u128);
+check!(cfg_128, HAS_STORE, i128, u128);
+check!(cfg_swap_8, HAS_SWAP, i8, u8);
+check!(cfg_swap_32, HAS_SWAP, i32, u32);
+check!(cfg_swap_16, HAS_SWAP, i16, u16);
+check!(cfg_cas_64, HAS_SWAP, i64, u64);
+check!(cfg_cas_128, HAS_SWAP, i128, u128);
+check!(cfg_swap_ptr, HAS_SWAP, isize, usize);
+check!(cfg_cas_8_16_32, HAS_CAS, i8, u8, i16, u16, i32, u32);
+check!(cfg_cas_64, HAS_CAS, i64, u64);
+check!(cfg_cas_128, HAS_CAS, i128, u128);
+check!(cfg_cas_ptr, HAS_CAS, isize, usize);
@@ -0,0 +1,121 @@
+//! Introspection of the implementation strategy.
+//!
+//! Several targets have more than one way to implement the same atomic
+//! operation, and which one is used depends on the enabled target features,
+//! cfgs, crate features, and (on some targets) run-time CPU feature detection.
+//! For example:
+//!
+//! - On AArch64, swap and CAS use FEAT_LSE instructions (`SWP*`/`CAS*`) when
+//!   the `lse` target feature is enabled, and LL/SC loops (`LDXR`/`STXR`) otherwise.
+//! - On x86, 64-bit loads and stores use SSE/SSE2 when the `sse` target feature is
+//!   enabled, and `LOCK CMPXCHG8B` otherwise.
+//! - On pre-v6 ARM Linux/Android, swap and CAS call the kernel user helpers.
+//! - On s390x, `SeqCst` stores use `BCR 14,0` when the `fast-serialization`
+//!   target feature is enabled, and `BCR 15,0` otherwise.
+//! - On RISC-V Linux/Android with `--cfg atomic_maybe_uninit_outline_atomics`,
+//!   Zacas/Zabha are detected at run-time.
+//!
+//! [`implementation_info`] returns which one the current build (and CPU)
+//! uses for each operation. This is intended for debugging and diagnostics:
+//! the descriptions are not stable and may change in any release.
+//!
+//! ```
+//! let info = atomic_maybe_uninit::implementation_info::<u64>();
+//! if let Some(cas) = info.compare_exchange {
+//!     println!("u64 CAS: {}", cas.description);
+//! }
+//! println!("{:#?}", info);
+//! ```
+
+use core::mem;
+
+use crate::{arch, raw::Primitive, AtomicMaybeUninit};
+
+/// Describes how the atomic operations on `T` are implemented on the current
+/// target.
+///
+/// Operations that are not available on the current target are `None`.
+/// If run-time CPU feature detection is used, this performs it (the result is
+/// cached in the same way as when the operations are called).
+///
+/// Under `cfg(loom)`, operations are additionally serialized by loom's
+/// scheduler; the described strategy is the one used to access the value.
+///
+/// See the [module-level documentation](self) for details.
+///
+/// # Examples
+///
+/// ```
+/// use atomic_maybe_uninit::{implementation_info, AtomicMaybeUninit};
+///
+/// let info = implementation_info::<u32>();
+/// assert_eq!(info.load.is_some(), AtomicMaybeUninit::<u32>::HAS_LOAD);
+/// ```
+#[must_use]
+#[inline]
+pub fn implementation_info<T: Primitive>() -> ImplementationInfo {
+    let fallback = <T as crate::private::PrimitivePriv>::IS_FALLBACK;
+    let describe = |op, available| {
+        if available {
+            Some(arch::describe(mem::size_of::<T>(), op, fallback))
+        } else {
+            None
+        }
+    };
+    ImplementationInfo {
+        load: describe(arch::Op::Load, AtomicMaybeUninit::<T>::HAS_LOAD),
+        store: describe(arch::Op::Store, AtomicMaybeUninit::<T>::HAS_STORE),
+        swap: describe(arch::Op::Swap, AtomicMaybeUninit::<T>::HAS_SWAP),
+        compare_exchange: describe(arch::Op::CompareExchange, AtomicMaybeUninit::<T>::HAS_CAS),
+        compare_exchange_weak: describe(
+            arch::Op::CompareExchangeWeak,
+            AtomicMaybeUninit::<T>::HAS_CAS,
+        ),
+        is_always_lock_free: AtomicMaybeUninit::<T>::IS_ALWAYS_LOCK_FREE,
+    }
+}
+
+/// Implementation strategy of the atomic operations on a type.
+///
+/// This is returned by [`implementation_info`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ImplementationInfo {
+    /// Strategy of [`load`](AtomicMaybeUninit::load), or `None` if not available.
+    pub load: Option<Strategy>,
+    /// Strategy of [`store`](AtomicMaybeUninit::store), or `None` if not available.
+    pub store: Option<Strategy>,
+    /// Strategy of [`swap`](AtomicMaybeUninit::swap), or `None` if not available.
+    pub swap: Option<Strategy>,
+    /// Strategy of [`compare_exchange`](AtomicMaybeUninit::compare_exchange),
+    /// or `None` if not available.
+    pub compare_exchange: Option<Strategy>,
+    /// Strategy of [`compare_exchange_weak`](AtomicMaybeUninit::compare_exchange_weak),
+    /// or `None` if not available.
+    pub compare_exchange_weak: Option<Strategy>,
+    /// Same as [`AtomicMaybeUninit::IS_ALWAYS_LOCK_FREE`].
+    pub is_always_lock_free: bool,
+}
+
+/// Implementation strategy of an atomic operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Strategy {
+    /// Short description of the instructions (or other mechanism) used,
+    /// e.g., `"CAS (FEAT_LSE)"` or `"LDXR/STXR loop"`.
+    ///
+    /// This is for humans; the exact wording is not stable.
+    pub description: &'static str,
+    /// `true` if this strategy was selected by run-time CPU feature detection.
+    pub runtime_detected: bool,
+}
+
+impl Strategy {
+    pub(crate) const fn new(description: &'static str) -> Self {
+        Self { description, runtime_detected: false }
+    }
+    #[allow(dead_code)]
+    pub(crate) const fn detected(description: &'static str) -> Self {
+        Self { description, runtime_detected: true }
+    }
+}
@@ -0,0 +1,271 @@
+// Reference conversions between AtomicMaybeUninit and the atomic types of the
+// standard library and portable-atomic.
+//
+// These are zero-cost pointer casts. The layouts are checked at compile-time
+// below, and the rules for accessing the same memory through both types are
+// documented in the "Mixed access" section of the documentation of
+// AtomicMaybeUninit.
+//
+// These are not available under cfg(loom), where AtomicMaybeUninit doesn't
+// have the same in-memory representation as the underlying integer type.
+//
+// The conversions for portable-atomic are only provided when both
+// implementations are always lock-free, because portable-atomic may otherwise
+// use a lock that this crate doesn't take (or vice versa). That is, the native
+// backend is used (not Miri or a user-provided backend), and the width is
+// natively supported: cfg(target_has_atomic) for up to 64 bits, and the
+// native conditions of cfg_has_atomic_cas_128! (without the `fallback`
+// feature) for 128 bits. This is checked at compile-time below.
+
+macro_rules! core_atomic {
+    ($ty:ident, $atomic:ident, $as_atomic:ident) => {
+        #[cfg(not(atomic_maybe_uninit_no_cfg_target_has_atomic))]
+        const _: () = {
+            use core::{mem, sync::atomic::$atomic};
+
+            use crate::AtomicMaybeUninit;
+
+            static_assert!(mem::size_of::<AtomicMaybeUninit<$ty>>() == mem::size_of::<$atomic>());
+            static_assert!(mem::align_of::<AtomicMaybeUninit<$ty>>() == mem::align_of::<$atomic>());
+
+            impl AtomicMaybeUninit<$ty> {
+                /// Converts a reference to the atomic integer type of the standard
+                /// library with the same size into a reference to `AtomicMaybeUninit`.
+                ///
+                /// This is a no-op: both have the same in-memory representation.
+                ///
+                /// This is available on Rust 1.60+ on targets where the standard
+                /// library provides the atomic type.
+                ///
+                /// # Safety
+                ///
+                /// While the memory is also accessed through the original reference,
+                /// the [mixed access rules](AtomicMaybeUninit#mixed-access) must be followed.
+                /// In particular, uninitialized bytes must not be stored through the
+                /// returned reference while they can be read through the original one.
+                ///
+                /// # Examples
+                ///
+                /// ```
+                /// use std::{
+                ///     mem::MaybeUninit,
+                ///     sync::atomic::{AtomicU32, Ordering},
+                /// };
+                ///
+                /// use atomic_maybe_uninit::AtomicMaybeUninit;
+                ///
+                /// let a = AtomicU32::new(5);
+                /// let v = unsafe { AtomicMaybeUninit::<u32>::from_atomic(&a) };
+                /// v.store(MaybeUninit::new(10), Ordering::Relaxed);
+                /// assert_eq!(a.load(Ordering::Relaxed), 10);
+                /// ```
+                #[inline]
+                #[must_use]
+                pub unsafe fn from_atomic(a: &$atomic) -> &Self {
+                    let a: *const $atomic = a;
+                    // SAFETY: the caller must uphold the safety contract.
+                    // The layouts are the same, as checked above.
+                    unsafe { &*a.cast::<Self>() }
+                }
+
+                /// Converts this reference into a reference to the atomic integer
+                /// type of the standard library with the same size.
+                ///
+                /// This is a no-op: both have the same in-memory representation.
+                ///
+                /// This is available on Rust 1.60+ on targets where the standard
+                /// library provides the atomic type.
+                ///
+                /// # Safety
+                ///
+                /// The value must be initialized, and while the memory is accessed
+                /// through the returned reference, the [mixed access rules](AtomicMaybeUninit#mixed-access)
+                /// must be followed. In particular, uninitialized bytes must not be stored
+                /// through `self` (or other references to the same memory) while they can
+                /// be read through the returned reference.
+                ///
+                /// # Examples
+                ///
+                /// ```
+                /// use std::sync::atomic::Ordering;
+                ///
+                /// use atomic_maybe_uninit::AtomicMaybeUninit;
+                ///
+                /// let v = AtomicMaybeUninit::from(5_u32);
+                /// let a = unsafe { v.as_atomic_u32() };
+                /// assert_eq!(a.fetch_add(1, Ordering::Relaxed), 5);
+                /// unsafe { assert_eq!(v.load(Ordering::Relaxed).assume_init(), 6) }
+                /// ```
+                #[inline]
+                #[must_use]
+                pub unsafe fn $as_atomic(&self) -> &$atomic {
+                    let this: *const Self = self;
+                    // SAFETY: the caller must uphold the safety contract.
+                    // The layouts are the same, as checked above.
+                    unsafe { &*this.cast::<$atomic>() }
+                }
+            }
+        };
+    };
+}
+
+// cfg(target_has_atomic) requires Rust 1.60. On older compilers, these are not
+// provided (see core_atomic! above).
+#[cfg_attr(not(atomic_maybe_uninit_no_cfg_target_has_atomic), cfg(target_has_atomic = "8"))]
+core_atomic!(i8, AtomicI8, as_atomic_i8);
+#[cfg_attr(not(atomic_maybe_uninit_no_cfg_target_has_atomic), cfg(target_has_atomic = "8"))]
+core_atomic!(u8, AtomicU8, as_atomic_u8);
+#[cfg_attr(not(atomic_maybe_uninit_no_cfg_target_has_atomic), cfg(target_has_atomic = "16"))]
+core_atomic!(i16, AtomicI16, as_atomic_i16);
+#[cfg_attr(not(atomic_maybe_uninit_no_cfg_target_has_atomic), cfg(target_has_atomic = "16"))]
+core_atomic!(u16, AtomicU16, as_atomic_u16);
+#[cfg_attr(not(atomic_maybe_uninit_no_cfg_target_has_atomic), cfg(target_has_atomic = "32"))]
+core_atomic!(i32, AtomicI32, as_atomic_i32);
+#[cfg_attr(not(atomic_maybe_uninit_no_cfg_target_has_atomic), cfg(target_has_atomic = "32"))]
+core_atomic!(u32, AtomicU32, as_atomic_u32);
+#[cfg_attr(not(atomic_maybe_uninit_no_cfg_target_has_atomic), cfg(target_has_atomic = "64"))]
+core_atomic!(i64, AtomicI64, as_atomic_i64);
+#[cfg_attr(not(atomic_maybe_uninit_no_cfg_target_has_atomic), cfg(target_has_atomic = "64"))]
+core_atomic!(u64, AtomicU64, as_atomic_u64);
+#[cfg_attr(not(atomic_maybe_uninit_no_cfg_target_has_atomic), cfg(target_has_atomic = "ptr"))]
+core_atomic!(isize, AtomicIsize, as_atomic_isize);
+#[cfg_attr(not(atomic_maybe_uninit_no_cfg_target_has_atomic), cfg(target_has_atomic = "ptr"))]
+core_atomic!(usize, AtomicUsize, as_atomic_usize);
+
+// Expands the given items if the conversions for portable-atomic are provided
+// for the given width (see above).
+#[cfg(feature = "portable-atomic")]
+macro_rules! cfg_portable_atomic {
+    ("128", $($item:item)*) => {
+        cfg_native! {
+            { cfg_native_cas_128! { { $($item)* } {} } }
+            {}
+        }
+    };
+    ($width:tt, $($item:item)*) => {
+        cfg_native! {
+            {
+                $(
+                    // cfg(target_has_atomic) requires Rust 1.60.
+                    #[cfg(not(atomic_maybe_uninit_no_cfg_target_has_atomic))]
+                    #[cfg_attr(
+                        not(atomic_maybe_uninit_no_cfg_target_has_atomic),
+                        cfg(target_has_atomic = $width)
+                    )]
+                    $item
+                )*
+            }
+            {}
+        }
+    };
+}
+
+#[cfg(feature = "portable-atomic")]
+macro_rules! portable_atomic {
+    ($width:tt, $cfg_has_atomic:ident, $ty:ident, $atomic:ident, $as_atomic:ident) => {
+        cfg_portable_atomic! {
+            $width,
+            portable_atomic::$cfg_has_atomic! {
+                const _: () = {
+                    use core::mem;
+
+                    use portable_atomic::$atomic;
+
+                    use crate::AtomicMaybeUninit;
+
+                    static_assert!(
+                        mem::size_of::<AtomicMaybeUninit<$ty>>() == mem::size_of::<$atomic>()
+                    );
+                    static_assert!(
+                        mem::align_of::<AtomicMaybeUninit<$ty>>() == mem::align_of::<$atomic>()
+                    );
+                    static_assert!(
+                        <$ty as crate::private::PrimitivePriv>::IS_ALWAYS_LOCK_FREE
+                            && $atomic::is_always_lock_free(),
+                        concat!("cfg_portable_atomic does not match the implementations for ", stringify!($ty))
+                    );
+
+                    impl AtomicMaybeUninit<$ty> {
+                        /// Converts a reference to the atomic integer type of [portable-atomic]
+                        /// with the same size into a reference to `AtomicMaybeUninit`.
+                        ///
+                        /// This is a no-op: both have the same in-memory representation.
+                        ///
+                        /// This is available when the `portable-atomic` feature is enabled, on
+                        /// targets where both this type and the atomic type of portable-atomic
+                        /// are always lock-free.
+                        ///
+                        /// # Safety
+                        ///
+                        /// While the memory is also accessed through the original reference,
+                        /// the [mixed access rules](AtomicMaybeUninit#mixed-access) must be followed.
+                        /// In particular, uninitialized bytes must not be stored through the
+                        /// returned reference while they can be read through the original one.
+                        ///
+                        /// [portable-atomic]: https://github.com/taiki-e/portable-atomic
+                        #[inline]
+                        #[must_use]
+                        pub unsafe fn from_portable_atomic(a: &$atomic) -> &Self {
+                            let a: *const $atomic = a;
+                            // SAFETY: the caller must uphold the safety contract.
+                            // The layouts are the same, as checked above.
+                            unsafe { &*a.cast::<Self>() }
+                        }
+
+                        /// Converts this reference into a reference to the atomic integer type
+                        /// of [portable-atomic] with the same size.
+                        ///
+                        /// This is a no-op: both have the same in-memory representation.
+                        ///
+                        /// This is available when the `portable-atomic` feature is enabled, on
+                        /// targets where both this type and the atomic type of portable-atomic
+                        /// are always lock-free.
+                        ///
+                        /// # Safety
+                        ///
+                        /// The value must be initialized, and while the memory is accessed
+                        /// through the returned reference, the [mixed access rules](AtomicMaybeUninit#mixed-access)
+                        /// must be followed. In particular, uninitialized bytes must not be stored
+                        /// through `self` (or other references to the same memory) while they can
+                        /// be read through the returned reference.
+                        ///
+                        /// [portable-atomic]: https://github.com/taiki-e/portable-atomic
+                        #[inline]
+                        #[must_use]
+                        pub unsafe fn $as_atomic(&self) -> &$atomic {
+                            let this: *const Self = self;
+                            // SAFETY: the caller must uphold the safety contract.
+                            // The layouts are the same, as checked above.
+                            unsafe { &*this.cast::<$atomic>() }
+                        }
+                    }
+                };
+            }
+        }
+    };
+}
+
+#[cfg(feature = "portable-atomic")]
+portable_atomic!("8", cfg_has_atomic_8, i8, AtomicI8, as_portable_atomic_i8);
+#[cfg(feature = "portable-atomic")]
+portable_atomic!("8", cfg_has_atomic_8, u8, AtomicU8, as_portable_atomic_u8);
+#[cfg(feature = "portable-atomic")]
+portable_atomic!("16", cfg_has_atomic_16, i16, AtomicI16, as_portable_atomic_i16);
+#[cfg(feature = "portable-atomic")]
+portable_atomic!("16", cfg_has_atomic_16, u16, AtomicU16, as_portable_atomic_u16);
+#[cfg(feature = "portable-atomic")]
+portable_atomic!("32", cfg_has_atomic_32, i32, AtomicI32, as_portable_atomic_i32);
+#[cfg(feature = "portable-atomic")]
+portable_atomic!("32", cfg_has_atomic_32, u32, AtomicU32, as_portable_atomic_u32);
+#[cfg(feature = "portable-atomic")]
+portable_atomic!("64", cfg_has_atomic_64, i64, AtomicI64, as_portable_atomic_i64);
+#[cfg(feature = "portable-atomic")]
+portable_atomic!("64", cfg_has_atomic_64, u64, AtomicU64, as_portable_atomic_u64);
+#[cfg(feature = "portable-atomic")]
+portable_atomic!("128", cfg_has_atomic_128, i128, AtomicI128, as_portable_atomic_i128);
+#[cfg(feature = "portable-atomic")]
+portable_atomic!("128", cfg_has_atomic_128, u128, AtomicU128, as_portable_atomic_u128);
+#[cfg(feature = "portable-atomic")]
+portable_atomic!("ptr", cfg_has_atomic_ptr, isize, AtomicIsize, as_portable_atomic_isize);
+#[cfg(feature = "portable-atomic")]
+portable_atomic!("ptr", cfg_has_atomic_ptr, usize, AtomicUsize, as_portable_atomic_usize);
@@ -10,18 +10,20 @@ This crate provides a way to soundly perform such operations.
 
 ## Platform Support
 
-Currently, x86, x86_64, ARM, AArch64, RISC-V, LoongArch64, MIPS32, MIPS64, PowerPC, s390x, MSP430, AVR, and Hexagon are supported.
+Currently, x86, x86_64, ARM, AArch64, RISC-V, LoongArch64, MIPS32, MIPS64, PowerPC, s390x, SPARC, SPARC64, M68k, MSP430, AVR, and Hexagon are supported.
 
 | target_arch                      | primitives                                          | load/store | swap/CAS |
 | -------------------------------- | --------------------------------------------------- |:----------:|:--------:|
 | x86                              | isize,usize,i8,u8,i16,u16,i32,u32,i64,u64           | ✓          | ✓        |
 | x86_64                           | isize,usize,i8,u8,i16,u16,i32,u32,i64,u64           | ✓          | ✓        |
 | x86_64 (+cmpxchg16b) \[5]        | i128,u128                                           | ✓          | ✓        |
-| arm (v6+ or Linux/Android)       | isize,usize,i8,u8,i16,u16,i32,u32                   | ✓          | ✓\[1]    |
+| arm                              | isize,usize,i8,u8,i16,u16,i32,u32                   | ✓          | ✓\[1]    |
 | arm (except for M-profile) \[2]  | i64,u64                                             | ✓          | ✓        |
 | aarch64                          | isize,usize,i8,u8,i16,u16,i32,u32,i64,u64,i128,u128 | ✓          | ✓        |
 | riscv32                          | isize,usize,i8,u8,i16,u16,i32,u32                   | ✓          | ✓\[1]    |
 | riscv64                          | isize,usize,i8,u8,i16,u16,i32,u32,i64,u64           | ✓          | ✓\[1]    |
+| riscv32 (+zacas) \[7]            | i64,u64                                             | ✓          | ✓        |
+| riscv64 (+zacas) \[7]            | i128,u128                                           | ✓          | ✓        |
 | loongarch64 \[3]                 | isize,usize,i8,u8,i16,u16,i32,u32,i64,u64           | ✓          | ✓        |
 | mips / mips32r6 \[4]             | isize,usize,i8,u8,i16,u16,i32,u32                   | ✓          | ✓        |
 | mips64 / mips64r6 \[4]           | isize,usize,i8,u8,i16,u16,i32,u32,i64,u64           | ✓          | ✓        |
@@ -29,25 +31,82 @@ Currently, x86, x86_64, ARM, AArch64, RISC-V, LoongArch64, MIPS32, MIPS64, Power
 | powerpc64 \[4]                   | isize,usize,i8,u8,i16,u16,i32,u32,i64,u64           | ✓          | ✓        |
 | powerpc64 (pwr8+) \[4] \[6]      | i128,u128                                           | ✓          | ✓        |
 | s390x \[4]                       | isize,usize,i8,u8,i16,u16,i32,u32,i64,u64,i128,u128 | ✓          | ✓        |
-| msp430 \[4]                      | isize,usize,i8,u8,i16,u16                           | ✓          |          |
-| avr \[4] (experimental)          | isize,usize,i8,u8,i16,u16                           | ✓          |          |
+| sparc \[4]                       | isize,usize,i8,u8,i16,u16,i32,u32                   | ✓          | ✓\[8]    |
+| sparc64 \[4]                     | isize,usize,i8,u8,i16,u16,i32,u32,i64,u64           | ✓          | ✓        |
+| m68k \[4]                        | isize,usize,i8,u8,i16,u16,i32,u32                   | ✓          | ✓\[9]    |
+| m68k (68020+) \[4] \[9]          | i64,u64                                             | ✓          | ✓        |
+| msp430 \[4]                      | isize,usize,i8,u8,i16,u16,i32,u32,i64,u64           | ✓          | ✓        |
+| avr \[4] (experimental)          | isize,usize,i8,u8,i16,u16,i32,u32,i64,u64           | ✓          | ✓        |
 | hexagon \[4] (experimental)      | isize,usize,i8,u8,i16,u16,i32,u32,i64,u64           | ✓          | ✓        |
 
-\[1] ARM's atomic RMW operations are not available on v6-m (thumbv6m). RISC-V's atomic RMW operations are not available on targets without the A (or G which means IMAFD) extension such as riscv32i, riscv32imc, etc.<br>
+\[1] ARM's atomic RMW operations are not available on v6-m (thumbv6m). On pre-v6 ARM except for Linux/Android (e.g., armv4t-none-eabi, armv5te-none-eabi), only 8-bit and 32-bit swap (SWP/SWPB, ARM mode only) are available by default. RISC-V's atomic RMW operations are not available on targets without the A (or G which means IMAFD) extension such as riscv32i, riscv32imc, etc. (See also `atomic_maybe_uninit_unsafe_assume_single_core` cfg and `critical-section` feature below.)<br>
 \[2] ARMv6+ or Linux/Android, except for M-profile architecture such as thumbv6m, thumbv7m, etc.<br>
 \[3] Requires Rust 1.72+.<br>
 \[4] Requires nightly due to `#![feature(asm_experimental_arch)]`.<br>
 \[5] Requires cmpxchg16b target feature (enabled by default on Apple targets).<br>
 \[6] Requires target-cpu pwr8+ (powerpc64le is pwr8 by default).<br>
+\[7] Requires `zacas` target feature.<br>
+\[8] Except for 32-bit swap (SWAP), requires V9 instructions (e.g., sparc-unknown-linux-gnu, `-C target-cpu=v9`) or `leoncasa` target feature (e.g., `-C target-cpu=leon4`, `-C target-cpu=leon3 -C target-feature=+leoncasa`).<br>
+\[9] Requires target-cpu M68020+ (m68k-unknown-linux-gnu is M68020 by default).<br>
+
+On RISC-V Linux/Android, run-time detection of the Zacas and Zabha extensions can be enabled by passing `--cfg atomic_maybe_uninit_outline_atomics` in `RUSTFLAGS`. If the extensions are available at run-time, they are used for swap/CAS instead of LR/SC loop. This requires a rustc whose LLVM supports these extensions (LLVM 20+).
+
+On single-core systems, swap/CAS on ARMv6-M (thumbv6m), pre-v6 ARM (ARM mode only), and RISC-V without the A extension can be enabled by passing `--cfg atomic_maybe_uninit_unsafe_assume_single_core` in `RUSTFLAGS`. They are implemented by disabling interrupts during the operation (PRIMASK on ARMv6-M, IRQ/FIQ mask bits in CPSR on pre-v6 ARM, mstatus.MIE on RISC-V). This cfg is `unsafe`: enabling it on multi-core systems is unsound, and the code must run in privileged mode (machine mode on RISC-V).
+
+When the optional `critical-section` feature is enabled, swap/CAS that the native backend lacks (on ARMv6-M, pre-v6 ARM, RISC-V without the A extension, and x86 without CMPXCHG) are implemented by using the critical section provided by the [critical-section] crate, and MSP430 and AVR use it instead of disabling interrupts. Unlike the above cfg, this is sound on multi-core systems as long as the critical-section implementation is (e.g., RP2040's hardware spinlock). On ARMv6-M, pre-v6 ARM, RISC-V, and x86 without CMPXCHG, stores (and swaps on pre-v6 ARM and x86) are also routed through the critical section. Note that a critical-section implementation must be provided by the user (or a crate such as a HAL); see the [critical-section] crate documentation for details. If both this feature and the above cfg are used, the critical-section implementation takes precedence.
+
+When the optional `fallback` feature is enabled, 64-bit and 128-bit integers that the target doesn't support natively (e.g., i64/u64 on riscv32 without Zacas, mips32, and powerpc, and i128/u128 on x86_64 without cmpxchg16b) are also supported by using a global table of spinlocks (indexed by address) built from 8-bit atomics. **Operations on these types are not lock-free**: a thread holding a lock blocks other threads accessing addresses that map to the same lock, and accessing them from interrupt or signal handlers can deadlock. If the `critical-section` feature is also enabled, the critical section is used instead of the spinlock table. This feature has no effect on targets that lack 8-bit swap or 32-bit CAS (e.g., ARMv6-M without the above cfg or feature).
+
+When the optional `libcalls` feature is enabled, this crate exports `extern "C"` implementations of the `__atomic_{load,store,exchange,compare_exchange}_N` and `__sync_{val,bool}_compare_and_swap_N`/`__sync_lock_{test_and_set,release}_N` functions that GCC and LLVM call for atomic operations the target doesn't support natively, for each width (N = 1, 2, 4, 8, 16) this crate supports on the target. This allows C code on such targets to share the implementations of this crate, including those provided by the `fallback` and `critical-section` features. They are implemented with the `raw` traits, so they are sound on values containing uninitialized bytes. `__atomic_fetch_*`, `__sync_fetch_and_*`, and `__sync_synchronize` are not provided, the pointer must be aligned to the size of the value, and these symbols conflict with those provided by libatomic or compiler-rt. This crate must be linked (e.g., `use atomic_maybe_uninit as _;`) for the symbols to be available.
+
+References to the atomic integer types of the standard library and (with the optional `portable-atomic` feature) [portable-atomic] can be converted to and from references to `AtomicMaybeUninit` of the same size, to access the same memory through both. See the "Mixed access" section of the [`AtomicMaybeUninit`](https://docs.rs/atomic-maybe-uninit/latest/atomic_maybe_uninit/struct.AtomicMaybeUninit.html#mixed-access) documentation for the rules.
+
+Under [Miri], which cannot run inline assembly, all integer types are supported by copying values in a critical section of a spinlock table built from the standard library's atomics. This keeps Miri's data race and uninitialized memory checks working, but these operations are stronger than the specified memory ordering, so Miri cannot detect insufficient ordering on them. Also, CAS compares values byte by byte, and since Miri cannot freeze uninitialized bytes like the native implementations do, it reports reaching an uninitialized byte (e.g., padding of otherwise equal values) as undefined behavior.
+
+On x86, x86_64, and AArch64, when [ThreadSanitizer] is enabled (`-Z sanitizer=thread`), atomic operations tell it about the happens-before edges they create, so synchronization via `AtomicMaybeUninit` does not cause false data race reports.
+
+When [MemorySanitizer] is enabled (`-Z sanitizer=memory`), `AtomicMaybeUninit` copies MemorySanitizer's shadow along with the values it loads and stores, so uninitialized bytes (e.g., padding) in values obtained from `load`, `swap`, and `compare_exchange` are still detected when used. The libcalls exported by the `libcalls` feature do the same, but the functions of the traits in the `raw` module do not, so callers of them need to copy the shadow themselves (e.g., with `__msan_copy_shadow`).
+
+Under `--cfg loom` (passed in `RUSTFLAGS`), the value of `AtomicMaybeUninit` is stored through an atomic of [loom], and each operation is mapped to the operation of that atomic with the specified memory ordering, so that algorithms built on it can be model-checked, including for insufficient memory orderings. Values (including uninitialized bytes) are returned as they were stored, and CAS compares them by using the native implementation, so the same padding behavior is exercised as without loom. Whether `compare_exchange_weak` fails spuriously is decided by loom. loom's atomics cannot be created in `const` contexts, so in this mode `new` and `const_new` are not `const fn`. Also, `AtomicMaybeUninit` does not have the same in-memory representation as the underlying integer type, and accesses through raw pointers (e.g., the `raw` traits) are not tracked by loom.
+
+On other targets (and on any target when `--cfg atomic_maybe_uninit_custom_backend` is passed in `RUSTFLAGS`), atomic operations are delegated to a backend provided by the user instead of failing to build. The backend is an implementation of the `unsafe` trait `backend::Backend` registered with the `set_backend!` macro, or functions following the same symbol protocol (similar to the [critical-section] crate). See the [`backend`](https://docs.rs/atomic-maybe-uninit/latest/atomic_maybe_uninit/backend/index.html) module documentation for details.
 
 Feel free to submit an issue if your target is not supported yet.
 
+## Available operations
+
+The operations available for each type on the current target (with the current cfgs and features) can be queried with the `HAS_LOAD`, `HAS_STORE`, `HAS_SWAP`, and `HAS_CAS` associated constants of `AtomicMaybeUninit`, and whether they are lock-free can be queried with `IS_ALWAYS_LOCK_FREE` and `is_lock_free`. To fail the build if an operation is not available, use the `assert_has_load!`, `assert_has_store!`, `assert_has_swap!`, `assert_has_cas!`, and `assert_is_always_lock_free!` macros (e.g., `atomic_maybe_uninit::assert_has_cas!(u64);`).
+
+To conditionally compile code based on them, use the `cfg_has_atomic_{8,16,32,64,128,ptr}!` and `cfg_no_atomic_{8,16,32,64,128,ptr}!` macros (load/store), the `cfg_has_atomic_swap_{8,16,32,64,128,ptr}!` and `cfg_no_atomic_swap_{8,16,32,64,128,ptr}!` macros (swap), and the `cfg_has_atomic_cas_{8,16,32,64,128,ptr}!` and `cfg_no_atomic_cas_{8,16,32,64,128,ptr}!` macros (swap and CAS). Swap may be available without CAS (e.g., SWP/SWPB on pre-v6 ARM). These macros are defined under the same conditions as the implementations, so they work on all targets without duplicating the target and feature detection of this crate.
+
+```rust
+use std::{mem::MaybeUninit, sync::atomic::Ordering};
+
+use atomic_maybe_uninit::AtomicMaybeUninit;
+
+atomic_maybe_uninit::cfg_has_atomic_cas_64! {
+    fn swap(a: &AtomicMaybeUninit<u64>, v: MaybeUninit<u64>) -> MaybeUninit<u64> {
+        a.swap(v, Ordering::AcqRel)
+    }
+}
+atomic_maybe_uninit::cfg_no_atomic_cas_64! {
+    // Use a lock or another algorithm...
+}
+```
+
+Which instruction sequence (or other mechanism) is used for each operation can be queried at run-time with `implementation_info::<T>()`, which returns a `Debug`-printable description of the strategy selected by the target features, cfgs, crate features, and run-time CPU feature detection (e.g., LSE or LL/SC on AArch64, SSE or `CMPXCHG8B` for 64-bit atomics on x86). This is intended for debugging; the descriptions are not stable.
+
 ## Related Projects
 
 - [portable-atomic]: Portable atomic types including support for 128-bit atomics, atomic float, etc.
 - [atomic-memcpy]: Byte-wise atomic memcpy.
 
 [atomic-memcpy]: https://github.com/taiki-e/atomic-memcpy
+[critical-section]: https://github.com/rust-embedded/critical-section
+[loom]: https://github.com/tokio-rs/loom
+[MemorySanitizer]: https://clang.llvm.org/docs/MemorySanitizer.html
+[Miri]: https://github.com/rust-lang/miri
+[ThreadSanitizer]: https://clang.llvm.org/docs/ThreadSanitizer.html
 [portable-atomic]: https://github.com/taiki-e/portable-atomic
 [undefined-behavior]: https://doc.rust-lang.org/reference/behavior-considered-undefined.html
 
@@ -100,6 +159,7 @@ Feel free to submit an issue if your target is not supported yet.
     any(
         target_arch = "avr",
         target_arch = "hexagon",
+        target_arch = "m68k",
         target_arch = "mips",
         target_arch = "mips32r6",
         target_arch = "mips64",
@@ -108,37 +168,92 @@ Feel free to submit an issue if your target is not supported yet.
         target_arch = "powerpc",
         target_arch = "powerpc64",
         target_arch = "s390x",
+        target_arch = "sparc",
+        target_arch = "sparc64",
     ),
     feature(asm_experimental_arch)
 )]
 
-#[cfg(test)]
+#[cfg(loom)]
+extern crate alloc;
+#[cfg(any(test, loom))]
 extern crate std;
 
 #[macro_use]
 mod utils;
 
+#[macro_use]
+mod cfgs;
+#[cfg(not(loom))]
+#[macro_use]
+mod interop;
+
 #[cfg(test)]
 #[macro_use]
 mod tests;
 
 mod arch;
+#[cfg(feature = "libcalls")]
+mod libcalls;
 
+pub mod backend;
+pub mod info;
 pub mod raw;
 
+#[cfg(not(loom))]
+use core::cell::UnsafeCell;
 #[cfg(doc)]
 use core::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release, SeqCst};
-use core::{cell::UnsafeCell, fmt, mem::MaybeUninit, sync::atomic::Ordering};
+use core::{fmt, mem::MaybeUninit, sync::atomic::Ordering};
 
+#[doc(no_inline)]
+pub use crate::info::implementation_info;
 use crate::raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap, Primitive};
 
 /// A potentially uninitialized integer type which can be safely shared between threads.
 ///
 /// This type has the same in-memory representation as the underlying
-/// integer type, `MaybeUninit<T>`.
+/// integer type, `MaybeUninit<T>` (except under `cfg(loom)`).
+///
+/// # Mixed access
+///
+/// References to the atomic integer types of the standard library (e.g.,
+/// `AtomicU64`) can be converted to and from references to
+/// `AtomicMaybeUninit` of the same size with `from_atomic` and `as_atomic_*`
+/// (e.g., [`AtomicMaybeUninit::<u64>::as_atomic_u64`](Self::as_atomic_u64)),
+/// and references to the atomic integer types of [portable-atomic] with
+/// `from_portable_atomic` and `as_portable_atomic_*` when the optional
+/// `portable-atomic` feature is enabled. This allows accessing a memory
+/// location that may be uninitialized through `AtomicMaybeUninit`, and through
+/// the other type otherwise. These conversions are `unsafe`, and the following
+/// rules must be followed while the memory is accessible through both types:
+///
+/// - The other type assumes the value is initialized: uninitialized bytes stored
+///   through `AtomicMaybeUninit` must not be read through the other type.
+///   Storing an initialized value through either type makes the value
+///   readable through the other one again.
+/// - Accesses through `AtomicMaybeUninit` and through the other type that are
+///   not ordered by happens-before (i.e., concurrent accesses) are only allowed
+///   if both implementations are lock-free, i.e.,
+///   [`IS_ALWAYS_LOCK_FREE`](Self::IS_ALWAYS_LOCK_FREE) is `true` and the
+///   other type's `is_lock_free` returns `true`. Otherwise, one of them may
+///   be implemented using a lock that the other doesn't take (e.g., the
+///   `fallback` feature, portable-atomic's fallback, or Miri), and such
+///   accesses are data races. For this reason, the conversions for
+///   portable-atomic's types are only provided on targets where both types
+///   are always lock-free.
+///
+/// These conversions are not available under `cfg(loom)`, and the conversions
+/// for the standard library's types require Rust 1.60+.
+///
+/// [portable-atomic]: https://github.com/taiki-e/portable-atomic
 #[repr(C)]
 pub struct AtomicMaybeUninit<T: Primitive> {
+    #[cfg(not(loom))]
     v: UnsafeCell<MaybeUninit<T>>,
+    /// The value, stored through a loom atomic.
+    #[cfg(loom)]
+    v: arch::loom::Cell<T>,
     /// `[T::Align; 0]` ensures alignment is at least that of `T::Align`.
     ///
     /// This is needed because x86's u64 is 4-byte aligned and x86_64's u128 is
@@ -178,11 +293,66 @@ unsafe impl<T: Primitive> Sync for AtomicMaybeUninit<T> {}
 impl<T: Primitive> core::panic::RefUnwindSafe for AtomicMaybeUninit<T> {}
 
 impl<T: Primitive> AtomicMaybeUninit<T> {
+    /// `true` if [`load`](Self::load) is available for this type on the current target.
+    ///
+    /// See also [`assert_has_load!`].
+    pub const HAS_LOAD: bool = <T as private::PrimitivePriv>::HAS_LOAD;
+    /// `true` if [`store`](Self::store) is available for this type on the current target.
+    ///
+    /// See also [`assert_has_store!`].
+    pub const HAS_STORE: bool = <T as private::PrimitivePriv>::HAS_STORE;
+    /// `true` if [`swap`](Self::swap) is available for this type on the current target.
+    ///
+    /// See also [`assert_has_swap!`].
+    pub const HAS_SWAP: bool = <T as private::PrimitivePriv>::HAS_SWAP;
+    /// `true` if [`compare_exchange`](Self::compare_exchange),
+    /// [`compare_exchange_weak`](Self::compare_exchange_weak), and
+    /// [`fetch_update`](Self::fetch_update) are available for this type on the
+    /// current target.
+    ///
+    /// See also [`assert_has_cas!`].
+    pub const HAS_CAS: bool = <T as private::PrimitivePriv>::HAS_CAS;
+    /// `true` if all available operations on this type are lock-free.
+    ///
+    /// This is `false` if this type is implemented using locks: the `fallback`
+    /// feature, the `critical-section` feature (its critical section may be a
+    /// lock), a user-provided backend (see the [`backend`] module), Miri, and
+    /// loom. Disabling interrupts (`--cfg atomic_maybe_uninit_unsafe_assume_single_core`)
+    /// is considered lock-free.
+    ///
+    /// This is also `false` if no operations are available for this type.
+    ///
+    /// See also [`is_lock_free`](Self::is_lock_free) and [`assert_is_always_lock_free!`].
+    pub const IS_ALWAYS_LOCK_FREE: bool = <T as private::PrimitivePriv>::IS_ALWAYS_LOCK_FREE;
+
+    /// Returns `true` if all available operations on this type are lock-free.
+    ///
+    /// Unlike [`IS_ALWAYS_LOCK_FREE`](Self::IS_ALWAYS_LOCK_FREE), this may
+    /// account for the CPU features detected at run-time. Currently, run-time
+    /// detection (e.g., Zacas/Zabha on RISC-V Linux with
+    /// `--cfg atomic_maybe_uninit_outline_atomics`) only chooses between
+    /// lock-free instruction sequences, so this always returns the same value
+    /// as `IS_ALWAYS_LOCK_FREE`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use atomic_maybe_uninit::AtomicMaybeUninit;
+    ///
+    /// let lock_free = AtomicMaybeUninit::<u64>::is_lock_free();
+    /// assert!(lock_free || !AtomicMaybeUninit::<u64>::IS_ALWAYS_LOCK_FREE);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn is_lock_free() -> bool {
+        Self::IS_ALWAYS_LOCK_FREE
+    }
+
     const_fn! {
-        const_if: #[cfg(not(atomic_maybe_uninit_no_const_fn_trait_bound))];
+        const_if: #[cfg(not(any(loom, atomic_maybe_uninit_no_const_fn_trait_bound)))];
         /// Creates a new atomic value from a potentially uninitialized integer.
         ///
-        /// This is `const fn` on Rust 1.61+. See also `const_new` function.
+        /// This is `const fn` on Rust 1.61+ (except under `cfg(loom)`). See also `const_new` function.
         ///
         /// # Examples
         ///
@@ -199,7 +369,13 @@ impl<T: Primitive> AtomicMaybeUninit<T> {
         #[inline]
         #[must_use]
         pub const fn new(v: MaybeUninit<T>) -> Self {
-            Self { v: UnsafeCell::new(v), _align: [] }
+            Self {
+                #[cfg(not(loom))]
+                v: UnsafeCell::new(v),
+                #[cfg(loom)]
+                v: arch::loom::Cell::new(v),
+                _align: [],
+            }
         }
     }
 
@@ -269,11 +445,22 @@ impl<T: Primitive> AtomicMaybeUninit<T> {
         T: AtomicLoad,
     {
         utils::assert_load_ordering(order);
-        let mut out = MaybeUninit::<T>::uninit();
-        // SAFETY: any data races are prevented by atomic intrinsics, the raw
-        // pointer passed in is valid because we got it from a reference,
-        // and we've checked the order is valid.
-        unsafe { T::atomic_load(self.v.get(), &mut out, order) }
+        #[cfg(loom)]
+        let out = self.v.load(order);
+        #[cfg(not(loom))]
+        let out = {
+            let mut out = MaybeUninit::<T>::uninit();
+            // SAFETY: any data races are prevented by atomic intrinsics, the raw
+            // pointer passed in is valid because we got it from a reference,
+            // and we've checked the order is valid.
+            unsafe { T::atomic_load(self.v.get(), &mut out, order) }
+            #[cfg(atomic_maybe_uninit_sanitize_memory)]
+            // SAFETY: both pointers are valid because we got them from references.
+            unsafe {
+                arch::msan::copy_shadow(&mut out, self.v.get());
+            }
+            out
+        };
         out
     }
 
@@ -304,10 +491,20 @@ impl<T: Primitive> AtomicMaybeUninit<T> {
         T: AtomicStore,
     {
         utils::assert_store_ordering(order);
-        // SAFETY: any data races are prevented by atomic intrinsics, the raw
-        // pointer passed in is valid because we got it from a reference,
-        // and we've checked the order is valid.
-        unsafe { T::atomic_store(self.v.get(), &val, order) }
+        #[cfg(loom)]
+        self.v.store(val, order);
+        #[cfg(not(loom))]
+        {
+            // SAFETY: any data races are prevented by atomic intrinsics, the raw
+            // pointer passed in is valid because we got it from a reference,
+            // and we've checked the order is valid.
+            unsafe { T::atomic_store(self.v.get(), &val, order) }
+            #[cfg(atomic_maybe_uninit_sanitize_memory)]
+            // SAFETY: both pointers are valid because we got them from references.
+            unsafe {
+                arch::msan::copy_shadow(self.v.get(), &val);
+            }
+        }
     }
 
     /// Stores a value into the atomic integer, returning the previous value.
@@ -335,10 +532,25 @@ impl<T: Primitive> AtomicMaybeUninit<T> {
     where
         T: AtomicSwap,
     {
-        let mut out = MaybeUninit::<T>::uninit();
-        // SAFETY: any data races are prevented by atomic intrinsics and the raw
-        // pointer passed in is valid because we got it from a reference.
-        unsafe { T::atomic_swap(self.v.get(), &val, &mut out, order) }
+        #[cfg(loom)]
+        let out = self.v.swap(val, order);
+        #[cfg(not(loom))]
+        let out = {
+            let mut out = MaybeUninit::<T>::uninit();
+            #[cfg(atomic_maybe_uninit_sanitize_memory)]
+            // SAFETY: the pointer is valid because we got it from a reference.
+            let prev_shadow = unsafe { arch::msan::save_shadow(self.v.get()) };
+            // SAFETY: any data races are prevented by atomic intrinsics and the raw
+            // pointer passed in is valid because we got it from a reference.
+            unsafe { T::atomic_swap(self.v.get(), &val, &mut out, order) }
+            #[cfg(atomic_maybe_uninit_sanitize_memory)]
+            // SAFETY: all pointers are valid because we got them from references.
+            unsafe {
+                arch::msan::copy_shadow(&mut out, &prev_shadow);
+                arch::msan::copy_shadow(self.v.get(), &val);
+            }
+            out
+        };
         out
     }
 
@@ -399,7 +611,7 @@ impl<T: Primitive> AtomicMaybeUninit<T> {
     /// To work around this problem, you need to use a helper like the following.
     ///
     /// ```
-    /// # if cfg!(valgrind) { return; }
+    /// # if cfg!(any(valgrind, miri)) { return; }
     /// # use std::{
     /// #     mem::{self, MaybeUninit},
     /// #     sync::atomic::Ordering,
@@ -500,17 +712,34 @@ impl<T: Primitive> AtomicMaybeUninit<T> {
         T: AtomicCompareExchange,
     {
         utils::assert_compare_exchange_ordering(success, failure);
-        let mut out = MaybeUninit::<T>::uninit();
-        // SAFETY: any data races are prevented by atomic intrinsics and the raw
-        // pointer passed in is valid because we got it from a reference.
-        let res = unsafe {
-            T::atomic_compare_exchange(self.v.get(), &current, &new, &mut out, success, failure)
+        #[cfg(loom)]
+        let res = self.v.compare_exchange(current, new, success, failure);
+        #[cfg(not(loom))]
+        let res = {
+            let mut out = MaybeUninit::<T>::uninit();
+            #[cfg(atomic_maybe_uninit_sanitize_memory)]
+            // SAFETY: the pointer is valid because we got it from a reference.
+            let prev_shadow = unsafe { arch::msan::save_shadow(self.v.get()) };
+            // SAFETY: any data races are prevented by atomic intrinsics and the raw
+            // pointer passed in is valid because we got it from a reference.
+            let res = unsafe {
+                T::atomic_compare_exchange(self.v.get(), &current, &new, &mut out, success, failure)
+            };
+            #[cfg(atomic_maybe_uninit_sanitize_memory)]
+            // SAFETY: all pointers are valid because we got them from references.
+            unsafe {
+                arch::msan::copy_shadow(&mut out, &prev_shadow);
+                if res {
+                    arch::msan::copy_shadow(self.v.get(), &new);
+                }
+            }
+            if res {
+                Ok(out)
+            } else {
+                Err(out)
+            }
         };
-        if res {
-            Ok(out)
-        } else {
-            Err(out)
-        }
+        res
     }
 
     /// Stores a value into the atomic integer if the current value is the same as
@@ -579,24 +808,41 @@ impl<T: Primitive> AtomicMaybeUninit<T> {
         T: AtomicCompareExchange,
     {
         utils::assert_compare_exchange_ordering(success, failure);
-        let mut out = MaybeUninit::<T>::uninit();
-        // SAFETY: any data races are prevented by atomic intrinsics and the raw
-        // pointer passed in is valid because we got it from a reference.
-        let res = unsafe {
-            T::atomic_compare_exchange_weak(
-                self.v.get(),
-                &current,
-                &new,
-                &mut out,
-                success,
-                failure,
-            )
+        #[cfg(loom)]
+        let res = self.v.compare_exchange_weak(current, new, success, failure);
+        #[cfg(not(loom))]
+        let res = {
+            let mut out = MaybeUninit::<T>::uninit();
+            #[cfg(atomic_maybe_uninit_sanitize_memory)]
+            // SAFETY: the pointer is valid because we got it from a reference.
+            let prev_shadow = unsafe { arch::msan::save_shadow(self.v.get()) };
+            // SAFETY: any data races are prevented by atomic intrinsics and the raw
+            // pointer passed in is valid because we got it from a reference.
+            let res = unsafe {
+                T::atomic_compare_exchange_weak(
+                    self.v.get(),
+                    &current,
+                    &new,
+                    &mut out,
+                    success,
+                    failure,
+                )
+            };
+            #[cfg(atomic_maybe_uninit_sanitize_memory)]
+            // SAFETY: all pointers are valid because we got them from references.
+            unsafe {
+                arch::msan::copy_shadow(&mut out, &prev_shadow);
+                if res {
+                    arch::msan::copy_shadow(self.v.get(), &new);
+                }
+            }
+            if res {
+                Ok(out)
+            } else {
+                Err(out)
+            }
         };
-        if res {
-            Ok(out)
-        } else {
-            Err(out)
-        }
+        res
     }
 
     /// Fetches the value, and applies a function to it that returns an optional
@@ -681,19 +927,39 @@ macro_rules! int {
         impl crate::raw::Primitive for $ty {}
         impl crate::private::PrimitivePriv for $ty {
             type Align = crate::private::$align;
+            const HAS_LOAD: bool = impls!($ty: crate::raw::AtomicLoad);
+            const HAS_STORE: bool = impls!($ty: crate::raw::AtomicStore);
+            const HAS_SWAP: bool = impls!($ty: crate::raw::AtomicSwap);
+            const HAS_CAS: bool = impls!($ty: crate::raw::AtomicCompareExchange);
+            const IS_ALWAYS_LOCK_FREE: bool =
+                Self::HAS_LOAD && !impls!($ty: crate::arch::NotLockFree) && !cfg!(loom);
+            const IS_FALLBACK: bool = impls!($ty: crate::arch::Fallback);
         }
         impl AtomicMaybeUninit<$ty> {
-            /// Creates a new atomic value from a potentially uninitialized integer.
-            /// Unlike [`new`](Self::new), this is always `const fn`.
-            #[inline]
-            #[must_use]
-            pub const fn const_new(v: MaybeUninit<$ty>) -> Self {
-                Self { v: UnsafeCell::new(v), _align: [] }
+            const_fn! {
+                const_if: #[cfg(not(loom))];
+                /// Creates a new atomic value from a potentially uninitialized integer.
+                /// Unlike [`new`](Self::new), this is always `const fn` (except under `cfg(loom)`).
+                #[inline]
+                #[must_use]
+                pub const fn const_new(v: MaybeUninit<$ty>) -> Self {
+                    Self {
+                        #[cfg(not(loom))]
+                        v: UnsafeCell::new(v),
+                        #[cfg(loom)]
+                        v: arch::loom::Cell::new(v),
+                        _align: [],
+                    }
+                }
             }
         }
+        // Under cfg(loom), the value is stored in a table referenced by a loom
+        // atomic (see arch/loom.rs), so the layout is not the same.
+        #[cfg(not(loom))]
         static_assert!(
             core::mem::size_of::<AtomicMaybeUninit<$ty>>() == core::mem::size_of::<$ty>()
         );
+        #[cfg(not(loom))]
         static_assert!(
             core::mem::align_of::<AtomicMaybeUninit<$ty>>() == core::mem::size_of::<$ty>()
         );
@@ -712,6 +978,121 @@ int!(u128, Align16);
 int!(isize, AlignPtr);
 int!(usize, AlignPtr);
 
+/// Asserts at compile time that `load` is available for the given type on the current target.
+///
+/// See [`AtomicMaybeUninit::HAS_LOAD`] for details.
+///
+/// # Examples
+///
+/// ```
+/// atomic_maybe_uninit::assert_has_load!(u32);
+/// ```
+#[macro_export]
+macro_rules! assert_has_load {
+    ($ty:ty) => {
+        const _: () = assert!(
+            $crate::AtomicMaybeUninit::<$ty>::HAS_LOAD,
+            concat!(
+                "AtomicMaybeUninit<",
+                stringify!($ty),
+                "> does not support load on this target"
+            ),
+        );
+    };
+}
+
+/// Asserts at compile time that `store` is available for the given type on the current target.
+///
+/// See [`AtomicMaybeUninit::HAS_STORE`] for details.
+///
+/// # Examples
+///
+/// ```
+/// atomic_maybe_uninit::assert_has_store!(u32);
+/// ```
+#[macro_export]
+macro_rules! assert_has_store {
+    ($ty:ty) => {
+        const _: () = assert!(
+            $crate::AtomicMaybeUninit::<$ty>::HAS_STORE,
+            concat!(
+                "AtomicMaybeUninit<",
+                stringify!($ty),
+                "> does not support store on this target"
+            ),
+        );
+    };
+}
+
+/// Asserts at compile time that `swap` is available for the given type on the current target.
+///
+/// See [`AtomicMaybeUninit::HAS_SWAP`] for details.
+///
+/// # Examples
+///
+/// ```
+/// atomic_maybe_uninit::assert_has_swap!(u32);
+/// ```
+#[macro_export]
+macro_rules! assert_has_swap {
+    ($ty:ty) => {
+        const _: () = assert!(
+            $crate::AtomicMaybeUninit::<$ty>::HAS_SWAP,
+            concat!(
+                "AtomicMaybeUninit<",
+                stringify!($ty),
+                "> does not support swap on this target"
+            ),
+        );
+    };
+}
+
+/// Asserts at compile time that `compare_exchange` is available for the given type on the current target.
+///
+/// See [`AtomicMaybeUninit::HAS_CAS`] for details.
+///
+/// # Examples
+///
+/// ```
+/// atomic_maybe_uninit::assert_has_cas!(u32);
+/// ```
+#[macro_export]
+macro_rules! assert_has_cas {
+    ($ty:ty) => {
+        const _: () = assert!(
+            $crate::AtomicMaybeUninit::<$ty>::HAS_CAS,
+            concat!(
+                "AtomicMaybeUninit<",
+                stringify!($ty),
+                "> does not support compare_exchange on this target"
+            ),
+        );
+    };
+}
+
+/// Asserts at compile time that operations on the given type are always lock-free on the current target.
+///
+/// See [`AtomicMaybeUninit::IS_ALWAYS_LOCK_FREE`] for details.
+///
+/// # Examples
+///
+/// ```
+/// atomic_maybe_uninit::assert_is_always_lock_free!(u32);
+/// ```
+#[macro_export]
+macro_rules! assert_is_always_lock_free {
+    ($ty:ty) => {
+        const _: () = assert!(
+            $crate::AtomicMaybeUninit::<$ty>::IS_ALWAYS_LOCK_FREE,
+            concat!(
+                "AtomicMaybeUninit<",
+                stringify!($ty),
+                "> is not always lock-free on this target"
+            ),
+        );
+    };
+}
+
 mod private {
     use core::panic::{RefUnwindSafe, UnwindSafe};
 
@@ -719,6 +1100,14 @@ mod private {
     pub trait PrimitivePriv: Copy + Send + Sync + Unpin + UnwindSafe + RefUnwindSafe {
         // See _align field of AtomicMaybeUninit.
         type Align: Send + Sync + Unpin + UnwindSafe + RefUnwindSafe;
+        // See AtomicMaybeUninit::HAS_* and AtomicMaybeUninit::IS_ALWAYS_LOCK_FREE.
+        const HAS_LOAD: bool;
+        const HAS_STORE: bool;
+        const HAS_SWAP: bool;
+        const HAS_CAS: bool;
+        const IS_ALWAYS_LOCK_FREE: bool;
+        // See implementation_info.
+        const IS_FALLBACK: bool;
     }
 
     #[allow(missing_debug_implementations)]
@@ -0,0 +1,407 @@
+// Export of the GCC/LLVM atomic libcalls.
+//
+// When the `libcalls` feature is enabled, this exports `extern "C"`
+// implementations of the `__atomic_*_N` and `__sync_*_N` functions that GCC
+// and LLVM call when the target lacks native instructions for an atomic
+// operation (e.g., 64-bit atomics on riscv32 with the `fallback` feature, or
+// swap/CAS on ARMv6-M with the `critical-section` feature), so that C code
+// and Rust code using the standard library's atomics can share them with this
+// crate. Functions are exported only for widths this crate supports on the
+// target (see the "Available operations" section in the crate-level
+// documentation).
+//
+// They are implemented by the raw traits, so they are sound on values that
+// contain uninitialized bytes, like the other operations of this crate. When
+// MemorySanitizer is enabled, they also propagate the shadow of the values
+// like the methods of AtomicMaybeUninit (see arch/msan.rs).
+// `__atomic_fetch_*`/`__sync_fetch_and_*` are not exported because they
+// require arithmetic on the value, and `__sync_synchronize` is not exported
+// because LLVM may lower fences in our implementations to it.
+//
+// Invalid memory orders (which are UB in C) are mapped to the strongest
+// ordering valid for the operation instead of panicking.
+//
+// Refs:
+// - https://gcc.gnu.org/wiki/Atomic/GCCMM/LIbrary
+// - https://llvm.org/docs/Atomics.html#libcalls-atomic
+// - https://gcc.gnu.org/onlinedocs/gcc/_005f_005fsync-Builtins.html
+
+// u128 is passed in the same way as __int128 on the targets that support
+// 128-bit atomics.
+#![allow(improper_ctypes_definitions)]
+
+use core::{mem::MaybeUninit, sync::atomic::Ordering};
+
+use crate::raw::{AtomicCompareExchange, AtomicLoad, AtomicStore, AtomicSwap};
+
+// core::ffi::c_int requires Rust 1.64.
+#[cfg(target_pointer_width = "16")]
+type CInt = i16;
+#[cfg(not(target_pointer_width = "16"))]
+type CInt = i32;
+
+// __ATOMIC_* constants.
+const RELAXED: CInt = 0;
+const CONSUME: CInt = 1;
+const ACQUIRE: CInt = 2;
+const RELEASE: CInt = 3;
+const ACQ_REL: CInt = 4;
+
+#[inline]
+fn load_order(order: CInt) -> Ordering {
+    match order {
+        RELAXED => Ordering::Relaxed,
+        CONSUME | ACQUIRE => Ordering::Acquire,
+        _ => Ordering::SeqCst,
+    }
+}
+#[inline]
+fn store_order(order: CInt) -> Ordering {
+    match order {
+        RELAXED => Ordering::Relaxed,
+        RELEASE => Ordering::Release,
+        _ => Ordering::SeqCst,
+    }
+}
+#[inline]
+fn rmw_order(order: CInt) -> Ordering {
+    match order {
+        RELAXED => Ordering::Relaxed,
+        CONSUME | ACQUIRE => Ordering::Acquire,
+        RELEASE => Ordering::Release,
+        ACQ_REL => Ordering::AcqRel,
+        _ => Ordering::SeqCst,
+    }
+}
+// The failure ordering is the load part of the operation.
+#[inline]
+fn failure_order(order: CInt) -> Ordering {
+    match order {
+        RELAXED | RELEASE => Ordering::Relaxed,
+        CONSUME | ACQUIRE | ACQ_REL => Ordering::Acquire,
+        _ => Ordering::SeqCst,
+    }
+}
+
+#[inline]
+unsafe fn load<T: AtomicLoad>(src: *const MaybeUninit<T>, order: Ordering) -> MaybeUninit<T> {
+    let mut out = MaybeUninit::<MaybeUninit<T>>::uninit();
+    // SAFETY: the caller must uphold the safety contract.
+    // `out` is valid for writes.
+    unsafe {
+        T::atomic_load(src, out.as_mut_ptr(), order);
+        #[cfg(atomic_maybe_uninit_sanitize_memory)]
+        crate::arch::msan::copy_shadow(out.as_mut_ptr(), src);
+        out.assume_init()
+    }
+}
+#[inline]
+unsafe fn store<T: AtomicStore>(dst: *mut MaybeUninit<T>, val: MaybeUninit<T>, order: Ordering) {
+    // SAFETY: the caller must uphold the safety contract.
+    unsafe {
+        T::atomic_store(dst, &val, order);
+        #[cfg(atomic_maybe_uninit_sanitize_memory)]
+        crate::arch::msan::copy_shadow(dst, &val);
+    }
+}
+#[inline]
+unsafe fn swap<T: AtomicSwap>(
+    dst: *mut MaybeUninit<T>,
+    val: MaybeUninit<T>,
+    order: Ordering,
+) -> MaybeUninit<T> {
+    let mut out = MaybeUninit::<MaybeUninit<T>>::uninit();
+    // SAFETY: the caller must uphold the safety contract.
+    // `out` is valid for writes.
+    unsafe {
+        #[cfg(atomic_maybe_uninit_sanitize_memory)]
+        let prev_shadow = crate::arch::msan::save_shadow(dst);
+        T::atomic_swap(dst, &val, out.as_mut_ptr(), order);
+        #[cfg(atomic_maybe_uninit_sanitize_memory)]
+        {
+            crate::arch::msan::copy_shadow(out.as_mut_ptr(), &prev_shadow);
+            crate::arch::msan::copy_shadow(dst, &val);
+        }
+        out.assume_init()
+    }
+}
+#[inline]
+unsafe fn compare_exchange<T: AtomicCompareExchange>(
+    dst: *mut MaybeUninit<T>,
+    current: MaybeUninit<T>,
+    new: MaybeUninit<T>,
+    success: Ordering,
+    failure: Ordering,
+) -> (MaybeUninit<T>, bool) {
+    let mut out = MaybeUninit::<MaybeUninit<T>>::uninit();
+    // SAFETY: the caller must uphold the safety contract.
+    // `out` is valid for writes.
+    unsafe {
+        #[cfg(atomic_maybe_uninit_sanitize_memory)]
+        let prev_shadow = crate::arch::msan::save_shadow(dst);
+        let r = T::atomic_compare_exchange(dst, &current, &new, out.as_mut_ptr(), success, failure);
+        #[cfg(atomic_maybe_uninit_sanitize_memory)]
+        {
+            crate::arch::msan::copy_shadow(out.as_mut_ptr(), &prev_shadow);
+            if r {
+                crate::arch::msan::copy_shadow(dst, &new);
+            }
+        }
+        (out.assume_init(), r)
+    }
+}
+
+macro_rules! load_store {
+    ($int_type:ident, $load:ident, $store:ident, $lock_release:ident) => {
+        #[no_mangle]
+        unsafe extern "C" fn $load(
+            src: *const MaybeUninit<$int_type>,
+            order: CInt,
+        ) -> MaybeUninit<$int_type> {
+            // SAFETY: the caller must uphold the safety contract.
+            unsafe { load(src, load_order(order)) }
+        }
+        #[no_mangle]
+        unsafe extern "C" fn $store(
+            dst: *mut MaybeUninit<$int_type>,
+            val: MaybeUninit<$int_type>,
+            order: CInt,
+        ) {
+            // SAFETY: the caller must uphold the safety contract.
+            unsafe { store(dst, val, store_order(order)) }
+        }
+        #[no_mangle]
+        unsafe extern "C" fn $lock_release(dst: *mut MaybeUninit<$int_type>) {
+            // SAFETY: the caller must uphold the safety contract.
+            unsafe { store(dst, MaybeUninit::new(0), Ordering::Release) }
+        }
+    };
+}
+
+macro_rules! swap_cas {
+    (
+        $int_type:ident,
+        $exchange:ident,
+        $compare_exchange:ident,
+        $val_compare_and_swap:ident,
+        $bool_compare_and_swap:ident,
+        $lock_test_and_set:ident
+    ) => {
+        #[no_mangle]
+        unsafe extern "C" fn $exchange(
+            dst: *mut MaybeUninit<$int_type>,
+            val: MaybeUninit<$int_type>,
+            order: CInt,
+        ) -> MaybeUninit<$int_type> {
+            // SAFETY: the caller must uphold the safety contract.
+            unsafe { swap(dst, val, rmw_order(order)) }
+        }
+        // Unlike the __atomic_compare_exchange_n builtin, the libcall doesn't
+        // take the `weak` argument.
+        #[no_mangle]
+        unsafe extern "C" fn $compare_exchange(
+            dst: *mut MaybeUninit<$int_type>,
+            expected: *mut MaybeUninit<$int_type>,
+            desired: MaybeUninit<$int_type>,
+            success: CInt,
+            failure: CInt,
+        ) -> bool {
+            // SAFETY: the caller must uphold the safety contract.
+            unsafe {
+                let (out, r) = compare_exchange(
+                    dst,
+                    expected.read(),
+                    desired,
+                    rmw_order(success),
+                    failure_order(failure),
+                );
+                if !r {
+                    expected.write(out);
+                }
+                r
+            }
+        }
+        #[no_mangle]
+        unsafe extern "C" fn $val_compare_and_swap(
+            dst: *mut MaybeUninit<$int_type>,
+            old: MaybeUninit<$int_type>,
+            new: MaybeUninit<$int_type>,
+        ) -> MaybeUninit<$int_type> {
+            // SAFETY: the caller must uphold the safety contract.
+            unsafe { compare_exchange(dst, old, new, Ordering::SeqCst, Ordering::SeqCst).0 }
+        }
+        #[no_mangle]
+        unsafe extern "C" fn $bool_compare_and_swap(
+            dst: *mut MaybeUninit<$int_type>,
+            old: MaybeUninit<$int_type>,
+            new: MaybeUninit<$int_type>,
+        ) -> bool {
+            // SAFETY: the caller must uphold the safety contract.
+            unsafe { compare_exchange(dst, old, new, Ordering::SeqCst, Ordering::SeqCst).1 }
+        }
+        #[no_mangle]
+        unsafe extern "C" fn $lock_test_and_set(
+            dst: *mut MaybeUninit<$int_type>,
+            val: MaybeUninit<$int_type>,
+        ) -> MaybeUninit<$int_type> {
+            // SAFETY: the caller must uphold the safety contract.
+            unsafe { swap(dst, val, Ordering::Acquire) }
+        }
+    };
+}
+
+load_store!(u8, __atomic_load_1, __atomic_store_1, __sync_lock_release_1);
+load_store!(u16, __atomic_load_2, __atomic_store_2, __sync_lock_release_2);
+load_store!(u32, __atomic_load_4, __atomic_store_4, __sync_lock_release_4);
+cfg_64! {
+    {
+        load_store!(u64, __atomic_load_8, __atomic_store_8, __sync_lock_release_8);
+    }
+    {}
+}
+cfg_128! {
+    {
+        load_store!(u128, __atomic_load_16, __atomic_store_16, __sync_lock_release_16);
+    }
+    {}
+}
+cfg_cas_8_16_32! {
+    {
+        swap_cas!(
+            u8,
+            __atomic_exchange_1,
+            __atomic_compare_exchange_1,
+            __sync_val_compare_and_swap_1,
+            __sync_bool_compare_and_swap_1,
+            __sync_lock_test_and_set_1
+        );
+        swap_cas!(
+            u16,
+            __atomic_exchange_2,
+            __atomic_compare_exchange_2,
+            __sync_val_compare_and_swap_2,
+            __sync_bool_compare_and_swap_2,
+            __sync_lock_test_and_set_2
+        );
+        swap_cas!(
+            u32,
+            __atomic_exchange_4,
+            __atomic_compare_exchange_4,
+            __sync_val_compare_and_swap_4,
+            __sync_bool_compare_and_swap_4,
+            __sync_lock_test_and_set_4
+        );
+    }
+    {}
+}
+cfg_cas_64! {
+    {
+        swap_cas!(
+            u64,
+            __atomic_exchange_8,
+            __atomic_compare_exchange_8,
+            __sync_val_compare_and_swap_8,
+            __sync_bool_compare_and_swap_8,
+            __sync_lock_test_and_set_8
+        );
+    }
+    {}
+}
+cfg_cas_128! {
+    {
+        swap_cas!(
+            u128,
+            __atomic_exchange_16,
+            __atomic_compare_exchange_16,
+            __sync_val_compare_and_swap_16,
+            __sync_bool_compare_and_swap_16,
+            __sync_lock_test_and_set_16
+        );
+    }
+    {}
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{mem::MaybeUninit, sync::atomic::Ordering};
+
+    use super::{failure_order, load_order, rmw_order, store_order, CInt, ACQ_REL};
+
+    // Call through the exported symbols.
+    extern "C" {
+        fn __atomic_load_4(src: *const MaybeUninit<u32>, order: CInt) -> MaybeUninit<u32>;
+        fn __atomic_store_4(dst: *mut MaybeUninit<u32>, val: MaybeUninit<u32>, order: CInt);
+        fn __atomic_exchange_4(
+            dst: *mut MaybeUninit<u32>,
+            val: MaybeUninit<u32>,
+            order: CInt,
+        ) -> MaybeUninit<u32>;
+        fn __atomic_compare_exchange_4(
+            dst: *mut MaybeUninit<u32>,
+            expected: *mut MaybeUninit<u32>,
+            desired: MaybeUninit<u32>,
+            success: CInt,
+            failure: CInt,
+        ) -> bool;
+        fn __sync_val_compare_and_swap_4(
+            dst: *mut MaybeUninit<u32>,
+            old: MaybeUninit<u32>,
+            new: MaybeUninit<u32>,
+        ) -> MaybeUninit<u32>;
+        fn __sync_bool_compare_and_swap_4(
+            dst: *mut MaybeUninit<u32>,
+            old: MaybeUninit<u32>,
+            new: MaybeUninit<u32>,
+        ) -> bool;
+        fn __sync_lock_test_and_set_4(
+            dst: *mut MaybeUninit<u32>,
+            val: MaybeUninit<u32>,
+        ) -> MaybeUninit<u32>;
+        fn __sync_lock_release_4(dst: *mut MaybeUninit<u32>);
+    }
+
+    #[test]
+    fn orders() {
+        // Every memory order maps to a valid ordering for the operation.
+        for order in -1..7 {
+            assert_ne!(load_order(order), Ordering::Release);
+            assert_ne!(load_order(order), Ordering::AcqRel);
+            assert_ne!(store_order(order), Ordering::Acquire);
+            assert_ne!(store_order(order), Ordering::AcqRel);
+            assert_ne!(failure_order(order), Ordering::Release);
+            assert_ne!(failure_order(order), Ordering::AcqRel);
+        }
+        assert_eq!(rmw_order(ACQ_REL), Ordering::AcqRel);
+        assert_eq!(failure_order(ACQ_REL), Ordering::Acquire);
+    }
+
+    #[test]
+    fn symbols_4() {
+        // SAFETY: `v` and `expected` are valid and aligned.
+        unsafe {
+            let mut v = MaybeUninit::new(1_u32);
+            assert_eq!(__atomic_load_4(&v, 5).assume_init(), 1);
+            __atomic_store_4(&mut v, MaybeUninit::new(2), 3);
+            assert_eq!(__atomic_exchange_4(&mut v, MaybeUninit::new(3), 4).assume_init(), 2);
+            let mut expected = MaybeUninit::new(0);
+            assert!(!__atomic_compare_exchange_4(&mut v, &mut expected, MaybeUninit::new(4), 5, 5));
+            assert_eq!(expected.assume_init(), 3);
+            assert!(__atomic_compare_exchange_4(&mut v, &mut expected, MaybeUninit::new(4), 5, 2));
+            assert_eq!(expected.assume_init(), 3);
+            let prev =
+                __sync_val_compare_and_swap_4(&mut v, MaybeUninit::new(4), MaybeUninit::new(5));
+            assert_eq!(prev.assume_init(), 4);
+            assert!(!__sync_bool_compare_and_swap_4(
+                &mut v,
+                MaybeUninit::new(4),
+                MaybeUninit::new(6)
+            ));
+            assert_eq!(__sync_lock_test_and_set_4(&mut v, MaybeUninit::new(1)).assume_init(), 5);
+            __sync_lock_release_4(&mut v);
+            assert_eq!(v.assume_init(), 0);
+            // Uninitialized bytes are passed through as is.
+            __atomic_store_4(&mut v, MaybeUninit::uninit(), 0);
+            let _ = __atomic_load_4(&v, 0);
+        }
+    }
+}
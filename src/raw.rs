@@ -1,4 +1,10 @@
 //! Low level API.
+//!
+//! Unlike the methods of [`AtomicMaybeUninit`](crate::AtomicMaybeUninit), the
+//! functions of these traits do not copy the shadow of MemorySanitizer along
+//! with the values, because the values are accessed only by inline assembly,
+//! which MemorySanitizer cannot see. When MemorySanitizer is enabled, callers
+//! need to copy the shadow themselves (e.g., with `__msan_copy_shadow`).
 
 #[cfg(doc)]
 use core::{
@@ -59,6 +59,10 @@ macro_rules! __test_atomic {
 
         use crate::{tests::helper::*, AtomicMaybeUninit};
 
+        crate::assert_has_load!($int_type);
+        crate::assert_has_store!($int_type);
+
+        #[cfg(not(loom))] // const_new is not const fn under loom
         #[test]
         fn load_store() {
             static VAR: AtomicMaybeUninit<$int_type> =
@@ -152,6 +156,7 @@ macro_rules! __test_atomic {
         }
     };
     (swap, $int_type:ident) => {
+        crate::assert_has_swap!($int_type);
         #[cfg(not(all(valgrind, target_arch = "aarch64")))] // TODO: flaky
         #[test]
         fn swap() {
@@ -271,6 +276,7 @@ macro_rules! __test_atomic {
         }
     };
     (cas, $int_type:ident) => {
+        crate::assert_has_cas!($int_type);
         #[test]
         fn compare_exchange() {
             unsafe {
@@ -305,28 +311,31 @@ macro_rules! __test_atomic {
                     );
                     assert_eq!(a.load(Ordering::Relaxed).assume_init(), 10);
 
-                    if !cfg!(valgrind) {
-                        let mut u = MaybeUninit::uninit();
-                        let a = AtomicMaybeUninit::<$int_type>::new(u);
+                    let mut u = MaybeUninit::uninit();
+                    let a = AtomicMaybeUninit::<$int_type>::new(u);
+                    // Valgrind and Miri report comparisons of uninitialized bytes.
+                    if cfg!(any(valgrind, miri)) {
+                        a.store(MaybeUninit::new(10), Ordering::Relaxed);
+                    } else {
                         while let Err(e) =
                             a.compare_exchange(u, MaybeUninit::new(10), success, failure)
                         {
                             u = e;
                         }
-                        assert_eq!(a.load(Ordering::Relaxed).assume_init(), 10);
-                        assert_eq!(
-                            a.compare_exchange(
-                                MaybeUninit::new(10),
-                                MaybeUninit::uninit(),
-                                success,
-                                failure
-                            )
-                            .unwrap()
-                            .assume_init(),
-                            10
-                        );
-                        let _v = a.load(Ordering::Relaxed);
                     }
+                    assert_eq!(a.load(Ordering::Relaxed).assume_init(), 10);
+                    assert_eq!(
+                        a.compare_exchange(
+                            MaybeUninit::new(10),
+                            MaybeUninit::uninit(),
+                            success,
+                            failure
+                        )
+                        .unwrap()
+                        .assume_init(),
+                        10
+                    );
+                    let _v = a.load(Ordering::Relaxed);
                 }
             }
         }
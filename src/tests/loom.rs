@@ -0,0 +1,110 @@
+use std::{mem::MaybeUninit, sync::atomic::Ordering};
+
+use loom::{cell::UnsafeCell, sync::Arc, thread};
+
+use crate::AtomicMaybeUninit;
+
+#[test]
+fn message_passing() {
+    loom::model(|| {
+        let data = Arc::new((UnsafeCell::new(0_i32), AtomicMaybeUninit::<u8>::from(0)));
+        let th = {
+            let data = data.clone();
+            thread::spawn(move || {
+                data.0.with_mut(|v| unsafe { *v = 1 });
+                data.1.store(MaybeUninit::new(1), Ordering::Release);
+            })
+        };
+        if unsafe { data.1.load(Ordering::Acquire).assume_init() } == 1 {
+            data.0.with(|v| assert_eq!(unsafe { *v }, 1));
+        }
+        th.join().unwrap();
+    });
+}
+
+#[test]
+fn fetch_update() {
+    loom::model(|| {
+        let a = Arc::new(AtomicMaybeUninit::<u32>::from(0));
+        let th = {
+            let a = a.clone();
+            thread::spawn(move || {
+                a.fetch_update(Ordering::AcqRel, Ordering::Acquire, |v| {
+                    Some(MaybeUninit::new(unsafe { v.assume_init() } + 1))
+                })
+                .unwrap();
+            })
+        };
+        a.fetch_update(Ordering::AcqRel, Ordering::Acquire, |v| {
+            Some(MaybeUninit::new(unsafe { v.assume_init() } + 1))
+        })
+        .unwrap();
+        th.join().unwrap();
+        assert_eq!(unsafe { a.load(Ordering::Relaxed).assume_init() }, 2);
+    });
+}
+
+#[test]
+#[should_panic(expected = "Causality violation")]
+fn message_passing_relaxed() {
+    loom::model(|| {
+        let data = Arc::new((UnsafeCell::new(0_i32), AtomicMaybeUninit::<u8>::from(0)));
+        let th = {
+            let data = data.clone();
+            thread::spawn(move || {
+                data.0.with_mut(|v| unsafe { *v = 1 });
+                data.1.store(MaybeUninit::new(1), Ordering::Relaxed);
+            })
+        };
+        if unsafe { data.1.load(Ordering::Relaxed).assume_init() } == 1 {
+            data.0.with(|v| assert_eq!(unsafe { *v }, 1));
+        }
+        th.join().unwrap();
+    });
+}
+
+#[test]
+fn compare_exchange_weak() {
+    loom::model(|| {
+        let a = AtomicMaybeUninit::<u64>::from(0);
+        while a
+            .compare_exchange_weak(
+                MaybeUninit::new(0),
+                MaybeUninit::new(1),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_err()
+        {}
+        assert_eq!(unsafe { a.load(Ordering::Relaxed).assume_init() }, 1);
+    });
+}
+
+#[test]
+fn uninit() {
+    loom::model(|| {
+        let a = Arc::new(AtomicMaybeUninit::<u32>::new(MaybeUninit::uninit()));
+        let th = {
+            let a = a.clone();
+            thread::spawn(move || {
+                let _v = a.swap(MaybeUninit::new(1), Ordering::AcqRel);
+            })
+        };
+        let _v = a.load(Ordering::Acquire);
+        th.join().unwrap();
+        assert_eq!(
+            unsafe {
+                a.compare_exchange(
+                    MaybeUninit::new(1),
+                    MaybeUninit::uninit(),
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                )
+                .unwrap()
+                .assume_init()
+            },
+            1
+        );
+        let _v = a.load(Ordering::Relaxed);
+    });
+}
@@ -9,6 +9,9 @@
 #[macro_use]
 pub(crate) mod helper;
 
+#[cfg(loom)]
+mod loom;
+
 macro_rules! test_common {
     ($int_type:ident) => {
         paste::paste! {
@@ -52,6 +55,139 @@ macro_rules! test_common {
                         assert_eq!(a.into_inner().assume_init(), b.into_inner().assume_init());
                     }
                 }
+                #[test]
+                fn capabilities() {
+                    type A = AtomicMaybeUninit<$int_type>;
+                    // swap and CAS require load and store.
+                    assert!(!A::HAS_SWAP || A::HAS_LOAD && A::HAS_STORE);
+                    assert!(!A::HAS_CAS || A::HAS_LOAD && A::HAS_STORE);
+                    assert!(!A::IS_ALWAYS_LOCK_FREE || A::HAS_LOAD);
+                    assert_eq!(A::is_lock_free(), A::IS_ALWAYS_LOCK_FREE);
+                    #[cfg(target_arch = "x86_64")]
+                    #[cfg(not(any(
+                        miri,
+                        loom,
+                        atomic_maybe_uninit_custom_backend,
+                        atomic_maybe_uninit_test_critical_section,
+                    )))]
+                    {
+                        let native = core::mem::size_of::<$int_type>() <= 8
+                            || cfg!(any(
+                                target_feature = "cmpxchg16b",
+                                atomic_maybe_uninit_target_feature = "cmpxchg16b",
+                            ));
+                        assert_eq!(A::HAS_CAS, native || cfg!(feature = "fallback"));
+                        assert_eq!(A::IS_ALWAYS_LOCK_FREE, native);
+                    }
+                    #[cfg(miri)]
+                    assert!(A::HAS_CAS);
+                    #[cfg(any(miri, loom))]
+                    assert!(!A::IS_ALWAYS_LOCK_FREE);
+                }
+                #[test]
+                fn implementation_info() {
+                    type A = AtomicMaybeUninit<$int_type>;
+                    let info = crate::implementation_info::<$int_type>();
+                    assert_eq!(info.load.is_some(), A::HAS_LOAD);
+                    assert_eq!(info.store.is_some(), A::HAS_STORE);
+                    assert_eq!(info.swap.is_some(), A::HAS_SWAP);
+                    assert_eq!(info.compare_exchange.is_some(), A::HAS_CAS);
+                    assert_eq!(info.compare_exchange_weak.is_some(), A::HAS_CAS);
+                    assert_eq!(info.is_always_lock_free, A::IS_ALWAYS_LOCK_FREE);
+                    assert!(std::format!("{:?}", info).starts_with("ImplementationInfo {"));
+                    #[cfg(target_arch = "x86_64")]
+                    #[cfg(not(any(
+                        miri,
+                        atomic_maybe_uninit_custom_backend,
+                        atomic_maybe_uninit_test_critical_section,
+                    )))]
+                    {
+                        let cas = info.compare_exchange.map(|s| s.description);
+                        if core::mem::size_of::<$int_type>() <= 8 {
+                            assert_eq!(cas, Some("LOCK CMPXCHG"));
+                        } else if cfg!(any(
+                            target_feature = "cmpxchg16b",
+                            atomic_maybe_uninit_target_feature = "cmpxchg16b",
+                        )) {
+                            assert_eq!(cas, Some("LOCK CMPXCHG16B"));
+                        } else if cfg!(feature = "fallback") {
+                            assert!(cas.unwrap().ends_with("(fallback)"));
+                        }
+                        assert!(info.load.iter().all(|s| !s.runtime_detected));
+                    }
+                    #[cfg(miri)]
+                    assert_eq!(info.load.unwrap().description, "spinlock table (Miri)");
+                    #[cfg(target_arch = "x86_64")]
+                    #[cfg(atomic_maybe_uninit_test_critical_section)]
+                    #[cfg(not(any(miri, atomic_maybe_uninit_custom_backend)))]
+                    if core::mem::size_of::<$int_type>() <= 8 {
+                        assert!(!A::IS_ALWAYS_LOCK_FREE);
+                        assert_eq!(info.load.unwrap().description, "MOV");
+                        assert_eq!(info.store.unwrap().description, "critical section");
+                        assert_eq!(info.swap.unwrap().description, "critical section");
+                        assert_eq!(info.compare_exchange.unwrap().description, "critical section");
+                    }
+                }
+            }
+        }
+    };
+}
+
+// See interop.rs.
+#[cfg(not(loom))]
+macro_rules! test_interop {
+    // The standard library's 128-bit atomic types are unstable.
+    ($int_type:ident, "128") => {
+        paste::paste! {
+            mod [<test_interop_ $int_type>] {
+                test_interop!(@portable_atomic $int_type, "128");
+            }
+        }
+    };
+    ($int_type:ident, $width:tt) => {
+        paste::paste! {
+            #[allow(clippy::undocumented_unsafe_blocks)]
+            mod [<test_interop_ $int_type>] {
+                #[cfg(not(atomic_maybe_uninit_no_cfg_target_has_atomic))]
+                #[test]
+                fn core_atomic() {
+                    use std::{mem::MaybeUninit, sync::atomic::Ordering};
+
+                    use crate::AtomicMaybeUninit;
+
+                    unsafe {
+                        let a = std::sync::atomic::[<Atomic $int_type:camel>]::new(1);
+                        let v = AtomicMaybeUninit::<$int_type>::from_atomic(&a);
+                        assert_eq!(v.swap(MaybeUninit::new(2), Ordering::Relaxed).assume_init(), 1);
+                        assert_eq!(a.fetch_add(1, Ordering::Relaxed), 2);
+                        assert_eq!(v.load(Ordering::Relaxed).assume_init(), 3);
+                        assert!(core::ptr::eq(v.[<as_atomic_ $int_type>](), &a));
+                    }
+                }
+                test_interop!(@portable_atomic $int_type, $width);
+            }
+        }
+    };
+    (@portable_atomic $int_type:ident, $width:tt) => {
+        #[cfg(feature = "portable-atomic")]
+        cfg_portable_atomic! {
+            $width,
+            paste::paste! {
+                #[allow(clippy::undocumented_unsafe_blocks)]
+                #[test]
+                fn portable_atomic() {
+                    use std::{mem::MaybeUninit, sync::atomic::Ordering};
+
+                    use crate::AtomicMaybeUninit;
+
+                    unsafe {
+                        let a = portable_atomic::[<Atomic $int_type:camel>]::new(1);
+                        let v = AtomicMaybeUninit::<$int_type>::from_portable_atomic(&a);
+                        assert_eq!(v.swap(MaybeUninit::new(2), Ordering::Relaxed).assume_init(), 1);
+                        assert_eq!(a.load(Ordering::Relaxed), 2);
+                        assert!(core::ptr::eq(v.[<as_portable_atomic_ $int_type>](), &a));
+                    }
+                }
             }
         }
     };
@@ -69,3 +205,28 @@ test_common!(i64);
 test_common!(u64);
 test_common!(i128);
 test_common!(u128);
+
+#[cfg(not(loom))]
+test_interop!(isize, "ptr");
+#[cfg(not(loom))]
+test_interop!(usize, "ptr");
+#[cfg(not(loom))]
+test_interop!(i8, "8");
+#[cfg(not(loom))]
+test_interop!(u8, "8");
+#[cfg(not(loom))]
+test_interop!(i16, "16");
+#[cfg(not(loom))]
+test_interop!(u16, "16");
+#[cfg(not(loom))]
+test_interop!(i32, "32");
+#[cfg(not(loom))]
+test_interop!(u32, "32");
+#[cfg(not(loom))]
+test_interop!(i64, "64");
+#[cfg(not(loom))]
+test_interop!(u64, "64");
+#[cfg(not(loom))]
+test_interop!(i128, "128");
+#[cfg(not(loom))]
+test_interop!(u128, "128");
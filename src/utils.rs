@@ -8,12 +8,33 @@ macro_rules! static_assert {
     ($cond:expr $(, $($msg:tt)*)?) => {
         const _: () = {
             if !$cond {
-                panic!($($msg)*)
+                panic!($($($msg)*)?)
             }
         };
     };
 }
 
+/// Returns `true` if the given type implements the given trait.
+///
+/// This works in const contexts: the inherent associated const of `Wrapper<T>`
+/// exists only if `T` implements the trait, and takes precedence over the
+/// trait's associated const of `DoesNotImpl`.
+macro_rules! impls {
+    ($ty:ty: $($trait:tt)+) => {{
+        #[allow(dead_code)]
+        trait DoesNotImpl {
+            const IMPLS: bool = false;
+        }
+        impl<T: ?Sized> DoesNotImpl for T {}
+        struct Wrapper<T: ?Sized>(core::marker::PhantomData<T>);
+        #[allow(dead_code)]
+        impl<T: ?Sized + $($trait)+> Wrapper<T> {
+            const IMPLS: bool = true;
+        }
+        <Wrapper<$ty>>::IMPLS
+    }};
+}
+
 /// Make the given function const if the given condition is true.
 macro_rules! const_fn {
     (
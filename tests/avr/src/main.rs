@@ -39,7 +39,6 @@ macro_rules! __test_atomic {
         }
         swap();
         fn swap() {
-            #[cfg(target_has_atomic = "ptr")]
             unsafe {
                 for order in swap_orderings() {
                     let a = AtomicMaybeUninit::<$int_type>::new(MaybeUninit::new(5));
@@ -54,7 +53,6 @@ macro_rules! __test_atomic {
         }
         compare_exchange();
         fn compare_exchange() {
-            #[cfg(target_has_atomic = "ptr")]
             unsafe {
                 for (success, failure) in compare_exchange_orderings() {
                     let a = AtomicMaybeUninit::<$int_type>::new(MaybeUninit::new(5));
@@ -87,7 +85,6 @@ macro_rules! __test_atomic {
         }
         compare_exchange_weak();
         fn compare_exchange_weak() {
-            #[cfg(target_has_atomic = "ptr")]
             unsafe {
                 for (success, failure) in compare_exchange_orderings() {
                     let a = AtomicMaybeUninit::<$int_type>::new(MaybeUninit::new(4));
@@ -116,7 +113,6 @@ macro_rules! __test_atomic {
         }
         fetch_update();
         fn fetch_update() {
-            #[cfg(target_has_atomic = "ptr")]
             unsafe {
                 for (success, failure) in compare_exchange_orderings() {
                     let a = AtomicMaybeUninit::<$int_type>::new(MaybeUninit::new(7));
@@ -183,6 +179,10 @@ fn main() -> ! {
     test_atomic!(u8);
     test_atomic!(i16);
     test_atomic!(u16);
+    test_atomic!(i32);
+    test_atomic!(u32);
+    test_atomic!(i64);
+    test_atomic!(u64);
 
     semihosting::exit(0)
 }
@@ -193,11 +193,9 @@ fn load_orderings() -> [Ordering; 3] {
 fn store_orderings() -> [Ordering; 3] {
     [Ordering::Relaxed, Ordering::Release, Ordering::SeqCst]
 }
-#[cfg(target_has_atomic = "ptr")]
 fn swap_orderings() -> [Ordering; 5] {
     [Ordering::Relaxed, Ordering::Release, Ordering::Acquire, Ordering::AcqRel, Ordering::SeqCst]
 }
-#[cfg(target_has_atomic = "ptr")]
 fn compare_exchange_orderings() -> [(Ordering, Ordering); 15] {
     [
         (Ordering::Relaxed, Ordering::Relaxed),
@@ -0,0 +1,206 @@
+// Test runtime for LEON3 on QEMU (`qemu-system-sparc -M leon3_generic`).
+//
+// semihosting-no-std-test-rt is used on other targets, but semihosting is not
+// available on SPARC, so this provides the equivalent: the output is written
+// to the APBUART, and the test exits by trapping with traps disabled, which
+// makes the CPU enter error mode. QEMU shuts down (with exit status 0) if the
+// trap is `ta 0`, and aborts (with a non-zero exit status) otherwise.
+//
+// Refs:
+// - The SPARC Architecture Manual, Version 8
+//   https://sparc.org/wp-content/uploads/2014/01/v8.pdf.gz
+// - GRLIB IP Core User's Manual (LEON3, APBUART)
+//   https://www.gaisler.com/products/grlib/grip.pdf
+
+use core::{
+    arch::{asm, global_asm},
+    fmt,
+};
+
+macro_rules! print {
+    ($($tt:tt)*) => {
+        <$crate::leon3::Uart as core::fmt::Write>::write_fmt(
+            &mut $crate::leon3::Uart,
+            format_args!($($tt)*),
+        )
+        .unwrap()
+    };
+}
+macro_rules! println {
+    ($($tt:tt)*) => {{
+        print!($($tt)*);
+        print!("\n");
+    }};
+}
+
+// APBUART registers of leon3_generic.
+const UART_DATA: *mut u32 = 0x8000_0100 as *mut u32;
+const UART_STATUS: *const u32 = 0x8000_0104 as *const u32;
+const UART_CTRL: *mut u32 = 0x8000_0108 as *mut u32;
+const UART_STATUS_TE: u32 = 1 << 2; // transmitter FIFO empty
+const UART_CTRL_TE: u32 = 1 << 1; // transmitter enable
+
+pub(crate) struct Uart;
+impl fmt::Write for Uart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for b in s.bytes() {
+            // SAFETY: UART_STATUS and UART_DATA are valid MMIO registers.
+            unsafe {
+                while UART_STATUS.read_volatile() & UART_STATUS_TE == 0 {}
+                UART_DATA.write_volatile(u32::from(b));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn exit(code: u32) -> ! {
+    // SAFETY: reading PSR, disabling traps, and trapping are fine in supervisor mode.
+    unsafe {
+        let psr: u32;
+        asm!("rd %psr, {}", out(reg) psr, options(nomem, nostack, preserves_flags));
+        asm!(
+            "wr {psr}, %g0, %psr",
+            "nop",
+            "nop",
+            "nop",
+            "cmp {code}, 0",
+            "bne 2f",
+            "nop", // (delay slot)
+            "ta 0",
+            "2:",
+            "ta 1",
+            psr = in(reg) psr & !0x20, // clear ET
+            code = in(reg) code,
+            options(noreturn, nostack),
+        )
+    }
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo<'_>) -> ! {
+    println!("{}", info);
+    exit(1)
+}
+
+#[no_mangle]
+extern "C" fn __leon3_rust_start() -> ! {
+    // SAFETY: UART_CTRL is a valid MMIO register.
+    unsafe { UART_CTRL.write_volatile(UART_CTRL.read_volatile() | UART_CTRL_TE) }
+    crate::run();
+    exit(0)
+}
+
+// The register window handlers assume 8 register windows, which is the number
+// of windows of leon3_generic.
+global_asm!(
+    ".section .text._start, \"ax\", @progbits",
+    ".global _start",
+    "_start:",
+    "set __trap_table, %g1",
+    "wr %g1, %g0, %tbr",
+    // Supervisor mode, traps enabled, all interrupts masked, FPU enabled, CWP = 0.
+    "set 0x1fe0, %g1",
+    "wr %g1, %g0, %psr",
+    // Window 1 is the one SAVE reaches after the others are in use.
+    "wr %g0, 2, %wim",
+    "nop",
+    "nop",
+    "nop",
+    // Zero .bss.
+    "set __bss_start, %g1",
+    "set __bss_end, %g2",
+    "2:",
+    "cmp %g1, %g2",
+    "bgeu 3f",
+    "nop", // (delay slot)
+    "st %g0, [%g1]",
+    "ba 2b",
+    "add %g1, 4, %g1", // (delay slot)
+    "3:",
+    // Leave room for the register save area and the outgoing arguments.
+    "set __stack_top, %sp",
+    "sub %sp, 96, %sp",
+    "mov %g0, %fp",
+    "call __leon3_rust_start",
+    "nop", // (delay slot)
+    "ta 1",
+    //
+    ".section .text.__trap_table, \"ax\", @progbits",
+    ".balign 4096",
+    "__trap_table:",
+    // 0x00-0x04: unexpected
+    ".rept 5",
+    "ta 1",
+    "nop",
+    "nop",
+    "nop",
+    ".endr",
+    // 0x05: window_overflow
+    "sethi %hi(__window_overflow), %l0",
+    "jmp %l0 + %lo(__window_overflow)",
+    "nop",
+    "nop",
+    // 0x06: window_underflow
+    "sethi %hi(__window_underflow), %l0",
+    "jmp %l0 + %lo(__window_underflow)",
+    "nop",
+    "nop",
+    // 0x07-0xFF: unexpected (trapping with traps disabled enters error mode)
+    ".rept 249",
+    "ta 1",
+    "nop",
+    "nop",
+    "nop",
+    ".endr",
+    //
+    // Save the window that SAVE would overwrite to its stack and rotate WIM right.
+    "__window_overflow:",
+    "rd %wim, %l3",
+    "mov %g1, %l7",
+    "srl %l3, 1, %g1",
+    "sll %l3, 7, %l4",
+    "or %l4, %g1, %g1",
+    "save %g0, %g0, %g0",
+    "wr %g1, %g0, %wim",
+    "nop",
+    "nop",
+    "nop",
+    "std %l0, [%sp + 0]",
+    "std %l2, [%sp + 8]",
+    "std %l4, [%sp + 16]",
+    "std %l6, [%sp + 24]",
+    "std %i0, [%sp + 32]",
+    "std %i2, [%sp + 40]",
+    "std %i4, [%sp + 48]",
+    "std %i6, [%sp + 56]",
+    "restore %g0, %g0, %g0",
+    "mov %l7, %g1",
+    "jmp %l1", // re-execute SAVE
+    "rett %l2",
+    //
+    // Restore the window that RESTORE would return to from its stack and rotate WIM left.
+    "__window_underflow:",
+    "rd %wim, %l3",
+    "sll %l3, 1, %l4",
+    "srl %l3, 7, %l5",
+    "or %l5, %l4, %l5",
+    "wr %l5, %g0, %wim",
+    "nop",
+    "nop",
+    "nop",
+    "restore %g0, %g0, %g0",
+    "restore %g0, %g0, %g0",
+    "ldd [%sp + 0], %l0",
+    "ldd [%sp + 8], %l2",
+    "ldd [%sp + 16], %l4",
+    "ldd [%sp + 24], %l6",
+    "ldd [%sp + 32], %i0",
+    "ldd [%sp + 40], %i2",
+    "ldd [%sp + 48], %i4",
+    "ldd [%sp + 56], %i6",
+    "save %g0, %g0, %g0",
+    "save %g0, %g0, %g0",
+    "jmp %l1", // re-execute RESTORE
+    "rett %l2",
+);
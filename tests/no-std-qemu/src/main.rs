@@ -1,14 +1,23 @@
 #![no_main]
 #![no_std]
 #![warn(rust_2018_idioms, single_use_lifetimes, unsafe_op_in_unsafe_fn)]
+#![cfg_attr(target_arch = "sparc", feature(asm_experimental_arch))]
+
+// Semihosting is not available on SPARC.
+#[cfg(target_arch = "sparc")]
+#[macro_use]
+mod leon3;
 
 use core::{mem::MaybeUninit, sync::atomic::Ordering};
 
 use atomic_maybe_uninit::*;
+#[cfg(not(target_arch = "sparc"))]
 use semihosting::{print, println};
 
+// Note that 32-bit SPARC without CASA (e.g., LEON3) has target_has_atomic = "ptr"
+// but doesn't have CAS, so the CAS tests are gated on leoncasa there.
 macro_rules! __test_atomic {
-    ($int_type:ident) => {
+    ($int_type:ident, $swap_cfg:meta) => {
         load_store();
         fn load_store() {
             unsafe {
@@ -38,7 +47,7 @@ macro_rules! __test_atomic {
         }
         swap();
         fn swap() {
-            #[cfg(target_has_atomic = "ptr")]
+            #[cfg($swap_cfg)]
             unsafe {
                 for order in swap_orderings() {
                     let a = AtomicMaybeUninit::<$int_type>::new(MaybeUninit::new(5));
@@ -53,7 +62,13 @@ macro_rules! __test_atomic {
         }
         compare_exchange();
         fn compare_exchange() {
-            #[cfg(target_has_atomic = "ptr")]
+            #[cfg(any(
+                all(
+                    target_has_atomic = "ptr",
+                    any(not(target_arch = "sparc"), target_feature = "leoncasa")
+                ),
+                atomic_maybe_uninit_unsafe_assume_single_core
+            ))]
             unsafe {
                 for (success, failure) in compare_exchange_orderings() {
                     let a = AtomicMaybeUninit::<$int_type>::new(MaybeUninit::new(5));
@@ -86,7 +101,13 @@ macro_rules! __test_atomic {
         }
         compare_exchange_weak();
         fn compare_exchange_weak() {
-            #[cfg(target_has_atomic = "ptr")]
+            #[cfg(any(
+                all(
+                    target_has_atomic = "ptr",
+                    any(not(target_arch = "sparc"), target_feature = "leoncasa")
+                ),
+                atomic_maybe_uninit_unsafe_assume_single_core
+            ))]
             unsafe {
                 for (success, failure) in compare_exchange_orderings() {
                     let a = AtomicMaybeUninit::<$int_type>::new(MaybeUninit::new(4));
@@ -115,7 +136,13 @@ macro_rules! __test_atomic {
         }
         fetch_update();
         fn fetch_update() {
-            #[cfg(target_has_atomic = "ptr")]
+            #[cfg(any(
+                all(
+                    target_has_atomic = "ptr",
+                    any(not(target_arch = "sparc"), target_feature = "leoncasa")
+                ),
+                atomic_maybe_uninit_unsafe_assume_single_core
+            ))]
             unsafe {
                 for (success, failure) in compare_exchange_orderings() {
                     let a = AtomicMaybeUninit::<$int_type>::new(MaybeUninit::new(7));
@@ -146,13 +173,65 @@ macro_rules! __test_atomic {
     };
 }
 
+#[cfg(not(target_arch = "sparc"))]
 semihosting_no_std_test_rt::entry!(run);
 fn run() {
     macro_rules! test_atomic {
         ($int_type:ident) => {
+            test_atomic!(
+                $int_type,
+                any(
+                    all(
+                        target_has_atomic = "ptr",
+                        any(not(target_arch = "sparc"), target_feature = "leoncasa")
+                    ),
+                    atomic_maybe_uninit_unsafe_assume_single_core
+                )
+            );
+        };
+        // Types that SWPB supports: swap is also available on pre-v6 ARM in ARM mode
+        // without atomic_maybe_uninit_unsafe_assume_single_core.
+        ($int_type:ident, swpb) => {
+            test_atomic!(
+                $int_type,
+                any(
+                    all(
+                        target_has_atomic = "ptr",
+                        any(not(target_arch = "sparc"), target_feature = "leoncasa")
+                    ),
+                    atomic_maybe_uninit_unsafe_assume_single_core,
+                    all(
+                        target_arch = "arm",
+                        not(target_feature = "v6"),
+                        not(target_feature = "thumb-mode"),
+                    ),
+                )
+            );
+        };
+        // Types that SWP and SWAP support: swap is also available on pre-v6 ARM in ARM mode
+        // without atomic_maybe_uninit_unsafe_assume_single_core and on 32-bit SPARC without CASA.
+        ($int_type:ident, swp) => {
+            test_atomic!(
+                $int_type,
+                any(
+                    all(
+                        target_has_atomic = "ptr",
+                        any(not(target_arch = "sparc"), target_feature = "leoncasa")
+                    ),
+                    atomic_maybe_uninit_unsafe_assume_single_core,
+                    all(
+                        target_arch = "arm",
+                        not(target_feature = "v6"),
+                        not(target_feature = "thumb-mode"),
+                    ),
+                    target_arch = "sparc",
+                )
+            );
+        };
+        ($int_type:ident, $swap_cfg:meta) => {
             paste::paste! {
                 fn [<test_atomic_ $int_type>]() {
-                    __test_atomic!($int_type);
+                    __test_atomic!($int_type, $swap_cfg);
                 }
                 print!("test test_atomic_{} ... ", stringify!($int_type));
                 [<test_atomic_ $int_type>]();
@@ -161,14 +240,14 @@ fn run() {
         };
     }
 
-    test_atomic!(isize);
-    test_atomic!(usize);
-    test_atomic!(i8);
-    test_atomic!(u8);
+    test_atomic!(isize, swp);
+    test_atomic!(usize, swp);
+    test_atomic!(i8, swpb);
+    test_atomic!(u8, swpb);
     test_atomic!(i16);
     test_atomic!(u16);
-    test_atomic!(i32);
-    test_atomic!(u32);
+    test_atomic!(i32, swp);
+    test_atomic!(u32, swp);
 }
 
 fn load_orderings() -> [Ordering; 3] {
@@ -177,11 +256,18 @@ fn load_orderings() -> [Ordering; 3] {
 fn store_orderings() -> [Ordering; 3] {
     [Ordering::Relaxed, Ordering::Release, Ordering::SeqCst]
 }
-#[cfg(target_has_atomic = "ptr")]
+#[cfg(any(
+    target_has_atomic = "ptr",
+    atomic_maybe_uninit_unsafe_assume_single_core,
+    all(target_arch = "arm", not(target_feature = "v6"), not(target_feature = "thumb-mode")),
+))]
 fn swap_orderings() -> [Ordering; 5] {
     [Ordering::Relaxed, Ordering::Release, Ordering::Acquire, Ordering::AcqRel, Ordering::SeqCst]
 }
-#[cfg(target_has_atomic = "ptr")]
+#[cfg(any(
+    all(target_has_atomic = "ptr", any(not(target_arch = "sparc"), target_feature = "leoncasa")),
+    atomic_maybe_uninit_unsafe_assume_single_core
+))]
 fn compare_exchange_orderings() -> [(Ordering, Ordering); 15] {
     [
         (Ordering::Relaxed, Ordering::Relaxed),
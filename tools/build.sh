@@ -42,9 +42,13 @@ default_targets=(
     # arm
     # rustc --print target-list | grep -E '^(arm|thumb)'
     # armv4t
+    armv4t-none-eabi
     armv4t-unknown-linux-gnueabi
+    thumbv4t-none-eabi
     # armv5te
+    armv5te-none-eabi
     armv5te-unknown-linux-gnueabi
+    thumbv5te-none-eabi
     # armv6
     arm-unknown-linux-gnueabi
     arm-unknown-linux-gnueabihf
@@ -116,6 +120,12 @@ default_targets=(
     # rustc --print target-list | grep -E '^s390'
     s390x-unknown-linux-gnu
 
+    # sparc
+    # rustc --print target-list | grep -E '^sparc'
+    sparc-unknown-linux-gnu
+    sparc-unknown-none-elf
+    sparc64-unknown-linux-gnu
+
     # msp430
     # rustc --print target-list | grep -E '^msp430'
     msp430-none-elf
@@ -127,6 +137,10 @@ default_targets=(
     # hexagon
     # rustc --print target-list | grep -E '^hexagon'
     hexagon-unknown-linux-musl
+
+    # m68k
+    # rustc --print target-list | grep -E '^m68k'
+    m68k-unknown-linux-gnu
 )
 known_cfgs=()
 
@@ -177,6 +191,8 @@ if [[ "${rustc_version}" == *"nightly"* ]] || [[ "${rustc_version}" == *"dev"* ]
         build_scripts=(build.rs)
         check_cfg='-Z unstable-options --check-cfg=values(target_pointer_width,"128") --check-cfg=values(target_arch,"xtensa","mips32r6","mips64r6") --check-cfg=values(feature,"cargo-clippy")'
         known_cfgs+=($(grep -E 'cargo:rustc-cfg=' "${build_scripts[@]}" | sed -E 's/^.*cargo:rustc-cfg=//; s/(=\\)?".*$//' | LC_ALL=C sort -u))
+        # cfgs set by users
+        known_cfgs+=(atomic_maybe_uninit_custom_backend atomic_maybe_uninit_outline_atomics atomic_maybe_uninit_unsafe_assume_single_core atomic_maybe_uninit_test_critical_section loom)
         # TODO: handle multi-line target_feature_if
         known_target_feature_values+=($(grep -E 'target_feature_if\("' "${build_scripts[@]}" | sed -E 's/^.*target_feature_if\(//; s/",.*$/"/' | LC_ALL=C sort -u))
         check_cfg+=" --check-cfg=values(atomic_maybe_uninit_target_feature,\"a\",$(IFS=',' && echo "${known_target_feature_values[*]}"))"
@@ -212,7 +228,7 @@ build() {
     fi
     if [[ -z "${nightly}" ]]; then
         case "${target}" in
-            avr* | hexagon* | mips* | msp430* | powerpc* | s390*)
+            avr* | hexagon* | m68k* | mips* | msp430* | powerpc* | s390* | sparc*)
                 echo "target '${target}' requires nightly compiler (skipped all checks)"
                 return 0
                 ;;
@@ -240,6 +256,13 @@ build() {
     )
     RUSTFLAGS="${target_rustflags}" \
         x_cargo "${args[@]}" "$@"
+    case "${target}" in
+        # targets without atomic RMW instructions
+        armv4t* | armv5te* | thumbv6m* | riscv32i-* | riscv32im-* | riscv32imc-* | riscv64i-*)
+            RUSTFLAGS="${target_rustflags} --cfg atomic_maybe_uninit_unsafe_assume_single_core" \
+                x_cargo "${args[@]}" --target-dir target/assume-single-core "$@"
+            ;;
+    esac
     case "${target}" in
         x86_64*)
             # Apple targets are skipped because they are +cmpxchg16b by default
@@ -251,6 +274,14 @@ build() {
                     ;;
             esac
             ;;
+        i586* | i686*)
+            # i486 doesn't have CMPXCHG8B, and i386 additionally doesn't have CMPXCHG
+            # (CAS is implemented by using the critical section with the `critical-section` feature).
+            RUSTFLAGS="${target_rustflags} -C target-cpu=i486" \
+                x_cargo "${args[@]}" --target-dir target/i486 "$@"
+            RUSTFLAGS="${target_rustflags} -C target-cpu=i386" \
+                x_cargo "${args[@]}" --target-dir target/i386 "$@"
+            ;;
         aarch64* | arm64*)
             # macOS is skipped because it is +lse,+lse2,+rcpc by default
             case "${target}" in
@@ -276,6 +307,13 @@ build() {
                     x_cargo "${args[@]}" --target-dir target/lse128-rcpc3 "$@"
             fi
             ;;
+        loongarch64*)
+            # Support for LAMCAS and LAM_BH requires LLVM 20+.
+            if [[ "${llvm_version}" -ge 20 ]]; then
+                RUSTFLAGS="${target_rustflags} -C target-cpu=la664" \
+                    x_cargo "${args[@]}" --target-dir target/la664 "$@"
+            fi
+            ;;
         powerpc64-*)
             # powerpc64le- (little-endian) is skipped because it is pwr8 by default
             RUSTFLAGS="${target_rustflags} -C target-cpu=pwr8" \
@@ -286,10 +324,45 @@ build() {
             RUSTFLAGS="${target_rustflags} -C target-cpu=pwr7" \
                 x_cargo "${args[@]}" --target-dir target/pwr7 "$@"
             ;;
+        riscv*)
+            # Support for non-experimental Zacas and Zabha extensions requires LLVM 20+.
+            if [[ "${llvm_version}" -ge 20 ]]; then
+                RUSTFLAGS="${target_rustflags} -C target-feature=+zacas" \
+                    x_cargo "${args[@]}" --target-dir target/zacas "$@"
+                RUSTFLAGS="${target_rustflags} -C target-feature=+zabha" \
+                    x_cargo "${args[@]}" --target-dir target/zabha "$@"
+                RUSTFLAGS="${target_rustflags} -C target-feature=+zabha,+zacas" \
+                    x_cargo "${args[@]}" --target-dir target/zabha-zacas "$@"
+                case "${target}" in
+                    *-linux-* | *-android*)
+                        RUSTFLAGS="${target_rustflags} --cfg atomic_maybe_uninit_outline_atomics" \
+                            x_cargo "${args[@]}" --target-dir target/outline-atomics "$@"
+                        ;;
+                esac
+            fi
+            ;;
         s390x*)
             RUSTFLAGS="${target_rustflags} -C target-cpu=z196" \
                 x_cargo "${args[@]}" --target-dir target/z196 "$@"
             ;;
+        sparc-unknown-none-elf)
+            RUSTFLAGS="${target_rustflags} -C target-cpu=v8" \
+                x_cargo "${args[@]}" --target-dir target/v8 "$@"
+            # CASA is optional on LEON3, so leoncasa is not enabled by default.
+            RUSTFLAGS="${target_rustflags} -C target-cpu=leon3 -C target-feature=+leoncasa" \
+                x_cargo "${args[@]}" --target-dir target/leon3-leoncasa "$@"
+            RUSTFLAGS="${target_rustflags} -C target-cpu=leon4" \
+                x_cargo "${args[@]}" --target-dir target/leon4 "$@"
+            RUSTFLAGS="${target_rustflags} -C target-cpu=v9" \
+                x_cargo "${args[@]}" --target-dir target/v9 "$@"
+            ;;
+        m68k*)
+            # 68000 doesn't have CAS/CAS2.
+            RUSTFLAGS="${target_rustflags} -C target-cpu=M68000" \
+                x_cargo "${args[@]}" --target-dir target/m68000 "$@"
+            RUSTFLAGS="${target_rustflags} -C target-cpu=M68040" \
+                x_cargo "${args[@]}" --target-dir target/m68040 "$@"
+            ;;
     esac
 }
 
@@ -12,6 +12,12 @@ trap -- 'echo >&2 "$0: trapped SIGINT"; exit 1' SIGINT
 #    ./tools/no-std.sh [+toolchain] [target]...
 
 default_targets=(
+    # armv4t
+    armv4t-none-eabi
+    thumbv4t-none-eabi
+    # armv5te
+    armv5te-none-eabi
+    thumbv5te-none-eabi
     # armv6-m
     thumbv6m-none-eabi
     # armv7-m
@@ -34,6 +40,9 @@ default_targets=(
     riscv64imac-unknown-none-elf
     riscv64gc-unknown-none-elf
 
+    # sparc
+    sparc-unknown-none-elf
+
     # avr
     avr-unknown-gnu-atmega2560 # custom target
 )
@@ -107,11 +116,20 @@ run() {
 
     local test_dir
     case "${target}" in
-        thumb* | riscv*)
+        arm* | thumb* | riscv*)
             test_dir=tests/no-std-qemu
             linker=link.x
             target_rustflags+=" -C link-arg=-T${linker}"
             ;;
+        sparc*)
+            test_dir=tests/no-std-qemu
+            # semihosting-no-std-test-rt doesn't support SPARC, so src/leon3.rs
+            # provides the runtime and the linker script. lld doesn't support
+            # 32-bit SPARC, so GNU ld is used.
+            target_rustflags+=" -C linker=sparc64-linux-gnu-ld -C linker-flavor=ld -C link-arg=-melf32_sparc"
+            target_rustflags+=" -C link-arg=-T$(pwd)/${test_dir}/leon3.ld"
+            target_rustflags+=" -C target-cpu=leon3"
+            ;;
         avr*)
             test_dir=tests/avr
             ;;
@@ -125,6 +143,22 @@ run() {
             x_cargo "${args[@]}" "$@"
         RUSTFLAGS="${target_rustflags}" \
             x_cargo "${args[@]}" --release "$@"
+        case "${target}" in
+            # targets without atomic RMW instructions
+            armv4t* | armv5te* | thumbv6m* | riscv32i-* | riscv32im-* | riscv32imc-* | riscv64i-*)
+                RUSTFLAGS="${target_rustflags} --cfg atomic_maybe_uninit_unsafe_assume_single_core" \
+                    x_cargo "${args[@]}" "$@"
+                RUSTFLAGS="${target_rustflags} --cfg atomic_maybe_uninit_unsafe_assume_single_core" \
+                    x_cargo "${args[@]}" --release "$@"
+                ;;
+            # CASA is optional on LEON3, so leoncasa is not enabled by default.
+            sparc*)
+                RUSTFLAGS="${target_rustflags} -C target-feature=+leoncasa" \
+                    x_cargo "${args[@]}" "$@"
+                RUSTFLAGS="${target_rustflags} -C target-feature=+leoncasa" \
+                    x_cargo "${args[@]}" --release "$@"
+                ;;
+        esac
     )
 }
 